@@ -0,0 +1,15 @@
+-- Draft pods, for practice drafts with a mix of humans and bots.
+-- The seats, packs and picks all live in the `state` blob, which
+-- is a serialized draft::Pod.
+CREATE TABLE pods (
+    id           UUID     NOT NULL PRIMARY KEY,
+    collector    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    set_code     VARCHAR  NOT NULL,
+    state        JSONB    NOT NULL DEFAULT '{}'::json,
+    finished     BOOLEAN  NOT NULL DEFAULT FALSE,
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
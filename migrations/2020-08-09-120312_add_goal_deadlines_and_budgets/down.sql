@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE goals
+  DROP COLUMN deadline,
+  DROP COLUMN budget;
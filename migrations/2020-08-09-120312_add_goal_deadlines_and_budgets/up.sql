@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE goals
+  ADD COLUMN deadline DATE    DEFAULT NULL,
+  ADD COLUMN budget   INTEGER DEFAULT NULL; -- pennies
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE collectors
+  ADD COLUMN currency VARCHAR NOT NULL DEFAULT 'usd';
@@ -0,0 +1,38 @@
+-- Your SQL goes here
+CREATE TABLE watchlists (
+    id           UUID     NOT NULL PRIMARY KEY,
+    collector    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    card         VARCHAR  NOT NULL,                 -- print or oracle id
+    scope        VARCHAR  NOT NULL DEFAULT 'print', -- print / oracle
+    finish       VARCHAR  NOT NULL DEFAULT 'nonfoil',
+    currency     VARCHAR  NOT NULL DEFAULT 'usd',
+    below        INTEGER  DEFAULT NULL,             -- pennies
+    above        INTEGER  DEFAULT NULL,             -- pennies
+    notes        TEXT     NOT NULL DEFAULT '',
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE alerts (
+    id           UUID     NOT NULL PRIMARY KEY,
+    watchlist    UUID     NOT NULL
+      REFERENCES watchlists (id) ON DELETE CASCADE,
+    collector    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    card         VARCHAR  NOT NULL, -- print id whose price moved
+    direction    VARCHAR  NOT NULL, -- above / below
+    threshold    INTEGER  NOT NULL, -- pennies
+    price        INTEGER  NOT NULL, -- pennies
+    currency     VARCHAR  NOT NULL,
+    dated        DATE     NOT NULL, -- of the price snapshot
+
+    delivered_at TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    -- re-uploading a day's prices shouldn't alert twice
+    UNIQUE(watchlist, card, direction, dated)
+);
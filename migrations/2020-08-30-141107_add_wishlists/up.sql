@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE wishlists (
+    id           UUID  NOT NULL PRIMARY KEY,
+    collector    UUID  NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    name         TEXT  NOT NULL,
+    description  TEXT  NOT NULL DEFAULT '',
+    cards        TEXT  NOT NULL DEFAULT '', -- CDIF, with wish constraints
+
+    UNIQUE(collector, name),
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,26 @@
+-- Your SQL goes here
+CREATE TABLE trades (
+    id           UUID     NOT NULL PRIMARY KEY,
+    proposer     UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+    recipient    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    offer        TEXT     NOT NULL DEFAULT '', -- CDIF, from the proposer
+    request      TEXT     NOT NULL DEFAULT '', -- CDIF, from the recipient
+    message      TEXT     NOT NULL DEFAULT '',
+    status       VARCHAR  NOT NULL DEFAULT 'proposed',
+
+    -- the trade this one counters, if any
+    parent       UUID     DEFAULT NULL
+      REFERENCES trades (id) ON DELETE SET NULL,
+
+    -- the mirrored transactions written when the trade was accepted
+    proposer_transaction   UUID  DEFAULT NULL
+      REFERENCES transactions (id) ON DELETE SET NULL,
+    recipient_transaction  UUID  DEFAULT NULL
+      REFERENCES transactions (id) ON DELETE SET NULL,
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE collectors
+  ADD COLUMN tradeable BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,30 @@
+-- Your SQL goes here
+CREATE TABLE locations (
+    id           UUID     NOT NULL PRIMARY KEY,
+    collector    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    -- rows live in boxes, pages in binders
+    parent       UUID     DEFAULT NULL
+      REFERENCES locations (id) ON DELETE CASCADE,
+
+    name         VARCHAR  NOT NULL, -- i.e. `binder-3`, for (loc: ...)
+    kind         VARCHAR  NOT NULL, -- box, row, binder, page or deckbox
+    description  TEXT     NOT NULL DEFAULT '',
+
+    UNIQUE(collector, name),
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- How many copies of each owned card are kept where.
+CREATE TABLE stowage (
+    location     UUID     NOT NULL
+      REFERENCES locations (id) ON DELETE CASCADE,
+    pid          VARCHAR  NOT NULL, -- print id
+    var          VARCHAR  NOT NULL DEFAULT '', -- global variants, i.e. `foil NM`
+    quantity     INTEGER  NOT NULL CHECK (quantity > 0),
+
+    PRIMARY KEY (location, pid, var)
+);
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE loans (
+    id           UUID     NOT NULL PRIMARY KEY,
+    collector    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    -- who has the cards; either free text, or another collector
+    borrower     TEXT     NOT NULL DEFAULT '',
+    borrower_collector  UUID  DEFAULT NULL
+      REFERENCES collectors (id) ON DELETE SET NULL,
+
+    cards        TEXT     NOT NULL DEFAULT '', -- CDIF
+    notes        TEXT     NOT NULL DEFAULT '',
+
+    lent_on      DATE     NOT NULL,
+    due_on       DATE     DEFAULT NULL,
+    returned_on  DATE     DEFAULT NULL,
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE slab_values (
+    collector    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    -- certification number of the graded copy, as in the CDIF
+    -- `(cert: ...)` variant; unique per collector.
+    cert         VARCHAR(100) NOT NULL,
+    value        INTEGER  NOT NULL CHECK (value >= 0), -- pennies
+    notes        TEXT     NOT NULL DEFAULT '',
+
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (collector, cert)
+);
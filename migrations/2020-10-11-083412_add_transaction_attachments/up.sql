@@ -0,0 +1,17 @@
+-- Your SQL goes here
+CREATE TABLE attachments (
+    id           UUID     NOT NULL PRIMARY KEY,
+    transaction  UUID     NOT NULL
+      REFERENCES transactions (id) ON DELETE CASCADE,
+
+    filename     TEXT     NOT NULL,
+    content_type VARCHAR(255) NOT NULL DEFAULT 'application/octet-stream',
+    size         INTEGER  NOT NULL CHECK (size > 0), -- bytes
+
+    -- contents are stored (once) in the collection's directory,
+    -- under their hex-encoded SHA-256 digest.
+    sha256       VARCHAR(64) NOT NULL,
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+CREATE INDEX attachments_transaction_idx ON attachments (transaction);
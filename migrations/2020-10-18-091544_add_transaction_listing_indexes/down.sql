@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX transactions_search_idx;
+DROP INDEX transactions_metadata_idx;
+DROP INDEX transactions_collection_dated_idx;
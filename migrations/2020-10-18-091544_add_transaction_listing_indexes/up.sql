@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+-- listing a collection's transactions, in date order, a page at a time
+CREATE INDEX transactions_collection_dated_idx ON transactions (collection, dated, id);
+
+-- "touches set X", via containment on the set_gain / set_loss metadata
+CREATE INDEX transactions_metadata_idx ON transactions USING GIN (metadata jsonb_path_ops);
+
+-- free-text search across summary and notes; queries must use the
+-- same expression for the planner to pick this up.
+CREATE INDEX transactions_search_idx ON transactions
+  USING GIN (to_tsvector('simple', summary || ' ' || notes));
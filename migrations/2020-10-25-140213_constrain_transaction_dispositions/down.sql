@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE transactions DROP CONSTRAINT transactions_disposition_check;
+
+UPDATE transactions
+   SET disposition = metadata->>'unmapped_disposition',
+       metadata = metadata - 'unmapped_disposition'
+ WHERE metadata ? 'unmapped_disposition';
@@ -0,0 +1,55 @@
+-- Your SQL goes here
+
+-- bring the free-form dispositions we've accumulated into line with the
+-- known kinds of transaction (see src/disposition.rs)
+UPDATE transactions
+   SET disposition = CASE
+         WHEN lower(trim(disposition)) IN
+           ('buy', 'buys', 'bought', 'purchase', 'purchased', 'acquired')
+         THEN 'buy'
+         WHEN lower(trim(disposition)) IN
+           ('sell', 'sells', 'sold', 'sale', 'sales')
+         THEN 'sell'
+         WHEN lower(trim(disposition)) IN
+           ('trade', 'trades', 'traded', 'swap', 'swapped', '')
+         THEN 'trade'
+         WHEN lower(trim(disposition)) IN
+           ('open', 'opened', 'opening', 'crack', 'cracked', 'pack',
+            'open pack', 'crack pack', 'pack opening', 'booster')
+         THEN 'open'
+         WHEN lower(trim(disposition)) IN
+           ('gift', 'gifts', 'gifted', 'given', 'received', 'giveaway')
+         THEN 'gift'
+         WHEN lower(trim(disposition)) IN
+           ('loss', 'lost', 'damage', 'damaged', 'stolen', 'destroyed')
+         THEN 'loss'
+         WHEN lower(trim(disposition)) IN
+           ('correction', 'corrected', 'adjustment', 'adjust', 'fix')
+         THEN 'correction'
+         WHEN lower(trim(disposition)) IN
+           ('import', 'imported', 'initial', 'initial import')
+         THEN 'import'
+       END
+ WHERE lower(trim(disposition)) IN
+   ('buy', 'buys', 'bought', 'purchase', 'purchased', 'acquired',
+    'sell', 'sells', 'sold', 'sale', 'sales',
+    'trade', 'trades', 'traded', 'swap', 'swapped', '',
+    'open', 'opened', 'opening', 'crack', 'cracked', 'pack',
+    'open pack', 'crack pack', 'pack opening', 'booster',
+    'gift', 'gifts', 'gifted', 'given', 'received', 'giveaway',
+    'loss', 'lost', 'damage', 'damaged', 'stolen', 'destroyed',
+    'correction', 'corrected', 'adjustment', 'adjust', 'fix',
+    'import', 'imported', 'initial', 'initial import');
+
+-- anything left over we can't make sense of; those become corrections
+-- (which is how cost basis treated them anyway), and the original value
+-- is kept in the metadata for `cardboard migrate` to report on.
+UPDATE transactions
+   SET metadata = metadata || jsonb_build_object('unmapped_disposition', disposition),
+       disposition = 'correction'
+ WHERE disposition NOT IN
+   ('buy', 'sell', 'trade', 'open', 'gift', 'loss', 'correction', 'import');
+
+ALTER TABLE transactions
+  ADD CONSTRAINT transactions_disposition_check CHECK (disposition IN
+    ('buy', 'sell', 'trade', 'open', 'gift', 'loss', 'correction', 'import'));
@@ -0,0 +1,25 @@
+-- Your SQL goes here
+CREATE TABLE sealed_products (
+    id           UUID     NOT NULL PRIMARY KEY,
+    collector    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    kind         VARCHAR(32) NOT NULL CHECK (kind IN
+      ('booster-pack', 'booster-box', 'bundle', 'collector-pack', 'collector-box')),
+    name         TEXT     NOT NULL DEFAULT '',
+    set_code     VARCHAR(16) NOT NULL,
+    packs        INTEGER  NOT NULL CHECK (packs > 0),   -- in each one
+
+    quantity     INTEGER  NOT NULL DEFAULT 1 CHECK (quantity >= 0), -- unopened
+    paid         INTEGER  DEFAULT NULL CHECK (paid >= 0), -- each, in pennies
+    acquired_on  DATE     NOT NULL,
+    notes        TEXT     NOT NULL DEFAULT '',
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- the sealed product an `open` transaction consumed
+ALTER TABLE transactions
+  ADD COLUMN opened UUID DEFAULT NULL
+    REFERENCES sealed_products (id) ON DELETE SET NULL;
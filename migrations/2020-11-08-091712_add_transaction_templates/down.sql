@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE recurring_transactions;
+DROP TABLE transaction_templates;
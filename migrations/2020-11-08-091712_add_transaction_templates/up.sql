@@ -0,0 +1,41 @@
+-- Your SQL goes here
+CREATE TABLE transaction_templates (
+    id           UUID     NOT NULL PRIMARY KEY,
+    collector    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    name         TEXT     NOT NULL,
+    summary      TEXT     NOT NULL DEFAULT '',  -- these four can have
+    notes        TEXT     NOT NULL DEFAULT '',  -- {placeholders} in them
+    gain         TEXT     NOT NULL DEFAULT '',
+    loss         TEXT     NOT NULL DEFAULT '',
+    disposition  VARCHAR(32) NOT NULL CHECK (disposition IN
+      ('buy', 'sell', 'trade', 'open', 'gift', 'loss', 'correction', 'import')),
+    paid         INTEGER  DEFAULT NULL,         -- in pennies
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE recurring_transactions (
+    id           UUID     NOT NULL PRIMARY KEY,
+    collector    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+    template     UUID     NOT NULL
+      REFERENCES transaction_templates (id) ON DELETE CASCADE,
+
+    every        VARCHAR(16) NOT NULL CHECK (every IN
+      ('weekly', 'monthly', 'quarterly', 'yearly')),
+    starts_on    DATE     NOT NULL,
+    ends_on      DATE     DEFAULT NULL,
+    vars         JSONB    NOT NULL DEFAULT '{}', -- placeholder values
+
+    occurrences  INTEGER  NOT NULL DEFAULT 0,   -- made so far
+    next_on      DATE     NOT NULL,             -- the one after that
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX recurring_transactions_next_on_idx
+          ON recurring_transactions (next_on);
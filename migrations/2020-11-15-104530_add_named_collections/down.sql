@@ -0,0 +1,15 @@
+-- This file should undo anything in `up.sql`
+UPDATE transactions SET disposition = 'correction' WHERE disposition = 'transfer';
+ALTER TABLE transactions
+  DROP CONSTRAINT transactions_disposition_check;
+ALTER TABLE transactions
+  ADD CONSTRAINT transactions_disposition_check CHECK (disposition IN
+    ('buy', 'sell', 'trade', 'open', 'gift', 'loss', 'correction', 'import'));
+
+ALTER TABLE transactions DROP COLUMN transfer;
+
+DROP INDEX collections_collector_name_idx;
+ALTER TABLE collections
+  DROP COLUMN name,
+  DROP COLUMN created_at,
+  DROP COLUMN updated_at;
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+ALTER TABLE collections
+  ADD COLUMN name       TEXT     NOT NULL DEFAULT 'Collection',
+  ADD COLUMN created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  ADD COLUMN updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
+
+CREATE UNIQUE INDEX collections_collector_name_idx
+                 ON collections (collector, LOWER(name));
+
+-- a transfer between collections is a pair of transactions, one taking
+-- the cards out of one collection, the other putting them in another;
+-- each half points at the other.
+ALTER TABLE transactions
+  ADD COLUMN transfer UUID DEFAULT NULL
+    REFERENCES transactions (id) ON DELETE SET NULL;
+
+ALTER TABLE transactions
+  DROP CONSTRAINT transactions_disposition_check;
+ALTER TABLE transactions
+  ADD CONSTRAINT transactions_disposition_check CHECK (disposition IN
+    ('buy', 'sell', 'trade', 'open', 'gift', 'loss', 'correction', 'import', 'transfer'));
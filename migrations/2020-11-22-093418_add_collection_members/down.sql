@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE transactions
+  DROP COLUMN created_by,
+  DROP COLUMN updated_by;
+
+DROP TABLE collection_members;
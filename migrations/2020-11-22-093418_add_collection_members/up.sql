@@ -0,0 +1,31 @@
+-- Your SQL goes here
+-- a collector always owns their own collections; anyone else gets in
+-- by being invited, with a role, and accepting the invitation.
+CREATE TABLE collection_members (
+    collection   UUID     NOT NULL
+      REFERENCES collections (id) ON DELETE CASCADE,
+    collector    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    role         VARCHAR(16) NOT NULL CHECK (role IN
+      ('owner', 'editor', 'viewer')),
+    invited_by   UUID     DEFAULT NULL
+      REFERENCES collectors (id) ON DELETE SET NULL,
+    accepted_at  TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (collection, collector)
+);
+
+CREATE INDEX collection_members_collector_idx
+          ON collection_members (collector);
+
+-- who made (and last changed) each transaction, now that it might not
+-- be the collector whose collection it's in.
+ALTER TABLE transactions
+  ADD COLUMN created_by UUID DEFAULT NULL
+    REFERENCES collectors (id) ON DELETE SET NULL,
+  ADD COLUMN updated_by UUID DEFAULT NULL
+    REFERENCES collectors (id) ON DELETE SET NULL;
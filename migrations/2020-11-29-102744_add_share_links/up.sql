@@ -0,0 +1,29 @@
+-- Your SQL goes here
+-- read-only links to a deck, a deck version, or a collection, for
+-- showing to people who don't have an account.
+CREATE TABLE share_links (
+    id           UUID     NOT NULL PRIMARY KEY,
+    collector    UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+    token        VARCHAR(64) NOT NULL UNIQUE,
+
+    kind         VARCHAR(16) NOT NULL CHECK (kind IN
+      ('deck', 'deck-version', 'collection')),
+    deck         UUID     DEFAULT NULL
+      REFERENCES decks (id) ON DELETE CASCADE,
+    collection   UUID     DEFAULT NULL
+      REFERENCES collections (id) ON DELETE CASCADE,
+    query        TEXT     NOT NULL DEFAULT '',  -- collections only
+
+    expires_at   TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+    revoked_at   TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CHECK ((kind = 'collection') = (collection IS NOT NULL)),
+    CHECK ((kind = 'collection') = (deck IS NULL))
+);
+
+CREATE INDEX share_links_collector_idx
+          ON share_links (collector);
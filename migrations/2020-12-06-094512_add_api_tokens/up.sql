@@ -0,0 +1,22 @@
+-- Your SQL goes here
+-- personal API tokens, for scripts; only a hash of each is kept.
+CREATE TABLE api_tokens (
+    id            UUID     NOT NULL PRIMARY KEY,
+    collector     UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    name          TEXT     NOT NULL,
+    hash          VARCHAR(64) NOT NULL UNIQUE,  -- SHA-256, in hex
+    hint          VARCHAR(8)  NOT NULL,         -- last few characters
+    scopes        TEXT[]   NOT NULL CHECK (cardinality(scopes) > 0 AND scopes <@
+      ARRAY['read-collection', 'write-transactions', 'manage-decks']::TEXT[]),
+
+    last_used_at  TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+    revoked_at    TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+
+    created_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX api_tokens_collector_idx
+          ON api_tokens (collector);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE account_tokens;
+ALTER TABLE collectors DROP COLUMN email_verified_at;
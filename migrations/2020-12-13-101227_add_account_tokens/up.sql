@@ -0,0 +1,26 @@
+-- Your SQL goes here
+-- when (if ever) the collector proved they can read mail sent to
+-- their email address.  changing the address un-verifies it.
+ALTER TABLE collectors
+  ADD COLUMN email_verified_at TIMESTAMP WITH TIME ZONE DEFAULT NULL;
+
+-- single-use tokens, mailed out for password resets and email
+-- verification; the tokens themselves are signed, and never stored.
+CREATE TABLE account_tokens (
+    id            UUID     NOT NULL PRIMARY KEY,
+    collector     UUID     NOT NULL
+      REFERENCES collectors (id) ON DELETE CASCADE,
+
+    purpose       VARCHAR(16) NOT NULL
+      CHECK (purpose IN ('reset-password', 'verify-email')),
+    email         TEXT     NOT NULL,  -- where the token was sent
+
+    expires_at    TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at       TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+
+    created_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX account_tokens_collector_idx
+          ON account_tokens (collector);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX locations_sibling_name_idx;
+DROP INDEX locations_top_level_name_idx;
+
+ALTER TABLE locations ADD CONSTRAINT locations_collector_name_key UNIQUE (collector, name);
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- Location names only need to be unique among their siblings, so that
+-- every binder can have its own `page-1`; (loc: ...) variants name a
+-- location by its full path, i.e. `binder-3/page-1`.
+ALTER TABLE locations DROP CONSTRAINT locations_collector_name_key;
+
+CREATE UNIQUE INDEX locations_top_level_name_idx
+  ON locations (collector, name) WHERE parent IS NULL;
+CREATE UNIQUE INDEX locations_sibling_name_idx
+  ON locations (parent, name) WHERE parent IS NOT NULL;
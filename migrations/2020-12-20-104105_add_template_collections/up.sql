@@ -0,0 +1,12 @@
+-- Your SQL goes here
+-- Transactions made from a template (by hand, or on a schedule) go to
+-- the collection the template names, not always the default one.
+ALTER TABLE transaction_templates
+  ADD COLUMN collection UUID DEFAULT NULL
+    REFERENCES collections (id) ON DELETE CASCADE;
+
+-- a collector's default collection shares their id.
+UPDATE transaction_templates SET collection = collector;
+
+ALTER TABLE transaction_templates
+  ALTER COLUMN collection SET NOT NULL;
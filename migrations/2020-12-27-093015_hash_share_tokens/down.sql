@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+-- there is no getting the tokens back; every link shared stops working.
+UPDATE share_links
+   SET revoked_at = NOW()
+ WHERE revoked_at IS NULL;
+
+ALTER TABLE share_links
+  DROP COLUMN hint;
+ALTER TABLE share_links
+  RENAME COLUMN hash TO token;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- share tokens are kept the way API tokens are: only a (SHA-256) hash
+-- of each, and the last few characters, for telling them apart.
+ALTER TABLE share_links
+  RENAME COLUMN token TO hash;
+ALTER TABLE share_links
+  ADD COLUMN hint VARCHAR(8) NOT NULL DEFAULT '';
+
+UPDATE share_links
+   SET hint = RIGHT(hash, 4),
+       hash = ENCODE(SHA256(hash::BYTEA), 'hex');
+
+ALTER TABLE share_links
+  ALTER COLUMN hint DROP DEFAULT;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- sessions remember which generation they were handed out in; resetting
+-- a password moves the collector on to the next, ending the rest.
+ALTER TABLE collectors
+  ADD COLUMN session_generation INTEGER NOT NULL DEFAULT 0;
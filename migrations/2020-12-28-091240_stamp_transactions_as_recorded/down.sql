@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE transactions
+  ALTER COLUMN created_at SET DEFAULT NOW();
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- NOW() is the same for everything written in one database transaction;
+-- a trade that first moves cards into the default collection has to
+-- come out in the order it was recorded, so stamp each row as it goes.
+ALTER TABLE transactions
+  ALTER COLUMN created_at SET DEFAULT clock_timestamp();
@@ -0,0 +1,212 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use sha2::{Digest, Sha256};
+use std::env;
+use uuid::Uuid;
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// Password resets and email verification both work by mailing the
+// collector a token, which they hand back to prove that they can read
+// mail sent to their address.  Tokens look like this:
+//
+//     <purpose>.<id>.<expires>.<signature>
+//
+// id is the UUID of the token's row in the database, which is what
+// makes tokens single-use (the row is marked as used, once and for
+// all, when the token is).  expires is a UNIX timestamp.  signature is
+// an HMAC-SHA256 of everything before it, in hex, keyed with the
+// server secret ($VCB_SECRET), so that forged or tampered-with tokens
+// are turned away without ever touching the database.
+//
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Purpose {
+    ResetPassword,
+    VerifyEmail,
+}
+
+impl Purpose {
+    pub fn parse(s: &str) -> Option<Purpose> {
+        match s {
+            "reset-password" => Some(Purpose::ResetPassword),
+            "verify-email" => Some(Purpose::VerifyEmail),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Purpose::ResetPassword => "reset-password",
+            Purpose::VerifyEmail => "verify-email",
+        }
+    }
+
+    // How long a token is good for, once issued.  Password resets are
+    // kept short, since anyone who can read the mail can take over
+    // the account.
+    pub fn lifetime(&self) -> Duration {
+        match self {
+            Purpose::ResetPassword => Duration::hours(1),
+            Purpose::VerifyEmail => Duration::days(2),
+        }
+    }
+}
+
+pub struct Claims {
+    pub purpose: Purpose,
+    pub id: Uuid,
+    pub expires: DateTime<Utc>,
+}
+
+// The server secret that tokens are signed with.  There is no default;
+// without one, tokens can be neither issued nor accepted.
+pub fn secret() -> Result<String> {
+    match env::var("VCB_SECRET") {
+        Ok(secret) if !secret.is_empty() => Ok(secret),
+        _ => Err("VCB_SECRET must be set in environment to issue account tokens".into()),
+    }
+}
+
+// HMAC-SHA256, per RFC 2104.
+fn hmac(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let pad = |with: u8| block.iter().map(|b| b ^ with).collect::<Vec<u8>>();
+    let mut inner = Sha256::new();
+    inner.update(pad(0x36));
+    inner.update(msg);
+    let mut outer = Sha256::new();
+    outer.update(pad(0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().to_vec()
+}
+
+fn signature(secret: &str, payload: &str) -> String {
+    hmac(secret.as_bytes(), payload.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn sign(secret: &str, purpose: Purpose, id: Uuid, expires: DateTime<Utc>) -> String {
+    let payload = format!("{}.{}.{}", purpose.as_str(), id, expires.timestamp());
+    let sig = signature(secret, &payload);
+    format!("{}.{}", payload, sig)
+}
+
+// Check a token's signature and expiry, returning what it claims (or a
+// failure code suitable for the API).  Whether it has been used yet is
+// up to the database.
+pub fn verify(secret: &str, token: &str, now: DateTime<Utc>) -> Result<Claims> {
+    let token = token.trim();
+    let (payload, sig) = match token.rfind('.') {
+        Some(i) => (&token[..i], &token[i + 1..]),
+        None => return Err("invalid-token".into()),
+    };
+
+    // compare in constant time, so as not to give away how much of a
+    // forged signature was right.
+    let want = signature(secret, payload);
+    if want.len() != sig.len()
+        || want
+            .bytes()
+            .zip(sig.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            != 0
+    {
+        return Err("invalid-token".into());
+    }
+
+    let parts: Vec<&str> = payload.split('.').collect();
+    let claims = match parts.as_slice() {
+        [purpose, id, expires] => match (
+            Purpose::parse(purpose),
+            Uuid::parse_str(id),
+            expires.parse::<i64>(),
+        ) {
+            (Some(purpose), Ok(id), Ok(expires)) => match Utc.timestamp_opt(expires, 0).single() {
+                Some(expires) => Claims {
+                    purpose,
+                    id,
+                    expires,
+                },
+                None => return Err("invalid-token".into()),
+            },
+            _ => return Err("invalid-token".into()),
+        },
+        _ => return Err("invalid-token".into()),
+    };
+    if claims.expires <= now {
+        return Err("expired-token".into());
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_compute_hmacs() {
+        // RFC 4231, test cases 2 and 6
+        let hex = |v: Vec<u8>| v.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        assert_eq!(
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            hex(hmac(b"Jefe", b"what do ya want for nothing?"))
+        );
+        assert_eq!(
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            hex(hmac(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ))
+        );
+    }
+
+    #[test]
+    fn should_sign_and_verify_tokens() {
+        let now = Utc.with_ymd_and_hms(2020, 12, 13, 9, 0, 0).unwrap();
+        let id = Uuid::new_v4();
+        let token = sign(
+            "s3cr3t",
+            Purpose::ResetPassword,
+            id,
+            now + Duration::hours(1),
+        );
+        assert!(token.starts_with(&format!("reset-password.{}.", id)));
+
+        let claims = verify("s3cr3t", &token, now).unwrap();
+        assert_eq!(Purpose::ResetPassword, claims.purpose);
+        assert_eq!(id, claims.id);
+        assert_eq!(now + Duration::hours(1), claims.expires);
+
+        let fail = |secret, token: &str, now| verify(secret, token, now).err().unwrap().to_string();
+        assert_eq!(
+            "expired-token",
+            fail("s3cr3t", &token, now + Duration::hours(2))
+        );
+        assert_eq!("invalid-token", fail("other", &token, now));
+        assert_eq!(
+            "invalid-token",
+            fail(
+                "s3cr3t",
+                &token.replace("reset-password", "verify-email"),
+                now
+            )
+        );
+        assert_eq!(
+            "invalid-token",
+            fail("s3cr3t", &token[..token.len() - 1], now)
+        );
+        assert_eq!("invalid-token", fail("s3cr3t", "", now));
+        assert_eq!("invalid-token", fail("s3cr3t", "a.b.c.d", now));
+    }
+}
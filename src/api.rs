@@ -1,11 +1,17 @@
 use chrono::{naive::NaiveDate, DateTime, Utc};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::Read;
 use unicode_segmentation::UnicodeSegmentation;
 use uuid::Uuid;
 
-use super::db;
+use super::prelude::*;
+use super::{
+    account, basis, card, cdif, db, disposition, draft, export, goal, grading, loan, mail,
+    matching, notify, query, role, sealed, share, storage, template, token, trade, valuation,
+    watchlist, wishlist,
+};
 
 mod errors {
     error_chain! {}
@@ -21,14 +27,59 @@ pub enum Object {
     Authenticated(Authenticated),
     Goal(Goal),
     Goals(Vec<Goal>),
+    Missing(Vec<goal::Missing>),
     Deck(Deck),
     Decks(Vec<Deck>),
+    Collection(Collection),
+    Collections(Collections),
+    Member(Member),
+    Members(Vec<Member>),
+    Memberships(Vec<Membership>),
     Transaction(Transaction),
     Transactions(Vec<Transaction>),
+    Transfer(Vec<Transaction>),
+    Page(TransactionPage),
+    Attachment(Attachment),
+    Pod(Pod),
+    Pods(Vec<Pod>),
+    Valuation(Vec<valuation::Valuation>),
+    Movers(Vec<valuation::Mover>),
+    Slabs(grading::Slabs),
+    History(PriceHistory),
+    Profit(basis::Report),
+    Watchlist(Watchlist),
+    Watchlists(Vec<Watchlist>),
+    Alerts(Vec<Alert>),
+    Wishlist(Wishlist),
+    Wishlists(Vec<Wishlist>),
+    Deals(Vec<wishlist::Deal>),
+    Satisfaction(Vec<wishlist::Satisfaction>),
+    Trade(Trade),
+    Trades(Vec<Trade>),
+    Partners(Vec<matching::Partner>),
+    Location(Location),
+    Locations(Vec<Location>),
+    Stowage(Vec<storage::Content>),
+    Whereabouts(storage::Whereabouts),
+    Picks(storage::PickList),
+    Loan(Loan),
+    Loans(Vec<Loan>),
+    SealedProduct(SealedProduct),
+    SealedProducts(SealedInventory),
+    Template(TransactionTemplate),
+    Templates(Vec<TransactionTemplate>),
+    Recurring(RecurringTransaction),
+    Recurrings(Vec<RecurringTransaction>),
+    Availability(Vec<loan::Availability>),
+    Share(ShareLink),
+    Shares(Vec<ShareLink>),
+    Shared(Shared),
+    Token(ApiToken),
+    Tokens(Vec<ApiToken>),
 }
 
 impl Object {
-    fn ok(msg: &str) -> Self {
+    pub fn ok(msg: &str) -> Self {
         Self::Response(Response {
             ok: true,
             message: msg.to_string(),
@@ -42,14 +93,6 @@ impl Object {
         })
     }
 
-    fn list_of_goals(other: Vec<db::Goal>) -> Self {
-        let mut goals = vec![];
-        for goal in other {
-            goals.push(Goal::from(goal));
-        }
-        Self::Goals(goals)
-    }
-
     fn list_of_decks(other: Vec<db::Deck>) -> Self {
         let mut decks = vec![];
         for deck in other {
@@ -58,12 +101,52 @@ impl Object {
         Self::Decks(decks)
     }
 
-    fn list_of_transactions(other: Vec<db::Transaction>) -> Self {
-        let mut transactions = vec![];
-        for txn in other {
-            transactions.push(Transaction::from(txn));
+    fn list_of_pods(other: Vec<db::Pod>) -> Self {
+        let mut pods = vec![];
+        for pod in other {
+            pods.push(Pod::from(pod));
+        }
+        Self::Pods(pods)
+    }
+
+    fn list_of_watchlists(other: Vec<db::Watchlist>) -> Self {
+        let mut watchlists = vec![];
+        for watch in other {
+            watchlists.push(Watchlist::from(watch));
+        }
+        Self::Watchlists(watchlists)
+    }
+
+    fn list_of_wishlists(other: Vec<db::Wishlist>) -> Self {
+        let mut wishlists = vec![];
+        for wishes in other {
+            wishlists.push(Wishlist::from(wishes));
+        }
+        Self::Wishlists(wishlists)
+    }
+
+    fn list_of_loans(other: Vec<db::Loan>) -> Self {
+        let mut loans = vec![];
+        for loan in other {
+            loans.push(Loan::from(loan));
+        }
+        Self::Loans(loans)
+    }
+
+    fn list_of_locations(other: Vec<db::Location>) -> Self {
+        let mut locations = vec![];
+        for location in other {
+            locations.push(Location::from(location));
         }
-        Self::Transactions(transactions)
+        Self::Locations(locations)
+    }
+
+    fn list_of_alerts(other: Vec<db::Alert>) -> Self {
+        let mut alerts = vec![];
+        for alert in other {
+            alerts.push(Alert::from(alert));
+        }
+        Self::Alerts(alerts)
     }
 }
 
@@ -85,6 +168,31 @@ fn not_found(kind: &str, id: &str, msg: Option<&str>) -> Object {
     })
 }
 
+// Serde reads `null` for an Option<Option<T>> as None ("leave it be"),
+// rather than Some(None) ("clear it"), so fields that can be cleared
+// need this (and #[serde(default)], for when they are left out).
+fn double_option<'de, T, D>(d: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(d).map(Some)
+}
+
+// Collectors choose which currency their collections are valued in;
+// anything we don't recognize is treated as US dollars.
+fn currency(collector: &db::Collector) -> card::Currency {
+    card::Currency::parse(&collector.currency).unwrap_or(card::Currency::Usd)
+}
+
+// Sessions are only good for the generation they were handed out in;
+// resetting a password moves the collector on to the next one.
+// Sessions from before generations were tracked count as the first.
+fn is_current(session: &db::Session, who: &db::Collector) -> bool {
+    let generation = session.attrs.get("generation").map(|g| g.as_str());
+    generation.unwrap_or("0") == who.session_generation.to_string()
+}
+
 #[derive(Serialize)]
 pub struct Response {
     ok: bool,
@@ -95,6 +203,7 @@ pub struct Authenticated {
     pub uid: String,
     pub username: String,
     pub session: String,
+    pub currency: String,
 }
 
 #[derive(Deserialize)]
@@ -103,6 +212,12 @@ pub struct AuthenticationAttempt {
     pub password: String,
 }
 
+#[derive(Deserialize)]
+pub struct CollectorUpdateAttempt {
+    pub currency: Option<String>,
+    pub tradeable: Option<bool>,
+}
+
 #[derive(Deserialize)]
 pub struct SignupAttempt {
     pub username: String,
@@ -125,6 +240,141 @@ impl SignupAttempt {
     }
 }
 
+// Password resets are asked for by username, not email address, since
+// more than one collector can share an address.
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub username: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetAttempt {
+    pub token: String,
+    pub password: String,
+}
+
+impl PasswordResetAttempt {
+    pub fn valid(&self) -> Result<()> {
+        if self.password.graphemes(true).count() < 8 {
+            Err("password-too-short".into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EmailVerificationAttempt {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct CollectionCreationAttempt {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct CollectionUpdateAttempt {
+    pub name: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Collection {
+    pub id: String,
+    pub collector: String,
+    pub name: String,
+    pub default: bool,
+    pub cards: u32,
+    pub unique: usize,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Collection {
+    fn from(other: db::Collection, cards: &card::Collection) -> Self {
+        Self {
+            id: other.id.to_string(),
+            collector: other.collector.to_string(),
+            default: other.is_default(),
+            name: other.name,
+            cards: cards.cards.iter().map(|(n, _)| n).sum(),
+            unique: cards.cards.len(),
+            created_at: other.created_at,
+            updated_at: other.updated_at,
+        }
+    }
+}
+
+// All of a collector's collections, with the totals across all of them.
+#[derive(Serialize)]
+pub struct Collections {
+    pub collections: Vec<Collection>,
+    pub cards: u32,
+    pub unique: usize,
+}
+
+#[derive(Deserialize)]
+pub struct InvitationAttempt {
+    pub username: String,
+    pub role: String,
+}
+
+#[derive(Deserialize)]
+pub struct MemberUpdateAttempt {
+    pub role: String,
+}
+
+// Someone a collection has been shared with.
+#[derive(Serialize)]
+pub struct Member {
+    pub collection: String,
+    pub collector: String,
+    pub username: String,
+    pub role: String,
+    pub invited_by: Option<String>,
+    pub accepted: bool,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Member {
+    fn from(other: db::CollectionMember, username: &str) -> Self {
+        Self {
+            collection: other.collection.to_string(),
+            collector: other.collector.to_string(),
+            username: username.to_string(),
+            role: other.role,
+            invited_by: other.invited_by.map(|id| id.to_string()),
+            accepted: other.accepted_at.is_some(),
+            accepted_at: other.accepted_at,
+            created_at: other.created_at,
+        }
+    }
+}
+
+// A collection someone else has shared with a collector (or invited
+// them into), from their side.
+#[derive(Serialize)]
+pub struct Membership {
+    pub collection: String,
+    pub name: String,
+    pub owner: String,
+    pub owner_username: String,
+    pub role: String,
+    pub accepted: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+// Cards to move out of one collection and into another, as CDIF.
+#[derive(Deserialize)]
+pub struct TransferAttempt {
+    pub to: String,
+    pub cards: String,
+    pub dated: Option<NaiveDate>,
+    pub summary: Option<String>,
+    pub notes: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct TransactionCreationAttempt {
     pub summary: String,
@@ -158,6 +408,10 @@ pub struct Transaction {
     pub gain: String,
     pub loss: String,
     pub paid: Option<i32>,
+    pub opened: Option<String>,   // uuid of the sealed product
+    pub transfer: Option<String>, // uuid of the other half of a transfer
+    pub created_by: Option<String>,
+    pub updated_by: Option<String>,
 
     pub total_card_gain: u32,
     pub total_card_loss: u32,
@@ -165,6 +419,8 @@ pub struct Transaction {
     pub unique_card_loss: u32,
     pub set_loss: Vec<String>,
     pub set_gain: Vec<String>,
+
+    pub attachments: Vec<Attachment>,
 }
 
 impl std::convert::From<db::Transaction> for Transaction {
@@ -187,18 +443,277 @@ impl std::convert::From<db::Transaction> for Transaction {
             gain: other.gain,
             loss: other.loss,
             paid: other.paid,
+            opened: other.opened.map(|id| id.to_string()),
+            transfer: other.transfer.map(|id| id.to_string()),
+            created_by: other.created_by.map(|id| id.to_string()),
+            updated_by: other.updated_by.map(|id| id.to_string()),
+
+            attachments: vec![],
+        }
+    }
+}
+
+// What to export, and how; see export::Filter and export::Format.  All
+// of these come straight from the query string, and may be empty.
+#[derive(Default)]
+pub struct ExportAttempt {
+    pub format: String,
+    pub from: String,
+    pub to: String,
+    pub disposition: String,
+    pub allocation: String,
+    pub method: String,
+}
+
+pub struct Export {
+    pub filename: String,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+// Which transactions to list, and how; see db::TransactionQuery.  As
+// with exports, these come straight from the query string:
+//
+//   ?from=2020-01-01&to=2020-06-30&disposition=buy,sell
+//   &q=ebay&card=Opt&set=XLN&sort=-dated&limit=50&after=<cursor>
+//
+#[derive(Default)]
+pub struct TransactionListingAttempt {
+    pub from: String,
+    pub to: String,
+    pub disposition: String,
+    pub q: String,
+    pub card: String,
+    pub set: String,
+    pub sort: String,
+    pub limit: String,
+    pub after: String,
+}
+
+// The most transactions that can be asked for in a single page, and how
+// many come back when no limit is given.
+pub const TRANSACTION_PAGE_LIMIT: i64 = 500;
+pub const TRANSACTION_PAGE_SIZE: i64 = 100;
+
+// A page of transactions, and the cursor to pass (as `after`) to get the
+// next one; `next` is absent on the last page.
+#[derive(Serialize)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    pub next: Option<String>,
+}
+
+// Cursors are the date and UUID of the last transaction on a page,
+// i.e. `2020-10-03.f8ad53e4-...`, which is all keyset pagination needs.
+fn transaction_cursor(txn: &db::Transaction) -> String {
+    format!("{}.{}", txn.dated.format("%Y-%m-%d"), txn.id)
+}
+
+fn parse_transaction_cursor(s: &str) -> Option<(NaiveDate, Uuid)> {
+    let mut parts = s.trim().splitn(2, '.');
+    match (parts.next(), parts.next()) {
+        (Some(dated), Some(id)) => Some((
+            NaiveDate::parse_from_str(dated, "%Y-%m-%d").ok()?,
+            Uuid::parse_str(id).ok()?,
+        )),
+        _ => None,
+    }
+}
+
+// Transactions whose cards no longer parse still go through the ledger,
+// only without any cards, so that what was paid is not lost.
+fn ledger_entry(lookup: &card::Map, txn: &db::Transaction) -> basis::Entry {
+    match (
+        cdif::File::from_string(&txn.gain),
+        cdif::File::from_string(&txn.loss),
+    ) {
+        (Ok(gain), Ok(loss)) => basis::Entry::resolve(
+            lookup,
+            txn.dated,
+            (txn.created_at, txn.id),
+            txn.disposition,
+            txn.paid,
+            &gain,
+            &loss,
+        ),
+        _ => basis::Entry {
+            dated: txn.dated,
+            recorded: (txn.created_at, txn.id),
+            collection: txn.collection,
+            transfer: txn.transfer,
+            disposition: txn.disposition,
+            paid: txn.paid,
+            gain: vec![],
+            loss: vec![],
+        },
+    }
+    .within(txn.collection, txn.transfer)
+}
+
+fn transaction_query(
+    attempt: &TransactionListingAttempt,
+) -> std::result::Result<db::TransactionQuery, String> {
+    let filter = export::Filter::parse(&attempt.from, &attempt.to, &attempt.disposition)
+        .map_err(|e| e.to_string())?;
+    let text = |s: &str| match s.trim() {
+        "" => None,
+        s => Some(s.to_string()),
+    };
+    let newest_first = match attempt.sort.trim() {
+        "" | "dated" => false,
+        "-dated" => true,
+        s => return Err(format!("invalid-sort: {}", s)),
+    };
+    let limit = match attempt.limit.trim() {
+        "" => None,
+        s => match s.parse::<i64>() {
+            Ok(n) if n > 0 && n <= TRANSACTION_PAGE_LIMIT => Some(n),
+            _ => return Err(format!("invalid-limit: {}", s)),
+        },
+    };
+    let after = match attempt.after.trim() {
+        "" => None,
+        s => match parse_transaction_cursor(s) {
+            Some(cursor) => Some(cursor),
+            None => return Err(format!("invalid-cursor: {}", s)),
+        },
+    };
+
+    Ok(db::TransactionQuery {
+        from: filter.from,
+        to: filter.to,
+        dispositions: filter.dispositions,
+        search: text(&attempt.q),
+        card: text(&attempt.card),
+        set: text(&attempt.set),
+        newest_first,
+        after,
+        limit,
+    })
+}
+
+// The largest file that can be attached to a transaction, in bytes.
+pub const ATTACHMENT_LIMIT: usize = 10 * 1024 * 1024;
+
+#[derive(Serialize)]
+pub struct Attachment {
+    pub id: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i32,
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl std::convert::From<db::Attachment> for Attachment {
+    fn from(other: db::Attachment) -> Self {
+        Self {
+            id: other.id.to_string(),
+            filename: other.filename,
+            content_type: other.content_type,
+            size: other.size,
+            sha256: other.sha256,
+            created_at: other.created_at,
         }
     }
 }
 
+// Parse the disposition of a transaction posted by hand (or from a
+// template).  Transfers only ever come in pairs, made by transfer_cards,
+// so they can't be posted on their own.
+fn postable(kind: &str) -> std::result::Result<disposition::Disposition, String> {
+    match disposition::Disposition::parse(kind).map_err(|e| e.to_string())? {
+        disposition::Disposition::Transfer => Err("invalid-disposition: transfer".to_string()),
+        kind => Ok(kind),
+    }
+}
+
+// Check that a transaction is of a known kind, and looks like one; see
+// disposition::Disposition::check.  CDIF that doesn't parse is left for
+// the collection code to complain about.
+fn transaction_kind(
+    kind: &str,
+    gain: &str,
+    loss: &str,
+    paid: Option<i32>,
+) -> std::result::Result<disposition::Disposition, String> {
+    let kind = disposition::Disposition::parse(kind).map_err(|e| e.to_string())?;
+    if let (Ok(gain), Ok(loss)) = (cdif::File::from_string(gain), cdif::File::from_string(loss)) {
+        kind.check(&gain, &loss, paid).map_err(|e| e.to_string())?;
+    }
+    Ok(kind)
+}
+
+// Check the template-able parts of a transaction template, returning
+// a failure code for the first that doesn't parse.
+fn check_template(texts: &[&str]) -> std::result::Result<(), String> {
+    template::required(texts)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+// Fill in a transaction template, for a transaction on the given date.
+// Templates without a summary make transactions named for them.
+fn instantiate(
+    t: &db::TransactionTemplate,
+    vars: &HashMap<String, String>,
+    dated: NaiveDate,
+) -> std::result::Result<TransactionCreationAttempt, String> {
+    let render = |s: &str| template::render(s, vars, dated).map_err(|e| e.to_string());
+    let summary = match render(&t.summary)? {
+        summary if summary.trim().is_empty() => t.name.to_string(),
+        summary => summary,
+    };
+    Ok(TransactionCreationAttempt {
+        summary,
+        notes: render(&t.notes)?,
+        dated,
+        disposition: t.disposition.to_string(),
+        paid: t.paid,
+        gain: render(&t.gain)?,
+        loss: render(&t.loss)?,
+    })
+}
+
+// Check that a template can be recurred with the given placeholder
+// values, between the given dates.
+fn check_recurrence(
+    t: &db::TransactionTemplate,
+    vars: &HashMap<String, String>,
+    starts_on: NaiveDate,
+    ends_on: Option<NaiveDate>,
+) -> std::result::Result<(), String> {
+    if ends_on.is_some_and(|ends_on| ends_on < starts_on) {
+        return Err("invalid-recurrence-end".to_string());
+    }
+    instantiate(t, vars, starts_on).map(|_| ())
+}
+
+// Strip any directory components a browser (or a curious client) might
+// have left on an uploaded file's name.
+fn attachment_filename(name: &str) -> Option<String> {
+    let name: String = name
+        .rsplit(&['/', '\\'][..])
+        .next()
+        .unwrap_or("")
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(255)
+        .collect();
+    match name.trim() {
+        "" | "." | ".." => None,
+        name => Some(name.to_string()),
+    }
+}
+
 #[derive(Deserialize)]
 pub struct GoalCreationAttempt {
     pub name: String,
     pub ordinal: i32,
     pub target: String,
     pub goal: String,
-    pub total: Option<i32>,
-    pub progress: Option<i32>,
+    pub deadline: Option<NaiveDate>,
+    pub budget: Option<i32>,
 }
 
 #[derive(Deserialize)]
@@ -207,8 +722,10 @@ pub struct GoalUpdateAttempt {
     pub ordinal: Option<i32>,
     pub target: Option<String>,
     pub goal: Option<String>,
-    pub total: Option<Option<i32>>,
-    pub progress: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub deadline: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub budget: Option<Option<i32>>,
 }
 
 #[derive(Serialize)]
@@ -221,8 +738,15 @@ pub struct Goal {
     pub goal: String,
     pub total: Option<i32>,
     pub progress: Option<i32>,
+    pub deadline: Option<NaiveDate>,
+    pub budget: Option<i32>, // pennies
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+
+    pub remaining_cost: Option<i32>, // pennies
+    pub unpriced: Option<u32>,
+    pub spent: Option<i32>, // pennies
+    pub projected_completion: Option<NaiveDate>,
 }
 
 impl std::convert::From<db::Goal> for Goal {
@@ -236,8 +760,15 @@ impl std::convert::From<db::Goal> for Goal {
             goal: other.goal,
             total: other.total,
             progress: other.progress,
+            deadline: other.deadline,
+            budget: other.budget,
             created_at: other.created_at,
             updated_at: other.updated_at,
+
+            remaining_cost: None,
+            unpriced: None,
+            spent: None,
+            projected_completion: None,
         }
     }
 }
@@ -297,611 +828,7031 @@ impl std::convert::From<db::Deck> for Deck {
     }
 }
 
-pub struct API {
-    db: db::Database,
+#[derive(Deserialize)]
+pub struct ShareLinkCreationAttempt {
+    pub kind: String,
+    pub deck: Option<String>,       // uuid, for decks and deck versions
+    pub collection: Option<String>, // uuid (or `_`), for collections
+    pub query: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
-impl API {
-    pub fn new(dat: db::Database) -> API {
-        API { db: dat }
-    }
+#[derive(Serialize)]
+pub struct ShareLink {
+    pub id: String, // uuid
+    pub hint: String,
+    pub kind: String,
+    pub deck: Option<String>,       // uuid
+    pub collection: Option<String>, // uuid
+    pub query: String,
+    pub live: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
 
-    pub fn guard(&self, sid: Option<String>, uid: &str) -> Option<i16> {
-        let sid = match sid {
-            None => return Some(401),
-            Some(sid) => match Uuid::parse_str(&sid) {
-                Err(_) => return Some(401),
-                Ok(sid) => sid,
-            },
-        };
+    // as with API tokens, the token itself is only ever handed back
+    // when the link is first created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
 
-        match self.db.get_session(sid) {
-            Err(_) => Some(403),
-            Ok(None) => Some(401),
-            Ok(Some(session)) => match session.attrs.get("user-id") {
-                Some(id) if id == uid => None,
-                Some(_) => Some(403),
-                None => Some(401),
-            },
+impl std::convert::From<db::ShareLink> for ShareLink {
+    fn from(other: db::ShareLink) -> ShareLink {
+        ShareLink {
+            live: other.is_live(Utc::now()),
+            id: other.id.to_string(),
+            hint: other.hint,
+            kind: other.kind,
+            deck: other.deck.map(|id| id.to_string()),
+            collection: other.collection.map(|id| id.to_string()),
+            query: other.query,
+            expires_at: other.expires_at,
+            revoked_at: other.revoked_at,
+            created_at: other.created_at,
+            token: None,
         }
     }
+}
 
-    pub fn guard_bulk(&self, sid: Option<String>, bulk: &str) -> Option<i16> {
-        match sid {
-            Some(sid) if sid == bulk => None,
-            Some(_) => Some(403),
-            None => Some(401),
-        }
-    }
+// What someone following a share link gets to see.  This is built up
+// field by field, rather than reusing Deck or Collection, so that
+// nothing private (notes, payments, email) can leak out through it.
+#[derive(Serialize)]
+pub struct Shared {
+    pub kind: String,
+    pub owner: String, // username
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deck: Option<SharedDeck>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<SharedCollection>,
+}
 
-    pub fn retrieve(&self, rel: &str) -> Result<std::fs::File> {
-        Ok(self.db.get_file(rel).chain_err(|| "unable to get file")?)
-    }
+#[derive(Serialize)]
+pub struct SharedDeck {
+    pub title: String,
+    pub code: String,
+    pub description: String,
+    pub main: String,
+    pub side: String,
+    pub maybe: String,
+    pub ordinal: i32,
+    pub updated_at: DateTime<Utc>,
+}
 
-    pub fn store<R: Read>(&self, rel: &str, from: R) -> Result<std::fs::File> {
-        Ok(self
-            .db
-            .put_file(rel, from)
-            .chain_err(|| "unable to overwrite file")?)
+impl std::convert::From<db::Deck> for SharedDeck {
+    fn from(other: db::Deck) -> SharedDeck {
+        SharedDeck {
+            title: other.title,
+            code: other.code,
+            description: other.description,
+            main: other.main,
+            side: other.side,
+            maybe: other.maybe,
+            ordinal: other.ordinal,
+            updated_at: other.updated_at,
+        }
     }
+}
 
-    pub fn whoami(&self, sid: Option<String>) -> Object {
-        let sid = match sid {
-            None => return Object::fail("not-authenticated"),
-            Some(sid) => match Uuid::parse_str(&sid) {
-                Ok(sid) => sid,
-                _ => return Object::fail("invalid-session-id"),
-            },
-        };
+#[derive(Serialize)]
+pub struct SharedCollection {
+    pub name: String,
+    pub query: String,
+    pub cards: Vec<share::Card>,
+}
 
-        let session = match self.db.get_session(sid) {
-            Ok(Some(session)) => session,
-            _ => return Object::fail("invalid-session-id"),
-        };
+#[derive(Deserialize)]
+pub struct ApiTokenCreationAttempt {
+    pub name: String,
+    pub scopes: Vec<String>,
+}
 
-        let uid = match session.attrs.get("user-id") {
-            Some(uid) => match Uuid::parse_str(&uid) {
-                Ok(uid) => uid,
-                _ => return Object::fail("invalid-session-id"),
-            },
-            _ => return Object::fail("invalid-session-id"),
-        };
+#[derive(Serialize)]
+pub struct ApiToken {
+    pub id: String, // uuid
+    pub name: String,
+    pub hint: String,
+    pub scopes: Vec<String>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+
+    // the token itself, which is only ever handed back once, when the
+    // token is first created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl std::convert::From<db::ApiToken> for ApiToken {
+    fn from(other: db::ApiToken) -> ApiToken {
+        ApiToken {
+            id: other.id.to_string(),
+            name: other.name,
+            hint: other.hint,
+            scopes: other.scopes,
+            last_used_at: other.last_used_at,
+            revoked_at: other.revoked_at,
+            created_at: other.created_at,
+            token: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SeatAttempt {
+    pub name: String,
+    pub collector: Option<String>,
+    pub bot: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PodCreationAttempt {
+    pub set: String,
+    pub rounds: Option<usize>,
+    pub seats: Vec<SeatAttempt>,
+}
+
+#[derive(Deserialize)]
+pub struct PickAttempt {
+    pub card: String,
+}
+
+// What everyone at the table gets to see of a seat: who is sitting
+// there, and how far along they are, but not what they are holding.
+#[derive(Serialize)]
+pub struct PodSeat {
+    pub name: String,
+    pub collector: Option<String>, // uuid, for human drafters
+    pub bot: Option<String>,
+    pub picked: bool,
+    pub pack: usize,  // cards left in the pack in front of them
+    pub picks: usize, // cards picked so far
+}
+
+impl std::convert::From<&draft::Seat> for PodSeat {
+    fn from(other: &draft::Seat) -> PodSeat {
+        PodSeat {
+            name: other.name.to_string(),
+            collector: other.collector.clone(),
+            bot: other.bot.clone(),
+            picked: other.picked,
+            pack: other.pack.len(),
+            picks: other.picks.len(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Pod {
+    pub id: String,        // uuid
+    pub collector: String, // uuid
+    pub set: String,
+    pub finished: bool,
+    pub round: usize,
+    pub rounds: usize,
+    pub waiting_on: Vec<usize>,
+    pub seats: Vec<PodSeat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seat: Option<usize>, // the drafter looking at the pod,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pack: Option<Vec<String>>, // ... the pack they hold,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub picks: Option<Vec<String>>, // ... and what they've picked
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Pod {
+    // A pod as seen from one of its seats, or from none of them.  Only
+    // that seat's own pack and picks are let out; the rest of the table
+    // is just names and counts.
+    fn seen_from(other: db::Pod, seat: Option<usize>) -> Pod {
+        let (round, rounds, waiting_on, seats) =
+            match serde_json::from_value::<draft::Pod>(other.state) {
+                Ok(state) => (state.round, state.rounds, state.waiting_on(), state.seats),
+                Err(_) => (0, 0, vec![], vec![]),
+            };
+        let (pack, picks) = match seat.and_then(|i| seats.get(i)) {
+            Some(mine) => (Some(mine.pack.clone()), Some(mine.picks.clone())),
+            None => (None, None),
+        };
+
+        Pod {
+            id: other.id.to_string(),
+            collector: other.collector.to_string(),
+            set: other.set_code,
+            finished: other.finished,
+            round,
+            rounds,
+            waiting_on,
+            seats: seats.iter().map(PodSeat::from).collect(),
+            seat: pack.as_ref().and(seat),
+            pack,
+            picks,
+            created_at: other.created_at,
+            updated_at: other.updated_at,
+        }
+    }
+}
+
+impl std::convert::From<db::Pod> for Pod {
+    fn from(other: db::Pod) -> Pod {
+        Pod::seen_from(other, None)
+    }
+}
+
+#[derive(Serialize)]
+pub struct PriceHistory {
+    pub id: String, // print id
+    pub dates: Vec<NaiveDate>,
+    #[serde(flatten)]
+    pub prices: card::Series,
+}
+
+#[derive(Deserialize)]
+pub struct WatchlistCreationAttempt {
+    pub card: String,
+    pub scope: Option<String>,
+    pub finish: Option<String>,
+    pub currency: Option<String>,
+    pub below: Option<i32>,
+    pub above: Option<i32>,
+    pub notes: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct WatchlistUpdateAttempt {
+    pub finish: Option<String>,
+    pub currency: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub below: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub above: Option<Option<i32>>,
+    pub notes: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Watchlist {
+    pub id: String,        // uuid
+    pub collector: String, // uuid
+    pub card: String,      // print or oracle id
+    pub scope: String,
+    pub finish: String,
+    pub currency: String,
+    pub below: Option<i32>,
+    pub above: Option<i32>,
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl std::convert::From<db::Watchlist> for Watchlist {
+    fn from(other: db::Watchlist) -> Watchlist {
+        Watchlist {
+            id: other.id.to_string(),
+            collector: other.collector.to_string(),
+            card: other.card,
+            scope: other.scope,
+            finish: other.finish,
+            currency: other.currency,
+            below: other.below,
+            above: other.above,
+            notes: other.notes,
+            created_at: other.created_at,
+            updated_at: other.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Alert {
+    pub id: String,        // uuid
+    pub watchlist: String, // uuid
+    pub card: String,      // print id
+    pub direction: String,
+    pub threshold: i32,
+    pub price: i32,
+    pub currency: String,
+    pub dated: NaiveDate,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl std::convert::From<db::Alert> for Alert {
+    fn from(other: db::Alert) -> Alert {
+        Alert {
+            id: other.id.to_string(),
+            watchlist: other.watchlist.to_string(),
+            card: other.card,
+            direction: other.direction,
+            threshold: other.threshold,
+            price: other.price,
+            currency: other.currency,
+            dated: other.dated,
+            delivered_at: other.delivered_at,
+            created_at: other.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct WishlistCreationAttempt {
+    pub name: String,
+    pub description: Option<String>,
+    pub cards: String,
+}
+
+#[derive(Deserialize)]
+pub struct WishlistUpdateAttempt {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub cards: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Wishlist {
+    pub id: String,        // uuid
+    pub collector: String, // uuid
+    pub name: String,
+    pub description: String,
+    pub cards: String, // CDIF
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl std::convert::From<db::Wishlist> for Wishlist {
+    fn from(other: db::Wishlist) -> Wishlist {
+        Wishlist {
+            id: other.id.to_string(),
+            collector: other.collector.to_string(),
+            name: other.name,
+            description: other.description,
+            cards: other.cards,
+            created_at: other.created_at,
+            updated_at: other.updated_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TradeProposalAttempt {
+    pub with: String, // uuid of the other collector
+    #[serde(default)]
+    pub offer: String, // CDIF
+    #[serde(default)]
+    pub request: String, // CDIF
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct TradeCounterAttempt {
+    #[serde(default)]
+    pub offer: String, // CDIF
+    #[serde(default)]
+    pub request: String, // CDIF
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct Trade {
+    pub id: String,        // uuid
+    pub proposer: String,  // uuid
+    pub recipient: String, // uuid
+    pub offer: String,     // CDIF
+    pub request: String,   // CDIF
+    pub message: String,
+    pub status: String,
+    pub parent: Option<String>,                // uuid
+    pub proposer_transaction: Option<String>,  // uuid
+    pub recipient_transaction: Option<String>, // uuid
+    pub balance: Option<trade::Balance>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl std::convert::From<db::Trade> for Trade {
+    fn from(other: db::Trade) -> Trade {
+        Trade {
+            id: other.id.to_string(),
+            proposer: other.proposer.to_string(),
+            recipient: other.recipient.to_string(),
+            offer: other.offer,
+            request: other.request,
+            message: other.message,
+            status: other.status,
+            parent: other.parent.map(|id| id.to_string()),
+            proposer_transaction: other.proposer_transaction.map(|id| id.to_string()),
+            recipient_transaction: other.recipient_transaction.map(|id| id.to_string()),
+            balance: None,
+            created_at: other.created_at,
+            updated_at: other.updated_at,
+        }
+    }
+}
+
+// Lend cards out, either to another collector (by uuid), or to anyone
+// else (by name).  Loans start today, unless told otherwise.
+#[derive(Deserialize)]
+pub struct LoanCreationAttempt {
+    pub borrower: Option<String>,
+    pub with: Option<String>, // uuid
+    pub cards: String,
+    pub notes: Option<String>,
+    pub lent_on: Option<NaiveDate>,
+    pub due_on: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+pub struct LoanUpdateAttempt {
+    pub borrower: Option<String>,
+    pub cards: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub due_on: Option<Option<NaiveDate>>,
+}
+
+#[derive(Deserialize)]
+pub struct LoanReturnAttempt {
+    pub returned_on: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+pub struct SlabValueAttempt {
+    pub value: i32, // pennies
+    pub notes: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Loan {
+    pub id: String,        // uuid
+    pub collector: String, // uuid
+    pub borrower: String,
+    pub borrower_collector: Option<String>, // uuid
+    pub cards: String,                      // CDIF
+    pub notes: String,
+    pub lent_on: NaiveDate,
+    pub due_on: Option<NaiveDate>,
+    pub returned_on: Option<NaiveDate>,
+    pub overdue: Option<i64>, // days, for open loans past due
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl std::convert::From<db::Loan> for Loan {
+    fn from(other: db::Loan) -> Loan {
+        let today = Utc::now().naive_utc().date();
+        Loan {
+            id: other.id.to_string(),
+            collector: other.collector.to_string(),
+            borrower: other.borrower,
+            borrower_collector: other.borrower_collector.map(|c| c.to_string()),
+            cards: other.cards,
+            notes: other.notes,
+            lent_on: other.lent_on,
+            due_on: other.due_on,
+            returned_on: other.returned_on,
+            overdue: match (other.due_on, other.returned_on) {
+                (Some(due), None) if due < today => Some((today - due).num_days()),
+                _ => None,
+            },
+            created_at: other.created_at,
+            updated_at: other.updated_at,
+        }
+    }
+}
+
+// Sealed product comes in by the box (or pack, or bundle); the number of
+// packs in each is worked out from the kind, unless given.
+#[derive(Deserialize)]
+pub struct SealedProductCreationAttempt {
+    pub kind: String,
+    pub name: Option<String>,
+    pub set: String,
+    pub packs: Option<i32>,
+    pub quantity: Option<i32>,
+    pub paid: Option<i32>, // each, in pennies
+    pub acquired_on: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SealedProductUpdateAttempt {
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub set: Option<String>,
+    pub packs: Option<i32>,
+    pub quantity: Option<i32>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub paid: Option<Option<i32>>,
+    pub acquired_on: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+// Open one of a sealed product; `gain` is everything that came out of
+// it, as CDIF.
+#[derive(Deserialize)]
+pub struct OpeningAttempt {
+    pub dated: Option<NaiveDate>,
+    pub summary: Option<String>,
+    pub notes: Option<String>,
+    pub gain: String,
+}
+
+#[derive(Serialize)]
+pub struct SealedProduct {
+    pub id: String,        // uuid
+    pub collector: String, // uuid
+    pub kind: String,
+    pub name: String,
+    pub set: String,
+    pub packs: i32,
+    pub quantity: i32,
+    pub paid: Option<i32>,
+    pub acquired_on: NaiveDate,
+    pub notes: String,
+    pub pack_ev: Option<i64>, // pennies, per pack
+    pub ev: Option<i64>,      // pennies, for each one
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SealedProduct {
+    fn from(
+        other: db::SealedProduct,
+        pool: &card::Pool,
+        prices: &card::Prices,
+        currency: card::Currency,
+    ) -> SealedProduct {
+        let pack_ev = sealed::Kind::parse(&other.kind)
+            .and_then(|kind| sealed::pack_ev(pool, prices, &other.set_code, kind, currency));
+        let ev = pack_ev.map(|ev| ev * other.packs as i64);
+        SealedProduct {
+            id: other.id.to_string(),
+            collector: other.collector.to_string(),
+            kind: other.kind,
+            name: other.name,
+            set: other.set_code,
+            packs: other.packs,
+            quantity: other.quantity,
+            paid: other.paid,
+            acquired_on: other.acquired_on,
+            notes: other.notes,
+            pack_ev,
+            ev,
+            created_at: other.created_at,
+            updated_at: other.updated_at,
+        }
+    }
+}
+
+// Everything a collector has yet to open, with totals for what it
+// cost, and what it should be worth opened.
+#[derive(Serialize)]
+pub struct SealedInventory {
+    pub products: Vec<SealedProduct>,
+    pub sealed: i64, // how many unopened
+    pub paid: i64,   // pennies
+    pub ev: i64,     // pennies
+}
+
+// Save a transaction to make again later; any of summary, notes, gain
+// and loss can have {placeholders} in them (see template.rs).  Made
+// transactions go to the named collection (the default one, if none).
+#[derive(Deserialize)]
+pub struct TemplateCreationAttempt {
+    pub collection: Option<String>, // uuid
+    pub name: String,
+    pub summary: Option<String>,
+    pub notes: Option<String>,
+    pub disposition: String,
+    pub gain: Option<String>,
+    pub loss: Option<String>,
+    pub paid: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct TemplateUpdateAttempt {
+    pub collection: Option<String>, // uuid
+    pub name: Option<String>,
+    pub summary: Option<String>,
+    pub notes: Option<String>,
+    pub disposition: Option<String>,
+    pub gain: Option<String>,
+    pub loss: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub paid: Option<Option<i32>>,
+}
+
+// Make a transaction from a template, today unless told otherwise.
+// `paid`, if given, is used instead of what the template says.
+#[derive(Deserialize)]
+pub struct TemplateUseAttempt {
+    pub dated: Option<NaiveDate>,
+    pub vars: Option<HashMap<String, String>>,
+    pub paid: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct TransactionTemplate {
+    pub id: String,         // uuid
+    pub collector: String,  // uuid
+    pub collection: String, // uuid
+    pub name: String,
+    pub summary: String,
+    pub notes: String,
+    pub disposition: String,
+    pub gain: String,
+    pub loss: String,
+    pub paid: Option<i32>,
+    pub placeholders: Vec<String>, // that need values
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl std::convert::From<db::TransactionTemplate> for TransactionTemplate {
+    fn from(other: db::TransactionTemplate) -> Self {
+        let placeholders =
+            template::required(&[&other.summary, &other.notes, &other.gain, &other.loss])
+                .unwrap_or_default();
+        Self {
+            id: other.id.to_string(),
+            collector: other.collector.to_string(),
+            collection: other.collection.to_string(),
+            name: other.name,
+            summary: other.summary,
+            notes: other.notes,
+            disposition: other.disposition.to_string(),
+            gain: other.gain,
+            loss: other.loss,
+            paid: other.paid,
+            placeholders,
+            created_at: other.created_at,
+            updated_at: other.updated_at,
+        }
+    }
+}
+
+// Make a transaction from a template every so often (starting today,
+// unless told otherwise), with the given placeholder values.
+#[derive(Deserialize)]
+pub struct RecurringTransactionCreationAttempt {
+    pub template: String, // uuid
+    pub every: String,
+    pub starts_on: Option<NaiveDate>,
+    pub ends_on: Option<NaiveDate>,
+    pub vars: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+pub struct RecurringTransactionUpdateAttempt {
+    pub every: Option<String>,
+    pub starts_on: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub ends_on: Option<Option<NaiveDate>>,
+    pub vars: Option<HashMap<String, String>>,
+}
+
+#[derive(Serialize)]
+pub struct RecurringTransaction {
+    pub id: String,        // uuid
+    pub collector: String, // uuid
+    pub template: String,  // uuid
+    pub every: String,
+    pub starts_on: NaiveDate,
+    pub ends_on: Option<NaiveDate>,
+    pub vars: HashMap<String, String>,
+    pub occurrences: i32,
+    pub next_on: Option<NaiveDate>, // none once it has ended
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl std::convert::From<db::RecurringTransaction> for RecurringTransaction {
+    fn from(other: db::RecurringTransaction) -> Self {
+        let next_on = match other.ends_on {
+            Some(ends_on) if other.next_on > ends_on => None,
+            _ => Some(other.next_on),
+        };
+        Self {
+            id: other.id.to_string(),
+            collector: other.collector.to_string(),
+            template: other.template.to_string(),
+            every: other.every,
+            starts_on: other.starts_on,
+            ends_on: other.ends_on,
+            vars: serde_json::from_value(other.vars).unwrap_or_default(),
+            occurrences: other.occurrences,
+            next_on,
+            created_at: other.created_at,
+            updated_at: other.updated_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LocationCreationAttempt {
+    pub name: String,
+    pub kind: String,
+    pub parent: Option<String>, // uuid
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LocationUpdateAttempt {
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Location {
+    pub id: String,        // uuid
+    pub collector: String, // uuid
+    pub parent: Option<String>,
+    pub name: String,
+    pub kind: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl std::convert::From<db::Location> for Location {
+    fn from(other: db::Location) -> Location {
+        Location {
+            id: other.id.to_string(),
+            collector: other.collector.to_string(),
+            parent: other.parent.map(|p| p.to_string()),
+            name: other.name,
+            kind: other.kind,
+            description: other.description,
+            created_at: other.created_at,
+            updated_at: other.updated_at,
+        }
+    }
+}
+
+// Move cards (as CDIF) between two locations, by name.  Leave off the
+// source to put unstored cards away, or the destination to take cards
+// out of storage altogether.
+#[derive(Deserialize)]
+pub struct MoveAttempt {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub cards: String,
+}
+
+// Pull the cards on a deck's pick list out of storage, and into the
+// named deck box.
+#[derive(Deserialize)]
+pub struct PullAttempt {
+    pub into: String,
+}
+
+// Format an amount of money for humans, i.e. in notifications.
+fn money(pennies: i32, currency: &str) -> String {
+    let amount = format!("{}.{:02}", pennies / 100, pennies % 100);
+    match currency {
+        "usd" => format!("${}", amount),
+        "eur" => format!("€{}", amount),
+        _ => format!("{} {}", amount, currency),
+    }
+}
+
+pub struct API {
+    db: db::Database,
+}
+
+impl API {
+    pub fn new(dat: db::Database) -> API {
+        API { db: dat }
+    }
+
+    // Who a session belongs to, or the HTTP status to turn them away with.
+    fn session_user(&self, sid: Option<String>) -> std::result::Result<String, i16> {
+        let sid = match sid {
+            None => return Err(401),
+            Some(sid) => match Uuid::parse_str(&sid) {
+                Err(_) => return Err(401),
+                Ok(sid) => sid,
+            },
+        };
+
+        let session = match self.db.get_session(sid) {
+            Err(_) => return Err(403),
+            Ok(None) => return Err(401),
+            Ok(Some(session)) => session,
+        };
+        let who = match session.attrs.get("user-id").map(|id| Uuid::parse_str(id)) {
+            Some(Ok(uid)) => match self.db.find_collector_by_uuid(uid) {
+                Err(_) => return Err(403),
+                Ok(who) => who,
+            },
+            _ => None,
+        };
+        match who {
+            Some(who) if is_current(&session, &who) => Ok(who.id.to_string()),
+            _ => Err(401),
+        }
+    }
+
+    // Who a personal API token belongs to, so long as it hasn't been
+    // revoked and was given the scope the route needs.
+    fn token_user(&self, t: &str, need: Option<token::Scope>) -> std::result::Result<String, i16> {
+        let found = match self.db.find_api_token_by_hash(&token::hash(t)) {
+            Ok(Some(found)) if found.revoked_at.is_none() => found,
+            Ok(_) => return Err(401),
+            Err(e) => {
+                println!("api-token-lookup-failed: {}", e);
+                return Err(403);
+            }
+        };
+        match need {
+            Some(need) if found.scopes.iter().any(|s| s == need.as_str()) => (),
+            _ => return Err(403),
+        }
+
+        if let Err(e) = self.db.touch_api_token(&found) {
+            println!("api-token-touch-failed: {}", e);
+        }
+        Ok(found.collector.to_string())
+    }
+
+    // Who is behind the credentials a request came with: a session id,
+    // or a personal API token.  Tokens only get in where the route needs
+    // a scope (and they have it); everything else takes a session.
+    fn credential_user(
+        &self,
+        cred: Option<String>,
+        need: Option<token::Scope>,
+    ) -> std::result::Result<String, i16> {
+        match cred {
+            Some(t) if token::is_token(&t) => self.token_user(&t, need),
+            sid => self.session_user(sid),
+        }
+    }
+
+    pub fn guard(&self, sid: Option<String>, uid: &str) -> Option<i16> {
+        match self.credential_user(sid, None) {
+            Ok(id) if id == uid => None,
+            Ok(_) => Some(403),
+            Err(code) => Some(code),
+        }
+    }
+
+    // Guard one of a collector's collections (or, for `_`, the one they
+    // started out with, which stands in for their decks and goals too),
+    // letting in the collector, and anyone they've shared it with who
+    // has at least the given role.  API tokens get in only if they have
+    // the given scope.  Hands back who was let in, so that what they do
+    // can be put down to them.
+    pub fn guard_collection(
+        &self,
+        sid: Option<String>,
+        uid: &str,
+        cid: &str,
+        need: role::Role,
+        scope: Option<token::Scope>,
+    ) -> std::result::Result<String, i16> {
+        let actor = self.credential_user(sid, scope)?;
+        if actor == uid {
+            return Ok(actor);
+        }
+
+        match self.may(&actor, uid, cid, need) {
+            Ok(true) => Ok(actor),
+            Ok(false) => Err(403),
+            Err(e) => {
+                println!("collection-guard-failed: {}", e);
+                Err(403)
+            }
+        }
+    }
+
+    // Whether someone can do something that needs (at least) the given
+    // role, with one of a collector's collections.
+    fn may(&self, who: &str, uid: &str, cid: &str, need: role::Role) -> Result<bool> {
+        let (who, uid) = match (Uuid::parse_str(who), Uuid::parse_str(uid)) {
+            (Ok(who), Ok(uid)) => (who, uid),
+            _ => return Ok(false),
+        };
+        Ok(match self.owned_collection(uid, cid)? {
+            Some(collection) => self
+                .role_of(who, &collection)?
+                .is_some_and(|role| role.allows(need)),
+            None => false,
+        })
+    }
+
+    // What someone can do with a collection: everything, if it's theirs,
+    // or whatever their (accepted) membership says, if it isn't.
+    fn role_of(&self, who: Uuid, collection: &db::Collection) -> Result<Option<role::Role>> {
+        if collection.collector == who {
+            return Ok(Some(role::Role::Owner));
+        }
+        Ok(self
+            .db
+            .find_collection_member(collection.id, who)
+            .chain_err(|| "unable to find collection member")?
+            .filter(|member| member.accepted_at.is_some())
+            .and_then(|member| role::Role::parse(&member.role).ok()))
+    }
+
+    pub fn guard_bulk(&self, sid: Option<String>, bulk: &str) -> Option<i16> {
+        match sid {
+            Some(sid) if sid == bulk => None,
+            Some(_) => Some(403),
+            None => Some(401),
+        }
+    }
+
+    pub fn retrieve(&self, rel: &str) -> Result<std::fs::File> {
+        Ok(self.db.get_file(rel).chain_err(|| "unable to get file")?)
+    }
+
+    pub fn store<R: Read>(&self, rel: &str, from: R) -> Result<std::fs::File> {
+        Ok(self
+            .db
+            .put_file(rel, from)
+            .chain_err(|| "unable to overwrite file")?)
+    }
+
+    pub fn whoami(&self, sid: Option<String>) -> Object {
+        let sid = match sid {
+            None => return Object::fail("not-authenticated"),
+            Some(sid) => match Uuid::parse_str(&sid) {
+                Ok(sid) => sid,
+                _ => return Object::fail("invalid-session-id"),
+            },
+        };
+
+        let session = match self.db.get_session(sid) {
+            Ok(Some(session)) => session,
+            _ => return Object::fail("invalid-session-id"),
+        };
+
+        let uid = match session.attrs.get("user-id") {
+            Some(uid) => match Uuid::parse_str(&uid) {
+                Ok(uid) => uid,
+                _ => return Object::fail("invalid-session-id"),
+            },
+            _ => return Object::fail("invalid-session-id"),
+        };
 
         let who = match self.db.find_collector_by_uuid(uid) {
-            Ok(Some(collector)) => collector,
+            Ok(Some(collector)) if is_current(&session, &collector) => collector,
             _ => return Object::fail("invalid-session-id"),
         };
 
-        Object::Authenticated(Authenticated {
-            uid: who.id.to_string(),
-            username: who.username.to_string(),
-            session: session.id.to_string(),
-        })
+        Object::Authenticated(Authenticated {
+            uid: who.id.to_string(),
+            username: who.username.to_string(),
+            session: session.id.to_string(),
+            currency: who.currency.to_string(),
+        })
+    }
+
+    pub fn authenticate(&self, a: AuthenticationAttempt) -> Result<Object> {
+        match self.db.authenticate_collector(&a.username, &a.password) {
+            Ok(Some(who)) => {
+                let mut session = db::Session::new(None);
+                session.set("user-id", &who.id.to_string());
+                session.set("generation", &who.session_generation.to_string());
+                match self.db.set_session(session) {
+                    Ok(session) => Ok(Object::Authenticated(Authenticated {
+                        uid: who.id.to_string(),
+                        username: who.username.to_string(),
+                        session: session.id.to_string(),
+                        currency: who.currency.to_string(),
+                    })),
+                    Err(e) => {
+                        println!("session-failed: {}", e);
+                        Ok(Object::fail("session-failed"))
+                    }
+                }
+            }
+            Ok(None) => {
+                println!("authentication-failed: (no user returned)",);
+                Ok(Object::fail("authentication-failed"))
+            }
+            Err(e) => {
+                println!("authentication-failed: {}", e);
+                Ok(Object::fail("authentication-failed"))
+            }
+        }
+    }
+
+    pub fn signup(&self, a: SignupAttempt) -> Result<Object> {
+        match a.valid() {
+            Ok(_) => {
+                let who = self
+                    .db
+                    .create_collector(
+                        None,
+                        db::NewCollector {
+                            username: &a.username,
+                            email: &a.email,
+                        },
+                        Some(&a.password),
+                    )
+                    .chain_err(|| "unable to sign up new collector")?;
+
+                let mut session = db::Session::new(None);
+                session.set("fresh", "signup");
+                session.set("user-id", &who.id.to_string());
+                session.set("generation", &who.session_generation.to_string());
+                match self.db.set_session(session) {
+                    Ok(session) => Ok(Object::Authenticated(Authenticated {
+                        uid: who.id.to_string(),
+                        username: who.username.to_string(),
+                        session: session.id.to_string(),
+                        currency: who.currency.to_string(),
+                    })),
+                    Err(e) => {
+                        println!("signup-failed: {}", e);
+                        Ok(Object::fail("signup-failed"))
+                    }
+                }
+            }
+            Err(e) => Ok(Object::fail(&e.to_string())),
+        }
+    }
+
+    // Issue a collector an account token, and mail it to them at the
+    // address we have on file for them.
+    fn mail_account_token(&self, who: &db::Collector, purpose: account::Purpose) -> Result<()> {
+        let secret = account::secret().chain_err(|| "unable to sign account token")?;
+        let mailer = mail::from_env().chain_err(|| "unable to set up mailer")?;
+
+        let expires = Utc::now() + purpose.lifetime();
+        let issued = self
+            .db
+            .create_account_token(
+                None,
+                db::NewAccountToken {
+                    collector: who.id,
+                    purpose: purpose.as_str(),
+                    email: &who.email,
+                    expires_at: expires,
+                },
+            )
+            .chain_err(|| "unable to issue account token")?;
+        let token = account::sign(&secret, purpose, issued.id, expires);
+
+        let (subject, body) = match purpose {
+            account::Purpose::ResetPassword => (
+                "Reset your Vault of Cardboard password",
+                format!(
+                    "Someone (hopefully you) asked to reset the password for {}.\n\n\
+                     To choose a new one, send this token to /v1/reset-password,\n\
+                     along with the new password:\n\n    {}\n\n\
+                     It can only be used once, and only for the next hour.  If you\n\
+                     didn't ask for this, you can safely ignore it.\n",
+                    who.username, token
+                ),
+            ),
+            account::Purpose::VerifyEmail => (
+                "Verify your Vault of Cardboard email address",
+                format!(
+                    "To confirm that {} is your email address, send this\n\
+                     token to /v1/verify-email:\n\n    {}\n\n\
+                     It can only be used once, and only for the next two days.\n",
+                    who.email, token
+                ),
+            ),
+        };
+        mailer
+            .send(&mail::Mail {
+                to: who.email.to_string(),
+                username: who.username.to_string(),
+                subject: subject.to_string(),
+                body,
+            })
+            .chain_err(|| format!("unable to mail {} token", purpose.as_str()))
+    }
+
+    // Check an account token's signature, purpose and expiry, returning
+    // what it claims, or the failure to respond with.
+    fn check_account_token(
+        &self,
+        token: &str,
+        purpose: account::Purpose,
+    ) -> Result<std::result::Result<account::Claims, Object>> {
+        let secret = account::secret().chain_err(|| "unable to check account token")?;
+        match account::verify(&secret, token, Utc::now()) {
+            Ok(claims) if claims.purpose == purpose => Ok(Ok(claims)),
+            Ok(_) => Ok(Err(Object::fail("invalid-token"))),
+            Err(e) => Ok(Err(Object::fail(&e.to_string()))),
+        }
+    }
+
+    // Check an account token, and use it up, returning who it was issued
+    // to (and the token's record), or the failure to respond with.
+    fn use_account_token(
+        &self,
+        token: &str,
+        purpose: account::Purpose,
+    ) -> Result<std::result::Result<(db::Collector, db::AccountToken), Object>> {
+        let claims = match self.check_account_token(token, purpose)? {
+            Ok(claims) => claims,
+            Err(fail) => return Ok(Err(fail)),
+        };
+
+        let used = match self
+            .db
+            .use_account_token(claims.id, Utc::now())
+            .chain_err(|| "unable to use account token")?
+        {
+            Some(used) => used,
+            None => return Ok(Err(Object::fail("invalid-token"))),
+        };
+        match self
+            .db
+            .find_collector_by_uuid(used.collector)
+            .chain_err(|| "unable to find collector for account token")?
+        {
+            Some(who) => Ok(Ok((who, used))),
+            None => Ok(Err(Object::fail("invalid-token"))),
+        }
+    }
+
+    // Mail a collector a password reset token, if there is anyone by that
+    // username.  This is run off to the side of the request (see the
+    // route), so that neither the answer nor how long it takes to come
+    // back can be used to find out who has an account.
+    pub fn request_password_reset(&self, a: PasswordResetRequest) -> Result<()> {
+        match self
+            .db
+            .find_collector_by_username(&a.username)
+            .chain_err(|| "unable to find collector to reset password for")?
+        {
+            Some(who) => self.mail_account_token(&who, account::Purpose::ResetPassword),
+            None => Ok(()),
+        }
+    }
+
+    // Reset a collector's password, using up the token they were mailed.
+    // Whoever had their old password may still be signed in, or have
+    // made API tokens, so all of those stop working too.  Either all of
+    // that happens, or (so that the token can be tried again) none of it.
+    pub fn reset_password(&self, a: PasswordResetAttempt) -> Result<Object> {
+        if let Err(e) = a.valid() {
+            return Ok(Object::fail(&e.to_string()));
+        }
+        let claims = match self.check_account_token(&a.token, account::Purpose::ResetPassword)? {
+            Ok(claims) => claims,
+            Err(fail) => return Ok(fail),
+        };
+
+        let reset = self.db.atomically(|| {
+            let who = match self.db.use_account_token(claims.id, Utc::now())? {
+                Some(used) => self.db.find_collector_by_uuid(used.collector)?,
+                None => None,
+            };
+            let who = match who {
+                Some(who) => self.db.set_collector_password(&who, &a.password)?,
+                None => return Ok(None),
+            };
+
+            // any other reset tokens they were sent are no good now.
+            self.db
+                .expire_account_tokens(who.id, account::Purpose::ResetPassword.as_str())?;
+            self.db.revoke_api_tokens_for_collector(who.id)?;
+            Ok(Some(self.db.end_collector_sessions(&who)?))
+        });
+        match reset {
+            Ok(Some(_)) => Ok(Object::ok("password-reset")),
+            Ok(None) => Ok(Object::fail("invalid-token")),
+            Err(e) => {
+                println!("password-reset-failed: {}", e);
+                Ok(Object::fail("password-reset-failed"))
+            }
+        }
+    }
+
+    pub fn request_email_verification(&self, uid: &str) -> Result<Object> {
+        let who = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector")?
+        {
+            Some(who) => who,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+        if who.email_verified_at.is_some() {
+            return Ok(Object::ok("email-already-verified"));
+        }
+
+        match self.mail_account_token(&who, account::Purpose::VerifyEmail) {
+            Ok(_) => Ok(Object::ok("email-verification-requested")),
+            Err(e) => {
+                println!("email-verification-failed: {}", e);
+                Ok(Object::fail("email-verification-failed"))
+            }
+        }
+    }
+
+    pub fn verify_email(&self, a: EmailVerificationAttempt) -> Result<Object> {
+        let (who, used) = match self.use_account_token(&a.token, account::Purpose::VerifyEmail)? {
+            Ok(found) => found,
+            Err(fail) => return Ok(fail),
+        };
+
+        // tokens only vouch for the address they were sent to; if the
+        // collector has changed it since, the new one needs its own.
+        if used.email != who.email {
+            return Ok(Object::fail("invalid-token"));
+        }
+        match self.db.verify_collector_email(&who) {
+            Ok(_) => Ok(Object::ok("email-verified")),
+            Err(e) => {
+                println!("email-verification-failed: {}", e);
+                Ok(Object::fail("email-verification-failed"))
+            }
+        }
+    }
+
+    pub fn update_collector(&self, uid: &str, upd: CollectorUpdateAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to update")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let currency = match upd.currency.map(|c| c.to_lowercase()) {
+            Some(c) if card::Currency::parse(&c).is_none() => {
+                return Ok(Object::fail("invalid-currency"))
+            }
+            currency => currency,
+        };
+        if currency.is_none() && upd.tradeable.is_none() {
+            return Ok(Object::ok("collector-updated"));
+        }
+
+        match self.db.update_collector(
+            &collector,
+            db::CollectorUpdate {
+                username: None,
+                email: None,
+                password: None,
+                currency: currency.as_deref(),
+                tradeable: upd.tradeable,
+            },
+        ) {
+            Ok(_) => Ok(Object::ok("collector-updated")),
+            Err(e) => {
+                println!("collector-update-failed: {}", e);
+                Ok(Object::fail("collector-update-failed"))
+            }
+        }
+    }
+
+    // Find one of a collector's collections, by its UUID, or by `_` for
+    // the collection they started out with.  Collections belonging to
+    // anyone else can't be found this way.
+    fn owned_collection(&self, uid: Uuid, cid: &str) -> Result<Option<db::Collection>> {
+        let cid = match cid {
+            "_" => uid,
+            cid => match Uuid::parse_str(cid) {
+                Ok(cid) => cid,
+                Err(_) => return Ok(None),
+            },
+        };
+        Ok(self
+            .db
+            .find_collection_by_uuid(cid)
+            .chain_err(|| "unable to find collection")?
+            .filter(|collection| collection.collector == uid))
+    }
+
+    // Resolve the collection id in a route to the UUID of one of the
+    // collector's collections, or None if they don't have one by that id.
+    pub fn resolve_collection(&self, uid: &str, cid: &str) -> Result<Option<String>> {
+        let uid = Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?;
+        Ok(self
+            .owned_collection(uid, cid)?
+            .map(|collection| collection.id.to_string()))
+    }
+
+    // Check a name for a collection, which has to be unique (ignoring
+    // case) among the collector's collections, save for the one being
+    // renamed, if any.
+    fn check_collection_name(
+        &self,
+        uid: Uuid,
+        name: &str,
+        except: Option<Uuid>,
+    ) -> Result<std::result::Result<(), String>> {
+        if name.trim().is_empty() {
+            return Ok(Err("invalid-collection-name".to_string()));
+        }
+        let taken = self
+            .db
+            .find_collections_for_collector(uid)
+            .chain_err(|| "unable to find collections for collector")?
+            .into_iter()
+            .any(|c| Some(c.id) != except && c.name.to_lowercase() == name.trim().to_lowercase());
+        match taken {
+            true => Ok(Err("collection-name-taken".to_string())),
+            false => Ok(Ok(())),
+        }
+    }
+
+    pub fn retrieve_collections(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve collections for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let mut collections = vec![];
+        let mut combined = card::Collection::new();
+        for collection in self
+            .db
+            .find_collections_for_collector(collector.id)
+            .chain_err(|| "unable to find collections for collector")?
+        {
+            let cards = self
+                .db
+                .load_collection(collection.id)
+                .chain_err(|| "unable to load collection")?;
+            collections.push(Collection::from(collection, &cards));
+            combined.absorb(cards);
+        }
+
+        Ok(Object::Collections(Collections {
+            collections,
+            cards: combined.cards.iter().map(|(n, _)| n).sum(),
+            unique: combined.cards.len(),
+        }))
+    }
+
+    // All of a collector's collections, as one collection.json aggregate.
+    pub fn retrieve_combined_collection(&self, uid: &str) -> Result<Option<String>> {
+        let uid = Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?;
+        if self
+            .db
+            .find_collector_by_uuid(uid)
+            .chain_err(|| "unable to find collector to combine collections for")?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let combined = self.load_combined_collection(uid)?;
+        Ok(Some(
+            serde_json::to_string(&card::Aggregate(combined.cards, vec![]))
+                .chain_err(|| "unable to serialize combined collection")?,
+        ))
+    }
+
+    pub fn create_collection(&self, uid: &str, new: CollectionCreationAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to create collection for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        if let Err(e) = self.check_collection_name(collector.id, &new.name, None)? {
+            return Ok(Object::fail(&e));
+        }
+
+        match self.db.create_collection(
+            None,
+            db::NewCollection {
+                collector: collector.id,
+                name: new.name.trim(),
+            },
+        ) {
+            Ok(collection) => Ok(Object::Collection(Collection::from(
+                collection,
+                &card::Collection::new(),
+            ))),
+            Err(e) => {
+                println!("collection-creation-failed: {}", e);
+                Ok(Object::fail("collection-creation-failed"))
+            }
+        }
+    }
+
+    pub fn update_collection(
+        &self,
+        uid: &str,
+        cid: &str,
+        upd: CollectionUpdateAttempt,
+    ) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to update collection for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let collection = match self.owned_collection(collector.id, cid)? {
+            Some(collection) => collection,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+        if let Some(name) = &upd.name {
+            if let Err(e) = self.check_collection_name(collector.id, name, Some(collection.id))? {
+                return Ok(Object::fail(&e));
+            }
+        }
+
+        match self.db.update_collection(
+            &collection,
+            db::UpdateCollection {
+                name: upd.name.map(|name| name.trim().to_string()),
+            },
+        ) {
+            Ok(collection) => {
+                let cards = self
+                    .db
+                    .load_collection(collection.id)
+                    .chain_err(|| "unable to load collection")?;
+                Ok(Object::Collection(Collection::from(collection, &cards)))
+            }
+            Err(e) => {
+                println!("collection-update-failed: {}", e);
+                Ok(Object::fail("collection-update-failed"))
+            }
+        }
+    }
+
+    // Delete one of a collector's collections.  The one they started
+    // out with is there to stay, and the cards in any other have to be
+    // transferred out (or otherwise disposed of) before it can go.
+    pub fn delete_collection(&self, uid: &str, cid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to delete collection for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let collection = match self.owned_collection(collector.id, cid)? {
+            Some(collection) => collection,
+            None => return Ok(Object::ok("collection-already-gone")),
+        };
+        if collection.is_default() {
+            return Ok(Object::fail("default-collection-not-removable"));
+        }
+        if !self
+            .db
+            .load_collection(collection.id)
+            .chain_err(|| "unable to load collection")?
+            .cards
+            .is_empty()
+        {
+            return Ok(Object::fail("collection-not-empty"));
+        }
+
+        match self.db.delete_collection(collection.id) {
+            Ok(true) => Ok(Object::ok("collection-removed")),
+            Ok(false) => Ok(Object::fail("collection-has-history")),
+            Err(e) => {
+                println!("collection-removal-failed: {}", e);
+                Ok(Object::fail("collection-removal-failed"))
+            }
+        }
+    }
+
+    pub fn retrieve_collection_members(&self, cid: &str) -> Result<Object> {
+        let collection = match self
+            .db
+            .find_collection_by_uuid(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            )
+            .chain_err(|| "unable to find collection to retrieve members of")?
+        {
+            Some(collection) => collection,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        let mut members = vec![];
+        for member in self
+            .db
+            .find_members_of_collection(collection.id)
+            .chain_err(|| "unable to find members of collection")?
+        {
+            let username = self.username_of(member.collector)?;
+            members.push(Member::from(member, &username));
+        }
+        Ok(Object::Members(members))
+    }
+
+    // Invite someone into a collection, by their username.  They don't
+    // get to do anything with it until they accept.
+    pub fn invite_collection_member(
+        &self,
+        cid: &str,
+        actor: &str,
+        attempt: InvitationAttempt,
+    ) -> Result<Object> {
+        let collection = match self
+            .db
+            .find_collection_by_uuid(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            )
+            .chain_err(|| "unable to find collection to invite member into")?
+        {
+            Some(collection) => collection,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        let role = match role::Role::parse(&attempt.role) {
+            Ok(role) => role,
+            Err(e) => return Ok(Object::fail(&e.to_string())),
+        };
+        let invitee = match self
+            .db
+            .find_collector_by_username(attempt.username.trim())
+            .chain_err(|| "unable to find collector to invite")?
+        {
+            Some(invitee) => invitee,
+            None => return Ok(not_found("collector", &attempt.username, None)),
+        };
+        if invitee.id == collection.collector
+            || self
+                .db
+                .find_collection_member(collection.id, invitee.id)
+                .chain_err(|| "unable to find collection member")?
+                .is_some()
+        {
+            return Ok(Object::fail("already-a-member"));
+        }
+
+        match self.db.invite_collection_member(db::NewCollectionMember {
+            collection: collection.id,
+            collector: invitee.id,
+            role: role.as_str(),
+            invited_by: Uuid::parse_str(actor).ok(),
+        }) {
+            Ok(member) => Ok(Object::Member(Member::from(member, &invitee.username))),
+            Err(e) => {
+                println!("member-invitation-failed: {}", e);
+                Ok(Object::fail("member-invitation-failed"))
+            }
+        }
+    }
+
+    pub fn update_collection_member(
+        &self,
+        cid: &str,
+        mid: &str,
+        attempt: MemberUpdateAttempt,
+    ) -> Result<Object> {
+        let member = match self
+            .db
+            .find_collection_member(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+                match Uuid::parse_str(mid) {
+                    Ok(mid) => mid,
+                    Err(_) => return Ok(not_found("member", mid, None)),
+                },
+            )
+            .chain_err(|| "unable to find collection member to update")?
+        {
+            Some(member) => member,
+            None => return Ok(not_found("member", mid, None)),
+        };
+
+        let role = match role::Role::parse(&attempt.role) {
+            Ok(role) => role,
+            Err(e) => return Ok(Object::fail(&e.to_string())),
+        };
+        match self.db.set_collection_member_role(&member, role.as_str()) {
+            Ok(member) => {
+                let username = self.username_of(member.collector)?;
+                Ok(Object::Member(Member::from(member, &username)))
+            }
+            Err(e) => {
+                println!("member-update-failed: {}", e);
+                Ok(Object::fail("member-update-failed"))
+            }
+        }
+    }
+
+    // Revoke someone's membership of a collection (or their invitation
+    // to it, if they haven't accepted yet).
+    pub fn revoke_collection_member(&self, cid: &str, mid: &str) -> Result<Object> {
+        let cid = Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?;
+        let mid = match Uuid::parse_str(mid) {
+            Ok(mid) => mid,
+            Err(_) => return Ok(Object::ok("member-already-gone")),
+        };
+        if self
+            .db
+            .find_collection_member(cid, mid)
+            .chain_err(|| "unable to find collection member to revoke")?
+            .is_none()
+        {
+            return Ok(Object::ok("member-already-gone"));
+        }
+
+        match self.db.delete_collection_member(cid, mid) {
+            Ok(_) => Ok(Object::ok("member-removed")),
+            Err(e) => {
+                println!("member-removal-failed: {}", e);
+                Ok(Object::fail("member-removal-failed"))
+            }
+        }
+    }
+
+    // The collections a collector has been invited into, accepted or not.
+    pub fn retrieve_memberships(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve memberships for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let mut memberships = vec![];
+        for member in self
+            .db
+            .find_memberships_for_collector(collector.id)
+            .chain_err(|| "unable to find memberships for collector")?
+        {
+            if let Some(membership) = self.membership(member)? {
+                memberships.push(membership);
+            }
+        }
+        Ok(Object::Memberships(memberships))
+    }
+
+    pub fn accept_membership(&self, uid: &str, cid: &str) -> Result<Object> {
+        let member = match self.find_membership(uid, cid)? {
+            Some(member) => member,
+            None => return Ok(not_found("invitation", cid, None)),
+        };
+
+        let member = match member.accepted_at {
+            Some(_) => member,
+            None => match self.db.accept_collection_membership(&member) {
+                Ok(member) => member,
+                Err(e) => {
+                    println!("invitation-acceptance-failed: {}", e);
+                    return Ok(Object::fail("invitation-acceptance-failed"));
+                }
+            },
+        };
+        let membership = self.membership(member)?;
+        Ok(Object::Memberships(membership.into_iter().collect()))
+    }
+
+    // Decline an invitation into a collection, or leave one.
+    pub fn leave_collection(&self, uid: &str, cid: &str) -> Result<Object> {
+        let member = match self.find_membership(uid, cid)? {
+            Some(member) => member,
+            None => return Ok(Object::ok("membership-already-gone")),
+        };
+
+        match self
+            .db
+            .delete_collection_member(member.collection, member.collector)
+        {
+            Ok(_) => Ok(Object::ok("membership-removed")),
+            Err(e) => {
+                println!("membership-removal-failed: {}", e);
+                Ok(Object::fail("membership-removal-failed"))
+            }
+        }
+    }
+
+    fn find_membership(&self, uid: &str, cid: &str) -> Result<Option<db::CollectionMember>> {
+        let uid = Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?;
+        let cid = match Uuid::parse_str(cid) {
+            Ok(cid) => cid,
+            Err(_) => return Ok(None),
+        };
+        self.db
+            .find_collection_member(cid, uid)
+            .chain_err(|| "unable to find collection membership")
+    }
+
+    fn membership(&self, member: db::CollectionMember) -> Result<Option<Membership>> {
+        let collection = match self
+            .db
+            .find_collection_by_uuid(member.collection)
+            .chain_err(|| "unable to find collection for membership")?
+        {
+            Some(collection) => collection,
+            None => return Ok(None),
+        };
+        Ok(Some(Membership {
+            owner_username: self.username_of(collection.collector)?,
+            owner: collection.collector.to_string(),
+            collection: collection.id.to_string(),
+            name: collection.name,
+            role: member.role,
+            accepted: member.accepted_at.is_some(),
+            created_at: member.created_at,
+        }))
+    }
+
+    fn username_of(&self, uid: Uuid) -> Result<String> {
+        Ok(self
+            .db
+            .find_collector_by_uuid(uid)
+            .chain_err(|| "unable to find collector")?
+            .map(|collector| collector.username)
+            .unwrap_or_default())
+    }
+
+    pub fn retrieve_transactions_for_collection(
+        &self,
+        cid: &str,
+        attempt: TransactionListingAttempt,
+    ) -> Result<Object> {
+        let collection = match self
+            .db
+            .find_collection_by_uuid(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            )
+            .chain_err(|| "unable to find collection to retrieve transactions for")?
+        {
+            Some(collection) => collection,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        let query = match transaction_query(&attempt) {
+            Ok(query) => query,
+            Err(e) => return Ok(Object::fail(&e)),
+        };
+
+        Ok(Object::Transactions(
+            self.with_attachments(
+                self.db
+                    .find_transactions_matching(collection.id, &query)
+                    .chain_err(|| "unable to find transactions by collection uuid")?,
+            )?,
+        ))
+    }
+
+    // Page through a collection's transactions, a page at a time.  Unlike
+    // the full listing, this always sends back a TransactionPage, so that
+    // clients can tell when they have reached the end.
+    pub fn retrieve_transaction_page_for_collection(
+        &self,
+        cid: &str,
+        attempt: TransactionListingAttempt,
+    ) -> Result<Object> {
+        let collection = match self
+            .db
+            .find_collection_by_uuid(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            )
+            .chain_err(|| "unable to find collection to retrieve transactions for")?
+        {
+            Some(collection) => collection,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        let mut query = match transaction_query(&attempt) {
+            Ok(query) => query,
+            Err(e) => return Ok(Object::fail(&e)),
+        };
+        let limit = query.limit.unwrap_or(TRANSACTION_PAGE_SIZE) as usize;
+
+        // ask for one more than will fit, to know if there is a next page.
+        query.limit = Some(limit as i64 + 1);
+        let mut txns = self
+            .db
+            .find_transactions_matching(collection.id, &query)
+            .chain_err(|| "unable to find transactions by collection uuid")?;
+        let next = if txns.len() > limit {
+            txns.truncate(limit);
+            txns.last().map(transaction_cursor)
+        } else {
+            None
+        };
+
+        Ok(Object::Page(TransactionPage {
+            transactions: self.with_attachments(txns)?,
+            next,
+        }))
+    }
+
+    pub fn post_transaction(
+        &self,
+        cid: &str,
+        actor: &str,
+        new: TransactionCreationAttempt,
+    ) -> Result<Object> {
+        let actor = Uuid::parse_str(actor).chain_err(|| "unable to parse actor uuid")?;
+        let collection = match self
+            .db
+            .find_collection_by_uuid(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            )
+            .chain_err(|| "unable to find collection to post transaction to")?
+        {
+            Some(collection) => collection,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        let kind = match postable(&new.disposition)
+            .and_then(|_| transaction_kind(&new.disposition, &new.gain, &new.loss, new.paid))
+        {
+            Ok(kind) => kind,
+            Err(e) => return Ok(Object::fail(&e)),
+        };
+        if let Err(e) = self.check_grading(collection.collector, &new.gain, &new.loss, None)? {
+            return Ok(Object::fail(&e));
+        }
+        let stowed = match self.stowage_changes(collection.collector, &new.gain, &new.loss)? {
+            Ok(changes) => changes,
+            Err(e) => return Ok(Object::fail(&e)),
+        };
+
+        match self.db.create_transaction(
+            None,
+            db::NewTransaction {
+                collection: collection.id,
+                summary: &new.summary,
+                notes: &new.notes,
+                dated: &new.dated,
+                disposition: kind,
+                gain: &new.gain,
+                loss: &new.loss,
+                paid: new.paid,
+                created_by: Some(actor),
+            },
+        ) {
+            Ok(txn) => {
+                self.refresh_goals(collection.collector);
+                self.restow(collection.collector, stowed);
+                Ok(Object::Transaction(Transaction::from(txn)))
+            }
+            Err(e) => {
+                println!("transaction-creation-failed: {}", e);
+                Ok(Object::fail("transaction-creation-failed"))
+            }
+        }
+    }
+
+    pub fn retrieve_transaction(&self, cid: &str, tid: &str) -> Result<Object> {
+        let collection = match self
+            .db
+            .find_collection_by_uuid(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            )
+            .chain_err(|| "unable to find collection to retrieve transaction from")?
+        {
+            Some(collection) => collection,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        match self
+            .db
+            .find_transaction_by_uuid(
+                collection.id,
+                Uuid::parse_str(tid).chain_err(|| "unable to parse transaction uuid")?,
+            )
+            .chain_err(|| "unable to find transaction by uuid")?
+        {
+            Some(transaction) => Ok(Object::Transaction(self.transaction(transaction)?)),
+            None => Ok(not_found("transaction", tid, None)),
+        }
+    }
+
+    pub fn update_transaction(
+        &self,
+        cid: &str,
+        tid: &str,
+        actor: &str,
+        upd: TransactionUpdateAttempt,
+    ) -> Result<Object> {
+        let actor = Uuid::parse_str(actor).chain_err(|| "unable to parse actor uuid")?;
+        let collection = match self
+            .db
+            .find_collection_by_uuid(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            )
+            .chain_err(|| "unable to find collection to update transaction for")?
+        {
+            Some(collection) => collection,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        let transaction = match self
+            .db
+            .find_transaction_by_uuid(
+                collection.id,
+                Uuid::parse_str(tid).chain_err(|| "unable to parse transaction uuid")?,
+            )
+            .chain_err(|| "unable to find transaction to update")?
+        {
+            Some(transaction) => transaction,
+            None => return Ok(not_found("transaction", tid, None)),
+        };
+
+        // transfers are edited in pairs, or not at all; only the details
+        // that leave both collections alone can change.
+        if transaction.disposition == disposition::Disposition::Transfer
+            && (upd.disposition.is_some()
+                || upd.gain.is_some()
+                || upd.loss.is_some()
+                || upd.paid.is_some())
+        {
+            return Ok(Object::fail("transfer-not-editable"));
+        }
+        if let Some(Err(e)) = upd.disposition.as_deref().map(postable) {
+            return Ok(Object::fail(&e));
+        }
+
+        // the kind of transaction has to fit what it is once updated
+        let kind = match transaction_kind(
+            upd.disposition
+                .as_deref()
+                .unwrap_or_else(|| transaction.disposition.as_str()),
+            upd.gain.as_deref().unwrap_or(&transaction.gain),
+            upd.loss.as_deref().unwrap_or(&transaction.loss),
+            upd.paid,
+        ) {
+            Ok(kind) => kind,
+            Err(e) => return Ok(Object::fail(&e)),
+        };
+        if upd.gain.is_some() || upd.loss.is_some() {
+            if let Err(e) = self.check_grading(
+                collection.collector,
+                upd.gain.as_deref().unwrap_or(&transaction.gain),
+                upd.loss.as_deref().unwrap_or(&transaction.loss),
+                Some(&transaction.gain),
+            )? {
+                return Ok(Object::fail(&e));
+            }
+        }
+
+        // work out what moves where, undoing the original transaction's
+        // storage changes (as best we can) and applying the new ones.
+        let mut stowed = vec![];
+        if upd.gain.is_some() || upd.loss.is_some() {
+            if let Ok(Ok(undo)) =
+                self.stowage_changes(collection.collector, &transaction.gain, &transaction.loss)
+            {
+                stowed.extend(undo.into_iter().map(|s| storage::Stowed {
+                    quantity: -s.quantity,
+                    ..s
+                }));
+            }
+            match self.stowage_changes(
+                collection.collector,
+                upd.gain.as_deref().unwrap_or(&transaction.gain),
+                upd.loss.as_deref().unwrap_or(&transaction.loss),
+            )? {
+                Ok(redo) => stowed.extend(redo),
+                Err(e) => return Ok(Object::fail(&e)),
+            }
+        }
+
+        // update the transaction details, selectively
+        match self.db.update_transaction(
+            &transaction,
+            db::UpdateTransaction {
+                summary: upd.summary,
+                notes: upd.notes,
+                dated: upd.dated,
+                disposition: upd.disposition.map(|_| kind),
+                gain: upd.gain,
+                loss: upd.loss,
+                paid: Some(upd.paid),
+                updated_by: Some(actor),
+            },
+        ) {
+            Ok(txn) => {
+                self.refresh_goals(collection.collector);
+                self.restow(collection.collector, stowed);
+                Ok(Object::Transaction(self.transaction(txn)?))
+            }
+            Err(e) => {
+                println!("transaction-update-failed: {}", e);
+                Ok(Object::fail("transaction-update-failed"))
+            }
+        }
+    }
+
+    pub fn delete_transaction(&self, cid: &str, tid: &str) -> Result<Object> {
+        let collection = match self
+            .db
+            .find_collection_by_uuid(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            )
+            .chain_err(|| "unable to find collection to update transaction for")?
+        {
+            Some(collection) => collection,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        let transaction = match self
+            .db
+            .find_transaction_by_uuid(
+                collection.id,
+                Uuid::parse_str(tid).chain_err(|| "unable to parse transaction uuid")?,
+            )
+            .chain_err(|| "unable to find transaction to update")?
+        {
+            Some(transaction) => transaction,
+            None => return Ok(not_found("transaction", tid, None)),
+        };
+
+        match self.db.delete_transaction(transaction.id) {
+            Ok(_) => {
+                self.refresh_goals(collection.collector);
+                self.restow(collection.collector, vec![]);
+                Ok(Object::ok("transaction-removed"))
+            }
+            Err(e) => {
+                println!("transaction-removal-failed: {}", e);
+                Ok(Object::fail("transaction-removal-failed"))
+            }
+        }
+    }
+
+    // Move cards from one of a collector's collections to another.  The
+    // collector still has the same cards, just in different places, so
+    // there's no grading, storage or goal bookkeeping to be done.
+    pub fn transfer_cards(
+        &self,
+        cid: &str,
+        actor: &str,
+        attempt: TransferAttempt,
+    ) -> Result<Object> {
+        let actor = Uuid::parse_str(actor).chain_err(|| "unable to parse actor uuid")?;
+        let from = match self
+            .db
+            .find_collection_by_uuid(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            )
+            .chain_err(|| "unable to find collection to transfer cards from")?
+        {
+            Some(collection) => collection,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+        let to = match self.owned_collection(from.collector, &attempt.to)? {
+            Some(collection) => collection,
+            None => return Ok(not_found("collection", &attempt.to, None)),
+        };
+        if to.id == from.id {
+            return Ok(Object::fail("transfer-to-same-collection"));
+        }
+        // whoever moves the cards has to own the other collection too.
+        if !self
+            .role_of(actor, &to)?
+            .is_some_and(|role| role.allows(role::Role::Owner))
+        {
+            return Ok(not_found("collection", &attempt.to, None));
+        }
+
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let cards = match trade::resolve(&lookup, &attempt.cards) {
+            Ok(cards) => cards,
+            Err(e) => return Ok(Object::fail(&e.to_string())),
+        };
+        if cards.is_empty() {
+            return Ok(Object::fail("transfer-without-cards"));
+        }
+        let dated = attempt
+            .dated
+            .unwrap_or_else(|| Utc::now().naive_utc().date());
+        let notes = attempt.notes.as_deref().unwrap_or("");
+        let (out, into) = match &attempt.summary {
+            Some(summary) => (summary.to_string(), summary.to_string()),
+            None => (
+                format!("Moved to {}", to.name),
+                format!("Moved from {}", from.name),
+            ),
+        };
+
+        // hold on to the source collection until the cards are gone from
+        // it, so that two transfers can't both move the same cards out.
+        let moved = self.db.atomically(|| {
+            if self.db.lock_collection(from.id)?.is_none() {
+                return Ok(None);
+            }
+            let held = self.db.load_collection(from.id)?;
+            if !trade::shortfall(&valuation::holdings(&held), &cards).is_empty() {
+                return Ok(Some(Err("transfer-not-owned")));
+            }
+
+            let (out, into, loss, gain) = self.db.insert_transfer(
+                db::NewTransaction {
+                    collection: from.id,
+                    summary: &out,
+                    notes,
+                    dated: &dated,
+                    disposition: disposition::Disposition::Transfer,
+                    gain: "",
+                    loss: &attempt.cards,
+                    paid: None,
+                    created_by: Some(actor),
+                },
+                db::NewTransaction {
+                    collection: to.id,
+                    summary: &into,
+                    notes,
+                    dated: &dated,
+                    disposition: disposition::Disposition::Transfer,
+                    gain: &attempt.cards,
+                    loss: "",
+                    paid: None,
+                    created_by: Some(actor),
+                },
+            )?;
+            // the next transfer out has to see these cards gone, so the
+            // files change before the lock is let go, not after.
+            self.db
+                .apply_transfer(out.collection, loss, into.collection, gain)?;
+            Ok(Some(Ok((out, into))))
+        });
+
+        match moved {
+            Ok(Some(Ok((out, into)))) => Ok(Object::Transfer(vec![
+                Transaction::from(out),
+                Transaction::from(into),
+            ])),
+            Ok(Some(Err(fail))) => Ok(Object::fail(fail)),
+            Ok(None) => Ok(not_found("collection", cid, None)),
+            Err(e) => {
+                println!("transfer-failed: {}", e);
+                Ok(Object::fail("transfer-failed"))
+            }
+        }
+    }
+
+    // Fill in the attachments for a batch of transactions.
+    fn with_attachments(&self, txns: Vec<db::Transaction>) -> Result<Vec<Transaction>> {
+        let ids: Vec<Uuid> = txns.iter().map(|t| t.id).collect();
+        let mut attached: HashMap<Uuid, Vec<Attachment>> = HashMap::new();
+        for attachment in self
+            .db
+            .find_attachments_for_transactions(&ids)
+            .chain_err(|| "unable to find attachments for transactions")?
+        {
+            attached
+                .entry(attachment.transaction)
+                .or_default()
+                .push(Attachment::from(attachment));
+        }
+
+        Ok(txns
+            .into_iter()
+            .map(|txn| {
+                let attachments = attached.remove(&txn.id).unwrap_or_default();
+                Transaction {
+                    attachments,
+                    ..Transaction::from(txn)
+                }
+            })
+            .collect())
+    }
+
+    fn transaction(&self, txn: db::Transaction) -> Result<Transaction> {
+        Ok(self.with_attachments(vec![txn])?.remove(0))
+    }
+
+    // Look up a transaction (and, optionally, one of its attachments),
+    // handing back a not-found object if either can't be found.
+    fn find_attachment(
+        &self,
+        cid: &str,
+        tid: &str,
+        aid: Option<&str>,
+    ) -> Result<std::result::Result<(db::Transaction, Option<db::Attachment>), Object>> {
+        let collection = match self
+            .db
+            .find_collection_by_uuid(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            )
+            .chain_err(|| "unable to find collection for attachment")?
+        {
+            Some(collection) => collection,
+            None => return Ok(Err(not_found("collection", cid, None))),
+        };
+
+        let transaction = match self
+            .db
+            .find_transaction_by_uuid(
+                collection.id,
+                Uuid::parse_str(tid).chain_err(|| "unable to parse transaction uuid")?,
+            )
+            .chain_err(|| "unable to find transaction for attachment")?
+        {
+            Some(transaction) => transaction,
+            None => return Ok(Err(not_found("transaction", tid, None))),
+        };
+
+        let aid = match aid {
+            Some(aid) => aid,
+            None => return Ok(Ok((transaction, None))),
+        };
+        match self
+            .db
+            .find_attachment_by_uuid(
+                transaction.id,
+                Uuid::parse_str(aid).chain_err(|| "unable to parse attachment uuid")?,
+            )
+            .chain_err(|| "unable to find attachment by uuid")?
+        {
+            Some(attachment) => Ok(Ok((transaction, Some(attachment)))),
+            None => Ok(Err(not_found("attachment", aid, None))),
+        }
+    }
+
+    pub fn attach_file<R: Read>(
+        &self,
+        cid: &str,
+        tid: &str,
+        filename: &str,
+        content_type: Option<String>,
+        from: R,
+    ) -> Result<Object> {
+        let transaction = match self.find_attachment(cid, tid, None)? {
+            Ok((transaction, _)) => transaction,
+            Err(nf) => return Ok(nf),
+        };
+        let filename = match attachment_filename(filename) {
+            Some(filename) => filename,
+            None => return Ok(Object::fail("missing-attachment-filename")),
+        };
+
+        // read one byte past the limit, so we can tell if it was hit.
+        let mut contents = vec![];
+        from.take(ATTACHMENT_LIMIT as u64 + 1)
+            .read_to_end(&mut contents)
+            .chain_err(|| "unable to read attachment contents")?;
+        if contents.is_empty() {
+            return Ok(Object::fail("empty-attachment"));
+        }
+        if contents.len() > ATTACHMENT_LIMIT {
+            return Ok(Object::fail("attachment-too-large"));
+        }
+
+        match self.db.create_attachment(
+            None,
+            &transaction,
+            &filename,
+            content_type
+                .as_deref()
+                .unwrap_or("application/octet-stream"),
+            &contents,
+        ) {
+            Ok(attachment) => Ok(Object::Attachment(Attachment::from(attachment))),
+            Err(e) => {
+                println!("attachment-creation-failed: {}", e);
+                Ok(Object::fail("attachment-creation-failed"))
+            }
+        }
+    }
+
+    // Retrieve an attachment's details and its contents, for download.
+    pub fn retrieve_attachment(
+        &self,
+        cid: &str,
+        tid: &str,
+        aid: &str,
+    ) -> Result<std::result::Result<(Attachment, std::fs::File), Object>> {
+        let attachment = match self.find_attachment(cid, tid, Some(aid))? {
+            Ok((_, Some(attachment))) => attachment,
+            Ok((_, None)) => return Ok(Err(not_found("attachment", aid, None))),
+            Err(nf) => return Ok(Err(nf)),
+        };
+        let contents = self
+            .db
+            .get_attachment_contents(&attachment)
+            .chain_err(|| "unable to retrieve attachment contents")?;
+        Ok(Ok((Attachment::from(attachment), contents)))
+    }
+
+    pub fn delete_attachment(&self, cid: &str, tid: &str, aid: &str) -> Result<Object> {
+        let attachment = match self.find_attachment(cid, tid, Some(aid))? {
+            Ok((_, Some(attachment))) => attachment,
+            Ok((_, None)) => return Ok(Object::ok("attachment-already-gone")),
+            Err(nf) => return Ok(nf),
+        };
+
+        match self.db.delete_attachment(&attachment) {
+            Ok(_) => Ok(Object::ok("attachment-removed")),
+            Err(e) => {
+                println!("attachment-removal-failed: {}", e);
+                Ok(Object::fail("attachment-removal-failed"))
+            }
+        }
+    }
+
+    pub fn create_goal(&self, uid: &str, new: GoalCreationAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to create goal for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        if let Err(e) = goal::validate(&new.target, &new.goal) {
+            return Ok(Object::fail(&e.to_string()));
+        }
+        let (total, progress) = self.evaluate_goal(collector.id, &new.target, &new.goal);
+
+        match self.db.create_goal(
+            None,
+            db::NewGoal {
+                collector: collector.id,
+                name: &new.name,
+                ordinal: new.ordinal,
+                target: &new.target,
+                goal: &new.goal,
+                total,
+                progress,
+                deadline: new.deadline,
+                budget: new.budget,
+            },
+        ) {
+            Ok(goal) => Ok(Object::Goal(Goal::from(goal))),
+            Err(e) => {
+                println!("goal-creation-failed: {}", e);
+                Ok(Object::fail("goal-creation-failed"))
+            }
+        }
+    }
+
+    pub fn update_goal(&self, uid: &str, gid: &str, upd: GoalUpdateAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to update goal for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let goal = match self
+            .db
+            .find_goal_by_uuid(
+                collector.id,
+                Uuid::parse_str(gid).chain_err(|| "unable to parse goal uuid")?,
+            )
+            .chain_err(|| "unable to find goal to update")?
+        {
+            Some(goal) => goal,
+            None => return Ok(not_found("goal", gid, None)),
+        };
+
+        let target = upd.target.as_ref().unwrap_or(&goal.target);
+        let success = upd.goal.as_ref().unwrap_or(&goal.goal);
+        if let Err(e) = goal::validate(target, success) {
+            return Ok(Object::fail(&e.to_string()));
+        }
+        let (total, progress) = self.evaluate_goal(collector.id, target, success);
+
+        match self.db.update_goal(
+            &goal,
+            db::UpdateGoal {
+                name: upd.name,
+                ordinal: upd.ordinal,
+                target: upd.target,
+                goal: upd.goal,
+                total: Some(total),
+                progress: Some(progress),
+                deadline: upd.deadline,
+                budget: upd.budget,
+            },
+        ) {
+            Ok(goal) => Ok(Object::Goal(Goal::from(goal))),
+            Err(e) => {
+                println!("goal-update-failed: {}", e);
+                Ok(Object::fail("goal-update-failed"))
+            }
+        }
+    }
+
+    pub fn delete_goal(&self, uid: &str, gid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to update goal for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let goal = match self
+            .db
+            .find_goal_by_uuid(
+                collector.id,
+                Uuid::parse_str(gid).chain_err(|| "unable to parse goal uuid")?,
+            )
+            .chain_err(|| "unable to find goal to update")?
+        {
+            Some(goal) => goal,
+            None => return Ok(Object::ok("goal-already-gone")),
+        };
+
+        match self.db.delete_goal(goal.id) {
+            Ok(_) => Ok(Object::ok("goal-removed")),
+            Err(e) => {
+                println!("goal-removal-failed: {}", e);
+                Ok(Object::fail("goal-removal-failed"))
+            }
+        }
+    }
+
+    pub fn retrieve_goals_for_collector(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve goals from")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        Ok(Object::Goals(
+            self.plan_goals(
+                &collector,
+                self.db
+                    .find_goals_for_collector(collector.id)
+                    .chain_err(|| "unable to find goals by collector uuid")?,
+            ),
+        ))
+    }
+
+    pub fn retrieve_goal(&self, uid: &str, gid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve goal for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        match self
+            .db
+            .find_goal_by_uuid(
+                collector.id,
+                Uuid::parse_str(gid).chain_err(|| "unable to parse goal uuid")?,
+            )
+            .chain_err(|| "unable to find goal by uuid")?
+        {
+            Some(goal) => Ok(Object::Goal(
+                self.plan_goals(&collector, vec![goal]).remove(0),
+            )),
+            None => Ok(not_found("goal", gid, None)),
+        }
+    }
+
+    pub fn retrieve_missing_cards_for_goal(&self, uid: &str, gid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve goal for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let goal = match self
+            .db
+            .find_goal_by_uuid(
+                collector.id,
+                Uuid::parse_str(gid).chain_err(|| "unable to parse goal uuid")?,
+            )
+            .chain_err(|| "unable to find goal by uuid")?
+        {
+            Some(goal) => goal,
+            None => return Ok(not_found("goal", gid, None)),
+        };
+
+        let (pool, collection) = self.load_pool_and_collection(collector.id)?;
+        match goal::evaluate(&pool, &collection, &goal.target, &goal.goal) {
+            Ok(progress) => Ok(Object::Missing(progress.missing)),
+            Err(e) => Ok(Object::fail(&e.to_string())),
+        }
+    }
+
+    fn load_pool_and_collection(&self, uid: Uuid) -> Result<(card::Pool, card::Collection)> {
+        Ok((
+            self.db.load_pool().chain_err(|| "unable to load card pool")?,
+            self.load_combined_collection(uid)?,
+        ))
+    }
+
+    // All of a collector's collections, taken together.  Goals, grading,
+    // storage and lending are about what the collector has, regardless
+    // of which collection it's in.
+    fn load_combined_collection(&self, uid: Uuid) -> Result<card::Collection> {
+        let mut combined = card::Collection::new();
+        for collection in self
+            .db
+            .find_collections_for_collector(uid)
+            .chain_err(|| "unable to find collections for collector")?
+        {
+            combined.absorb(
+                self.db
+                    .load_collection(collection.id)
+                    .chain_err(|| "unable to load collection")?,
+            );
+        }
+        Ok(combined)
+    }
+
+    // Evaluate a goal against the card pool and the collector's current
+    // collection.  If either of those cannot be loaded, the goal is left
+    // unevaluated; the next transaction will try again.
+    fn evaluate_goal(&self, uid: Uuid, target: &str, success: &str) -> (Option<i32>, Option<i32>) {
+        let (pool, collection) = match self.load_pool_and_collection(uid) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                println!("goal-evaluation-failed: {}", e);
+                return (None, None);
+            }
+        };
+
+        match goal::evaluate(&pool, &collection, target, success) {
+            Ok(p) => (Some(p.total), Some(p.progress)),
+            Err(e) => {
+                println!("goal-evaluation-failed: {}", e);
+                (None, None)
+            }
+        }
+    }
+
+    // Work out what it will take to finish each goal: the cost of the
+    // missing cards, what has been spent so far, and when it should be
+    // done.  If the card pool, prices or collection can't be loaded, the
+    // goals are returned as-is.
+    fn plan_goals(&self, collector: &db::Collector, goals: Vec<db::Goal>) -> Vec<Goal> {
+        let uid = collector.id;
+        let loaded = self.load_pool_and_collection(uid).and_then(|(pool, collection)| {
+            Ok((
+                pool,
+                collection,
+                self.db.load_prices().chain_err(|| "unable to load prices")?,
+                self.db.load_lookup().chain_err(|| "unable to load lookup table")?,
+                self.collector_transactions(uid)?,
+            ))
+        });
+        let (pool, collection, prices, lookup, transactions) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                println!("goal-planning-failed: {}", e);
+                return goals.into_iter().map(Goal::from).collect();
+            }
+        };
+
+        let today = Utc::now().naive_utc().date();
+        let mut planned = vec![];
+        for g in goals {
+            let progress = match goal::evaluate(&pool, &collection, &g.target, &g.goal) {
+                Ok(progress) => progress,
+                Err(_) => {
+                    planned.push(Goal::from(g));
+                    continue;
+                }
+            };
+
+            let mut history = vec![];
+            for txn in &transactions {
+                // moving cards between collections brings nothing new in.
+                if txn.disposition == disposition::Disposition::Transfer {
+                    continue;
+                }
+                if let Ok(gain) = cdif::File::from_string(&txn.gain) {
+                    if let Some(c) =
+                        goal::Contribution::of(&progress, &lookup, &gain, txn.dated, txn.paid)
+                    {
+                        history.push(c);
+                    }
+                }
+            }
+
+            let plan = goal::plan(&progress, &prices, currency(collector), &history, today);
+            let mut g = Goal::from(g);
+            g.total = Some(progress.total);
+            g.progress = Some(progress.progress);
+            g.remaining_cost = Some(plan.remaining_cost);
+            g.unpriced = Some(plan.unpriced);
+            g.spent = Some(plan.spent);
+            g.projected_completion = plan.projected;
+            planned.push(g);
+        }
+        planned
+    }
+
+    // Re-evaluate all of a collector's goals, after their collection
+    // has changed.  Failures are logged, but otherwise ignored.
+    fn refresh_goals(&self, uid: Uuid) {
+        let goals = match self.db.find_goals_for_collector(uid) {
+            Ok(goals) => goals,
+            Err(e) => {
+                println!("goal-refresh-failed: {}", e);
+                return;
+            }
+        };
+        if goals.is_empty() {
+            return;
+        }
+
+        let (pool, collection) = match self.load_pool_and_collection(uid) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                println!("goal-refresh-failed: {}", e);
+                return;
+            }
+        };
+
+        for g in goals {
+            let (total, progress) = match goal::evaluate(&pool, &collection, &g.target, &g.goal) {
+                Ok(p) => (Some(p.total), Some(p.progress)),
+                Err(_) => (None, None),
+            };
+            if let Err(e) = self.db.update_goal(
+                &g,
+                db::UpdateGoal {
+                    name: None,
+                    ordinal: None,
+                    target: None,
+                    goal: None,
+                    total: Some(total),
+                    progress: Some(progress),
+                    deadline: None,
+                    budget: None,
+                },
+            ) {
+                println!("goal-refresh-failed: {}", e);
+            }
+        }
+    }
+
+    pub fn create_deck(&self, uid: &str, new: DeckCreationAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to create deck for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        match self.db.create_deck(
+            None,
+            db::NewDeck {
+                collector: collector.id,
+                title: &new.title,
+                code: &new.code,
+                description: &new.description,
+                main: &new.main,
+                side: &new.side,
+                maybe: &new.maybe,
+            },
+        ) {
+            Ok(deck) => Ok(Object::Deck(Deck::from(deck))),
+            Err(e) => {
+                println!("deck-creation-failed: {}", e);
+                Ok(Object::fail("deck-creation-failed"))
+            }
+        }
+    }
+
+    pub fn update_deck(&self, uid: &str, did: &str, upd: DeckUpdateAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to update deck for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let deck = match self
+            .db
+            .find_deck_by_uuid(
+                collector.id,
+                Uuid::parse_str(did).chain_err(|| "unable to parse deck uuid")?,
+            )
+            .chain_err(|| "unable to find deck to update")?
+        {
+            Some(deck) => deck,
+            None => return Ok(not_found("deck", did, None)),
+        };
+
+        match self.db.update_deck(
+            &deck,
+            db::UpdateDeck {
+                title: upd.title,
+                code: upd.code,
+                description: upd.description,
+                main: upd.main,
+                side: upd.side,
+                maybe: upd.maybe,
+            },
+        ) {
+            Ok(deck) => Ok(Object::Deck(Deck::from(deck))),
+            Err(e) => {
+                println!("deck-update-failed: {}", e);
+                Ok(Object::fail("deck-update-failed"))
+            }
+        }
+    }
+
+    pub fn delete_deck(&self, uid: &str, did: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to update deck for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let deck = match self
+            .db
+            .find_deck_by_uuid(
+                collector.id,
+                Uuid::parse_str(did).chain_err(|| "unable to parse deck uuid")?,
+            )
+            .chain_err(|| "unable to find deck to update")?
+        {
+            Some(deck) => deck,
+            None => return Ok(Object::ok("deck-already-gone")),
+        };
+
+        match self.db.delete_deck(deck.id) {
+            Ok(_) => Ok(Object::ok("deck-removed")),
+            Err(e) => {
+                println!("deck-removal-failed: {}", e);
+                Ok(Object::fail("deck-removal-failed"))
+            }
+        }
+    }
+
+    pub fn retrieve_decks_for_collector(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve decks from")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        Ok(Object::list_of_decks(
+            self.db
+                .find_decks_for_collector(collector.id)
+                .chain_err(|| "unable to find decks by collector uuid")?,
+        ))
+    }
+
+    pub fn retrieve_deck(&self, uid: &str, did: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve deck for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        match self
+            .db
+            .find_deck_by_uuid(
+                collector.id,
+                Uuid::parse_str(did).chain_err(|| "unable to parse deck uuid")?,
+            )
+            .chain_err(|| "unable to find deck by uuid")?
+        {
+            Some(deck) => Ok(Object::Deck(Deck::from(deck))),
+            None => Ok(not_found("deck", did, None)),
+        }
+    }
+
+    pub fn retrieve_share_links(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve share links for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        Ok(Object::Shares(
+            self.db
+                .find_share_links_for_collector(collector.id)
+                .chain_err(|| "unable to find share links for collector")?
+                .into_iter()
+                .map(ShareLink::from)
+                .collect(),
+        ))
+    }
+
+    pub fn create_share_link(&self, uid: &str, new: ShareLinkCreationAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to create share link for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let kind = match share::Kind::parse(&new.kind) {
+            Ok(kind) => kind,
+            Err(e) => return Ok(Object::fail(&e.to_string())),
+        };
+        if matches!(new.expires_at, Some(at) if at <= Utc::now()) {
+            return Ok(Object::fail("share-link-already-expired"));
+        }
+
+        let q = new.query.unwrap_or_default();
+        let q = q.trim();
+        let (deck, collection) = match kind {
+            share::Kind::Deck | share::Kind::DeckVersion => {
+                if !q.is_empty() {
+                    return Ok(Object::fail("share-query-without-collection"));
+                }
+                let did = new.deck.unwrap_or_default();
+                let deck = match Uuid::parse_str(&did) {
+                    Ok(id) => self
+                        .db
+                        .find_deck_by_uuid(collector.id, id)
+                        .chain_err(|| "unable to find deck to share")?,
+                    Err(_) => None,
+                };
+                match deck {
+                    Some(deck) => (Some(deck.id), None),
+                    None => return Ok(not_found("deck", &did, None)),
+                }
+            }
+            share::Kind::Collection => {
+                if !q.is_empty() && query::Query::parse(q).is_err() {
+                    return Ok(Object::fail("invalid-share-query"));
+                }
+                let cid = new.collection.unwrap_or_else(|| "_".to_string());
+                match self.owned_collection(collector.id, &cid)? {
+                    Some(collection) => (None, Some(collection.id)),
+                    None => return Ok(not_found("collection", &cid, None)),
+                }
+            }
+        };
+
+        let secret = share::token();
+        match self.db.create_share_link(
+            None,
+            db::NewShareLink {
+                collector: collector.id,
+                hash: &token::hash(&secret),
+                hint: &token::hint(&secret),
+                kind: kind.as_str(),
+                deck,
+                collection,
+                query: q,
+                expires_at: new.expires_at,
+            },
+        ) {
+            Ok(link) => {
+                let mut link = ShareLink::from(link);
+                link.token = Some(secret);
+                Ok(Object::Share(link))
+            }
+            Err(e) => {
+                println!("share-link-creation-failed: {}", e);
+                Ok(Object::fail("share-link-creation-failed"))
+            }
+        }
+    }
+
+    pub fn revoke_share_link(&self, uid: &str, sid: &str) -> Result<Object> {
+        let uid = Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?;
+        let link = match Uuid::parse_str(sid) {
+            Ok(id) => self
+                .db
+                .find_share_link_by_uuid(uid, id)
+                .chain_err(|| "unable to find share link to revoke")?,
+            Err(_) => None,
+        };
+        let link = match link {
+            Some(link) => link,
+            None => return Ok(not_found("share", sid, None)),
+        };
+        if link.revoked_at.is_some() {
+            return Ok(Object::ok("share-already-revoked"));
+        }
+
+        match self.db.revoke_share_link(&link) {
+            Ok(link) => Ok(Object::Share(ShareLink::from(link))),
+            Err(e) => {
+                println!("share-link-revocation-failed: {}", e);
+                Ok(Object::fail("share-link-revocation-failed"))
+            }
+        }
+    }
+
+    // Follow a share link, on behalf of someone who need not have an
+    // account.  Links that never existed, have been revoked, or have
+    // expired all look the same from the outside.
+    pub fn retrieve_shared(&self, token: &str) -> Result<Object> {
+        let link = match self
+            .db
+            .find_share_link_by_hash(&token::hash(token))
+            .chain_err(|| "unable to find share link")?
+        {
+            Some(link) if link.is_live(Utc::now()) => link,
+            _ => return Ok(not_found("share", token, None)),
+        };
+        let kind =
+            share::Kind::parse(&link.kind).chain_err(|| "unable to parse share link kind")?;
+
+        let mut shared = Shared {
+            kind: link.kind.to_string(),
+            owner: self.username_of(link.collector)?,
+            expires_at: link.expires_at,
+            deck: None,
+            collection: None,
+        };
+        match (kind, link.deck, link.collection) {
+            (share::Kind::Collection, _, Some(cid)) => {
+                let collection = match self
+                    .db
+                    .find_collection_by_uuid(cid)
+                    .chain_err(|| "unable to find shared collection")?
+                {
+                    Some(collection) => collection,
+                    None => return Ok(not_found("share", token, None)),
+                };
+                let q = match link.query.as_str() {
+                    "" => None,
+                    q => Some(query::Query::parse(q).chain_err(|| "unable to parse share query")?),
+                };
+                let pool = self
+                    .db
+                    .load_pool()
+                    .chain_err(|| "unable to load card pool")?;
+                let cards = self
+                    .db
+                    .load_collection(collection.id)
+                    .chain_err(|| "unable to load collection")?;
+                shared.collection = Some(SharedCollection {
+                    name: collection.name,
+                    query: link.query,
+                    cards: share::cards(&pool, &cards, q.as_ref()),
+                });
+            }
+            (_, Some(did), _) => {
+                let deck = match self
+                    .db
+                    .find_deck_by_uuid(link.collector, did)
+                    .chain_err(|| "unable to find shared deck")?
+                {
+                    Some(deck) => deck,
+                    None => return Ok(not_found("share", token, None)),
+                };
+                let deck = match kind {
+                    share::Kind::Deck => self
+                        .db
+                        .find_latest_deck_in_lineage(deck.lineage)
+                        .chain_err(|| "unable to find latest version of shared deck")?
+                        .unwrap_or(deck),
+                    _ => deck,
+                };
+                shared.deck = Some(SharedDeck::from(deck));
+            }
+            _ => return Ok(not_found("share", token, None)),
+        }
+        Ok(Object::Shared(shared))
+    }
+
+    pub fn retrieve_api_tokens(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve api tokens for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        Ok(Object::Tokens(
+            self.db
+                .find_api_tokens_for_collector(collector.id)
+                .chain_err(|| "unable to find api tokens for collector")?
+                .into_iter()
+                .map(ApiToken::from)
+                .collect(),
+        ))
+    }
+
+    pub fn create_api_token(&self, uid: &str, new: ApiTokenCreationAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to create api token for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let name = new.name.trim();
+        if name.is_empty() {
+            return Ok(Object::fail("invalid-token-name"));
+        }
+        let mut scopes: Vec<String> = vec![];
+        for scope in &new.scopes {
+            match token::Scope::parse(scope) {
+                Ok(scope) if !scopes.iter().any(|s| s == scope.as_str()) => {
+                    scopes.push(scope.as_str().to_string())
+                }
+                Ok(_) => (),
+                Err(e) => return Ok(Object::fail(&e.to_string())),
+            }
+        }
+        if scopes.is_empty() {
+            return Ok(Object::fail("token-without-scopes"));
+        }
+
+        let secret = token::generate();
+        match self.db.create_api_token(
+            None,
+            db::NewApiToken {
+                collector: collector.id,
+                name,
+                hash: &token::hash(&secret),
+                hint: &token::hint(&secret),
+                scopes,
+            },
+        ) {
+            Ok(created) => {
+                let mut created = ApiToken::from(created);
+                created.token = Some(secret);
+                Ok(Object::Token(created))
+            }
+            Err(e) => {
+                println!("api-token-creation-failed: {}", e);
+                Ok(Object::fail("api-token-creation-failed"))
+            }
+        }
+    }
+
+    pub fn revoke_api_token(&self, uid: &str, tid: &str) -> Result<Object> {
+        let uid = Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?;
+        let found = match Uuid::parse_str(tid) {
+            Ok(id) => self
+                .db
+                .find_api_token_by_uuid(uid, id)
+                .chain_err(|| "unable to find api token to revoke")?,
+            Err(_) => None,
+        };
+        let found = match found {
+            Some(found) => found,
+            None => return Ok(not_found("token", tid, None)),
+        };
+        if found.revoked_at.is_some() {
+            return Ok(Object::ok("token-already-revoked"));
+        }
+
+        match self.db.revoke_api_token(&found) {
+            Ok(revoked) => Ok(Object::Token(ApiToken::from(revoked))),
+            Err(e) => {
+                println!("api-token-revocation-failed: {}", e);
+                Ok(Object::fail("api-token-revocation-failed"))
+            }
+        }
+    }
+
+    pub fn create_pod(&self, uid: &str, new: PodCreationAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to create draft pod for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        // don't go looking up drafters for a table that can't be seated.
+        if new.seats.len() > *draft::SEATS.end() {
+            return Ok(Object::fail("too-many-drafters"));
+        }
+        let mut seats = vec![];
+        for seat in &new.seats {
+            seats.push(match (&seat.bot, &seat.collector) {
+                (Some(bot), _) => draft::Seat::bot(&seat.name, bot),
+                (None, Some(cid)) => {
+                    match self
+                        .db
+                        .find_collector_by_uuid(
+                            Uuid::parse_str(cid).chain_err(|| "unable to parse drafter uuid")?,
+                        )
+                        .chain_err(|| "unable to find drafter to seat in draft pod")?
+                    {
+                        Some(who) => draft::Seat::human(&seat.name, Some(&who.id.to_string())),
+                        None => return Ok(not_found("collector", cid, None)),
+                    }
+                }
+                (None, None) => draft::Seat::human(&seat.name, None),
+            });
+        }
+
+        let mut state = match draft::Pod::new(&new.set, seats, new.rounds.unwrap_or(3)) {
+            Ok(state) => state,
+            Err(e) => return Ok(Object::fail(&e.to_string())),
+        };
+
+        let pool = self.db.load_pool().chain_err(|| "unable to load card pool")?;
+        let prices = self.db.load_prices().chain_err(|| "unable to load prices")?;
+        let ctx = draft::Context::new(&pool, &prices);
+        if let Err(e) = state.advance(&ctx, &mut rand::thread_rng()) {
+            return Ok(Object::fail(&e.to_string()));
+        }
+
+        let value = serde_json::to_value(&state).chain_err(|| "unable to serialize pod")?;
+        let created = self.db.atomically(|| {
+            let pod = self.db.create_pod(
+                None,
+                db::NewPod {
+                    collector: collector.id,
+                    set_code: &state.set,
+                    state: value,
+                },
+            )?;
+
+            // an all-bot table drafts to the end right away.
+            if state.finished() {
+                return self.finish_pod(pod, &state, &ctx);
+            }
+            Ok(pod)
+        });
+        match created {
+            Ok(pod) => Ok(Object::Pod(Pod::from(pod))),
+            Err(e) => {
+                println!("pod-creation-failed: {}", e);
+                Ok(Object::fail("pod-creation-failed"))
+            }
+        }
+    }
+
+    // Save every drafter's picks as a deck, and mark the pod finished.
+    // This only ever happens inside atomically(), along with the last
+    // pick, so that a pod is never finished without its decks.
+    fn finish_pod(
+        &self,
+        pod: db::Pod,
+        state: &draft::Pod,
+        ctx: &draft::Context,
+    ) -> std::result::Result<db::Pod, db::Error> {
+        for (i, seat) in state.seats.iter().enumerate() {
+            let collector = match &seat.collector {
+                Some(cid) => Uuid::parse_str(cid)
+                    .map_err(|e| db::Error::with_chain(e, "unable to parse drafter uuid"))?,
+                None => pod.collector,
+            };
+            let main = state
+                .deck(i, ctx)
+                .map_err(|e| db::Error::with_chain(e, "unable to build deck from draft picks"))?;
+
+            self.db.create_deck(
+                None,
+                db::NewDeck {
+                    collector,
+                    title: &format!("{} ({} draft)", seat.name, state.set),
+                    code: &state.set.to_lowercase(),
+                    description: &format!("Drafted from seat {} of pod {}.", i + 1, pod.id),
+                    main: &main,
+                    side: "",
+                    maybe: "",
+                },
+            )?;
+        }
+
+        self.db.update_pod(
+            &pod,
+            db::UpdatePod {
+                state: None,
+                finished: Some(true),
+            },
+        )
+    }
+
+    // Determine which collector is allowed to pick for a given seat;
+    // seats without their own collector belong to the pod organizer.
+    pub fn pod_seat_owner(&self, pid: &str, seat: usize) -> Result<Option<String>> {
+        let pod = match self
+            .db
+            .find_pod_by_uuid(Uuid::parse_str(pid).chain_err(|| "unable to parse pod uuid")?)
+            .chain_err(|| "unable to find draft pod")?
+        {
+            Some(pod) => pod,
+            None => return Ok(None),
+        };
+
+        let state: draft::Pod =
+            serde_json::from_value(pod.state).chain_err(|| "unable to parse draft pod state")?;
+        Ok(match state.seats.get(seat) {
+            Some(draft::Seat {
+                collector: Some(cid),
+                ..
+            }) => Some(cid.to_string()),
+            Some(_) => Some(pod.collector.to_string()),
+            None => None,
+        })
+    }
+
+    pub fn pick(&self, pid: &str, seat: usize, attempt: PickAttempt) -> Result<Object> {
+        let id = Uuid::parse_str(pid).chain_err(|| "unable to parse pod uuid")?;
+        let pool = self.db.load_pool().chain_err(|| "unable to load card pool")?;
+        let prices = self.db.load_prices().chain_err(|| "unable to load prices")?;
+        let ctx = draft::Context::new(&pool, &prices);
+
+        // the pod stays locked from reading the pick to writing it (and,
+        // after the last pick, the decks), so concurrent picks queue up
+        // behind one another instead of losing updates.
+        let picked = self.db.atomically(|| {
+            let pod = match self.db.lock_pod(id)? {
+                Some(pod) => pod,
+                None => return Ok(None),
+            };
+
+            let mut state: draft::Pod = serde_json::from_value(pod.state.clone())
+                .map_err(|e| db::Error::with_chain(e, "unable to parse draft pod state"))?;
+            if let Err(e) = state.pick(seat, &attempt.card) {
+                return Ok(Some(Err(e.to_string())));
+            }
+            if let Err(e) = state.advance(&ctx, &mut rand::thread_rng()) {
+                return Ok(Some(Err(e.to_string())));
+            }
+
+            let pod = self.db.update_pod(
+                &pod,
+                db::UpdatePod {
+                    state: Some(
+                        serde_json::to_value(&state)
+                            .map_err(|e| db::Error::with_chain(e, "unable to serialize pod"))?,
+                    ),
+                    finished: None,
+                },
+            )?;
+            if state.finished() {
+                return Ok(Some(Ok(self.finish_pod(pod, &state, &ctx)?)));
+            }
+            Ok(Some(Ok(pod)))
+        });
+
+        match picked {
+            Ok(Some(Ok(pod))) => Ok(Object::Pod(Pod::seen_from(pod, Some(seat)))),
+            Ok(Some(Err(fail))) => Ok(Object::fail(&fail)),
+            Ok(None) => Ok(not_found("pod", pid, None)),
+            Err(e) => {
+                println!("pick-failed: {}", e);
+                Ok(Object::fail("pick-failed"))
+            }
+        }
+    }
+
+    pub fn retrieve_pods_for_collector(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve draft pods from")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        Ok(Object::list_of_pods(
+            self.db
+                .find_pods_for_collector(collector.id)
+                .chain_err(|| "unable to find draft pods by collector uuid")?,
+        ))
+    }
+
+    // Retrieve a draft pod, as seen by whoever is sitting in the given
+    // seat (if any); see Pod::seen_from().
+    pub fn retrieve_pod(&self, pid: &str, seat: Option<usize>) -> Result<Object> {
+        match self
+            .db
+            .find_pod_by_uuid(Uuid::parse_str(pid).chain_err(|| "unable to parse pod uuid")?)
+            .chain_err(|| "unable to find draft pod by uuid")?
+        {
+            Some(pod) => Ok(Object::Pod(Pod::seen_from(pod, seat))),
+            None => Ok(not_found("pod", pid, None)),
+        }
+    }
+
+    pub fn delete_pod(&self, uid: &str, pid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to remove draft pod for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let pod = match self
+            .db
+            .find_pod_by_uuid(Uuid::parse_str(pid).chain_err(|| "unable to parse pod uuid")?)
+            .chain_err(|| "unable to find draft pod to remove")?
+        {
+            Some(pod) if pod.collector == collector.id => pod,
+            _ => return Ok(Object::ok("pod-already-gone")),
+        };
+
+        match self.db.delete_pod(pod.id) {
+            Ok(_) => Ok(Object::ok("pod-removed")),
+            Err(e) => {
+                println!("pod-removal-failed: {}", e);
+                Ok(Object::fail("pod-removal-failed"))
+            }
+        }
+    }
+
+    pub fn retrieve_card_price_history(&self, pid: &str) -> Result<Object> {
+        let mut history = self
+            .db
+            .load_history()
+            .chain_err(|| "unable to load price history")?;
+
+        match history.prints.remove(pid) {
+            Some(mut series) => {
+                series.resize(history.dates.len());
+                Ok(Object::History(PriceHistory {
+                    id: pid.to_string(),
+                    dates: history.dates,
+                    prices: series,
+                }))
+            }
+            None => Ok(not_found("card", pid, None)),
+        }
+    }
+
+    // Find a collection by its UUID, along with the collector it
+    // belongs to.
+    fn collection_and_collector(
+        &self,
+        cid: &str,
+    ) -> Result<Option<(db::Collection, db::Collector)>> {
+        let collection = match self
+            .db
+            .find_collection_by_uuid(
+                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            )
+            .chain_err(|| "unable to find collection")?
+        {
+            Some(collection) => collection,
+            None => return Ok(None),
+        };
+        Ok(self
+            .db
+            .find_collector_by_uuid(collection.collector)
+            .chain_err(|| "unable to find collector for collection")?
+            .map(|collector| (collection, collector)))
+    }
+
+    pub fn retrieve_collection_value(&self, cid: &str) -> Result<Object> {
+        let (collection, collector) = match self.collection_and_collector(cid)? {
+            Some(found) => found,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        let history = self
+            .db
+            .load_history()
+            .chain_err(|| "unable to load price history")?;
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let cards = self
+            .db
+            .load_collection(collection.id)
+            .chain_err(|| "unable to load collection")?;
+
+        let mut changes = vec![];
+        for txn in self
+            .db
+            .find_transactions_for_collection(collection.id)
+            .chain_err(|| "unable to find transactions for collection")?
+        {
+            if let (Ok(gain), Ok(loss)) = (
+                cdif::File::from_string(&txn.gain),
+                cdif::File::from_string(&txn.loss),
+            ) {
+                changes.push(valuation::Change::resolve(&lookup, txn.dated, &gain, &loss));
+            }
+        }
+
+        Ok(Object::Valuation(valuation::timeline(
+            &history,
+            &valuation::raw_holdings(&cards),
+            changes,
+            currency(&collector),
+        )))
+    }
+
+    pub fn retrieve_movers(&self, cid: &str, days: i64, limit: usize) -> Result<Object> {
+        let (collection, collector) = match self.collection_and_collector(cid)? {
+            Some(found) => found,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        let history = self
+            .db
+            .load_history()
+            .chain_err(|| "unable to load price history")?;
+        let collection = self
+            .db
+            .load_collection(collection.id)
+            .chain_err(|| "unable to load collection")?;
+
+        Ok(Object::Movers(valuation::movers(
+            &history,
+            &valuation::raw_holdings(&collection),
+            currency(&collector),
+            days,
+            limit,
+        )))
+    }
+
+    pub fn retrieve_collection_profit(
+        &self,
+        cid: &str,
+        allocation: &str,
+        method: &str,
+    ) -> Result<Object> {
+        let (collection, collector) = match self.collection_and_collector(cid)? {
+            Some(found) => found,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        let (allocation, method) = match (
+            basis::Allocation::parse(allocation),
+            basis::Method::parse(method),
+        ) {
+            (Ok(a), Ok(m)) => (a, m),
+            (Err(e), _) | (_, Err(e)) => return Ok(Object::fail(&e.to_string())),
+        };
+
+        let history = self
+            .db
+            .load_history()
+            .chain_err(|| "unable to load price history")?;
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+
+        let entries = self
+            .collector_transactions(collector.id)?
+            .iter()
+            .map(|txn| ledger_entry(&lookup, txn))
+            .collect();
+
+        Ok(Object::Profit(
+            basis::Ledger::run(allocation, method, currency(&collector), &history, entries)
+                .report(&history, collection.id),
+        ))
+    }
+
+    // Every transaction in every one of a collector's collections.  Cost
+    // basis follows cards as they are transferred from one collection to
+    // another, and goals count cards wherever they are, so the ledger and
+    // goal planning both have to see all of them.
+    fn collector_transactions(&self, uid: Uuid) -> Result<Vec<db::Transaction>> {
+        let mut txns = vec![];
+        for c in self
+            .db
+            .find_collections_for_collector(uid)
+            .chain_err(|| "unable to find collections for collector")?
+        {
+            txns.extend(
+                self.db
+                    .find_transactions_for_collection(c.id)
+                    .chain_err(|| "unable to find transactions for collection")?,
+            );
+        }
+        Ok(txns)
+    }
+
+    // Export a collector's transactions for bookkeeping, with the cost
+    // basis of anything sold.  Basis depends on every purchase that came
+    // before a sale, so the whole history goes through the ledger, and
+    // only then is it filtered down.
+    pub fn export_transactions(
+        &self,
+        cid: &str,
+        attempt: ExportAttempt,
+    ) -> Result<std::result::Result<Export, Object>> {
+        let (collection, collector) = match self.collection_and_collector(cid)? {
+            Some(found) => found,
+            None => return Ok(Err(not_found("collection", cid, None))),
+        };
+
+        let (format, filter, allocation, method) = match (
+            export::Format::parse(&attempt.format).map_err(|e| e.to_string()),
+            export::Filter::parse(&attempt.from, &attempt.to, &attempt.disposition)
+                .map_err(|e| e.to_string()),
+            basis::Allocation::parse(&attempt.allocation).map_err(|e| e.to_string()),
+            basis::Method::parse(&attempt.method).map_err(|e| e.to_string()),
+        ) {
+            (Ok(f), Ok(w), Ok(a), Ok(m)) => (f, w, a, m),
+            (Err(e), _, _, _) | (_, Err(e), _, _) | (_, _, Err(e), _) | (_, _, _, Err(e)) => {
+                return Ok(Err(Object::fail(&e)))
+            }
+        };
+
+        let history = self
+            .db
+            .load_history()
+            .chain_err(|| "unable to load price history")?;
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+
+        let mut txns = self.collector_transactions(collector.id)?;
+        txns.sort_by_key(|t| (t.dated, t.created_at, t.id));
+
+        let entries: Vec<basis::Entry> = txns
+            .iter()
+            .map(|txn| ledger_entry(&lookup, txn))
+            .collect();
+        let bases = basis::Ledger::disposals(
+            allocation,
+            method,
+            currency(&collector),
+            &history,
+            &entries,
+        );
+
+        let rows: Vec<export::Row> = txns
+            .iter()
+            .zip(bases)
+            .filter(|(txn, _)| txn.collection == collection.id)
+            .filter(|(txn, _)| filter.matches(txn.dated, txn.disposition))
+            .map(|(txn, basis)| export::Row {
+                id: txn.id.to_string(),
+                dated: txn.dated,
+                disposition: txn.disposition,
+                summary: txn.summary.to_string(),
+                notes: txn.notes.to_string(),
+                paid: txn.paid,
+                cards_gained: txn.total_card_gain(),
+                unique_gained: txn.unique_card_gain(),
+                cards_lost: txn.total_card_loss(),
+                unique_lost: txn.unique_card_loss(),
+                basis: if txn.disposition == disposition::Disposition::Sell {
+                    Some(basis)
+                } else {
+                    None
+                },
+            })
+            .collect();
+
+        Ok(Ok(Export {
+            filename: format!("transactions.{}", format.extension()),
+            content_type: format.content_type(),
+            body: export::render(format, &rows, &collector.currency),
+        }))
+    }
+
+    // Check the grading details of graded copies in a transaction; see
+    // grading::check.  CDIF that doesn't parse is left for the rest of
+    // the transaction machinery to deal with.
+    fn check_grading(
+        &self,
+        uid: Uuid,
+        gain: &str,
+        loss: &str,
+        previous: Option<&str>,
+    ) -> Result<std::result::Result<(), String>> {
+        let (gain, loss) = match (cdif::File::from_string(gain), cdif::File::from_string(loss)) {
+            (Ok(gain), Ok(loss)) => (gain, loss),
+            _ => return Ok(Ok(())),
+        };
+        let previous = previous.and_then(|p| cdif::File::from_string(p).ok());
+        let collection = self.load_combined_collection(uid)?;
+        Ok(grading::check(&gain, &loss, &collection, previous.as_ref()).map_err(|e| e.to_string()))
+    }
+
+    pub fn retrieve_graded_copies(&self, cid: &str) -> Result<Object> {
+        let (collection, collector) = match self.collection_and_collector(cid)? {
+            Some(found) => found,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        let pool = self
+            .db
+            .load_pool()
+            .chain_err(|| "unable to load card pool")?;
+        let collection = self
+            .db
+            .load_collection(collection.id)
+            .chain_err(|| "unable to load collection")?;
+        let prices = self.db.load_prices().chain_err(|| "unable to load prices")?;
+        let values: HashMap<String, i64> = self
+            .db
+            .find_slab_values_for_collector(collector.id)
+            .chain_err(|| "unable to find slab values for collector")?
+            .into_iter()
+            .map(|v| (v.cert, v.value as i64))
+            .collect();
+
+        Ok(Object::Slabs(grading::report(
+            &pool,
+            &prices,
+            &collection,
+            &values,
+            currency(&collector),
+        )))
+    }
+
+    pub fn set_slab_value(
+        &self,
+        cid: &str,
+        cert: &str,
+        attempt: SlabValueAttempt,
+    ) -> Result<Object> {
+        let (_, collector) = match self.collection_and_collector(cid)? {
+            Some(found) => found,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        // slab values are the collector's, whichever collection the
+        // slab happens to be in.
+        let collection = self.load_combined_collection(collector.id)?;
+        if !grading::holds(&collection, cert) {
+            return Ok(not_found("slab", cert, None));
+        }
+        if attempt.value < 0 {
+            return Ok(Object::fail("invalid-slab-value"));
+        }
+
+        match self.db.set_slab_value(
+            collector.id,
+            cert,
+            attempt.value,
+            attempt.notes.as_deref().unwrap_or(""),
+        ) {
+            Ok(_) => self.retrieve_graded_copies(cid),
+            Err(e) => {
+                println!("slab-valuation-failed: {}", e);
+                Ok(Object::fail("slab-valuation-failed"))
+            }
+        }
+    }
+
+    pub fn clear_slab_value(&self, cid: &str, cert: &str) -> Result<Object> {
+        let (_, collector) = match self.collection_and_collector(cid)? {
+            Some(found) => found,
+            None => return Ok(not_found("collection", cid, None)),
+        };
+
+        match self.db.clear_slab_value(collector.id, cert) {
+            Ok(_) => Ok(Object::ok("slab-value-cleared")),
+            Err(e) => {
+                println!("slab-value-removal-failed: {}", e);
+                Ok(Object::fail("slab-value-removal-failed"))
+            }
+        }
+    }
+
+    pub fn create_watchlist(&self, uid: &str, new: WatchlistCreationAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to create watchlist for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let scope = new.scope.unwrap_or_else(|| "print".to_string());
+        let finish = new.finish.unwrap_or_else(|| "nonfoil".to_string()).to_lowercase();
+        let currency = new.currency.unwrap_or(collector.currency).to_lowercase();
+        if let Err(e) = watchlist::validate(&scope, &finish, &currency, new.below, new.above) {
+            return Ok(Object::fail(&e.to_string()));
+        }
+
+        let pool = self.db.load_pool().chain_err(|| "unable to load card pool")?;
+        let known = match watchlist::Scope::parse(&scope) {
+            Some(watchlist::Scope::Oracle) => pool.cards.contains_key(&new.card),
+            _ => pool.prints().contains_key(new.card.as_str()),
+        };
+        if !known {
+            return Ok(not_found("card", &new.card, None));
+        }
+
+        match self.db.create_watchlist(
+            None,
+            db::NewWatchlist {
+                collector: collector.id,
+                card: &new.card,
+                scope: &scope,
+                finish: &finish,
+                currency: &currency,
+                below: new.below,
+                above: new.above,
+                notes: new.notes.as_deref().unwrap_or(""),
+            },
+        ) {
+            Ok(watch) => Ok(Object::Watchlist(Watchlist::from(watch))),
+            Err(e) => {
+                println!("watchlist-creation-failed: {}", e);
+                Ok(Object::fail("watchlist-creation-failed"))
+            }
+        }
+    }
+
+    pub fn update_watchlist(
+        &self,
+        uid: &str,
+        wid: &str,
+        upd: WatchlistUpdateAttempt,
+    ) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to update watchlist for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let watch = match self
+            .db
+            .find_watchlist_by_uuid(
+                collector.id,
+                Uuid::parse_str(wid).chain_err(|| "unable to parse watchlist uuid")?,
+            )
+            .chain_err(|| "unable to find watchlist to update")?
+        {
+            Some(watch) => watch,
+            None => return Ok(not_found("watchlist", wid, None)),
+        };
+
+        let finish = upd.finish.map(|f| f.to_lowercase());
+        let currency = upd.currency.map(|c| c.to_lowercase());
+        if let Err(e) = watchlist::validate(
+            &watch.scope,
+            finish.as_ref().unwrap_or(&watch.finish),
+            currency.as_ref().unwrap_or(&watch.currency),
+            upd.below.unwrap_or(watch.below),
+            upd.above.unwrap_or(watch.above),
+        ) {
+            return Ok(Object::fail(&e.to_string()));
+        }
+
+        match self.db.update_watchlist(
+            &watch,
+            db::UpdateWatchlist {
+                finish,
+                currency,
+                below: upd.below,
+                above: upd.above,
+                notes: upd.notes,
+            },
+        ) {
+            Ok(watch) => Ok(Object::Watchlist(Watchlist::from(watch))),
+            Err(e) => {
+                println!("watchlist-update-failed: {}", e);
+                Ok(Object::fail("watchlist-update-failed"))
+            }
+        }
+    }
+
+    pub fn delete_watchlist(&self, uid: &str, wid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to remove watchlist for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let watch = match self
+            .db
+            .find_watchlist_by_uuid(
+                collector.id,
+                Uuid::parse_str(wid).chain_err(|| "unable to parse watchlist uuid")?,
+            )
+            .chain_err(|| "unable to find watchlist to remove")?
+        {
+            Some(watch) => watch,
+            None => return Ok(Object::ok("watchlist-already-gone")),
+        };
+
+        match self.db.delete_watchlist(watch.id) {
+            Ok(_) => Ok(Object::ok("watchlist-removed")),
+            Err(e) => {
+                println!("watchlist-removal-failed: {}", e);
+                Ok(Object::fail("watchlist-removal-failed"))
+            }
+        }
+    }
+
+    pub fn retrieve_watchlists_for_collector(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve watchlists from")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        Ok(Object::list_of_watchlists(
+            self.db
+                .find_watchlists_for_collector(collector.id)
+                .chain_err(|| "unable to find watchlists by collector uuid")?,
+        ))
+    }
+
+    pub fn retrieve_watchlist(&self, uid: &str, wid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve watchlist for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        match self
+            .db
+            .find_watchlist_by_uuid(
+                collector.id,
+                Uuid::parse_str(wid).chain_err(|| "unable to parse watchlist uuid")?,
+            )
+            .chain_err(|| "unable to find watchlist by uuid")?
+        {
+            Some(watch) => Ok(Object::Watchlist(Watchlist::from(watch))),
+            None => Ok(not_found("watchlist", wid, None)),
+        }
+    }
+
+    pub fn retrieve_alerts_for_collector(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve alerts for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        Ok(Object::list_of_alerts(
+            self.db
+                .find_alerts_for_collector(collector.id)
+                .chain_err(|| "unable to find alerts by collector uuid")?,
+        ))
+    }
+
+    // Check every watchlist against the latest price snapshot (i.e.
+    // after a rescry uploads a new history.json), recording an alert
+    // for each threshold crossed.  The alerts wait in the database
+    // until deliver_alerts() gets to them.
+    pub fn check_watchlists(&self) -> Result<()> {
+        let history = self
+            .db
+            .load_history()
+            .chain_err(|| "unable to load price history")?;
+        let pool = self.db.load_pool().chain_err(|| "unable to load card pool")?;
+        let dated = match history.dates.last() {
+            Some(dated) => *dated,
+            None => return Ok(()),
+        };
+
+        let mut oracles: HashMap<&str, Vec<String>> = HashMap::new();
+        for set in pool.sets.values() {
+            for print in &set.cards {
+                oracles
+                    .entry(print.oid.as_str())
+                    .or_default()
+                    .push(print.id.to_string());
+            }
+        }
+
+        for w in self
+            .db
+            .find_watchlists()
+            .chain_err(|| "unable to find watchlists")?
+        {
+            let prints = match watchlist::Scope::parse(&w.scope) {
+                Some(watchlist::Scope::Oracle) => oracles.get(w.card.as_str()).cloned().unwrap_or_default(),
+                _ => vec![w.card.to_string()],
+            };
+            let watch = watchlist::Watch {
+                prints: &prints,
+                finish: card::Finish::parse(&w.finish).unwrap_or(card::Finish::Nonfoil),
+                currency: card::Currency::parse(&w.currency).unwrap_or(card::Currency::Usd),
+                below: w.below,
+                above: w.above,
+            };
+
+            for t in watch.evaluate(&history) {
+                self.db
+                    .create_alert(
+                        None,
+                        db::NewAlert {
+                            watchlist: w.id,
+                            collector: w.collector,
+                            card: &t.card,
+                            direction: t.direction.as_str(),
+                            threshold: t.threshold,
+                            price: t.price,
+                            currency: &w.currency,
+                            dated,
+                        },
+                    )
+                    .chain_err(|| "unable to record alert")?;
+            }
+        }
+        Ok(())
+    }
+
+    // Deliver any alerts that have not yet been delivered, including
+    // those that failed last time.  Notifiers can be slow (or down), so
+    // this runs on its own, and never as part of answering a request.
+    pub fn deliver_alerts(&self) -> Result<()> {
+        let notifier = notify::from_env().chain_err(|| "unable to set up notifier")?;
+        let pool = self
+            .db
+            .load_pool()
+            .chain_err(|| "unable to load card pool")?;
+        let prints = pool.prints();
+
+        for alert in self
+            .db
+            .find_undelivered_alerts()
+            .chain_err(|| "unable to find undelivered alerts")?
+        {
+            let collector = match self.db.find_collector_by_uuid(alert.collector) {
+                Ok(Some(collector)) => collector,
+                _ => continue,
+            };
+
+            let name = match prints.get(alert.card.as_str()) {
+                Some((set, print)) => match pool.cards.get(&print.oid) {
+                    Some(oracle) => format!("{} ({})", oracle.name, set.code),
+                    None => alert.card.to_string(),
+                },
+                None => alert.card.to_string(),
+            };
+            let subject = format!(
+                "{} is now {} {}",
+                name,
+                alert.direction,
+                money(alert.threshold, &alert.currency)
+            );
+            let body = format!(
+                "{}\n\nAs of {}, {} is selling for {}.\n",
+                subject,
+                alert.dated,
+                name,
+                money(alert.price, &alert.currency)
+            );
+
+            let msg = notify::Message {
+                to: collector.email.to_string(),
+                username: collector.username.to_string(),
+                subject,
+                body,
+                data: serde_json::json!(Alert::from(alert.clone())),
+            };
+            match notifier.notify(&msg) {
+                Ok(_) => {
+                    self.db
+                        .mark_alert_delivered(&alert)
+                        .chain_err(|| "unable to mark alert as delivered")?;
+                }
+                Err(e) => println!("alert-delivery-failed: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    // Parse a wishlist against the card pool, so that we don't store
+    // wishes for cards that don't exist, or constraints we don't grok.
+    fn parse_wishes(&self, cards: &str) -> Result<std::result::Result<Vec<wishlist::Wish>, String>> {
+        let pool = self.db.load_pool().chain_err(|| "unable to load card pool")?;
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        Ok(wishlist::parse(&pool, &lookup, cards).map_err(|e| e.to_string()))
+    }
+
+    pub fn create_wishlist(&self, uid: &str, new: WishlistCreationAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to create wishlist for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        if let Err(e) = self.parse_wishes(&new.cards)? {
+            return Ok(Object::fail(&e));
+        }
+
+        match self.db.create_wishlist(
+            None,
+            db::NewWishlist {
+                collector: collector.id,
+                name: &new.name,
+                description: new.description.as_deref().unwrap_or(""),
+                cards: &new.cards,
+            },
+        ) {
+            Ok(wishes) => Ok(Object::Wishlist(Wishlist::from(wishes))),
+            Err(e) => {
+                println!("wishlist-creation-failed: {}", e);
+                Ok(Object::fail("wishlist-creation-failed"))
+            }
+        }
+    }
+
+    pub fn update_wishlist(
+        &self,
+        uid: &str,
+        wid: &str,
+        upd: WishlistUpdateAttempt,
+    ) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to update wishlist for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let wishes = match self
+            .db
+            .find_wishlist_by_uuid(
+                collector.id,
+                Uuid::parse_str(wid).chain_err(|| "unable to parse wishlist uuid")?,
+            )
+            .chain_err(|| "unable to find wishlist to update")?
+        {
+            Some(wishes) => wishes,
+            None => return Ok(not_found("wishlist", wid, None)),
+        };
+
+        if let Some(cards) = &upd.cards {
+            if let Err(e) = self.parse_wishes(cards)? {
+                return Ok(Object::fail(&e));
+            }
+        }
+
+        match self.db.update_wishlist(
+            &wishes,
+            db::UpdateWishlist {
+                name: upd.name,
+                description: upd.description,
+                cards: upd.cards,
+            },
+        ) {
+            Ok(wishes) => Ok(Object::Wishlist(Wishlist::from(wishes))),
+            Err(e) => {
+                println!("wishlist-update-failed: {}", e);
+                Ok(Object::fail("wishlist-update-failed"))
+            }
+        }
+    }
+
+    pub fn delete_wishlist(&self, uid: &str, wid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to remove wishlist for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let wishes = match self
+            .db
+            .find_wishlist_by_uuid(
+                collector.id,
+                Uuid::parse_str(wid).chain_err(|| "unable to parse wishlist uuid")?,
+            )
+            .chain_err(|| "unable to find wishlist to remove")?
+        {
+            Some(wishes) => wishes,
+            None => return Ok(Object::ok("wishlist-already-gone")),
+        };
+
+        match self.db.delete_wishlist(wishes.id) {
+            Ok(_) => Ok(Object::ok("wishlist-removed")),
+            Err(e) => {
+                println!("wishlist-removal-failed: {}", e);
+                Ok(Object::fail("wishlist-removal-failed"))
+            }
+        }
+    }
+
+    pub fn retrieve_wishlists_for_collector(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve wishlists from")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        Ok(Object::list_of_wishlists(
+            self.db
+                .find_wishlists_for_collector(collector.id)
+                .chain_err(|| "unable to find wishlists by collector uuid")?,
+        ))
+    }
+
+    pub fn retrieve_wishlist(&self, uid: &str, wid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve wishlist for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        match self
+            .db
+            .find_wishlist_by_uuid(
+                collector.id,
+                Uuid::parse_str(wid).chain_err(|| "unable to parse wishlist uuid")?,
+            )
+            .chain_err(|| "unable to find wishlist by uuid")?
+        {
+            Some(wishes) => Ok(Object::Wishlist(Wishlist::from(wishes))),
+            None => Ok(not_found("wishlist", wid, None)),
+        }
+    }
+
+    // Find the wishes on a wishlist that can be bought, right now, for
+    // no more than the collector is willing to pay.
+    pub fn retrieve_wishlist_deals(&self, uid: &str, wid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve wishlist deals for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let wishes = match self
+            .db
+            .find_wishlist_by_uuid(
+                collector.id,
+                Uuid::parse_str(wid).chain_err(|| "unable to parse wishlist uuid")?,
+            )
+            .chain_err(|| "unable to find wishlist by uuid")?
+        {
+            Some(wishes) => wishes,
+            None => return Ok(not_found("wishlist", wid, None)),
+        };
+
+        let pool = self.db.load_pool().chain_err(|| "unable to load card pool")?;
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let prices = self
+            .db
+            .load_prices()
+            .chain_err(|| "unable to load card prices")?;
+
+        match wishlist::parse(&pool, &lookup, &wishes.cards) {
+            Ok(wishes) => Ok(Object::Deals(wishlist::deals(
+                &pool,
+                &prices,
+                &wishes,
+                currency(&collector),
+            ))),
+            Err(e) => Ok(Object::fail(&e.to_string())),
+        }
+    }
+
+    // Check which wishes on a wishlist are already satisfied by what
+    // the collector holds.
+    pub fn retrieve_wishlist_satisfaction(&self, uid: &str, wid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to check wishlist for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let wishes = match self
+            .db
+            .find_wishlist_by_uuid(
+                collector.id,
+                Uuid::parse_str(wid).chain_err(|| "unable to parse wishlist uuid")?,
+            )
+            .chain_err(|| "unable to find wishlist by uuid")?
+        {
+            Some(wishes) => wishes,
+            None => return Ok(not_found("wishlist", wid, None)),
+        };
+
+        let pool = self.db.load_pool().chain_err(|| "unable to load card pool")?;
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let collection = self.load_combined_collection(collector.id)?;
+
+        match wishlist::parse(&pool, &lookup, &wishes.cards) {
+            Ok(wishes) => Ok(Object::Satisfaction(wishlist::satisfaction(
+                &pool,
+                &collection,
+                &wishes,
+            ))),
+            Err(e) => Ok(Object::fail(&e.to_string())),
+        }
+    }
+
+    // Value both sides of some trades, in the viewing collector's currency.
+    fn balance_trades(&self, trades: Vec<db::Trade>, collector: &db::Collector) -> Result<Vec<Trade>> {
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let prices = self
+            .db
+            .load_prices()
+            .chain_err(|| "unable to load card prices")?;
+
+        let mut balanced = vec![];
+        for other in trades {
+            let balance = match (
+                trade::resolve(&lookup, &other.offer),
+                trade::resolve(&lookup, &other.request),
+            ) {
+                (Ok(offer), Ok(request)) => Some(trade::balance(
+                    &prices,
+                    &offer,
+                    &request,
+                    currency(collector),
+                )),
+                _ => None,
+            };
+            balanced.push(Trade {
+                balance,
+                ..Trade::from(other)
+            });
+        }
+        Ok(balanced)
+    }
+
+    fn balance_trade(&self, other: db::Trade, collector: &db::Collector) -> Result<Object> {
+        match self.balance_trades(vec![other], collector)?.pop() {
+            Some(t) => Ok(Object::Trade(t)),
+            None => Ok(Object::fail("trade-not-found")),
+        }
+    }
+
+    // Make sure that both parties to a trade have what they are giving
+    // up, as of right now.
+    fn check_trade(
+        &self,
+        proposer: Uuid,
+        recipient: Uuid,
+        offer: &str,
+        request: &str,
+    ) -> Result<std::result::Result<(), String>> {
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let mine = self.load_combined_collection(proposer)?;
+        let theirs = self.load_combined_collection(recipient)?;
+        let mine = self.on_hand(proposer, &lookup, &mine)?;
+        let theirs = self.on_hand(recipient, &lookup, &theirs)?;
+
+        Ok(trade::check(&lookup, offer, request, &mine, &theirs).map_err(|e| e.to_string()))
+    }
+
+    // What has to be moved into a collector's default collection, from
+    // each of their others, before they can give up a pile of cards.
+    fn trade_sources(
+        &self,
+        uid: Uuid,
+        lookup: &card::Map,
+        pile: &str,
+    ) -> Result<Vec<(Uuid, String)>> {
+        let pile = match trade::resolve(lookup, pile) {
+            Ok(pile) => pile,
+            Err(_) => return Ok(vec![]),
+        };
+        let mut held = vec![];
+        for c in self
+            .db
+            .find_collections_for_collector(uid)
+            .chain_err(|| "unable to find collections for collector")?
+        {
+            let collection = self
+                .db
+                .load_collection(c.id)
+                .chain_err(|| "unable to load collection")?;
+            held.push((c.id, valuation::holdings(&collection)));
+        }
+        Ok(trade::sources(&pile, &held))
+    }
+
+    // Find a trade that the collector is a party to, and that is still
+    // waiting on a response.
+    fn find_open_trade(
+        &self,
+        collector: &db::Collector,
+        tid: &str,
+    ) -> Result<std::result::Result<db::Trade, Object>> {
+        match self
+            .db
+            .find_trade_by_uuid(
+                collector.id,
+                Uuid::parse_str(tid).chain_err(|| "unable to parse trade uuid")?,
+            )
+            .chain_err(|| "unable to find trade by uuid")?
+        {
+            Some(t) if t.status == "proposed" => Ok(Ok(t)),
+            Some(_) => Ok(Err(Object::fail("trade-not-open"))),
+            None => Ok(Err(not_found("trade", tid, None))),
+        }
+    }
+
+    pub fn propose_trade(&self, uid: &str, new: TradeProposalAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to propose trade for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let other = match Uuid::parse_str(&new.with) {
+            Ok(id) => self
+                .db
+                .find_collector_by_uuid(id)
+                .chain_err(|| "unable to find collector to propose trade to")?,
+            Err(_) => None,
+        };
+        let other = match other {
+            Some(other) if other.id != collector.id => other,
+            Some(_) => return Ok(Object::fail("cannot-trade-with-self")),
+            None => return Ok(not_found("collector", &new.with, None)),
+        };
+
+        if let Err(e) = self.check_trade(collector.id, other.id, &new.offer, &new.request)? {
+            return Ok(Object::fail(&e));
+        }
+
+        match self.db.create_trade(
+            None,
+            db::NewTrade {
+                proposer: collector.id,
+                recipient: other.id,
+                offer: &new.offer,
+                request: &new.request,
+                message: &new.message,
+                parent: None,
+            },
+        ) {
+            Ok(t) => self.balance_trade(t, &collector),
+            Err(e) => {
+                println!("trade-proposal-failed: {}", e);
+                Ok(Object::fail("trade-proposal-failed"))
+            }
+        }
+    }
+
+    // Counter a trade proposed to us, with one of our own, going back
+    // the other way.  The offer is what we will give up, and the request
+    // is what we want from the original proposer.
+    pub fn counter_trade(&self, uid: &str, tid: &str, new: TradeCounterAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to counter trade for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let original = match self.find_open_trade(&collector, tid)? {
+            Ok(t) if t.recipient == collector.id => t,
+            Ok(_) => return Ok(Object::fail("not-trade-recipient")),
+            Err(res) => return Ok(res),
+        };
+
+        if let Err(e) =
+            self.check_trade(collector.id, original.proposer, &new.offer, &new.request)?
+        {
+            return Ok(Object::fail(&e));
+        }
+
+        match self.db.counter_trade(
+            &original,
+            None,
+            db::NewTrade {
+                proposer: collector.id,
+                recipient: original.proposer,
+                offer: &new.offer,
+                request: &new.request,
+                message: &new.message,
+                parent: Some(original.id),
+            },
+        ) {
+            Ok(Some(t)) => self.balance_trade(t, &collector),
+            Ok(None) => Ok(Object::fail("trade-not-open")),
+            Err(e) => {
+                println!("trade-counter-failed: {}", e);
+                Ok(Object::fail("trade-counter-failed"))
+            }
+        }
+    }
+
+    // Accept a trade proposed to us, recording the exchange in both
+    // collections as (mirrored) trade transactions.
+    pub fn accept_trade(&self, uid: &str, tid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to accept trade for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let t = match self.find_open_trade(&collector, tid)? {
+            Ok(t) if t.recipient == collector.id => t,
+            Ok(_) => return Ok(Object::fail("not-trade-recipient")),
+            Err(res) => return Ok(res),
+        };
+        let proposer = match self
+            .db
+            .find_collector_by_uuid(t.proposer)
+            .chain_err(|| "unable to find trade proposer")?
+        {
+            Some(proposer) => proposer,
+            None => return Ok(not_found("collector", &t.proposer.to_string(), None)),
+        };
+
+        // collections change; make sure everyone still has the goods
+        if let Err(e) = self.check_trade(t.proposer, t.recipient, &t.offer, &t.request)? {
+            return Ok(Object::fail(&e));
+        }
+
+        // both halves have to pass muster, as any other transaction would.
+        let kind = trade::disposition(&t.offer, &t.request);
+        let (a, b) = match (
+            transaction_kind(kind.as_str(), &t.request, &t.offer, None),
+            transaction_kind(kind.as_str(), &t.offer, &t.request, None),
+        ) {
+            (Ok(a), Ok(b)) => (a, b),
+            (Err(e), _) | (_, Err(e)) => return Ok(Object::fail(&e)),
+        };
+
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let mut sources = vec![];
+        for (uid, pile, with) in &[
+            (t.proposer, &t.offer, &collector.username),
+            (t.recipient, &t.request, &proposer.username),
+        ] {
+            let summary = format!("Set aside for a trade with {}", with);
+            for (from, cards) in self.trade_sources(*uid, &lookup, pile)? {
+                sources.push((from, *uid, cards, summary.to_string()));
+            }
+        }
+
+        let today = Utc::now().naive_utc().date();
+        let moves = sources
+            .iter()
+            .map(|(from, to, cards, summary)| {
+                let half = |collection, gain, loss| db::NewTransaction {
+                    collection,
+                    summary,
+                    notes: "",
+                    dated: &today,
+                    disposition: disposition::Disposition::Transfer,
+                    gain,
+                    loss,
+                    paid: None,
+                    created_by: Some(collector.id),
+                };
+                (half(*from, "", cards), half(*to, cards, ""))
+            })
+            .collect();
+
+        let mine = format!("Traded with {}", collector.username);
+        let theirs = format!("Traded with {}", proposer.username);
+        match self.db.accept_trade(
+            &t,
+            moves,
+            db::NewTransaction {
+                collection: t.proposer,
+                summary: &mine,
+                notes: &t.message,
+                dated: &today,
+                disposition: a,
+                gain: &t.request,
+                loss: &t.offer,
+                paid: None,
+                created_by: Some(collector.id),
+            },
+            db::NewTransaction {
+                collection: t.recipient,
+                summary: &theirs,
+                notes: &t.message,
+                dated: &today,
+                disposition: b,
+                gain: &t.offer,
+                loss: &t.request,
+                paid: None,
+                created_by: Some(collector.id),
+            },
+        ) {
+            Ok(Some(t)) => {
+                self.refresh_goals(t.proposer);
+                self.refresh_goals(t.recipient);
+                self.balance_trade(t, &collector)
+            }
+            Ok(None) => Ok(Object::fail("trade-not-open")),
+            Err(e) => {
+                println!("trade-acceptance-failed: {}", e);
+                Ok(Object::fail("trade-acceptance-failed"))
+            }
+        }
+    }
+
+    pub fn decline_trade(&self, uid: &str, tid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to decline trade for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let t = match self.find_open_trade(&collector, tid)? {
+            Ok(t) if t.recipient == collector.id => t,
+            Ok(_) => return Ok(Object::fail("not-trade-recipient")),
+            Err(res) => return Ok(res),
+        };
+
+        match self.db.decline_trade(&t) {
+            Ok(Some(t)) => self.balance_trade(t, &collector),
+            Ok(None) => Ok(Object::fail("trade-not-open")),
+            Err(e) => {
+                println!("trade-decline-failed: {}", e);
+                Ok(Object::fail("trade-decline-failed"))
+            }
+        }
+    }
+
+    pub fn withdraw_trade(&self, uid: &str, tid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to withdraw trade for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let t = match self.find_open_trade(&collector, tid)? {
+            Ok(t) if t.proposer == collector.id => t,
+            Ok(_) => return Ok(Object::fail("not-trade-proposer")),
+            Err(res) => return Ok(res),
+        };
+
+        match self.db.withdraw_trade(&t) {
+            Ok(Some(t)) => self.balance_trade(t, &collector),
+            Ok(None) => Ok(Object::fail("trade-not-open")),
+            Err(e) => {
+                println!("trade-withdrawal-failed: {}", e);
+                Ok(Object::fail("trade-withdrawal-failed"))
+            }
+        }
+    }
+
+    pub fn retrieve_trades_for_collector(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve trades for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let trades = self
+            .db
+            .find_trades_for_collector(collector.id)
+            .chain_err(|| "unable to find trades by collector uuid")?;
+        Ok(Object::Trades(self.balance_trades(trades, &collector)?))
+    }
+
+    pub fn retrieve_trade(&self, uid: &str, tid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve trade for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        match self
+            .db
+            .find_trade_by_uuid(
+                collector.id,
+                Uuid::parse_str(tid).chain_err(|| "unable to parse trade uuid")?,
+            )
+            .chain_err(|| "unable to find trade by uuid")?
+        {
+            Some(t) => self.balance_trade(t, &collector),
+            None => Ok(not_found("trade", tid, None)),
+        }
+    }
+
+    // Gather up what a collector wants (for their decks, goals and
+    // wishlists) and what they can spare, for trade matching.  Goals and
+    // wishlists that no longer make sense are skipped.
+    fn trader(
+        &self,
+        collector: &db::Collector,
+        pool: &card::Pool,
+        lookup: &card::Map,
+    ) -> Result<matching::Trader> {
+        let collection = self.load_combined_collection(collector.id)?;
+
+        let mut decks = vec![];
+        for deck in self
+            .db
+            .find_decks_for_collector(collector.id)
+            .chain_err(|| "unable to find decks for collector")?
+        {
+            if let Ok(cards) = cdif::File::from_string(&format!("{}\n{}", deck.main, deck.side)) {
+                decks.push(cards);
+            }
+        }
+        let needs = matching::deck_needs(pool, lookup, &decks);
+
+        let mut goals = vec![];
+        for g in self
+            .db
+            .find_goals_for_collector(collector.id)
+            .chain_err(|| "unable to find goals for collector")?
+        {
+            if let Ok(progress) = goal::evaluate(pool, &collection, &g.target, &g.goal) {
+                goals.push(progress);
+            }
+        }
+
+        let mut wishes = vec![];
+        for w in self
+            .db
+            .find_wishlists_for_collector(collector.id)
+            .chain_err(|| "unable to find wishlists for collector")?
+        {
+            if let Ok(parsed) = wishlist::parse(pool, lookup, &w.cards) {
+                wishes.extend(parsed);
+            }
+        }
+
+        let on_hand = self.on_hand(collector.id, lookup, &collection)?;
+        Ok(matching::Trader {
+            id: collector.id.to_string(),
+            username: collector.username.to_string(),
+            wants: matching::wants(pool, &collection, &needs, &goals, &wishes),
+            spares: matching::surplus(pool, &on_hand, &needs),
+        })
+    }
+
+    // Match a collector's wants against the surplus of every collector
+    // who has opted in to trading, and vice versa.
+    pub fn retrieve_trade_matches(&self, uid: &str, limit: usize) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to match trades for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let pool = self.db.load_pool().chain_err(|| "unable to load card pool")?;
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let prices = self
+            .db
+            .load_prices()
+            .chain_err(|| "unable to load card prices")?;
+
+        let me = self.trader(&collector, &pool, &lookup)?;
+        let mut others = vec![];
+        for other in self
+            .db
+            .find_tradeable_collectors()
+            .chain_err(|| "unable to find tradeable collectors")?
+        {
+            if other.id != collector.id {
+                others.push(self.trader(&other, &pool, &lookup)?);
+            }
+        }
+
+        Ok(Object::Partners(matching::rank(
+            &pool,
+            &prices,
+            currency(&collector),
+            &me,
+            &others,
+            limit,
+        )))
+    }
+
+    // Look up a collector's locations, for working out paths.
+    fn storage_locations(&self, uid: Uuid) -> Result<Vec<storage::Location>> {
+        Ok(self
+            .db
+            .find_locations_for_collector(uid)
+            .chain_err(|| "unable to find locations for collector")?
+            .into_iter()
+            .map(|l| storage::Location {
+                id: l.id.to_string(),
+                parent: l.parent.map(|p| p.to_string()),
+                kind: storage::Kind::parse(&l.kind).unwrap_or(storage::Kind::Box),
+                name: l.name,
+            })
+            .collect())
+    }
+
+    // Map location paths to ids, for resolving `(loc: ...)` variants.
+    fn location_paths(&self, uid: Uuid) -> Result<HashMap<String, String>> {
+        Ok(storage::paths(&self.storage_locations(uid)?))
+    }
+
+    fn stowage(&self, uid: Uuid) -> Result<Vec<storage::Stowed>> {
+        Ok(self
+            .db
+            .find_stowage_for_collector(uid)
+            .chain_err(|| "unable to find stowage for collector")?
+            .into_iter()
+            .map(|s| storage::Stowed {
+                location: s.location.to_string(),
+                pid: s.pid,
+                var: storage::variants(&s.var),
+                quantity: s.quantity,
+            })
+            .collect())
+    }
+
+    fn stow(&self, changes: Vec<storage::Stowed>) -> Result<()> {
+        let mut stowed = vec![];
+        for s in changes {
+            stowed.push(db::Stowed {
+                location: Uuid::parse_str(&s.location)
+                    .chain_err(|| "unable to parse location uuid")?,
+                pid: s.pid,
+                var: storage::variant(&s.var),
+                quantity: s.quantity,
+            });
+        }
+        self.db
+            .stow(&stowed)
+            .chain_err(|| "unable to stow cards")
+    }
+
+    // Work out where the cards in a transaction are going to (or coming
+    // from), for any tagged with a `(loc: ...)` variant.  CDIF that
+    // doesn't parse is left for the transaction itself to complain about.
+    fn stowage_changes(
+        &self,
+        uid: Uuid,
+        gain: &str,
+        loss: &str,
+    ) -> Result<std::result::Result<Vec<storage::Stowed>, String>> {
+        let (gain, loss) = match (cdif::File::from_string(gain), cdif::File::from_string(loss)) {
+            (Ok(gain), Ok(loss)) => (gain, loss),
+            _ => return Ok(Ok(vec![])),
+        };
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        Ok(
+            storage::changes(&lookup, &self.location_paths(uid)?, &gain, &loss)
+                .map_err(|e| e.to_string()),
+        )
+    }
+
+    // Take copies that are no longer in the collection out of storage.
+    fn sync_stowage(&self, uid: Uuid) -> Result<()> {
+        let collection = self.load_combined_collection(uid)?;
+        self.stow(storage::sync(&collection, &self.stowage(uid)?))
+    }
+
+    // Apply storage changes from a transaction, and then bring storage
+    // back in line with the (now updated) collection.  Like goals, any
+    // failure here is logged; the next sync will try again.
+    fn restow(&self, uid: Uuid, changes: Vec<storage::Stowed>) {
+        if let Err(e) = self.stow(changes).and_then(|_| self.sync_stowage(uid)) {
+            println!("restow-failed: {}", e);
+        }
+    }
+
+    pub fn create_location(&self, uid: &str, new: LocationCreationAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to create location for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let parent = match &new.parent {
+            None => None,
+            Some(lid) => match self
+                .db
+                .find_location_by_uuid(
+                    collector.id,
+                    Uuid::parse_str(lid).chain_err(|| "unable to parse location uuid")?,
+                )
+                .chain_err(|| "unable to find parent location")?
+            {
+                Some(parent) => Some(parent),
+                None => return Ok(not_found("location", lid, None)),
+            },
+        };
+
+        let within = parent.as_ref().map(|p| p.kind.as_str());
+        let kind = match storage::check(&new.name, &new.kind, within) {
+            Ok(kind) => kind,
+            Err(e) => return Ok(Object::fail(&e.to_string())),
+        };
+
+        match self.db.create_location(
+            None,
+            db::NewLocation {
+                collector: collector.id,
+                parent: parent.map(|p| p.id),
+                name: &new.name,
+                kind: kind.as_str(),
+                description: new.description.as_deref().unwrap_or(""),
+            },
+        ) {
+            Ok(location) => Ok(Object::Location(Location::from(location))),
+            Err(e) => {
+                println!("location-creation-failed: {}", e);
+                Ok(Object::fail("location-creation-failed"))
+            }
+        }
+    }
+
+    pub fn update_location(
+        &self,
+        uid: &str,
+        lid: &str,
+        upd: LocationUpdateAttempt,
+    ) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to update location for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let location = match self
+            .db
+            .find_location_by_uuid(
+                collector.id,
+                Uuid::parse_str(lid).chain_err(|| "unable to parse location uuid")?,
+            )
+            .chain_err(|| "unable to find location to update")?
+        {
+            Some(location) => location,
+            None => return Ok(not_found("location", lid, None)),
+        };
+
+        if let Some(name) = &upd.name {
+            if !storage::valid_name(name) {
+                return Ok(Object::fail("invalid-location-name"));
+            }
+        }
+
+        match self.db.update_location(
+            &location,
+            db::UpdateLocation {
+                name: upd.name,
+                description: upd.description,
+            },
+        ) {
+            Ok(location) => Ok(Object::Location(Location::from(location))),
+            Err(e) => {
+                println!("location-update-failed: {}", e);
+                Ok(Object::fail("location-update-failed"))
+            }
+        }
+    }
+
+    pub fn delete_location(&self, uid: &str, lid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to remove location for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let location = match self
+            .db
+            .find_location_by_uuid(
+                collector.id,
+                Uuid::parse_str(lid).chain_err(|| "unable to parse location uuid")?,
+            )
+            .chain_err(|| "unable to find location to remove")?
+        {
+            Some(location) => location,
+            None => return Ok(Object::ok("location-already-gone")),
+        };
+
+        match self.db.delete_location(location.id) {
+            Ok(_) => Ok(Object::ok("location-removed")),
+            Err(e) => {
+                println!("location-removal-failed: {}", e);
+                Ok(Object::fail("location-removal-failed"))
+            }
+        }
+    }
+
+    pub fn retrieve_locations_for_collector(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve locations from")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        Ok(Object::list_of_locations(
+            self.db
+                .find_locations_for_collector(collector.id)
+                .chain_err(|| "unable to find locations by collector uuid")?,
+        ))
+    }
+
+    pub fn retrieve_location(&self, uid: &str, lid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve location for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        match self
+            .db
+            .find_location_by_uuid(
+                collector.id,
+                Uuid::parse_str(lid).chain_err(|| "unable to parse location uuid")?,
+            )
+            .chain_err(|| "unable to find location by uuid")?
+        {
+            Some(location) => Ok(Object::Location(Location::from(location))),
+            None => Ok(not_found("location", lid, None)),
+        }
+    }
+
+    // List the cards kept in a location (but not in any of the rows or
+    // pages inside of it).
+    pub fn retrieve_location_contents(&self, uid: &str, lid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve location contents for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let location = match self
+            .db
+            .find_location_by_uuid(
+                collector.id,
+                Uuid::parse_str(lid).chain_err(|| "unable to parse location uuid")?,
+            )
+            .chain_err(|| "unable to find location by uuid")?
+        {
+            Some(location) => location,
+            None => return Ok(not_found("location", lid, None)),
+        };
+
+        self.sync_stowage(collector.id)?;
+        let pool = self.db.load_pool().chain_err(|| "unable to load card pool")?;
+        Ok(Object::Stowage(storage::contents(
+            &pool,
+            &self.stowage(collector.id)?,
+            &location.id.to_string(),
+        )))
+    }
+
+    pub fn move_cards(&self, uid: &str, attempt: MoveAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to move cards for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let paths = self.location_paths(collector.id)?;
+        let mut ends = vec![];
+        for path in &[&attempt.from, &attempt.to] {
+            match path {
+                None => ends.push(None),
+                Some(path) => match paths.get(path) {
+                    Some(id) => ends.push(Some(id.as_str())),
+                    None => return Ok(Object::fail(&format!("unknown-location: {}", path))),
+                },
+            }
+        }
+
+        let cards = match cdif::File::from_string(&attempt.cards) {
+            Ok(cards) => cards,
+            Err(_) => return Ok(Object::fail("invalid-cdif")),
+        };
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let changes = match storage::shift(&lookup, ends[0], ends[1], &cards) {
+            Ok(changes) => changes,
+            Err(e) => return Ok(Object::fail(&e.to_string())),
+        };
+
+        self.sync_stowage(collector.id)?;
+        let collection = self.load_combined_collection(collector.id)?;
+        if let Err(e) = storage::verify(&collection, &self.stowage(collector.id)?, &changes) {
+            return Ok(Object::fail(&e.to_string()));
+        }
+
+        match self.stow(changes) {
+            Ok(_) => Ok(Object::ok("cards-moved")),
+            Err(e) => {
+                println!("card-move-failed: {}", e);
+                Ok(Object::fail("card-move-failed"))
+            }
+        }
+    }
+
+    // Where are my copies of X?
+    pub fn retrieve_whereabouts(&self, uid: &str, name: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to locate cards for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        self.sync_stowage(collector.id)?;
+        let (pool, collection) = self.load_pool_and_collection(collector.id)?;
+        match storage::whereis(
+            &pool,
+            &collection,
+            &self.stowage(collector.id)?,
+            &self.storage_locations(collector.id)?,
+            name,
+        ) {
+            Some(whereabouts) => Ok(Object::Whereabouts(whereabouts)),
+            None => Ok(not_found("card", name, None)),
+        }
+    }
+
+    // Work out where to pull a deck's cards from, after bringing storage
+    // back in line with the collection.
+    fn plan_picks(
+        &self,
+        collector: &db::Collector,
+        deck: &db::Deck,
+        into: Option<&str>,
+    ) -> Result<storage::PickList> {
+        self.sync_stowage(collector.id)?;
+        let pool = self.db.load_pool().chain_err(|| "unable to load card pool")?;
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let cards = cdif::File::from_string(&format!("{}\n{}", deck.main, deck.side))
+            .chain_err(|| "unable to parse deck")?;
+        let collection = self.load_combined_collection(collector.id)?;
+
+        Ok(storage::pick(
+            &pool,
+            &matching::deck_needs(&pool, &lookup, &[cards]),
+            &self.stowage(collector.id)?,
+            &self.storage_locations(collector.id)?,
+            into,
+            &self.on_hand(collector.id, &lookup, &collection)?,
+        ))
+    }
+
+    // Resolve a deck box (by name), for pulling cards into.
+    fn find_deck_box(&self, uid: Uuid, name: &str) -> Result<Option<storage::Location>> {
+        Ok(self
+            .storage_locations(uid)?
+            .into_iter()
+            .find(|l| l.name == name && l.kind == storage::Kind::Deckbox))
+    }
+
+    pub fn retrieve_deck_picks(&self, uid: &str, did: &str, into: Option<&str>) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to plan picks for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let deck = match self
+            .db
+            .find_deck_by_uuid(
+                collector.id,
+                Uuid::parse_str(did).chain_err(|| "unable to parse deck uuid")?,
+            )
+            .chain_err(|| "unable to find deck to plan picks for")?
+        {
+            Some(deck) => deck,
+            None => return Ok(not_found("deck", did, None)),
+        };
+
+        let into = match into {
+            None => None,
+            Some(name) => match self.find_deck_box(collector.id, name)? {
+                Some(location) => Some(location.id),
+                None => return Ok(Object::fail(&format!("unknown-deck-box: {}", name))),
+            },
+        };
+
+        match self.plan_picks(&collector, &deck, into.as_deref()) {
+            Ok(list) => Ok(Object::Picks(list)),
+            Err(e) => {
+                println!("pick-list-failed: {}", e);
+                Ok(Object::fail("pick-list-failed"))
+            }
+        }
     }
 
-    pub fn authenticate(&self, a: AuthenticationAttempt) -> Result<Object> {
-        match self.db.authenticate_collector(&a.username, &a.password) {
-            Ok(Some(who)) => {
-                let mut session = db::Session::new(None);
-                session.set("user-id", &who.id.to_string());
-                match self.db.set_session(session) {
-                    Ok(session) => Ok(Object::Authenticated(Authenticated {
-                        uid: who.id.to_string(),
-                        username: who.username.to_string(),
-                        session: session.id.to_string(),
-                    })),
-                    Err(e) => {
-                        println!("session-failed: {}", e);
-                        Ok(Object::fail("session-failed"))
-                    }
+    // Pull a deck's cards out of storage, recording it as a move into a
+    // deck box, so that everyone knows those copies are in use.
+    pub fn pull_deck(&self, uid: &str, did: &str, attempt: PullAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to pull deck for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let deck = match self
+            .db
+            .find_deck_by_uuid(
+                collector.id,
+                Uuid::parse_str(did).chain_err(|| "unable to parse deck uuid")?,
+            )
+            .chain_err(|| "unable to find deck to pull")?
+        {
+            Some(deck) => deck,
+            None => return Ok(not_found("deck", did, None)),
+        };
+
+        let into = match self.find_deck_box(collector.id, &attempt.into)? {
+            Some(location) => location,
+            None => return Ok(Object::fail(&format!("unknown-deck-box: {}", attempt.into))),
+        };
+
+        let list = match self.plan_picks(&collector, &deck, Some(&into.id)) {
+            Ok(list) => list,
+            Err(e) => {
+                println!("pick-list-failed: {}", e);
+                return Ok(Object::fail("pick-list-failed"));
+            }
+        };
+
+        match self.stow(storage::pulled(&list, &into.id)) {
+            Ok(_) => Ok(Object::Picks(list)),
+            Err(e) => {
+                println!("deck-pull-failed: {}", e);
+                Ok(Object::fail("deck-pull-failed"))
+            }
+        }
+    }
+
+    // Check that a collector can lend out some cards, on top of whatever
+    // else they have out on loan (besides the loan being changed, if any).
+    fn check_loan(
+        &self,
+        uid: Uuid,
+        cards: &str,
+        except: Option<Uuid>,
+    ) -> Result<std::result::Result<(), String>> {
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let collection = self.load_combined_collection(uid)?;
+        let open = self
+            .db
+            .find_open_loans_for_collector(uid)
+            .chain_err(|| "unable to find open loans for collector")?;
+        let lent: Vec<&str> = open
+            .iter()
+            .filter(|l| Some(l.id) != except)
+            .map(|l| l.cards.as_str())
+            .collect();
+
+        Ok(loan::check(&lookup, cards, &collection, &lent).map_err(|e| e.to_string()))
+    }
+
+    pub fn create_loan(&self, uid: &str, new: LoanCreationAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to create loan for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let with = match &new.with {
+            None => None,
+            Some(cid) => match self
+                .db
+                .find_collector_by_uuid(
+                    Uuid::parse_str(cid).chain_err(|| "unable to parse borrower uuid")?,
+                )
+                .chain_err(|| "unable to find collector to lend cards to")?
+            {
+                Some(other) if other.id != collector.id => Some(other),
+                Some(_) => return Ok(Object::fail("cannot-lend-to-self")),
+                None => return Ok(not_found("collector", cid, None)),
+            },
+        };
+
+        let borrower = match (&new.borrower, &with) {
+            (Some(name), _) if !name.trim().is_empty() => name.trim().to_string(),
+            (_, Some(other)) => other.username.to_string(),
+            _ => return Ok(Object::fail("missing-borrower")),
+        };
+
+        let lent_on = new.lent_on.unwrap_or_else(|| Utc::now().naive_utc().date());
+        if new.due_on.is_some_and(|due| due < lent_on) {
+            return Ok(Object::fail("invalid-due-date"));
+        }
+
+        if let Err(e) = self.check_loan(collector.id, &new.cards, None)? {
+            return Ok(Object::fail(&e));
+        }
+
+        match self.db.create_loan(
+            None,
+            db::NewLoan {
+                collector: collector.id,
+                borrower: &borrower,
+                borrower_collector: with.map(|c| c.id),
+                cards: &new.cards,
+                notes: new.notes.as_deref().unwrap_or(""),
+                lent_on,
+                due_on: new.due_on,
+            },
+        ) {
+            Ok(loan) => Ok(Object::Loan(Loan::from(loan))),
+            Err(e) => {
+                println!("loan-creation-failed: {}", e);
+                Ok(Object::fail("loan-creation-failed"))
+            }
+        }
+    }
+
+    pub fn update_loan(&self, uid: &str, lid: &str, upd: LoanUpdateAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to update loan for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let loan = match self
+            .db
+            .find_loan_by_uuid(
+                collector.id,
+                Uuid::parse_str(lid).chain_err(|| "unable to parse loan uuid")?,
+            )
+            .chain_err(|| "unable to find loan to update")?
+        {
+            Some(loan) => loan,
+            None => return Ok(not_found("loan", lid, None)),
+        };
+
+        if let Some(Some(due)) = upd.due_on {
+            if due < loan.lent_on {
+                return Ok(Object::fail("invalid-due-date"));
+            }
+        }
+        if let Some(cards) = &upd.cards {
+            if loan.returned_on.is_none() {
+                if let Err(e) = self.check_loan(collector.id, cards, Some(loan.id))? {
+                    return Ok(Object::fail(&e));
                 }
             }
-            Ok(None) => {
-                println!("authentication-failed: (no user returned)",);
-                Ok(Object::fail("authentication-failed"))
+        }
+
+        match self.db.update_loan(
+            &loan,
+            db::UpdateLoan {
+                borrower: upd.borrower,
+                cards: upd.cards,
+                notes: upd.notes,
+                due_on: upd.due_on,
+            },
+        ) {
+            Ok(loan) => Ok(Object::Loan(Loan::from(loan))),
+            Err(e) => {
+                println!("loan-update-failed: {}", e);
+                Ok(Object::fail("loan-update-failed"))
             }
+        }
+    }
+
+    // The cards came back; close out the loan.
+    pub fn return_loan(&self, uid: &str, lid: &str, attempt: LoanReturnAttempt) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to return loan for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let loan = match self
+            .db
+            .find_loan_by_uuid(
+                collector.id,
+                Uuid::parse_str(lid).chain_err(|| "unable to parse loan uuid")?,
+            )
+            .chain_err(|| "unable to find loan to return")?
+        {
+            Some(loan) => loan,
+            None => return Ok(not_found("loan", lid, None)),
+        };
+
+        let on = attempt
+            .returned_on
+            .unwrap_or_else(|| Utc::now().naive_utc().date());
+        if on < loan.lent_on {
+            return Ok(Object::fail("invalid-return-date"));
+        }
+
+        match self.db.return_loan(&loan, on) {
+            Ok(Some(loan)) => Ok(Object::Loan(Loan::from(loan))),
+            Ok(None) => Ok(Object::fail("loan-already-returned")),
             Err(e) => {
-                println!("authentication-failed: {}", e);
-                Ok(Object::fail("authentication-failed"))
+                println!("loan-return-failed: {}", e);
+                Ok(Object::fail("loan-return-failed"))
+            }
+        }
+    }
+
+    pub fn delete_loan(&self, uid: &str, lid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to remove loan for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let loan = match self
+            .db
+            .find_loan_by_uuid(
+                collector.id,
+                Uuid::parse_str(lid).chain_err(|| "unable to parse loan uuid")?,
+            )
+            .chain_err(|| "unable to find loan to remove")?
+        {
+            Some(loan) => loan,
+            None => return Ok(Object::ok("loan-already-gone")),
+        };
+
+        match self.db.delete_loan(loan.id) {
+            Ok(_) => Ok(Object::ok("loan-removed")),
+            Err(e) => {
+                println!("loan-removal-failed: {}", e);
+                Ok(Object::fail("loan-removal-failed"))
             }
         }
     }
 
-    pub fn signup(&self, a: SignupAttempt) -> Result<Object> {
-        match a.valid() {
-            Ok(_) => {
-                let who = self
-                    .db
-                    .create_collector(
-                        None,
-                        db::NewCollector {
-                            username: &a.username,
-                            email: &a.email,
-                        },
-                        Some(&a.password),
-                    )
-                    .chain_err(|| "unable to sign up new collector")?;
+    pub fn retrieve_loans_for_collector(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve loans from")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        Ok(Object::list_of_loans(
+            self.db
+                .find_loans_for_collector(collector.id)
+                .chain_err(|| "unable to find loans by collector uuid")?,
+        ))
+    }
+
+    pub fn retrieve_overdue_loans(&self, uid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve overdue loans from")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        Ok(Object::list_of_loans(
+            self.db
+                .find_overdue_loans_for_collector(collector.id, Utc::now().naive_utc().date())
+                .chain_err(|| "unable to find overdue loans by collector uuid")?,
+        ))
+    }
+
+    pub fn retrieve_loan(&self, uid: &str, lid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to retrieve loan for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        match self
+            .db
+            .find_loan_by_uuid(
+                collector.id,
+                Uuid::parse_str(lid).chain_err(|| "unable to parse loan uuid")?,
+            )
+            .chain_err(|| "unable to find loan by uuid")?
+        {
+            Some(loan) => Ok(Object::Loan(Loan::from(loan))),
+            None => Ok(not_found("loan", lid, None)),
+        }
+    }
+
+    // What a collector has on hand: their collection, less whatever they
+    // have out on loan.
+    fn on_hand(
+        &self,
+        uid: Uuid,
+        lookup: &card::Map,
+        collection: &card::Collection,
+    ) -> Result<valuation::Holdings> {
+        let open = self
+            .db
+            .find_open_loans_for_collector(uid)
+            .chain_err(|| "unable to find open loans for collector")?;
+        let lent: Vec<&str> = open.iter().map(|l| l.cards.as_str()).collect();
+        Ok(loan::on_hand(lookup, collection, &lent))
+    }
+
+    // How much of a deck can be built right now, with lent cards set
+    // aside.
+    pub fn retrieve_deck_availability(&self, uid: &str, did: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to check deck availability for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let deck = match self
+            .db
+            .find_deck_by_uuid(
+                collector.id,
+                Uuid::parse_str(did).chain_err(|| "unable to parse deck uuid")?,
+            )
+            .chain_err(|| "unable to find deck to check availability of")?
+        {
+            Some(deck) => deck,
+            None => return Ok(not_found("deck", did, None)),
+        };
+
+        let cards = match cdif::File::from_string(&format!("{}\n{}", deck.main, deck.side)) {
+            Ok(cards) => cards,
+            Err(_) => return Ok(Object::fail("invalid-deck")),
+        };
+        let (pool, collection) = self.load_pool_and_collection(collector.id)?;
+        let lookup = self
+            .db
+            .load_lookup()
+            .chain_err(|| "unable to load lookup table")?;
+        let open = self
+            .db
+            .find_open_loans_for_collector(collector.id)
+            .chain_err(|| "unable to find open loans for collector")?;
+        let lent: Vec<&str> = open.iter().map(|l| l.cards.as_str()).collect();
+
+        Ok(Object::Availability(loan::availability(
+            &pool,
+            &lookup,
+            &collection,
+            &cards,
+            &lent,
+        )))
+    }
+
+    // Value sealed product, by expected value, from current prices.
+    fn sealed_products(
+        &self,
+        collector: &db::Collector,
+        products: Vec<db::SealedProduct>,
+    ) -> Result<Vec<SealedProduct>> {
+        let pool = self
+            .db
+            .load_pool()
+            .chain_err(|| "unable to load card pool")?;
+        let prices = self
+            .db
+            .load_prices()
+            .chain_err(|| "unable to load prices")?;
+        Ok(products
+            .into_iter()
+            .map(|p| SealedProduct::from(p, &pool, &prices, currency(collector)))
+            .collect())
+    }
 
-                let mut session = db::Session::new(None);
-                session.set("fresh", "signup");
-                session.set("user-id", &who.id.to_string());
-                match self.db.set_session(session) {
-                    Ok(session) => Ok(Object::Authenticated(Authenticated {
-                        uid: who.id.to_string(),
-                        username: who.username.to_string(),
-                        session: session.id.to_string(),
-                    })),
-                    Err(e) => {
-                        println!("signup-failed: {}", e);
-                        Ok(Object::fail("signup-failed"))
-                    }
-                }
-            }
-            Err(e) => Ok(Object::fail(&e.to_string())),
-        }
+    fn sealed_product(
+        &self,
+        collector: &db::Collector,
+        product: db::SealedProduct,
+    ) -> Result<Object> {
+        Ok(Object::SealedProduct(
+            self.sealed_products(collector, vec![product])?.remove(0),
+        ))
     }
 
-    pub fn retrieve_transactions_for_collection(&self, cid: &str) -> Result<Object> {
-        let collection = match self
+    pub fn retrieve_sealed_products(&self, uid: &str) -> Result<Object> {
+        let collector = match self
             .db
-            .find_collection_by_uuid(
-                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collection to retrieve transactions for")?
+            .chain_err(|| "unable to find collector to retrieve sealed products for")?
         {
-            Some(collection) => collection,
-            None => return Ok(not_found("collection", cid, None)),
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
         };
 
-        Ok(Object::list_of_transactions(
+        let products = self.sealed_products(
+            &collector,
             self.db
-                .find_transactions_for_collection(collection.id)
-                .chain_err(|| "unable to find transactions by collection uuid")?,
-        ))
+                .find_sealed_products_for_collector(collector.id)
+                .chain_err(|| "unable to find sealed products for collector")?,
+        )?;
+        let each = |p: &SealedProduct, v: Option<i64>| v.unwrap_or(0) * p.quantity as i64;
+        Ok(Object::SealedProducts(SealedInventory {
+            sealed: products.iter().map(|p| p.quantity as i64).sum(),
+            paid: products
+                .iter()
+                .map(|p| each(p, p.paid.map(|v| v as i64)))
+                .sum(),
+            ev: products.iter().map(|p| each(p, p.ev)).sum(),
+            products,
+        }))
     }
 
-    pub fn post_transaction(&self, cid: &str, new: TransactionCreationAttempt) -> Result<Object> {
-        let collection = match self
+    pub fn create_sealed_product(
+        &self,
+        uid: &str,
+        new: SealedProductCreationAttempt,
+    ) -> Result<Object> {
+        let collector = match self
             .db
-            .find_collection_by_uuid(
-                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collection to post transaction to")?
+            .chain_err(|| "unable to find collector to create sealed product for")?
         {
-            Some(collection) => collection,
-            None => return Ok(not_found("collection", cid, None)),
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
         };
 
-        match self.db.create_transaction(
+        let pool = self
+            .db
+            .load_pool()
+            .chain_err(|| "unable to load card pool")?;
+        let packs = new
+            .packs
+            .or_else(|| sealed::Kind::parse(&new.kind).map(|k| k.packs()))
+            .unwrap_or(0);
+        let quantity = new.quantity.unwrap_or(1);
+        let kind = match sealed::check(&pool, &new.kind, &new.set, packs, quantity, new.paid) {
+            Ok(kind) => kind,
+            Err(e) => return Ok(Object::fail(&e.to_string())),
+        };
+
+        match self.db.create_sealed_product(
             None,
-            db::NewTransaction {
-                collection: collection.id,
-                summary: &new.summary,
-                notes: &new.notes,
-                dated: &new.dated,
-                disposition: &new.disposition,
-                gain: &new.gain,
-                loss: &new.loss,
+            db::NewSealedProduct {
+                collector: collector.id,
+                kind: kind.as_str(),
+                name: new.name.as_deref().unwrap_or("").trim(),
+                set_code: &new.set.trim().to_uppercase(),
+                packs,
+                quantity,
                 paid: new.paid,
+                acquired_on: new
+                    .acquired_on
+                    .unwrap_or_else(|| Utc::now().naive_utc().date()),
+                notes: new.notes.as_deref().unwrap_or(""),
             },
         ) {
-            Ok(txn) => Ok(Object::Transaction(Transaction::from(txn))),
+            Ok(product) => self.sealed_product(&collector, product),
             Err(e) => {
-                println!("transaction-creation-failed: {}", e);
-                Ok(Object::fail("transaction-creation-failed"))
+                println!("sealed-product-creation-failed: {}", e);
+                Ok(Object::fail("sealed-product-creation-failed"))
             }
         }
     }
 
-    pub fn retrieve_transaction(&self, cid: &str, tid: &str) -> Result<Object> {
-        let collection = match self
+    pub fn update_sealed_product(
+        &self,
+        uid: &str,
+        sid: &str,
+        upd: SealedProductUpdateAttempt,
+    ) -> Result<Object> {
+        let collector = match self
             .db
-            .find_collection_by_uuid(
-                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collection to retrieve transaction from")?
+            .chain_err(|| "unable to find collector to update sealed product for")?
         {
-            Some(collection) => collection,
-            None => return Ok(not_found("collection", cid, None)),
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
         };
 
-        match self
+        let product = match self
             .db
-            .find_transaction_by_uuid(
-                collection.id,
-                Uuid::parse_str(tid).chain_err(|| "unable to parse transaction uuid")?,
+            .find_sealed_product_by_uuid(
+                collector.id,
+                Uuid::parse_str(sid).chain_err(|| "unable to parse sealed product uuid")?,
             )
-            .chain_err(|| "unable to find transaction by uuid")?
+            .chain_err(|| "unable to find sealed product to update")?
         {
-            Some(transaction) => Ok(Object::Transaction(Transaction::from(transaction))),
-            None => Ok(not_found("transaction", tid, None)),
+            Some(product) => product,
+            None => return Ok(not_found("sealed-product", sid, None)),
+        };
+
+        let pool = self
+            .db
+            .load_pool()
+            .chain_err(|| "unable to load card pool")?;
+        let kind = match sealed::check(
+            &pool,
+            upd.kind.as_deref().unwrap_or(&product.kind),
+            upd.set.as_deref().unwrap_or(&product.set_code),
+            upd.packs.unwrap_or(product.packs),
+            upd.quantity.unwrap_or(product.quantity),
+            upd.paid.unwrap_or(product.paid),
+        ) {
+            Ok(kind) => kind,
+            Err(e) => return Ok(Object::fail(&e.to_string())),
+        };
+
+        match self.db.update_sealed_product(
+            &product,
+            db::UpdateSealedProduct {
+                kind: upd.kind.map(|_| kind.as_str().to_string()),
+                name: upd.name.map(|n| n.trim().to_string()),
+                set_code: upd.set.map(|s| s.trim().to_uppercase()),
+                packs: upd.packs,
+                quantity: upd.quantity,
+                paid: upd.paid,
+                acquired_on: upd.acquired_on,
+                notes: upd.notes,
+            },
+        ) {
+            Ok(product) => self.sealed_product(&collector, product),
+            Err(e) => {
+                println!("sealed-product-update-failed: {}", e);
+                Ok(Object::fail("sealed-product-update-failed"))
+            }
         }
     }
 
-    pub fn update_transaction(
+    pub fn delete_sealed_product(&self, uid: &str, sid: &str) -> Result<Object> {
+        let collector = match self
+            .db
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+            )
+            .chain_err(|| "unable to find collector to remove sealed product for")?
+        {
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
+        };
+
+        let product = match self
+            .db
+            .find_sealed_product_by_uuid(
+                collector.id,
+                Uuid::parse_str(sid).chain_err(|| "unable to parse sealed product uuid")?,
+            )
+            .chain_err(|| "unable to find sealed product to remove")?
+        {
+            Some(product) => product,
+            None => return Ok(Object::ok("sealed-product-already-gone")),
+        };
+
+        match self.db.delete_sealed_product(product.id) {
+            Ok(_) => Ok(Object::ok("sealed-product-removed")),
+            Err(e) => {
+                println!("sealed-product-removal-failed: {}", e);
+                Ok(Object::fail("sealed-product-removal-failed"))
+            }
+        }
+    }
+
+    // Open one of a sealed product, crediting the collection with what
+    // came out of it.  What was paid for the product becomes the cost
+    // basis of the cards pulled from it.
+    pub fn open_sealed_product(
         &self,
-        cid: &str,
-        tid: &str,
-        upd: TransactionUpdateAttempt,
+        uid: &str,
+        sid: &str,
+        attempt: OpeningAttempt,
     ) -> Result<Object> {
-        let collection = match self
+        let collector = match self
             .db
-            .find_collection_by_uuid(
-                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collection to update transaction for")?
+            .chain_err(|| "unable to find collector to open sealed product for")?
         {
-            Some(collection) => collection,
-            None => return Ok(not_found("collection", cid, None)),
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
         };
 
-        let transaction = match self
+        let product = match self
             .db
-            .find_transaction_by_uuid(
-                collection.id,
-                Uuid::parse_str(tid).chain_err(|| "unable to parse transaction uuid")?,
+            .find_sealed_product_by_uuid(
+                collector.id,
+                Uuid::parse_str(sid).chain_err(|| "unable to parse sealed product uuid")?,
             )
-            .chain_err(|| "unable to find transaction to update")?
+            .chain_err(|| "unable to find sealed product to open")?
         {
-            Some(transaction) => transaction,
-            None => return Ok(not_found("transaction", tid, None)),
+            Some(product) => product,
+            None => return Ok(not_found("sealed-product", sid, None)),
         };
+        if product.quantity < 1 {
+            return Ok(Object::fail("sealed-product-all-opened"));
+        }
 
-        // update the transaction details, selectively
-        match self.db.update_transaction(
-            &transaction,
-            db::UpdateTransaction {
-                summary: upd.summary,
-                notes: upd.notes,
-                dated: upd.dated,
-                disposition: upd.disposition,
-                gain: upd.gain,
-                loss: upd.loss,
-                paid: Some(upd.paid),
+        let kind = match transaction_kind("open", &attempt.gain, "", product.paid) {
+            Ok(kind) => kind,
+            Err(e) => return Ok(Object::fail(&e)),
+        };
+        if let Err(e) = self.check_grading(collector.id, &attempt.gain, "", None)? {
+            return Ok(Object::fail(&e));
+        }
+        let stowed = match self.stowage_changes(collector.id, &attempt.gain, "")? {
+            Ok(changes) => changes,
+            Err(e) => return Ok(Object::fail(&e)),
+        };
+
+        let summary = match attempt.summary.as_deref().map(str::trim) {
+            Some(summary) if !summary.is_empty() => summary.to_string(),
+            _ => match product.name.as_str() {
+                "" => format!("Opened {} {}", product.set_code, product.kind),
+                name => format!("Opened {}", name),
+            },
+        };
+        let dated = attempt
+            .dated
+            .unwrap_or_else(|| Utc::now().naive_utc().date());
+
+        match self.db.open_sealed_product(
+            &product,
+            db::NewTransaction {
+                collection: collector.id,
+                summary: &summary,
+                notes: attempt.notes.as_deref().unwrap_or(""),
+                dated: &dated,
+                disposition: kind,
+                gain: &attempt.gain,
+                loss: "",
+                paid: product.paid,
+                created_by: Some(collector.id),
             },
         ) {
-            Ok(txn) => Ok(Object::Transaction(Transaction::from(txn))),
+            Ok(Some(txn)) => {
+                self.refresh_goals(collector.id);
+                self.restow(collector.id, stowed);
+                Ok(Object::Transaction(self.transaction(txn)?))
+            }
+            Ok(None) => Ok(Object::fail("sealed-product-all-opened")),
             Err(e) => {
-                println!("transaction-update-failed: {}", e);
-                Ok(Object::fail("transaction-update-failed"))
+                println!("sealed-product-opening-failed: {}", e);
+                Ok(Object::fail("sealed-product-opening-failed"))
             }
         }
     }
 
-    pub fn delete_transaction(&self, cid: &str, tid: &str) -> Result<Object> {
-        let collection = match self
+    pub fn retrieve_templates(&self, uid: &str) -> Result<Object> {
+        let collector = match self
             .db
-            .find_collection_by_uuid(
-                Uuid::parse_str(cid).chain_err(|| "unable to parse collection uuid")?,
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collection to update transaction for")?
+            .chain_err(|| "unable to find collector to retrieve templates for")?
         {
-            Some(collection) => collection,
-            None => return Ok(not_found("collection", cid, None)),
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
         };
 
-        let transaction = match self
+        Ok(Object::Templates(
+            self.db
+                .find_transaction_templates_for_collector(collector.id)
+                .chain_err(|| "unable to find templates for collector")?
+                .into_iter()
+                .map(TransactionTemplate::from)
+                .collect(),
+        ))
+    }
+
+    pub fn create_template(&self, uid: &str, new: TemplateCreationAttempt) -> Result<Object> {
+        let collector = match self
             .db
-            .find_transaction_by_uuid(
-                collection.id,
-                Uuid::parse_str(tid).chain_err(|| "unable to parse transaction uuid")?,
+            .find_collector_by_uuid(
+                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find transaction to update")?
+            .chain_err(|| "unable to find collector to create template for")?
         {
-            Some(transaction) => transaction,
-            None => return Ok(not_found("transaction", tid, None)),
+            Some(collector) => collector,
+            None => return Ok(not_found("collector", uid, None)),
         };
 
-        match self.db.delete_transaction(transaction.id) {
-            Ok(_) => Ok(Object::ok("transaction-removed")),
+        let collection = match new.collection.as_deref() {
+            None => collector.id,
+            Some(cid) => match self.owned_collection(collector.id, cid)? {
+                Some(collection) => collection.id,
+                None => return Ok(not_found("collection", cid, None)),
+            },
+        };
+        let name = new.name.trim();
+        if name.is_empty() {
+            return Ok(Object::fail("invalid-template-name"));
+        }
+        let kind = match postable(&new.disposition) {
+            Ok(kind) => kind,
+            Err(e) => return Ok(Object::fail(&e)),
+        };
+        let summary = new.summary.as_deref().unwrap_or("");
+        let notes = new.notes.as_deref().unwrap_or("");
+        let gain = new.gain.as_deref().unwrap_or("");
+        let loss = new.loss.as_deref().unwrap_or("");
+        if let Err(e) = check_template(&[summary, notes, gain, loss]) {
+            return Ok(Object::fail(&e));
+        }
+
+        match self.db.create_transaction_template(
+            None,
+            db::NewTransactionTemplate {
+                collector: collector.id,
+                collection,
+                name,
+                summary,
+                notes,
+                gain,
+                loss,
+                disposition: kind,
+                paid: new.paid,
+            },
+        ) {
+            Ok(t) => Ok(Object::Template(TransactionTemplate::from(t))),
             Err(e) => {
-                println!("transaction-removal-failed: {}", e);
-                Ok(Object::fail("transaction-removal-failed"))
+                println!("template-creation-failed: {}", e);
+                Ok(Object::fail("template-creation-failed"))
             }
         }
     }
 
-    pub fn create_goal(&self, uid: &str, new: GoalCreationAttempt) -> Result<Object> {
+    pub fn update_template(
+        &self,
+        uid: &str,
+        tid: &str,
+        upd: TemplateUpdateAttempt,
+    ) -> Result<Object> {
         let collector = match self
             .db
             .find_collector_by_uuid(
                 Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collector to create goal for")?
+            .chain_err(|| "unable to find collector to update template for")?
         {
             Some(collector) => collector,
             None => return Ok(not_found("collector", uid, None)),
         };
 
-        match self.db.create_goal(
-            None,
-            db::NewGoal {
-                collector: collector.id,
-                name: &new.name,
-                ordinal: new.ordinal,
-                target: &new.target,
-                goal: &new.goal,
-                total: new.total,
-                progress: new.progress,
+        let t = match self
+            .db
+            .find_transaction_template_by_uuid(
+                collector.id,
+                Uuid::parse_str(tid).chain_err(|| "unable to parse template uuid")?,
+            )
+            .chain_err(|| "unable to find template to update")?
+        {
+            Some(t) => t,
+            None => return Ok(not_found("template", tid, None)),
+        };
+
+        let collection = match upd.collection.as_deref() {
+            None => None,
+            Some(cid) => match self.owned_collection(collector.id, cid)? {
+                Some(collection) => Some(collection.id),
+                None => return Ok(not_found("collection", cid, None)),
+            },
+        };
+        if upd
+            .name
+            .as_deref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            return Ok(Object::fail("invalid-template-name"));
+        }
+        let kind = match upd.disposition.as_deref().map(postable) {
+            Some(Ok(kind)) => Some(kind),
+            Some(Err(e)) => return Ok(Object::fail(&e)),
+            None => None,
+        };
+        if let Err(e) = check_template(&[
+            upd.summary.as_deref().unwrap_or(""),
+            upd.notes.as_deref().unwrap_or(""),
+            upd.gain.as_deref().unwrap_or(""),
+            upd.loss.as_deref().unwrap_or(""),
+        ]) {
+            return Ok(Object::fail(&e));
+        }
+
+        match self.db.update_transaction_template(
+            &t,
+            db::UpdateTransactionTemplate {
+                collection,
+                name: upd.name.map(|name| name.trim().to_string()),
+                summary: upd.summary,
+                notes: upd.notes,
+                gain: upd.gain,
+                loss: upd.loss,
+                disposition: kind,
+                paid: upd.paid,
             },
         ) {
-            Ok(goal) => Ok(Object::Goal(Goal::from(goal))),
+            Ok(t) => Ok(Object::Template(TransactionTemplate::from(t))),
             Err(e) => {
-                println!("goal-creation-failed: {}", e);
-                Ok(Object::fail("goal-creation-failed"))
+                println!("template-update-failed: {}", e);
+                Ok(Object::fail("template-update-failed"))
             }
         }
     }
 
-    pub fn update_goal(&self, uid: &str, gid: &str, upd: GoalUpdateAttempt) -> Result<Object> {
+    pub fn delete_template(&self, uid: &str, tid: &str) -> Result<Object> {
         let collector = match self
             .db
             .find_collector_by_uuid(
                 Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collector to update goal for")?
+            .chain_err(|| "unable to find collector to remove template for")?
         {
             Some(collector) => collector,
             None => return Ok(not_found("collector", uid, None)),
         };
 
-        let goal = match self
+        let t = match self
             .db
-            .find_goal_by_uuid(
+            .find_transaction_template_by_uuid(
                 collector.id,
-                Uuid::parse_str(gid).chain_err(|| "unable to parse goal uuid")?,
+                Uuid::parse_str(tid).chain_err(|| "unable to parse template uuid")?,
             )
-            .chain_err(|| "unable to find goal to update")?
+            .chain_err(|| "unable to find template to remove")?
         {
-            Some(goal) => goal,
-            None => return Ok(not_found("goal", gid, None)),
+            Some(t) => t,
+            None => return Ok(Object::ok("template-already-gone")),
         };
 
-        match self.db.update_goal(
-            &goal,
-            db::UpdateGoal {
-                name: upd.name,
-                ordinal: upd.ordinal,
-                target: upd.target,
-                goal: upd.goal,
-                total: upd.total,
-                progress: upd.progress,
-            },
-        ) {
-            Ok(goal) => Ok(Object::Goal(Goal::from(goal))),
+        match self.db.delete_transaction_template(t.id) {
+            Ok(_) => Ok(Object::ok("template-removed")),
             Err(e) => {
-                println!("goal-update-failed: {}", e);
-                Ok(Object::fail("goal-update-failed"))
+                println!("template-removal-failed: {}", e);
+                Ok(Object::fail("template-removal-failed"))
             }
         }
     }
 
-    pub fn delete_goal(&self, uid: &str, gid: &str) -> Result<Object> {
+    // Make a transaction from a template, just as if it had been posted
+    // to the template's collection by hand.
+    pub fn use_template(
+        &self,
+        uid: &str,
+        tid: &str,
+        attempt: TemplateUseAttempt,
+    ) -> Result<Object> {
         let collector = match self
             .db
             .find_collector_by_uuid(
                 Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collector to update goal for")?
+            .chain_err(|| "unable to find collector to use template for")?
         {
             Some(collector) => collector,
             None => return Ok(not_found("collector", uid, None)),
         };
 
-        let goal = match self
+        let t = match self
             .db
-            .find_goal_by_uuid(
+            .find_transaction_template_by_uuid(
                 collector.id,
-                Uuid::parse_str(gid).chain_err(|| "unable to parse goal uuid")?,
+                Uuid::parse_str(tid).chain_err(|| "unable to parse template uuid")?,
             )
-            .chain_err(|| "unable to find goal to update")?
+            .chain_err(|| "unable to find template to use")?
         {
-            Some(goal) => goal,
-            None => return Ok(Object::ok("goal-already-gone")),
+            Some(t) => t,
+            None => return Ok(not_found("template", tid, None)),
         };
 
-        match self.db.delete_goal(goal.id) {
-            Ok(_) => Ok(Object::ok("goal-removed")),
-            Err(e) => {
-                println!("goal-removal-failed: {}", e);
-                Ok(Object::fail("goal-removal-failed"))
-            }
+        let dated = attempt
+            .dated
+            .unwrap_or_else(|| Utc::now().naive_utc().date());
+        let mut new = match instantiate(&t, &attempt.vars.unwrap_or_default(), dated) {
+            Ok(new) => new,
+            Err(e) => return Ok(Object::fail(&e)),
+        };
+        if attempt.paid.is_some() {
+            new.paid = attempt.paid;
         }
+        self.post_transaction(&t.collection.to_string(), &collector.id.to_string(), new)
     }
 
-    pub fn retrieve_goals_for_collector(&self, uid: &str) -> Result<Object> {
+    pub fn retrieve_recurring_transactions(&self, uid: &str) -> Result<Object> {
         let collector = match self
             .db
             .find_collector_by_uuid(
                 Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collector to retrieve goals from")?
+            .chain_err(|| "unable to find collector to retrieve recurring transactions for")?
         {
             Some(collector) => collector,
             None => return Ok(not_found("collector", uid, None)),
         };
 
-        Ok(Object::list_of_goals(
+        Ok(Object::Recurrings(
             self.db
-                .find_goals_for_collector(collector.id)
-                .chain_err(|| "unable to find goals by collector uuid")?,
+                .find_recurring_transactions_for_collector(collector.id)
+                .chain_err(|| "unable to find recurring transactions for collector")?
+                .into_iter()
+                .map(RecurringTransaction::from)
+                .collect(),
         ))
     }
 
-    pub fn retrieve_goal(&self, uid: &str, gid: &str) -> Result<Object> {
+    pub fn create_recurring_transaction(
+        &self,
+        uid: &str,
+        new: RecurringTransactionCreationAttempt,
+    ) -> Result<Object> {
         let collector = match self
             .db
             .find_collector_by_uuid(
                 Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collector to retrieve goal for")?
+            .chain_err(|| "unable to find collector to create recurring transaction for")?
         {
             Some(collector) => collector,
             None => return Ok(not_found("collector", uid, None)),
         };
 
-        match self
+        let t = match self
             .db
-            .find_goal_by_uuid(
+            .find_transaction_template_by_uuid(
                 collector.id,
-                Uuid::parse_str(gid).chain_err(|| "unable to parse goal uuid")?,
+                Uuid::parse_str(&new.template).chain_err(|| "unable to parse template uuid")?,
             )
-            .chain_err(|| "unable to find goal by uuid")?
+            .chain_err(|| "unable to find template to recur")?
         {
-            Some(goal) => Ok(Object::Goal(Goal::from(goal))),
-            None => Ok(not_found("goal", gid, None)),
-        }
-    }
+            Some(t) => t,
+            None => return Ok(not_found("template", &new.template, None)),
+        };
 
-    pub fn create_deck(&self, uid: &str, new: DeckCreationAttempt) -> Result<Object> {
-        let collector = match self
-            .db
-            .find_collector_by_uuid(
-                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
-            )
-            .chain_err(|| "unable to find collector to create deck for")?
-        {
-            Some(collector) => collector,
-            None => return Ok(not_found("collector", uid, None)),
+        let every = match template::Every::parse(&new.every) {
+            Some(every) => every,
+            None => return Ok(Object::fail("invalid-recurrence")),
         };
+        let starts_on = new
+            .starts_on
+            .unwrap_or_else(|| Utc::now().naive_utc().date());
+        let vars = new.vars.unwrap_or_default();
+        if let Err(e) = check_recurrence(&t, &vars, starts_on, new.ends_on) {
+            return Ok(Object::fail(&e));
+        }
 
-        match self.db.create_deck(
+        match self.db.create_recurring_transaction(
             None,
-            db::NewDeck {
+            db::NewRecurringTransaction {
                 collector: collector.id,
-                title: &new.title,
-                code: &new.code,
-                description: &new.description,
-                main: &new.main,
-                side: &new.side,
-                maybe: &new.maybe,
+                template: t.id,
+                every: every.as_str(),
+                starts_on,
+                ends_on: new.ends_on,
+                vars: serde_json::json!(vars),
+                next_on: starts_on,
             },
         ) {
-            Ok(deck) => Ok(Object::Deck(Deck::from(deck))),
+            Ok(r) => Ok(Object::Recurring(RecurringTransaction::from(r))),
             Err(e) => {
-                println!("deck-creation-failed: {}", e);
-                Ok(Object::fail("deck-creation-failed"))
+                println!("recurring-transaction-creation-failed: {}", e);
+                Ok(Object::fail("recurring-transaction-creation-failed"))
             }
         }
     }
 
-    pub fn update_deck(&self, uid: &str, did: &str, upd: DeckUpdateAttempt) -> Result<Object> {
+    // Changing when (or how often) something recurs doesn't forget what
+    // has been recorded already; the next one is worked out afresh from
+    // the (new) start, skipping as many as have been made so far.
+    pub fn update_recurring_transaction(
+        &self,
+        uid: &str,
+        rid: &str,
+        upd: RecurringTransactionUpdateAttempt,
+    ) -> Result<Object> {
         let collector = match self
             .db
             .find_collector_by_uuid(
                 Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collector to update deck for")?
+            .chain_err(|| "unable to find collector to update recurring transaction for")?
         {
             Some(collector) => collector,
             None => return Ok(not_found("collector", uid, None)),
         };
 
-        let deck = match self
+        let r = match self
             .db
-            .find_deck_by_uuid(
+            .find_recurring_transaction_by_uuid(
                 collector.id,
-                Uuid::parse_str(did).chain_err(|| "unable to parse deck uuid")?,
+                Uuid::parse_str(rid).chain_err(|| "unable to parse recurring transaction uuid")?,
             )
-            .chain_err(|| "unable to find deck to update")?
+            .chain_err(|| "unable to find recurring transaction to update")?
         {
-            Some(deck) => deck,
-            None => return Ok(not_found("deck", did, None)),
+            Some(r) => r,
+            None => return Ok(not_found("recurring-transaction", rid, None)),
+        };
+        let t = match self
+            .db
+            .find_transaction_template_by_uuid(collector.id, r.template)
+            .chain_err(|| "unable to find template of recurring transaction")?
+        {
+            Some(t) => t,
+            None => return Ok(not_found("template", &r.template.to_string(), None)),
         };
 
-        match self.db.update_deck(
-            &deck,
-            db::UpdateDeck {
-                title: upd.title,
-                code: upd.code,
-                description: upd.description,
-                main: upd.main,
-                side: upd.side,
-                maybe: upd.maybe,
+        let every = match template::Every::parse(upd.every.as_deref().unwrap_or(&r.every)) {
+            Some(every) => every,
+            None => return Ok(Object::fail("invalid-recurrence")),
+        };
+        let starts_on = upd.starts_on.unwrap_or(r.starts_on);
+        let ends_on = upd.ends_on.unwrap_or(r.ends_on);
+        let vars = match upd.vars {
+            Some(vars) => vars,
+            None => serde_json::from_value(r.vars.clone()).unwrap_or_default(),
+        };
+        if let Err(e) = check_recurrence(&t, &vars, starts_on, ends_on) {
+            return Ok(Object::fail(&e));
+        }
+
+        match self.db.update_recurring_transaction(
+            &r,
+            db::UpdateRecurringTransaction {
+                every: Some(every.as_str().to_string()),
+                starts_on: Some(starts_on),
+                ends_on: upd.ends_on,
+                vars: Some(serde_json::json!(vars)),
+                next_on: Some(every.nth(starts_on, r.occurrences)),
             },
         ) {
-            Ok(deck) => Ok(Object::Deck(Deck::from(deck))),
+            Ok(r) => Ok(Object::Recurring(RecurringTransaction::from(r))),
             Err(e) => {
-                println!("deck-update-failed: {}", e);
-                Ok(Object::fail("deck-update-failed"))
+                println!("recurring-transaction-update-failed: {}", e);
+                Ok(Object::fail("recurring-transaction-update-failed"))
             }
         }
     }
 
-    pub fn delete_deck(&self, uid: &str, did: &str) -> Result<Object> {
+    pub fn delete_recurring_transaction(&self, uid: &str, rid: &str) -> Result<Object> {
         let collector = match self
             .db
             .find_collector_by_uuid(
                 Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
             )
-            .chain_err(|| "unable to find collector to update deck for")?
+            .chain_err(|| "unable to find collector to remove recurring transaction for")?
         {
             Some(collector) => collector,
             None => return Ok(not_found("collector", uid, None)),
         };
 
-        let deck = match self
+        let r = match self
             .db
-            .find_deck_by_uuid(
+            .find_recurring_transaction_by_uuid(
                 collector.id,
-                Uuid::parse_str(did).chain_err(|| "unable to parse deck uuid")?,
+                Uuid::parse_str(rid).chain_err(|| "unable to parse recurring transaction uuid")?,
             )
-            .chain_err(|| "unable to find deck to update")?
+            .chain_err(|| "unable to find recurring transaction to remove")?
         {
-            Some(deck) => deck,
-            None => return Ok(Object::ok("deck-already-gone")),
+            Some(r) => r,
+            None => return Ok(Object::ok("recurring-transaction-already-gone")),
         };
 
-        match self.db.delete_deck(deck.id) {
-            Ok(_) => Ok(Object::ok("deck-removed")),
+        match self.db.delete_recurring_transaction(r.id) {
+            Ok(_) => Ok(Object::ok("recurring-transaction-removed")),
             Err(e) => {
-                println!("deck-removal-failed: {}", e);
-                Ok(Object::fail("deck-removal-failed"))
+                println!("recurring-transaction-removal-failed: {}", e);
+                Ok(Object::fail("recurring-transaction-removal-failed"))
             }
         }
     }
 
-    pub fn retrieve_decks_for_collector(&self, uid: &str) -> Result<Object> {
-        let collector = match self
+    // Record every occurrence of every recurring transaction that has
+    // come due on or before the given date, catching up on any that
+    // were missed (i.e. if the worker hasn't run for a while).  Those
+    // that can't be recorded (say, because their template no longer
+    // makes sense) are skipped until they can, and noted in the logs.
+    // Returns how many transactions were recorded.
+    pub fn run_recurring_transactions(&self, on: NaiveDate) -> Result<usize> {
+        let mut made = 0;
+        for mut r in self
             .db
-            .find_collector_by_uuid(
-                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
-            )
-            .chain_err(|| "unable to find collector to retrieve decks from")?
+            .find_due_recurring_transactions(on)
+            .chain_err(|| "unable to find due recurring transactions")?
         {
-            Some(collector) => collector,
-            None => return Ok(not_found("collector", uid, None)),
-        };
+            let every = match template::Every::parse(&r.every) {
+                Some(every) => every,
+                None => continue,
+            };
+            let t = match self
+                .db
+                .find_transaction_template_by_uuid(r.collector, r.template)
+                .chain_err(|| "unable to find template of recurring transaction")?
+            {
+                Some(t) => t,
+                None => continue,
+            };
+            let vars: HashMap<String, String> =
+                serde_json::from_value(r.vars.clone()).unwrap_or_default();
 
-        Ok(Object::list_of_decks(
-            self.db
-                .find_decks_for_collector(collector.id)
-                .chain_err(|| "unable to find decks by collector uuid")?,
-        ))
+            while r.next_on <= on && r.ends_on.is_none_or(|ends_on| r.next_on <= ends_on) {
+                let next_on = every.nth(r.starts_on, r.occurrences + 1);
+                match self.recur(&r, &t, &vars, next_on) {
+                    Ok(true) => made += 1,
+                    Ok(false) => break,
+                    Err(e) => {
+                        println!(
+                            "recurring-transaction-failed: {} on {}: {}",
+                            r.id, r.next_on, e
+                        );
+                        break;
+                    }
+                }
+                r.occurrences += 1;
+                r.next_on = next_on;
+            }
+        }
+        Ok(made)
     }
 
-    pub fn retrieve_deck(&self, uid: &str, did: &str) -> Result<Object> {
-        let collector = match self
-            .db
-            .find_collector_by_uuid(
-                Uuid::parse_str(uid).chain_err(|| "unable to parse collector uuid")?,
+    // Record the next occurrence of a recurring transaction, returning
+    // false if it had already been recorded elsewhere.
+    fn recur(
+        &self,
+        r: &db::RecurringTransaction,
+        t: &db::TransactionTemplate,
+        vars: &HashMap<String, String>,
+        next_on: NaiveDate,
+    ) -> std::result::Result<bool, String> {
+        let new = instantiate(t, vars, r.next_on)?;
+        let kind = transaction_kind(&new.disposition, &new.gain, &new.loss, new.paid)?;
+        self.check_grading(r.collector, &new.gain, &new.loss, None)
+            .map_err(|e| e.to_string())??;
+        let stowed = self
+            .stowage_changes(r.collector, &new.gain, &new.loss)
+            .map_err(|e| e.to_string())??;
+
+        match self.db.record_recurring_transaction(
+            r,
+            next_on,
+            db::NewTransaction {
+                collection: t.collection,
+                summary: &new.summary,
+                notes: &new.notes,
+                dated: &new.dated,
+                disposition: kind,
+                gain: &new.gain,
+                loss: &new.loss,
+                paid: new.paid,
+                created_by: None,
+            },
+        ) {
+            Ok(Some(_)) => {
+                self.refresh_goals(r.collector);
+                self.restow(r.collector, stowed);
+                Ok(true)
+            }
+            Ok(None) => Ok(false),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_keep_collections_from_anyone_they_are_not_shared_with() {
+        use role::Role;
+        use token::Scope;
+
+        let (_tmp, db) = db::test::connect();
+        let mut ids = vec![];
+        for (username, email) in &[
+            ("jhunt", "james@example.com"),
+            ("ebarnes", "eve@example.com"),
+            ("kmoore", "kim@example.com"),
+        ] {
+            let c = db
+                .create_collector(None, db::NewCollector { username, email }, None)
+                .unwrap();
+            let t = token::generate();
+            db.create_api_token(
+                None,
+                db::NewApiToken {
+                    collector: c.id,
+                    name: "reader",
+                    hash: &token::hash(&t),
+                    hint: &token::hint(&t),
+                    scopes: vec![Scope::ReadCollection.as_str().to_string()],
+                },
+            )
+            .unwrap();
+            ids.push((c.id, t));
+        }
+        let (owner, member, stranger) = (&ids[0], &ids[1], &ids[2]);
+
+        let invite = db
+            .invite_collection_member(db::NewCollectionMember {
+                collection: owner.0,
+                collector: member.0,
+                role: "viewer",
+                invited_by: Some(owner.0),
+            })
+            .unwrap();
+        db.accept_collection_membership(&invite).unwrap();
+
+        let api = API::new(db);
+        let uid = owner.0.to_string();
+        let read = |cred: Option<&String>| {
+            api.guard_collection(
+                cred.cloned(),
+                &uid,
+                "_",
+                Role::Viewer,
+                Some(Scope::ReadCollection),
             )
-            .chain_err(|| "unable to find collector to retrieve deck for")?
-        {
-            Some(collector) => collector,
-            None => return Ok(not_found("collector", uid, None)),
         };
+        assert_eq!(Err(401), read(None));
+        assert_eq!(Err(403), read(Some(&stranger.1)));
+        assert_eq!(Ok(member.0.to_string()), read(Some(&member.1)));
+        assert_eq!(Ok(uid.to_string()), read(Some(&owner.1)));
+    }
 
-        match self
+    #[test]
+    fn should_end_sessions_and_revoke_api_tokens_on_password_reset() {
+        std::env::set_var("VCB_SECRET", "sekrit");
+        let (_tmp, db) = db::test::connect();
+        let who = db
+            .create_collector(
+                None,
+                db::NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                Some("hunter22"),
+            )
+            .unwrap();
+        let t = token::generate();
+        db.create_api_token(
+            None,
+            db::NewApiToken {
+                collector: who.id,
+                name: "nightly import",
+                hash: &token::hash(&t),
+                hint: &token::hint(&t),
+                scopes: vec![token::Scope::ReadCollection.as_str().to_string()],
+            },
+        )
+        .unwrap();
+
+        let purpose = account::Purpose::ResetPassword;
+        let expires = Utc::now() + purpose.lifetime();
+        let issued = db
+            .create_account_token(
+                None,
+                db::NewAccountToken {
+                    collector: who.id,
+                    purpose: purpose.as_str(),
+                    email: &who.email,
+                    expires_at: expires,
+                },
+            )
+            .unwrap();
+        let reset = || PasswordResetAttempt {
+            token: account::sign("sekrit", purpose, issued.id, expires),
+            password: "correct horse".to_string(),
+        };
+
+        let api = API::new(db);
+        assert_eq!(
+            Ok(who.id.to_string()),
+            api.token_user(&t, Some(token::Scope::ReadCollection))
+        );
+
+        let said = |o: Object| match o {
+            Object::Response(r) => (r.ok, r.message),
+            _ => panic!("expected a response"),
+        };
+        assert_eq!(
+            (true, "password-reset".to_string()),
+            said(api.reset_password(reset()).unwrap())
+        );
+        assert_eq!(
+            (false, "invalid-token".to_string()),
+            said(api.reset_password(reset()).unwrap())
+        );
+
+        let now = api.db.find_collector_by_uuid(who.id).unwrap().unwrap();
+        assert_eq!(who.session_generation + 1, now.session_generation);
+        assert!(api
             .db
-            .find_deck_by_uuid(
-                collector.id,
-                Uuid::parse_str(did).chain_err(|| "unable to parse deck uuid")?,
+            .authenticate_collector("jhunt", "correct horse")
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            Err(401),
+            api.token_user(&t, Some(token::Scope::ReadCollection))
+        );
+
+        let mut session = db::Session::new(None);
+        session.set("generation", &who.session_generation.to_string());
+        assert!(!is_current(&session, &now));
+        session.set("generation", &now.session_generation.to_string());
+        assert!(is_current(&session, &now));
+    }
+
+    #[test]
+    fn should_tell_cleared_fields_from_missing_ones() {
+        let upd: GoalUpdateAttempt =
+            serde_json::from_str(r#"{"deadline": null, "budget": 2500}"#).unwrap();
+        assert_eq!(Some(None), upd.deadline);
+        assert_eq!(Some(Some(2500)), upd.budget);
+
+        let upd: GoalUpdateAttempt = serde_json::from_str(r#"{"name": "Moar"}"#).unwrap();
+        assert_eq!(None, upd.deadline);
+        assert_eq!(None, upd.budget);
+    }
+
+    #[test]
+    fn should_clear_watchlist_thresholds() {
+        let upd: WatchlistUpdateAttempt =
+            serde_json::from_str(r#"{"below": null, "above": 1500}"#).unwrap();
+        assert_eq!(Some(None), upd.below);
+        assert_eq!(Some(Some(1500)), upd.above);
+
+        let upd: WatchlistUpdateAttempt = serde_json::from_str(r#"{"notes": "hm"}"#).unwrap();
+        assert_eq!(None, upd.below);
+        assert_eq!(None, upd.above);
+    }
+
+    #[test]
+    fn should_clear_what_was_paid_for_sealed_product() {
+        let upd: SealedProductUpdateAttempt = serde_json::from_str(r#"{"paid": null}"#).unwrap();
+        assert_eq!(Some(None), upd.paid);
+
+        let upd: SealedProductUpdateAttempt = serde_json::from_str(r#"{"packs": 36}"#).unwrap();
+        assert_eq!(None, upd.paid);
+    }
+
+    #[test]
+    fn should_clear_template_payments_and_recurrence_ends() {
+        let upd: TemplateUpdateAttempt = serde_json::from_str(r#"{"paid": null}"#).unwrap();
+        assert_eq!(Some(None), upd.paid);
+        let upd: TemplateUpdateAttempt = serde_json::from_str(r#"{"name": "subs"}"#).unwrap();
+        assert_eq!(None, upd.paid);
+
+        let upd: RecurringTransactionUpdateAttempt =
+            serde_json::from_str(r#"{"ends_on": null}"#).unwrap();
+        assert_eq!(Some(None), upd.ends_on);
+        let upd: RecurringTransactionUpdateAttempt =
+            serde_json::from_str(r#"{"every": "weekly"}"#).unwrap();
+        assert_eq!(None, upd.ends_on);
+    }
+
+    #[test]
+    fn should_keep_the_history_of_collections_cards_were_transferred_out_of() {
+        let (_tmp, db) = db::test::connect();
+        let jhunt = db
+            .create_collector(
+                None,
+                db::NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
             )
-            .chain_err(|| "unable to find deck by uuid")?
+            .unwrap();
+        let trades = db
+            .create_collection(
+                None,
+                db::NewCollection {
+                    collector: jhunt.id,
+                    name: "Trades",
+                },
+            )
+            .unwrap();
+
+        let bought = NaiveDate::from_ymd_opt(2020, 11, 14).unwrap();
+        let dated = NaiveDate::from_ymd_opt(2020, 11, 15).unwrap();
+        let half = |collection, gain, loss| db::NewTransaction {
+            collection,
+            summary: "",
+            notes: "",
+            dated: &dated,
+            disposition: disposition::Disposition::Transfer,
+            gain,
+            loss,
+            paid: None,
+            created_by: None,
+        };
+        db.create_transaction(
+            None,
+            db::NewTransaction {
+                dated: &bought,
+                disposition: disposition::Disposition::Buy,
+                paid: Some(300),
+                ..half(trades.id, "1x XLN Opt\n", "")
+            },
+        )
+        .unwrap();
+        db.transfer_cards(
+            half(trades.id, "", "1x XLN Opt\n"),
+            half(jhunt.id, "1x XLN Opt\n", ""),
+        )
+        .unwrap();
+
+        let api = API::new(db);
+        let uid = jhunt.id.to_string();
+        match api.delete_collection(&uid, &trades.id.to_string()).unwrap() {
+            Object::Response(r) => assert_eq!("collection-has-history", r.message),
+            _ => panic!("expected a response"),
+        }
+        match api.retrieve_collection_profit(&uid, "", "").unwrap() {
+            Object::Profit(report) => {
+                assert_eq!(1, report.total.held);
+                assert_eq!(300, report.total.cost);
+            }
+            _ => panic!("expected a profit report"),
+        }
+    }
+
+    #[test]
+    fn should_trade_away_cards_kept_outside_the_default_collection() {
+        let (_tmp, db) = db::test::connect();
+        let mut who = vec![];
+        for (username, email) in &[
+            ("jhunt", "james@example.com"),
+            ("ebarnes", "eve@example.com"),
+        ] {
+            who.push(
+                db.create_collector(None, db::NewCollector { username, email }, None)
+                    .unwrap(),
+            );
+        }
+        let (jhunt, ebarnes) = (&who[0], &who[1]);
+        let binder = db
+            .create_collection(
+                None,
+                db::NewCollection {
+                    collector: jhunt.id,
+                    name: "Binder",
+                },
+            )
+            .unwrap();
+
+        let dated = NaiveDate::from_ymd_opt(2020, 11, 14).unwrap();
+        let buy = |collection, gain| db::NewTransaction {
+            collection,
+            summary: "",
+            notes: "",
+            dated: &dated,
+            disposition: disposition::Disposition::Buy,
+            gain,
+            loss: "",
+            paid: Some(300),
+            created_by: None,
+        };
+        db.create_transaction(None, buy(jhunt.id, "1x XLN Opt\n"))
+            .unwrap();
+        db.create_transaction(None, buy(binder.id, "2x XLN Opt\n"))
+            .unwrap();
+        db.create_transaction(None, buy(ebarnes.id, "1x GRN Radical Idea\n"))
+            .unwrap();
+
+        let api = API::new(db);
+        let prices = r#"{"xln-opt-fake-id": {"usd": 0.25}, "grn-rad-fake-id": {"usd": 0.75}}"#;
+        api.store("prices.json", prices.as_bytes()).unwrap();
+        let proposal = TradeProposalAttempt {
+            with: ebarnes.id.to_string(),
+            offer: "3x XLN Opt\n".to_string(),
+            request: "1x GRN Radical Idea\n".to_string(),
+            message: "".to_string(),
+        };
+        let t = match api.propose_trade(&jhunt.id.to_string(), proposal).unwrap() {
+            Object::Trade(t) => t.id,
+            _ => panic!("expected a trade"),
+        };
+        match api.accept_trade(&ebarnes.id.to_string(), &t).unwrap() {
+            Object::Trade(t) => assert_eq!("accepted", t.status),
+            _ => panic!("expected an accepted trade"),
+        }
+
+        let held = |id| valuation::holdings(&api.db.load_collection(id).unwrap());
+        let opt = ("xln-opt-fake-id".to_string(), card::Finish::Nonfoil);
+        assert_eq!(None, held(binder.id).get(&opt).filter(|n| **n != 0));
+        assert_eq!(None, held(jhunt.id).get(&opt).filter(|n| **n != 0));
+        assert_eq!(Some(&3), held(ebarnes.id).get(&opt));
+    }
+
+    #[test]
+    fn should_only_transfer_cards_the_collection_still_holds() {
+        let (_tmp, db) = db::test::connect();
+        let jhunt = db
+            .create_collector(
+                None,
+                db::NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
+        let binder = db
+            .create_collection(
+                None,
+                db::NewCollection {
+                    collector: jhunt.id,
+                    name: "Binder",
+                },
+            )
+            .unwrap();
+        let dated = NaiveDate::from_ymd_opt(2020, 11, 14).unwrap();
+        db.create_transaction(
+            None,
+            db::NewTransaction {
+                collection: jhunt.id,
+                summary: "",
+                notes: "",
+                dated: &dated,
+                disposition: disposition::Disposition::Buy,
+                gain: "2x XLN Opt\n",
+                loss: "",
+                paid: Some(50),
+                created_by: None,
+            },
+        )
+        .unwrap();
+
+        let api = API::new(db);
+        let (uid, cid) = (jhunt.id.to_string(), jhunt.id.to_string());
+        let transfer = |n| {
+            let attempt = TransferAttempt {
+                to: binder.id.to_string(),
+                cards: format!("{}x XLN Opt\n", n),
+                dated: None,
+                summary: None,
+                notes: None,
+            };
+            match api.transfer_cards(&cid, &uid, attempt).unwrap() {
+                Object::Transfer(_) => "moved".to_string(),
+                Object::Response(r) => r.message,
+                _ => panic!("expected a transfer"),
+            }
+        };
+        assert_eq!("moved", transfer(1));
+        assert_eq!("transfer-not-owned", transfer(2));
+        assert_eq!("moved", transfer(1));
+        assert_eq!("transfer-not-owned", transfer(1));
+    }
+
+    #[test]
+    fn should_refuse_pods_too_big_to_draft() {
+        let (_tmp, db) = db::test::connect();
+        let jhunt = db
+            .create_collector(
+                None,
+                db::NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
+
+        let api = API::new(db);
+        let create = |seats, rounds| {
+            let new = PodCreationAttempt {
+                set: "MIR".to_string(),
+                rounds,
+                seats: (0..seats)
+                    .map(|i| SeatAttempt {
+                        name: format!("Bot {}", i),
+                        collector: None,
+                        bot: Some("rarity".to_string()),
+                    })
+                    .collect(),
+            };
+            match api.create_pod(&jhunt.id.to_string(), new).unwrap() {
+                Object::Response(r) => r.message,
+                _ => panic!("expected a failure"),
+            }
+        };
+        assert_eq!("too-many-drafters", create(9, None));
+        assert_eq!("too-many-drafters", create(1000, Some(3)));
+        assert_eq!("invalid-draft-rounds", create(8, Some(0)));
+        assert_eq!("invalid-draft-rounds", create(8, Some(100)));
+    }
+
+    #[test]
+    fn should_give_back_goal_progress_when_a_buy_is_deleted() {
+        let (_tmp, db) = db::test::connect();
+        let jhunt = db
+            .create_collector(
+                None,
+                db::NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
+        db.create_goal(
+            None,
+            db::NewGoal {
+                collector: jhunt.id,
+                name: "Afterlives",
+                ordinal: 0,
+                target: "set:MIR and name:afterlife",
+                goal: "playset",
+                total: None,
+                progress: None,
+                deadline: None,
+                budget: None,
+            },
+        )
+        .unwrap();
+
+        let api = API::new(db);
+        let (_, lookup) = card::test_pool();
+        let lookup = serde_json::to_string(&lookup).unwrap();
+        let cards = std::fs::File::open("test/cards.json").unwrap();
+        api.store("cards.json", cards).unwrap();
+        api.store("lookup.json", lookup.as_bytes()).unwrap();
+
+        let uid = jhunt.id.to_string();
+        let buy = |gain: &str| TransactionCreationAttempt {
+            summary: "".to_string(),
+            notes: "".to_string(),
+            dated: NaiveDate::from_ymd_opt(2020, 11, 14).unwrap(),
+            disposition: "buy".to_string(),
+            paid: Some(100),
+            gain: gain.to_string(),
+            loss: "".to_string(),
+        };
+        let progress = || api.db.find_goals_for_collector(jhunt.id).unwrap()[0].progress;
+
+        api.post_transaction(&uid, &uid, buy("1x MIR Afterlife\n"))
+            .unwrap();
+        let tid = match api
+            .post_transaction(&uid, &uid, buy("3x MIR Afterlife\n"))
+            .unwrap()
         {
-            Some(deck) => Ok(Object::Deck(Deck::from(deck))),
-            None => Ok(not_found("deck", did, None)),
+            Object::Transaction(txn) => txn.id,
+            _ => panic!("expected a transaction"),
+        };
+        assert_eq!(Some(1), progress());
+
+        match api.delete_transaction(&uid, &tid).unwrap() {
+            Object::Response(r) => assert_eq!("transaction-removed", r.message),
+            _ => panic!("expected a response"),
         }
+        assert_eq!(Some(0), progress());
     }
 }
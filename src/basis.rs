@@ -0,0 +1,759 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use uuid::Uuid;
+
+use crate::disposition::Disposition;
+use crate::valuation::{self, Key};
+use crate::{card, cdif};
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// How the amount paid for a transaction is spread across the cards it
+// brought into the collection: either in proportion to what each card
+// was worth on the day, or evenly, card-by-card.
+//
+// Market allocation gives cards without a price on the day no part of
+// the cost; if none of the cards were priced, it falls back to even.
+//
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Allocation {
+    Market,
+    Even,
+}
+
+impl Allocation {
+    pub fn parse(s: &str) -> Result<Allocation> {
+        match s {
+            "" | "market" => Ok(Allocation::Market),
+            "even" => Ok(Allocation::Even),
+            _ => Err(format!("invalid-allocation: {}", s).into()),
+        }
+    }
+}
+
+// How cards leaving the collection are matched up with the lots they
+// were bought in: oldest lot first, or at the average cost of every
+// copy held.
+//
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Method {
+    Fifo,
+    Average,
+}
+
+impl Method {
+    pub fn parse(s: &str) -> Result<Method> {
+        match s {
+            "" | "fifo" => Ok(Method::Fifo),
+            "average" => Ok(Method::Average),
+            _ => Err(format!("invalid-cost-method: {}", s).into()),
+        }
+    }
+}
+
+// A transaction, with its gains and losses resolved to print ids.
+//
+// What `paid` means depends on the disposition: for a buy, it is what
+// was paid for the cards gained; for a sell, it is what was received
+// for the cards lost.  Trades realize the cards given up at their
+// market value, which (plus anything paid on top) becomes the cost
+// of the cards received.
+//
+// Entries dated the same day are taken in the order they were recorded
+// (and, failing that, by id), so that buys and sells on the same day
+// always come out the same way, whoever is running the ledger.
+//
+// Transfers between a collector's collections carry the cost basis of
+// the cards moved along with them, so each entry knows the collection
+// it was posted to and, for transfers, the id of the other half.
+//
+pub struct Entry {
+    pub dated: NaiveDate,
+    pub recorded: (DateTime<Utc>, Uuid),
+    pub collection: Uuid,
+    pub transfer: Option<Uuid>,
+    pub disposition: Disposition,
+    pub paid: Option<i32>,
+    pub gain: Vec<(Key, String, i64)>,
+    pub loss: Vec<(Key, String, i64)>,
+}
+
+impl Entry {
+    pub fn resolve(
+        lookup: &card::Map,
+        dated: NaiveDate,
+        recorded: (DateTime<Utc>, Uuid),
+        disposition: Disposition,
+        paid: Option<i32>,
+        gain: &cdif::File,
+        loss: &cdif::File,
+    ) -> Entry {
+        Entry {
+            dated,
+            recorded,
+            collection: Uuid::nil(),
+            transfer: None,
+            disposition,
+            paid,
+            gain: valuation::resolve(lookup, gain),
+            loss: valuation::resolve(lookup, loss),
+        }
+    }
+
+    pub fn within(self, collection: Uuid, transfer: Option<Uuid>) -> Entry {
+        Entry {
+            collection,
+            transfer,
+            ..self
+        }
+    }
+}
+
+struct Lot {
+    quantity: i64,
+    cost: f64, // pennies, per card
+}
+
+#[derive(Default)]
+struct Position {
+    lots: VecDeque<Lot>,
+    sold: i64,
+    proceeds: f64,
+    basis: f64,
+}
+
+impl Position {
+    fn held(&self) -> i64 {
+        self.lots.iter().map(|l| l.quantity).sum()
+    }
+
+    fn cost(&self) -> f64 {
+        self.lots.iter().map(|l| l.quantity as f64 * l.cost).sum()
+    }
+
+    fn acquire(&mut self, method: Method, quantity: i64, cost: f64) {
+        match (method, self.lots.front_mut()) {
+            (Method::Average, Some(lot)) => {
+                let total = lot.quantity + quantity;
+                lot.cost = (lot.quantity as f64 * lot.cost + quantity as f64 * cost) / total as f64;
+                lot.quantity = total;
+            }
+            _ => self.lots.push_back(Lot { quantity, cost }),
+        }
+    }
+
+    // Take cards out of the position, oldest lot first, returning the
+    // (parts of) lots taken.  Cards we have no lot for (i.e. that came
+    // into the collection some other way) have no basis.
+    fn dispose(&mut self, mut quantity: i64) -> Vec<Lot> {
+        let mut taken = vec![];
+        while quantity > 0 {
+            let lot = match self.lots.front_mut() {
+                Some(lot) => lot,
+                None => break,
+            };
+            let n = quantity.min(lot.quantity);
+            taken.push(Lot {
+                quantity: n,
+                cost: lot.cost,
+            });
+            lot.quantity -= n;
+            quantity -= n;
+            if lot.quantity == 0 {
+                self.lots.pop_front();
+            }
+        }
+        taken
+    }
+}
+
+pub struct Ledger {
+    allocation: Allocation,
+    method: Method,
+    currency: card::Currency,
+    positions: HashMap<(Uuid, Key), Position>,
+    sets: HashMap<String, String>,
+    postings: HashMap<Uuid, Uuid>, // entry id -> collection
+}
+
+fn price(
+    history: &card::History,
+    key: &Key,
+    currency: card::Currency,
+    dated: NaiveDate,
+) -> Option<f64> {
+    // before the history starts, the oldest prices are the best we have.
+    let i = match history.index_at(dated) {
+        Some(i) => i,
+        None if !history.dates.is_empty() => 0,
+        None => return None,
+    };
+    valuation::price_at(history, i, key, currency).map(|p| p as f64)
+}
+
+// Spread a total amount across some cards, returning the share of a
+// single copy of each.
+fn allocate(
+    ledger: &Ledger,
+    history: &card::History,
+    dated: NaiveDate,
+    total: f64,
+    cards: &[(Key, String, i64)],
+) -> HashMap<Key, f64> {
+    let mut shares = HashMap::new();
+    let n: i64 = cards.iter().map(|(_, _, n)| n).sum();
+    if n == 0 {
+        return shares;
+    }
+
+    if ledger.allocation == Allocation::Market {
+        let prices: Vec<Option<f64>> = cards
+            .iter()
+            .map(|(key, _, _)| price(history, key, ledger.currency, dated))
+            .collect();
+        let worth: f64 = cards
+            .iter()
+            .zip(&prices)
+            .map(|((_, _, n), p)| *n as f64 * p.unwrap_or(0.0))
+            .sum();
+
+        if worth > 0.0 {
+            for ((key, _, _), p) in cards.iter().zip(prices) {
+                shares.insert(key.clone(), total * p.unwrap_or(0.0) / worth);
+            }
+            return shares;
+        }
+    }
+
+    for (key, _, _) in cards {
+        shares.insert(key.clone(), total / n as f64);
+    }
+    shares
+}
+
+impl Ledger {
+    pub fn new(allocation: Allocation, method: Method, currency: card::Currency) -> Ledger {
+        Ledger {
+            allocation,
+            method,
+            currency,
+            positions: HashMap::new(),
+            sets: HashMap::new(),
+            postings: HashMap::new(),
+        }
+    }
+
+    fn post(&mut self, entries: &[Entry]) {
+        for e in entries {
+            self.postings.insert(e.recorded.1, e.collection);
+        }
+    }
+
+    // Run a collector's transactions through the ledger, in the order
+    // they happened.
+    pub fn run(
+        allocation: Allocation,
+        method: Method,
+        currency: card::Currency,
+        history: &card::History,
+        mut entries: Vec<Entry>,
+    ) -> Ledger {
+        entries.sort_by_key(|e| (e.dated, e.recorded));
+        let mut ledger = Ledger::new(allocation, method, currency);
+        ledger.post(&entries);
+        for entry in &entries {
+            ledger.apply(history, entry);
+        }
+        ledger
+    }
+
+    // Run a collector's transactions through the ledger (as with run),
+    // returning the cost basis of the cards each one disposed of, in
+    // pennies, in the same order as the entries were given.
+    pub fn disposals(
+        allocation: Allocation,
+        method: Method,
+        currency: card::Currency,
+        history: &card::History,
+        entries: &[Entry],
+    ) -> Vec<i64> {
+        let mut order: Vec<usize> = (0..entries.len()).collect();
+        order.sort_by_key(|i| (entries[*i].dated, entries[*i].recorded));
+
+        let mut ledger = Ledger::new(allocation, method, currency);
+        ledger.post(entries);
+        let mut basis = vec![0; entries.len()];
+        for i in order {
+            basis[i] = ledger.apply(history, &entries[i]).round() as i64;
+        }
+        basis
+    }
+
+    // Apply a single transaction, returning the cost basis of the cards
+    // it took out of the collection, in pennies.
+    //
+    // The half of a transfer that takes cards out of one collection
+    // moves their lots into the other, so long as the ledger has seen
+    // the other half; that half then brings in nothing of its own.
+    pub fn apply(&mut self, history: &card::History, entry: &Entry) -> f64 {
+        let worth: f64 = entry
+            .loss
+            .iter()
+            .map(|(key, _, n)| {
+                *n as f64 * price(history, key, self.currency, entry.dated).unwrap_or(0.0)
+            })
+            .sum();
+        let paid = entry.paid.unwrap_or(0) as f64;
+
+        // other dispositions (corrections, gifts, etc.) remove cards
+        // from the collection without realizing anything.
+        let proceeds = match entry.disposition {
+            Disposition::Sell => Some(paid),
+            Disposition::Trade => Some(worth),
+            _ => None,
+        };
+        let cost = match entry.disposition {
+            Disposition::Sell => 0.0,
+            Disposition::Trade => worth + paid,
+            _ => paid,
+        };
+
+        let carried = match (entry.disposition, entry.transfer) {
+            (Disposition::Transfer, Some(other)) => self.postings.get(&other).copied(),
+            _ => None,
+        };
+
+        let shares = proceeds.map(|total| allocate(self, history, entry.dated, total, &entry.loss));
+        let mut disposed = 0.0;
+        for (key, set, n) in &entry.loss {
+            self.sets.insert(key.0.to_string(), set.to_string());
+            let position = self
+                .positions
+                .entry((entry.collection, key.clone()))
+                .or_default();
+            let lots = position.dispose(*n);
+            let basis: f64 = lots.iter().map(|l| l.quantity as f64 * l.cost).sum();
+            disposed += basis;
+            if let Some(shares) = &shares {
+                position.sold += n;
+                position.proceeds += shares[key] * *n as f64;
+                position.basis += basis;
+            }
+            if let Some(to) = carried {
+                let position = self.positions.entry((to, key.clone())).or_default();
+                for lot in lots {
+                    position.acquire(self.method, lot.quantity, lot.cost);
+                }
+            }
+        }
+        if carried.is_some() {
+            return disposed;
+        }
+
+        let shares = allocate(self, history, entry.dated, cost, &entry.gain);
+        for (key, set, n) in &entry.gain {
+            self.sets.insert(key.0.to_string(), set.to_string());
+            self.positions
+                .entry((entry.collection, key.clone()))
+                .or_default()
+                .acquire(self.method, *n, shares[key]);
+        }
+        disposed
+    }
+
+    // Report realized and unrealized gains in one collection, card-by-
+    // card, set-by-set and in total, valuing what is still held at the
+    // most recent prices in the history.
+    pub fn report(&self, history: &card::History, collection: Uuid) -> Report {
+        let now = history.dates.len().checked_sub(1);
+
+        let mut cards = vec![];
+        let mut sets: BTreeMap<String, Profit> = BTreeMap::new();
+        let mut total = Profit::default();
+
+        for ((cid, key), position) in &self.positions {
+            if *cid != collection {
+                continue;
+            }
+            let held = position.held();
+            if held == 0 && position.sold == 0 {
+                continue;
+            }
+
+            let mut profit = Profit {
+                held,
+                cost: position.cost().round() as i64,
+                sold: position.sold,
+                proceeds: position.proceeds.round() as i64,
+                realized: (position.proceeds - position.basis).round() as i64,
+                ..Profit::default()
+            };
+            if held > 0 {
+                match now.and_then(|i| valuation::price_at(history, i, key, self.currency)) {
+                    Some(p) => {
+                        profit.value = p * held;
+                        profit.unrealized = profit.value - profit.cost;
+                    }
+                    None => profit.unpriced = held,
+                }
+            }
+
+            let set = self.sets.get(&key.0).cloned().unwrap_or_default();
+            sets.entry(set.to_string()).or_default().add(&profit);
+            total.add(&profit);
+            cards.push(CardProfit {
+                id: key.0.to_string(),
+                finish: key.1,
+                set,
+                profit,
+            });
+        }
+
+        cards.sort_by(|a, b| (&a.set, &a.id, a.finish).cmp(&(&b.set, &b.id, b.finish)));
+        Report {
+            cards,
+            sets: sets
+                .into_iter()
+                .map(|(set, profit)| SetProfit { set, profit })
+                .collect(),
+            total,
+        }
+    }
+}
+
+// All amounts are in pennies.  Unrealized gains only account for the
+// cards we have a current price for; the rest are counted as unpriced.
+#[derive(Serialize, Default, Clone, Debug, PartialEq)]
+pub struct Profit {
+    pub held: i64,
+    pub cost: i64,
+    pub value: i64,
+    pub unrealized: i64,
+    pub unpriced: i64,
+    pub sold: i64,
+    pub proceeds: i64,
+    pub realized: i64,
+}
+
+impl Profit {
+    fn add(&mut self, other: &Profit) {
+        self.held += other.held;
+        self.cost += other.cost;
+        self.value += other.value;
+        self.unrealized += other.unrealized;
+        self.unpriced += other.unpriced;
+        self.sold += other.sold;
+        self.proceeds += other.proceeds;
+        self.realized += other.realized;
+    }
+}
+
+#[derive(Serialize)]
+pub struct CardProfit {
+    pub id: String, // print id
+    pub finish: card::Finish,
+    pub set: String,
+    #[serde(flatten)]
+    pub profit: Profit,
+}
+
+#[derive(Serialize)]
+pub struct SetProfit {
+    pub set: String,
+    #[serde(flatten)]
+    pub profit: Profit,
+}
+
+#[derive(Serialize)]
+pub struct Report {
+    pub cards: Vec<CardProfit>,
+    pub sets: Vec<SetProfit>,
+    pub total: Profit,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 8, d).unwrap()
+    }
+
+    fn quote(usd: f64) -> card::Quote {
+        card::Quote {
+            usd: Some(usd),
+            ..card::Quote::default()
+        }
+    }
+
+    fn history() -> card::History {
+        let mut history = card::History::default();
+        let mut quotes = HashMap::new();
+        quotes.insert("bolt".to_string(), quote(3.00));
+        quotes.insert("opt".to_string(), quote(1.00));
+        history.record(day(1), &quotes);
+
+        quotes.insert("bolt".to_string(), quote(5.00));
+        history.record(day(10), &quotes);
+        history
+    }
+
+    fn cards(v: &[(&str, i64)]) -> Vec<(Key, String, i64)> {
+        v.iter()
+            .map(|(pid, n)| {
+                (
+                    (pid.to_string(), card::Finish::Nonfoil),
+                    "M10".to_string(),
+                    *n,
+                )
+            })
+            .collect()
+    }
+
+    fn entry(
+        d: u32,
+        disposition: &str,
+        paid: i32,
+        gain: &[(&str, i64)],
+        loss: &[(&str, i64)],
+    ) -> Entry {
+        Entry {
+            dated: day(d),
+            recorded: (
+                Utc.with_ymd_and_hms(2020, 8, d, 0, 0, 0).unwrap(),
+                Uuid::nil(),
+            ),
+            collection: Uuid::nil(),
+            transfer: None,
+            disposition: Disposition::parse(disposition).unwrap(),
+            paid: Some(paid),
+            gain: cards(gain),
+            loss: cards(loss),
+        }
+    }
+
+    fn card<'a>(report: &'a Report, id: &str) -> &'a Profit {
+        &report.cards.iter().find(|c| c.id == id).unwrap().profit
+    }
+
+    #[test]
+    fn should_parse_allocations_and_methods() {
+        assert_eq!(Allocation::Market, Allocation::parse("").unwrap());
+        assert_eq!(Allocation::Even, Allocation::parse("even").unwrap());
+        assert!(Allocation::parse("lifo").is_err());
+        assert_eq!(Method::Fifo, Method::parse("").unwrap());
+        assert_eq!(Method::Average, Method::parse("average").unwrap());
+        assert!(Method::parse("market").is_err());
+    }
+
+    #[test]
+    fn should_allocate_cost_by_market_price_or_evenly() {
+        let history = history();
+        let buy = vec![entry(2, "buy", 1000, &[("bolt", 2), ("opt", 4)], &[])];
+
+        // bolts were $3 and opts $1, so the $10 splits 6:4
+        let report = Ledger::run(
+            Allocation::Market,
+            Method::Fifo,
+            card::Currency::Usd,
+            &history,
+            buy,
+        )
+        .report(&history, Uuid::nil());
+        assert_eq!(600, card(&report, "bolt").cost);
+        assert_eq!(400, card(&report, "opt").cost);
+        assert_eq!(1000, card(&report, "bolt").value);
+        assert_eq!(400, card(&report, "bolt").unrealized);
+        assert_eq!(400, report.total.unrealized);
+        assert_eq!(1, report.sets.len());
+
+        let buy = vec![entry(2, "buy", 1200, &[("bolt", 2), ("opt", 4)], &[])];
+        let report = Ledger::run(
+            Allocation::Even,
+            Method::Fifo,
+            card::Currency::Usd,
+            &history,
+            buy,
+        )
+        .report(&history, Uuid::nil());
+        assert_eq!(400, card(&report, "bolt").cost);
+        assert_eq!(800, card(&report, "opt").cost);
+    }
+
+    #[test]
+    fn should_realize_gains_fifo_or_average() {
+        let history = history();
+        let entries = || {
+            vec![
+                entry(12, "sell", 900, &[], &[("bolt", 2)]),
+                entry(1, "buy", 200, &[("bolt", 2)], &[]),
+                entry(5, "buy", 800, &[("bolt", 2)], &[]),
+            ]
+        };
+
+        // FIFO: the two $1 bolts were sold for $4.50 each
+        let ledger = Ledger::run(
+            Allocation::Even,
+            Method::Fifo,
+            card::Currency::Usd,
+            &history,
+            entries(),
+        );
+        let report = ledger.report(&history, Uuid::nil());
+        let bolt = card(&report, "bolt");
+        assert_eq!(2, bolt.sold);
+        assert_eq!(900, bolt.proceeds);
+        assert_eq!(700, bolt.realized);
+        assert_eq!(2, bolt.held);
+        assert_eq!(800, bolt.cost);
+        assert_eq!(200, bolt.unrealized);
+
+        // Average: every bolt cost $2.50
+        let ledger = Ledger::run(
+            Allocation::Even,
+            Method::Average,
+            card::Currency::Usd,
+            &history,
+            entries(),
+        );
+        let report = ledger.report(&history, Uuid::nil());
+        let bolt = card(&report, "bolt");
+        assert_eq!(400, bolt.realized);
+        assert_eq!(500, bolt.cost);
+        assert_eq!(500, bolt.unrealized);
+    }
+
+    #[test]
+    fn should_order_same_day_entries_as_they_were_recorded() {
+        let history = history();
+        let at = |mut e: Entry, hour: i64| {
+            e.recorded.0 += Duration::hours(hour);
+            e
+        };
+        let entries = || {
+            vec![
+                at(entry(1, "buy", 800, &[("bolt", 2)], &[]), 12),
+                at(entry(1, "sell", 300, &[], &[("bolt", 1)]), 11),
+                at(entry(1, "buy", 200, &[("bolt", 2)], &[]), 10),
+            ]
+        };
+
+        // FIFO: the bolt sold was a $1 bolt, bought that morning
+        let report = Ledger::run(
+            Allocation::Even,
+            Method::Fifo,
+            card::Currency::Usd,
+            &history,
+            entries(),
+        )
+        .report(&history, Uuid::nil());
+        assert_eq!(200, card(&report, "bolt").realized);
+
+        let basis = Ledger::disposals(
+            Allocation::Even,
+            Method::Fifo,
+            card::Currency::Usd,
+            &history,
+            &entries(),
+        );
+        assert_eq!(vec![0, 100, 0], basis);
+    }
+
+    #[test]
+    fn should_carry_trade_value_into_the_cards_received() {
+        let history = history();
+        let entries = vec![
+            entry(1, "buy", 100, &[("opt", 4)], &[]),
+            // four $1 opts for a bolt, plus a dollar
+            entry(10, "trade", 100, &[("bolt", 1)], &[("opt", 4)]),
+        ];
+        let report = Ledger::run(
+            Allocation::Market,
+            Method::Fifo,
+            card::Currency::Usd,
+            &history,
+            entries,
+        )
+        .report(&history, Uuid::nil());
+        let opt = card(&report, "opt");
+        assert_eq!(400, opt.proceeds);
+        assert_eq!(300, opt.realized);
+        assert_eq!(0, opt.held);
+
+        let bolt = card(&report, "bolt");
+        assert_eq!(500, bolt.cost);
+        assert_eq!(0, bolt.unrealized);
+        assert_eq!(300, report.total.realized);
+    }
+
+    #[test]
+    fn should_carry_basis_across_transfers() {
+        let history = history();
+        let binder = Uuid::new_v4();
+        let (out, into) = (Uuid::new_v4(), Uuid::new_v4());
+        let half = |mut e: Entry, id: Uuid, other: Uuid| {
+            e.recorded.1 = id;
+            e.paid = None;
+            e.within(if id == into { binder } else { Uuid::nil() }, Some(other))
+        };
+        let entries = vec![
+            entry(1, "buy", 200, &[("bolt", 2)], &[]),
+            half(entry(5, "transfer", 0, &[], &[("bolt", 2)]), out, into),
+            half(entry(5, "transfer", 0, &[("bolt", 2)], &[]), into, out),
+            entry(12, "sell", 900, &[], &[("bolt", 2)]).within(binder, None),
+        ];
+
+        // the bolts sold from the binder still cost $1 each
+        let ledger = Ledger::run(
+            Allocation::Even,
+            Method::Fifo,
+            card::Currency::Usd,
+            &history,
+            entries,
+        );
+        let bolt = card(&ledger.report(&history, binder), "bolt").clone();
+        assert_eq!(900, bolt.proceeds);
+        assert_eq!(700, bolt.realized);
+        assert_eq!(0, bolt.held);
+
+        let report = ledger.report(&history, Uuid::nil());
+        assert_eq!(0, report.total.held);
+        assert_eq!(0, report.total.realized);
+    }
+
+    #[test]
+    fn should_find_the_basis_of_each_disposal() {
+        let history = history();
+        let entries = vec![
+            entry(12, "sell", 900, &[], &[("bolt", 3)]),
+            entry(1, "buy", 200, &[("bolt", 2)], &[]),
+            entry(5, "buy", 800, &[("bolt", 2)], &[]),
+            entry(14, "sell", 300, &[], &[("bolt", 1)]),
+        ];
+
+        // FIFO: two $1 bolts and a $4 bolt, then the last $4 bolt
+        let basis = Ledger::disposals(
+            Allocation::Even,
+            Method::Fifo,
+            card::Currency::Usd,
+            &history,
+            &entries,
+        );
+        assert_eq!(vec![600, 0, 0, 400], basis);
+
+        let basis = Ledger::disposals(
+            Allocation::Even,
+            Method::Average,
+            card::Currency::Usd,
+            &history,
+            &entries,
+        );
+        assert_eq!(vec![750, 0, 0, 250], basis);
+    }
+}
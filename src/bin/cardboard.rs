@@ -1,5 +1,6 @@
 #[macro_use]
 extern crate clap;
+use vault_of_cardboard::api::ExportAttempt;
 use vault_of_cardboard::cli;
 
 fn main() {
@@ -9,6 +10,16 @@ fn main() {
     (about: "Vault of Cardboard multi-tool")
     (@subcommand api =>
      (about: "The Vault of Cardboard server API daemon"))
+    (@subcommand export =>
+     (about: "Export a collector's transactions for bookkeeping, as CSV, OFX or QIF.")
+     (@arg collector:   -c --collector   +takes_value +required "UUID of the collector whose transactions to export.")
+     (@arg format:      -f --format      +takes_value "Output format: csv (the default), ofx or qif.")
+     (@arg from:        --from           +takes_value "Only export transactions dated on or after this date (YYYY-MM-DD).")
+     (@arg to:          --to             +takes_value "Only export transactions dated on or before this date (YYYY-MM-DD).")
+     (@arg disposition: -d --disposition +takes_value "Only export transactions with these (comma-separated) dispositions.")
+     (@arg allocation:  --allocation     +takes_value "How to allocate purchase costs to cards: market (the default) or even.")
+     (@arg method:      --method         +takes_value "How to match sales to purchases: fifo (the default) or average.")
+     (@arg output:      -o --output      +takes_value "Where to write the export (defaults to standard output)."))
     (@subcommand migrate =>
      (about: "Apply Vault of Cardboard API Database Migrations"))
     (@subcommand passwd =>
@@ -18,17 +29,35 @@ fn main() {
     (@subcommand reconciler =>
      (about: "Reconciles a collection by applying outstanding patches to it.")
      (@arg FILE: +required "Path to the collection.json file to reconcile."))
+    (@subcommand recur =>
+     (about: "Records recurring transactions that have come due; run it daily, i.e. from cron.")
+     (@arg asof: --("as-of") +takes_value "Record everything due on or before this date (YYYY-MM-DD), instead of today."))
     (@subcommand rescry =>
      (about: "Transforms ingested Scryfall set/card data into usable formats.")
      (@arg raw:    -r --raw    +takes_value +required "Where to find the raw Scryfall set files.")
      (@arg cards:  -c --cards  +takes_value "Where to put the output cards.json file.")
      (@arg prices: -p --prices +takes_value "Where to put the output prices.json file.")
-     (@arg lookup: -l --lookup +takes_value "Where to put the output lookup.json file."))
+     (@arg lookup: -l --lookup +takes_value "Where to put the output lookup.json file.")
+     (@arg history: -H --history +takes_value "A history.json file to append today's prices to."))
     )
     .get_matches();
 
     if args.is_present("api") {
         cli::api::run();
+    } else if let Some(subargs) = args.subcommand_matches("export") {
+        let arg = |name| subargs.value_of(name).unwrap_or("").to_string();
+        cli::export::run(
+            subargs.value_of("collector").unwrap(),
+            ExportAttempt {
+                format: arg("format"),
+                from: arg("from"),
+                to: arg("to"),
+                disposition: arg("disposition"),
+                allocation: arg("allocation"),
+                method: arg("method"),
+            },
+            subargs.value_of("output"),
+        );
     } else if args.is_present("migrate") {
         cli::migrate::run();
     } else if let Some(subargs) = args.subcommand_matches("passwd") {
@@ -38,12 +67,15 @@ fn main() {
         );
     } else if let Some(subargs) = args.subcommand_matches("reconciler") {
         cli::reconciler::run(subargs.value_of("FILE").unwrap());
+    } else if let Some(subargs) = args.subcommand_matches("recur") {
+        cli::recur::run(subargs.value_of("asof"));
     } else if let Some(subargs) = args.subcommand_matches("rescry") {
         cli::rescry::run(
             subargs.value_of("raw"),
             subargs.value_of("cards"),
             subargs.value_of("prices"),
             subargs.value_of("lookup"),
+            subargs.value_of("history"),
         );
     }
 }
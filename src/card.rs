@@ -1,4 +1,5 @@
-use serde::{Deserialize, Serialize, Serializer};
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 use std::io;
 
@@ -50,26 +51,73 @@ impl Pile {
     }
 }
 
+// Grading details for a slabbed copy, from the CDIF local variants
+// `(grader: PSA) (grade: 9) (subgrades: 9.5/9/9/10) (cert: 12345678)`.
+// Each graded copy is tracked on its own, by certification number.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Grade {
+    pub grader: String,
+    pub grade: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subgrades: Vec<String>,
+    pub cert: String,
+}
+
+impl Grade {
+    // The local variants that make up a grade.
+    pub const VARS: [&'static str; 4] = ["grader", "grade", "subgrades", "cert"];
+
+    pub fn from_lvars(lvars: &[(String, String)]) -> Option<Grade> {
+        let get = |k: &str| lvars.iter().find(|(key, _)| key == k).map(|(_, v)| v.trim());
+        Some(Grade {
+            grader: get("grader")?.to_uppercase(),
+            grade: get("grade")?.to_string(),
+            subgrades: get("subgrades")
+                .map(|v| v.split('/').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default(),
+            cert: get("cert")?.to_string(),
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct OwnedCard {
     pub pid: String,
     pub var: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grade: Option<Grade>,
 }
 pub struct Collection {
     pub cards: Vec<(u32, OwnedCard)>,
 }
 
+// Variants are a set, not a list: "NM foil" and "foil NM" are the same
+// copy, and should merge into the same line.
+fn same_var(a: &[String], b: &[String]) -> bool {
+    let (mut a, mut b) = (a.to_vec(), b.to_vec());
+    a.sort();
+    b.sort();
+    a == b
+}
+
 impl Collection {
     pub fn new() -> Self {
         Collection { cards: vec![] }
     }
 
+    // Graded copies only ever merge with the copy bearing the same
+    // certification number; everything else merges on print id and
+    // global variants.
     pub fn merge(&mut self, patch: &Pile) {
         for new in &patch.cards {
+            let grade = Grade::from_lvars(&new.lvars);
+            let cert = grade.as_ref().map(|g| &g.cert);
             let mut found = false;
             for (i, owned) in self.cards.iter().enumerate() {
-                if owned.1.pid == new.id {
-                    // FIXME ignores variants
+                if owned.1.pid == new.id
+                    && same_var(&owned.1.var, &new.gvars)
+                    && owned.1.grade.as_ref().map(|g| &g.cert) == cert
+                {
                     found = true;
                     self.cards[i].0 = match new.quantity + self.cards[i].0 as i32 {
                         q if q < 0 => 0,
@@ -86,12 +134,74 @@ impl Collection {
                     new.quantity as u32,
                     OwnedCard {
                         pid: new.id.to_string(),
-                        var: vec![], // FIXME ignores variants
+                        var: new.gvars.clone(),
+                        grade,
                     },
                 ));
             }
         }
     }
+
+    // Fold another collection into this one, as when looking at all of
+    // a collector's collections at once.  Copies match up the same way
+    // they do in merge.
+    pub fn absorb(&mut self, other: Collection) {
+        for (n, card) in other.cards {
+            let cert = card.grade.as_ref().map(|g| &g.cert);
+            match self.cards.iter_mut().find(|(_, owned)| {
+                owned.pid == card.pid
+                    && same_var(&owned.var, &card.var)
+                    && owned.grade.as_ref().map(|g| &g.cert) == cert
+            }) {
+                Some(owned) => owned.0 += n,
+                None => self.cards.push((n, card)),
+            }
+        }
+    }
+
+    // Total number of copies owned of each print, across all variants.
+    pub fn owned(&self) -> HashMap<&str, u32> {
+        let mut owned = HashMap::new();
+        for (n, card) in &self.cards {
+            *owned.entry(card.pid.as_str()).or_insert(0) += n;
+        }
+        owned
+    }
+
+    // Number of foil copies owned of each print.
+    pub fn foils(&self) -> HashMap<&str, u32> {
+        let mut foils = HashMap::new();
+        for (n, card) in &self.cards {
+            if card.var.iter().any(|v| v == "foil") {
+                *foils.entry(card.pid.as_str()).or_insert(0) += n;
+            }
+        }
+        foils
+    }
+}
+
+// The on-disk form of a collection: a base list of owned cards,
+// followed by every patch (transaction) applied since the last time
+// the reconciler ran.
+#[derive(Serialize, Deserialize)]
+pub struct Aggregate(pub Vec<(u32, OwnedCard)>, pub Vec<Vec<Card>>);
+
+impl Persistable for Aggregate {
+    fn from_reader<T: io::Read>(src: &mut T) -> std::result::Result<Self, io::Error> {
+        let mut s = String::new();
+        src.read_to_string(&mut s)?;
+        Ok(serde_json::from_str(&s)?)
+    }
+}
+
+impl std::convert::From<Aggregate> for Collection {
+    fn from(agg: Aggregate) -> Self {
+        let mut collection = Collection { cards: agg.0 };
+        for cards in agg.1 {
+            collection.merge(&Pile { cards });
+        }
+        collection
+    }
 }
 
 pub type Map = HashMap<String, String>;
@@ -104,15 +214,255 @@ impl Persistable for Map {
     }
 }
 
-#[derive(Serialize)]
+// Current market prices for every print, by print id (prices.json).
+pub type Prices = HashMap<String, Quote>;
+
+impl Persistable for Prices {
+    fn from_reader<T: io::Read>(src: &mut T) -> std::result::Result<Self, io::Error> {
+        let mut s = String::new();
+        src.read_to_string(&mut s)?;
+        Ok(serde_json::from_str(&s)?)
+    }
+}
+
+// How a physical card was finished.  Owned cards carry this in their
+// global variants (i.e. `| foil` or `| etched` in CDIF).
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Finish {
+    Nonfoil,
+    Foil,
+    Etched,
+}
+
+impl Finish {
+    pub fn parse(s: &str) -> Option<Finish> {
+        match s.to_lowercase().as_str() {
+            "nonfoil" => Some(Finish::Nonfoil),
+            "foil" => Some(Finish::Foil),
+            "etched" => Some(Finish::Etched),
+            _ => None,
+        }
+    }
+
+    pub fn of(vars: &[String]) -> Finish {
+        let has = |finish: &str| vars.iter().any(|v| v.eq_ignore_ascii_case(finish));
+        if has("etched") {
+            Finish::Etched
+        } else if has("foil") {
+            Finish::Foil
+        } else {
+            Finish::Nonfoil
+        }
+    }
+}
+
+// The currencies that card prices are quoted in: US dollars, euros,
+// and MTGO event tickets.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Currency {
+    Usd,
+    Eur,
+    Tix,
+}
+
+impl Currency {
+    pub fn parse(s: &str) -> Option<Currency> {
+        match s.to_lowercase().as_str() {
+            "usd" => Some(Currency::Usd),
+            "eur" => Some(Currency::Eur),
+            "tix" => Some(Currency::Tix),
+            _ => None,
+        }
+    }
+}
+
+// A single day's market prices for a print, in whatever currency each
+// marketplace uses (USD, EUR, and MTGO event tickets).
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct Quote {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usd: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usd_foil: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usd_etched: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eur: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eur_foil: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tix: Option<f64>,
+}
+
+impl std::convert::From<&scryfall::Prices> for Quote {
+    fn from(prices: &scryfall::Prices) -> Self {
+        let parse = |v: &Option<String>| v.as_ref().and_then(|v| v.parse::<f64>().ok());
+        Quote {
+            usd: parse(&prices.usd),
+            usd_foil: parse(&prices.usd_foil),
+            usd_etched: parse(&prices.usd_etched),
+            eur: parse(&prices.eur),
+            eur_foil: parse(&prices.eur_foil),
+            tix: parse(&prices.tix),
+        }
+    }
+}
+
+impl Quote {
+    pub fn is_empty(&self) -> bool {
+        *self == Quote::default()
+    }
+
+    // The price of a card with the given finish.  When a market has no
+    // price for a finish, the card is unpriced; a foil's non-foil price
+    // says little about what it's worth.  MTGO has no finishes.
+    pub fn price(&self, finish: Finish, currency: Currency) -> Option<f64> {
+        match (currency, finish) {
+            (Currency::Usd, Finish::Nonfoil) => self.usd,
+            (Currency::Usd, Finish::Foil) => self.usd_foil,
+            (Currency::Usd, Finish::Etched) => self.usd_etched,
+            (Currency::Eur, Finish::Nonfoil) => self.eur,
+            (Currency::Eur, _) => self.eur_foil,
+            (Currency::Tix, _) => self.tix,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Series {
+    pub usd: Vec<Option<f64>>,
+    pub usd_foil: Vec<Option<f64>>,
+    #[serde(default)]
+    pub usd_etched: Vec<Option<f64>>,
+    pub eur: Vec<Option<f64>>,
+    #[serde(default)]
+    pub eur_foil: Vec<Option<f64>>,
+    pub tix: Vec<Option<f64>>,
+}
+
+impl Series {
+    fn columns(&mut self) -> Vec<&mut Vec<Option<f64>>> {
+        vec![
+            &mut self.usd,
+            &mut self.usd_foil,
+            &mut self.usd_etched,
+            &mut self.eur,
+            &mut self.eur_foil,
+            &mut self.tix,
+        ]
+    }
+
+    // Make every column exactly n snapshots long, padding with nulls.
+    // Columns added after a history was started will be short.
+    pub fn resize(&mut self, n: usize) {
+        for column in self.columns() {
+            column.resize(n, None);
+        }
+    }
+
+    fn push(&mut self, q: &Quote) {
+        self.usd.push(q.usd);
+        self.usd_foil.push(q.usd_foil);
+        self.usd_etched.push(q.usd_etched);
+        self.eur.push(q.eur);
+        self.eur_foil.push(q.eur_foil);
+        self.tix.push(q.tix);
+    }
+}
+
+// Price history, stored by column: one list of snapshot dates, and for
+// each print, one list of prices per currency, running parallel to the
+// dates.  Prints that were not priced on a given day (or didn't exist
+// yet) have a null in that position.
+//
+#[derive(Serialize, Deserialize, Default)]
+pub struct History {
+    pub dates: Vec<NaiveDate>,
+    pub prints: HashMap<String, Series>,
+}
+
+impl Persistable for History {
+    fn from_reader<T: io::Read>(src: &mut T) -> std::result::Result<Self, io::Error> {
+        let mut s = String::new();
+        src.read_to_string(&mut s)?;
+        Ok(serde_json::from_str(&s)?)
+    }
+}
+
+impl History {
+    // Append a snapshot of prices, as of the given date.  Re-recording
+    // the most recent date replaces that day's snapshot.
+    pub fn record(&mut self, date: NaiveDate, prices: &Prices) {
+        if self.dates.last() == Some(&date) {
+            self.dates.pop();
+        }
+
+        let n = self.dates.len();
+        self.dates.push(date);
+        for series in self.prints.values_mut() {
+            series.resize(n);
+        }
+        for (pid, quote) in prices {
+            let series = self.prints.entry(pid.to_string()).or_default();
+            series.resize(n);
+            series.push(quote);
+        }
+        for series in self.prints.values_mut() {
+            series.resize(n + 1);
+        }
+    }
+
+    // Find the most recent snapshot taken on or before the given date.
+    pub fn index_at(&self, date: NaiveDate) -> Option<usize> {
+        self.dates.iter().rposition(|d| *d <= date)
+    }
+
+    pub fn quote(&self, pid: &str, i: usize) -> Option<Quote> {
+        self.prints.get(pid).map(|s| Quote {
+            usd: s.usd.get(i).cloned().flatten(),
+            usd_foil: s.usd_foil.get(i).cloned().flatten(),
+            usd_etched: s.usd_etched.get(i).cloned().flatten(),
+            eur: s.eur.get(i).cloned().flatten(),
+            eur_foil: s.eur_foil.get(i).cloned().flatten(),
+            tix: s.tix.get(i).cloned().flatten(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Pool {
     pub sets: HashMap<String, Set>,
     pub cards: HashMap<String, OracleCard>,
 
-    #[serde(skip_serializing)]
+    #[serde(skip)]
     pub lookup: Map,
-    #[serde(skip_serializing)]
-    pub prices: HashMap<String, Option<String>>,
+    #[serde(skip)]
+    pub prices: Prices,
+}
+
+impl Persistable for Pool {
+    fn from_reader<T: io::Read>(src: &mut T) -> std::result::Result<Self, io::Error> {
+        let mut s = String::new();
+        src.read_to_string(&mut s)?;
+        Ok(serde_json::from_str(&s)?)
+    }
+}
+
+// The card pool that tests run against, along with a lookup table for
+// resolving its cards by CDIF identity (by number, or by name alone).
+#[cfg(test)]
+pub fn test_pool() -> (Pool, Map) {
+    let pool = Pool::from_file("test/cards.json").expect("reading card pool");
+    let mut lookup = Map::new();
+    for set in pool.sets.values() {
+        for print in &set.cards {
+            let name = &pool.cards[&print.oid].name;
+            lookup.insert(pool.cdif_id(set, print).unwrap(), print.id.to_string());
+            lookup.insert(format!("{} * {}", set.code, name), print.id.to_string());
+        }
+    }
+    (pool, lookup)
 }
 
 impl Pool {
@@ -142,6 +492,26 @@ impl Pool {
         (self.cards.len(), self.sets.len(), prints)
     }
 
+    // Index every print card in the pool by its print id, alongside
+    // the set it was printed in.
+    pub fn prints(&self) -> HashMap<&str, (&Set, &PrintCard)> {
+        let mut index = HashMap::new();
+        for set in self.sets.values() {
+            for print in &set.cards {
+                index.insert(print.id.as_str(), (set, print));
+            }
+        }
+        index
+    }
+
+    // Format the CDIF identity of a print card (i.e. `SET *NUM Name`),
+    // suitable for resolution through the lookup table.
+    pub fn cdif_id(&self, set: &Set, print: &PrintCard) -> Option<String> {
+        self.cards
+            .get(&print.oid)
+            .map(|oracle| format!("{} *{} {}", set.code, print.number, oracle.name))
+    }
+
     pub fn add_set(&mut self, set: &scryfall::Set) {
         if !self.sets.contains_key(&set.code) {
             let code = set.code.to_uppercase();
@@ -159,22 +529,18 @@ impl Pool {
                 self.lookup
                     .insert(format!("{} * {}", &code, oracle.name), card.id.to_string());
 
-                self.prices.insert(
-                    card.id.to_string(),
-                    match &card.prices {
-                        None => None,
-                        Some(prices) => match &prices.usd {
-                            None => None,
-                            Some(usd) => Some(usd.to_string()),
-                        },
-                    },
-                );
+                if let Some(prices) = &card.prices {
+                    let quote = Quote::from(prices);
+                    if !quote.is_empty() {
+                        self.prices.insert(card.id.to_string(), quote);
+                    }
+                }
             }
         }
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Set {
     pub code: String,
     pub name: String,
@@ -246,6 +612,25 @@ impl Legality {
 
         s
     }
+
+    pub fn unpack(s: &str) -> Self {
+        Legality {
+            brawl: s.contains('B'),
+            commander: s.contains('E'),
+            duel: s.contains('d'),
+            frontier: s.contains('j'),
+            future: s.contains('f'),
+            historic: s.contains('h'),
+            legacy: s.contains('l'),
+            modern: s.contains('m'),
+            old_school: s.contains('o'),
+            pauper: s.contains('P'),
+            penny: s.contains('$'),
+            pioneer: s.contains('p'),
+            standard: s.contains('s'),
+            vintage: s.contains('v'),
+        }
+    }
 }
 
 impl Serialize for Legality {
@@ -257,7 +642,16 @@ impl Serialize for Legality {
     }
 }
 
-#[derive(Serialize)]
+impl<'de> Deserialize<'de> for Legality {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self::unpack(&String::deserialize(deserializer)?))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct OracleCard {
     pub id: String,
     pub name: String,
@@ -436,6 +830,30 @@ impl Flags {
 
         s
     }
+
+    pub fn unpack(s: &str) -> Self {
+        Flags {
+            full_art: s.contains('^'),
+            oversized: s.contains('O'),
+            reprint: s.contains('+'),
+            reserved: s.contains('!'),
+            variation: s.contains('~'),
+            story_spotlight: s.contains('@'),
+
+            rarity: if s.contains('1') {
+                "common"
+            } else if s.contains('2') {
+                "uncommon"
+            } else if s.contains('3') {
+                "rare"
+            } else if s.contains('4') {
+                "mythic"
+            } else {
+                ""
+            }
+            .to_string(),
+        }
+    }
 }
 
 impl Serialize for Flags {
@@ -447,6 +865,15 @@ impl Serialize for Flags {
     }
 }
 
+impl<'de> Deserialize<'de> for Flags {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self::unpack(&String::deserialize(deserializer)?))
+    }
+}
+
 pub struct Frame {
     pub legendary: bool,
     pub miracle: bool,
@@ -504,6 +931,37 @@ impl Frame {
 
         s
     }
+
+    pub fn unpack(s: &str) -> Self {
+        Frame {
+            legendary: s.contains('L'),
+            miracle: s.contains('m'),
+            nyx_touched: s.contains('n'),
+            draft: s.contains('D'),
+            devoid: s.contains('d'),
+            tombstone: s.contains('t'),
+            color_shifted: s.contains('$'),
+            showcase: s.contains('s'),
+            compass: s.contains('c'),
+            extended_art: s.contains('+'),
+            companion: s.contains('C'),
+
+            frame: if s.contains('3') {
+                "1993"
+            } else if s.contains('7') {
+                "1997"
+            } else if s.contains('M') {
+                "2003"
+            } else if s.contains('N') {
+                "2015"
+            } else if s.contains('F') {
+                "future"
+            } else {
+                ""
+            }
+            .to_string(),
+        }
+    }
 }
 
 impl Serialize for Frame {
@@ -515,7 +973,16 @@ impl Serialize for Frame {
     }
 }
 
-#[derive(Serialize)]
+impl<'de> Deserialize<'de> for Frame {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self::unpack(&String::deserialize(deserializer)?))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct PrintCard {
     pub id: String,
     pub oid: String,
@@ -964,6 +1431,7 @@ mod test {
                     Some(v) => v,
                     None => vec![],
                 },
+                grade: None,
             },
         )
     }
@@ -1116,4 +1584,97 @@ mod test {
         let s = json!(c.cards).to_string();
         assert_eq!(s, r#"[[2,{"pid":"mir-swamp","var":[]}]]"#);
     }
+
+    #[test]
+    fn should_track_graded_copies_separately_through_merges() {
+        let mut c = Collection::new();
+        c.cards.push(owned(2, "mir-afterlife", None));
+
+        let slab = |quantity, cert: &str| Card {
+            id: "mir-afterlife".to_string(),
+            quantity,
+            gvars: vec![],
+            lvars: vec![
+                ("grader".to_string(), "psa".to_string()),
+                ("grade".to_string(), "9".to_string()),
+                ("cert".to_string(), cert.to_string()),
+            ],
+        };
+        c.merge(&Pile {
+            cards: vec![slab(1, "111"), slab(1, "222")],
+        });
+        assert_eq!(
+            json!(c.cards).to_string(),
+            concat!(
+                r#"[[2,{"pid":"mir-afterlife","var":[]}],"#,
+                r#"[1,{"grade":{"cert":"111","grade":"9","grader":"PSA"},"pid":"mir-afterlife","var":[]}],"#,
+                r#"[1,{"grade":{"cert":"222","grade":"9","grader":"PSA"},"pid":"mir-afterlife","var":[]}]]"#
+            )
+        );
+
+        c.merge(&Pile {
+            cards: vec![slab(-1, "111")],
+        });
+        assert_eq!(2, c.cards.len());
+        assert_eq!(
+            Some("222"),
+            c.cards[1].1.grade.as_ref().map(|g| g.cert.as_str())
+        );
+        assert_eq!(2, c.cards[0].0);
+    }
+
+    #[test]
+    fn should_merge_variants_regardless_of_order() {
+        let mut c = Collection::new();
+        c.cards.push(owned(
+            1,
+            "mir-plains",
+            Some(vec!["NM".to_string(), "foil".to_string()]),
+        ));
+
+        c.merge(&Pile {
+            cards: vec![Card {
+                id: "mir-plains".to_string(),
+                quantity: 2,
+                gvars: vec!["foil".to_string(), "NM".to_string()],
+                lvars: vec![],
+            }],
+        });
+        assert_eq!(
+            json!(c.cards).to_string(),
+            r#"[[3,{"pid":"mir-plains","var":["NM","foil"]}]]"#
+        );
+
+        let mut other = Collection::new();
+        other.cards.push(owned(
+            1,
+            "mir-plains",
+            Some(vec!["foil".to_string(), "NM".to_string()]),
+        ));
+        other.cards.push(owned(
+            1,
+            "mir-plains",
+            Some(vec!["foil".to_string(), "foil".to_string()]),
+        ));
+        c.absorb(other);
+        assert_eq!(2, c.cards.len());
+        assert_eq!(4, c.cards[0].0);
+    }
+
+    #[test]
+    fn should_absorb_one_collection_into_another() {
+        let mut c = Collection::new();
+        c.cards.push(owned(2, "mir-plains", None));
+        c.cards.push(owned(1, "mir-island", None));
+
+        let mut other = Collection::new();
+        other.cards.push(owned(3, "mir-plains", None));
+        other.cards.push(owned(4, "mir-swamp", None));
+
+        c.absorb(other);
+        assert_eq!(
+            json!(c.cards).to_string(),
+            r#"[[5,{"pid":"mir-plains","var":[]}],[1,{"pid":"mir-island","var":[]}],[4,{"pid":"mir-swamp","var":[]}]]"#
+        );
+    }
 }
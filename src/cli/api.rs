@@ -7,11 +7,15 @@ use router::Router;
 use serde_json::json;
 use std::env;
 use std::path::Path;
+use std::thread;
 
 header! { (WwwAuthenticate, "WWW-Authenticate") => [String] }
 
-use crate::api::{Object, API};
+use crate::api::{ExportAttempt, Object, TransactionListingAttempt, API};
 use crate::db::Database;
+use crate::role::Role;
+use crate::token::Scope;
+use crate::valuation;
 
 fn boot() -> API {
     let idle = 3600;
@@ -33,9 +37,14 @@ fn boot() -> API {
     )
 }
 
+// Credentials come in as HTTP Basic auth, with the session id as the
+// username, or as a Bearer token (for personal API tokens).
 fn auth(r: &Request) -> Option<String> {
-    use iron::headers::{Authorization, Basic};
+    use iron::headers::{Authorization, Basic, Bearer};
 
+    if let Some(v) = r.headers.get::<Authorization<Bearer>>() {
+        return Some(v.token.to_string());
+    }
     match r.headers.get::<Authorization<Basic>>() {
         Some(v) => Some(v.username.to_string()),
         None => None,
@@ -52,6 +61,29 @@ fn param(r: &Request, name: &str) -> Option<String> {
     }
 }
 
+fn query(r: &Request, name: &str) -> Option<String> {
+    let url: &iron::url::Url = r.url.as_ref();
+    url.query_pairs()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.to_string())
+}
+
+// Transaction listings (paged or not) are filtered by query string.
+fn listing(r: &Request) -> TransactionListingAttempt {
+    let arg = |name| query(r, name).unwrap_or_default();
+    TransactionListingAttempt {
+        from: arg("from"),
+        to: arg("to"),
+        disposition: arg("disposition"),
+        q: arg("q"),
+        card: arg("card"),
+        set: arg("set"),
+        sort: arg("sort"),
+        limit: arg("limit"),
+        after: arg("after"),
+    }
+}
+
 macro_rules! param {
     ($r: expr, $e: expr) => {
         match param($r, $e) {
@@ -69,6 +101,24 @@ macro_rules! auth {
             _ => (),
         };
     };
+    // Guard one of the collector's collections, letting in anyone it has
+    // been shared with who has at least the given role, and evaluating
+    // to who was let in.
+    ($api: expr, $r: expr, $uid: expr, $cid: expr, $role: expr) => {
+        match $api.guard_collection(auth($r), &$uid, &$cid, $role, None) {
+            Ok(actor) => actor,
+            Err(401) => return done!(401 => "authentication required"),
+            Err(_) => return done!(403 => "forbidden"),
+        }
+    };
+    // ... and any of the collector's API tokens with the given scope.
+    ($api: expr, $r: expr, $uid: expr, $cid: expr, $role: expr, $scope: expr) => {
+        match $api.guard_collection(auth($r), &$uid, &$cid, $role, Some($scope)) {
+            Ok(actor) => actor,
+            Err(401) => return done!(401 => "authentication required"),
+            Err(_) => return done!(403 => "forbidden"),
+        }
+    };
 }
 
 macro_rules! bulk {
@@ -81,6 +131,21 @@ macro_rules! bulk {
     };
 }
 
+// Resolve the :cid in a route to one of the collector's collections,
+// where `_` is the one they started out with.
+macro_rules! collection {
+    ($api: expr, $uid: expr, $cid: expr) => {
+        match $api.resolve_collection(&$uid, &$cid) {
+            Ok(Some(cid)) => cid,
+            Ok(None) => return done!(404 => "no such collection"),
+            Err(e) => {
+                println!("collection resolution fail: {}", e);
+                return done!(500 => "collection resolution failed");
+            }
+        }
+    };
+}
+
 fn json_response(code: status::Status, json: String) -> Response {
     let mut r = Response::with((code, format!("{}\n", json)));
     r.headers.set(ContentType(Mime(
@@ -120,6 +185,10 @@ macro_rules! done {
         Ok(Response::with((status::Forbidden, format!("{}\n", $s))))
     };
 
+    (404 => $s: expr) => {
+        Ok(Response::with((status::NotFound, format!("{}\n", $s))))
+    };
+
     (500 => $s: expr) => {
         Ok(Response::with((
             status::InternalServerError,
@@ -242,6 +311,74 @@ pub fn run() {
         "update_prices_json_file",
     );
 
+    router.get(
+        "/history.json",
+        |_: &mut Request| {
+            let api = boot();
+            match api.retrieve("history.json") {
+                Ok(f) => {
+                    let mut r = Response::with((status::Ok, f));
+                    r.headers.set(ContentType(Mime(
+                        TopLevel::Application,
+                        SubLevel::Json,
+                        vec![],
+                    )));
+                    Ok(r)
+                }
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(500 => "internal server error")
+                }
+            }
+        },
+        "history_json_file",
+    );
+
+    router.post(
+        "/history.json",
+        |r: &mut Request| {
+            let api = boot();
+            bulk!(api, r);
+            match api.store("history.json", &mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(500 => "update failed")
+                }
+                Ok(_) => {
+                    if let Err(e) = api.check_watchlists() {
+                        println!("watchlist check fail: {}", e);
+                    }
+                    // don't hold up the upload waiting on mail relays
+                    // and webhooks; the alerts are queued in the database.
+                    thread::spawn(|| {
+                        if let Err(e) = boot().deliver_alerts() {
+                            println!("alert delivery fail: {}", e);
+                        }
+                    });
+                    done!(204)
+                }
+            }
+        },
+        "update_history_json_file",
+    );
+
+    router.get(
+        "/v1/cards/:pid/prices",
+        |r: &mut Request| {
+            let api = boot();
+            let pid = param!(r, "pid");
+
+            match api.retrieve_card_price_history(&pid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("card price history retrieval fail: {}", e);
+                    done!(500 => "card price history retrieval failed")
+                }
+            }
+        },
+        "v1_get_card_price_history_handler",
+    );
+
     router.get(
         "/cards/*card",
         |r: &mut Request| {
@@ -254,11 +391,27 @@ pub fn run() {
     );
 
     router.get(
-        "/collectors/:uid/collections/_/collection.json",
+        "/collectors/:uid/collections/:cid/collection.json",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
-            match api.retrieve(&format!("c/{}/_/collection.json", uid)) {
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Viewer, Scope::ReadCollection);
+
+            // `all` is every one of the collector's collections, combined.
+            if cid == "all" {
+                return match api.retrieve_combined_collection(&uid) {
+                    Ok(Some(json)) => Ok(json_response(status::Ok, json)),
+                    Ok(None) => done!(404 => "no such collector"),
+                    Err(e) => {
+                        println!("error: {}", e);
+                        done!(500 => "internal server error")
+                    }
+                };
+            }
+
+            let cid = collection!(api, uid, cid);
+            match api.retrieve(&format!("c/{}/_/collection.json", cid)) {
                 Ok(f) => {
                     let mut r = Response::with((status::Ok, f));
                     r.headers.set(ContentType(Mime(
@@ -274,7 +427,7 @@ pub fn run() {
                 }
             }
         },
-        "default_collection_json_file",
+        "collection_json_file",
     );
 
     router.post(
@@ -337,71 +490,137 @@ pub fn run() {
         "v1_signup_handler",
     );
 
-    router.get(
-        "/v1/collectors/:uid/collections/_/transactions",
+    router.patch(
+        "/v1/collectors/:uid",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
+            auth!(api, r, &uid);
 
-            match api.retrieve_transactions_for_collection(&uid) {
-                Ok(res) => done!(res),
+            match serde_json::from_reader(&mut r.body) {
                 Err(e) => {
-                    println!("transactions retrieval fail: {}", e);
-                    done!(500 => "transactions retrieval failed")
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.update_collector(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("collector update fail: {}", e);
+                        done!(500 => "collector update failed")
+                    }
+                },
+            }
+        },
+        "v1_update_collector_handler",
+    );
+
+    router.post(
+        "/v1/forgot-password",
+        |r: &mut Request| {
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => {
+                    // answer straight away, the same way for everyone;
+                    // waiting on the mail relay would give away who has
+                    // an account.
+                    thread::spawn(move || {
+                        if let Err(e) = boot().request_password_reset(attempt) {
+                            println!("password reset fail: {}", e);
+                        }
+                    });
+                    done!(Object::ok("password-reset-requested"))
                 }
             }
         },
-        "v1_get_all_transactions_handler",
+        "v1_forgot_password_handler",
+    );
+
+    router.post(
+        "/v1/reset-password",
+        |r: &mut Request| {
+            let api = boot();
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.reset_password(attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("password reset fail: {}", e);
+                        done!(500 => "password reset failed")
+                    }
+                },
+            }
+        },
+        "v1_reset_password_handler",
     );
 
     router.post(
-        "/v1/collectors/:uid/collections/_/transactions",
+        "/v1/collectors/:uid/verify-email",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
             auth!(api, r, &uid);
 
+            match api.request_email_verification(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("email verification fail: {}", e);
+                    done!(500 => "email verification failed")
+                }
+            }
+        },
+        "v1_request_email_verification_handler",
+    );
+
+    router.post(
+        "/v1/verify-email",
+        |r: &mut Request| {
+            let api = boot();
             match serde_json::from_reader(&mut r.body) {
                 Err(e) => {
                     println!("error: {}", e);
                     done!(400 => "bad request")
                 }
-                Ok(attempt) => match api.post_transaction(&uid, attempt) {
+                Ok(attempt) => match api.verify_email(attempt) {
                     Ok(res) => done!(res),
                     Err(e) => {
-                        println!("transaction fail: {}", e);
-                        done!(403 => "transaction creation failed")
+                        println!("email verification fail: {}", e);
+                        done!(500 => "email verification failed")
                     }
                 },
             }
         },
-        "v1_post_new_transaction_handler",
+        "v1_verify_email_handler",
     );
 
     router.get(
-        "/v1/collectors/:uid/collections/_/transactions/:tid",
+        "/v1/collectors/:uid/collections",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
-            let tid = param!(r, "tid");
+            auth!(api, r, &uid, "_", Role::Viewer, Scope::ReadCollection);
 
-            match api.retrieve_transaction(&uid, &tid) {
+            match api.retrieve_collections(&uid) {
                 Ok(res) => done!(res),
                 Err(e) => {
-                    println!("transaction retrieval fail: {}", e);
-                    done!(500 => "transaction retrieval failed")
+                    println!("collections retrieval fail: {}", e);
+                    done!(500 => "collections retrieval failed")
                 }
             }
         },
-        "v1_get_single_transaction_handler",
+        "v1_get_collections_handler",
     );
 
-    router.patch(
-        "/v1/collectors/:uid/collections/_/transactions/:tid",
+    router.post(
+        "/v1/collectors/:uid/collections",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
-            let tid = param!(r, "tid");
             auth!(api, r, &uid);
 
             match serde_json::from_reader(&mut r.body) {
@@ -409,236 +628,1993 @@ pub fn run() {
                     println!("error: {}", e);
                     done!(400 => "bad request")
                 }
-                Ok(attempt) => match api.update_transaction(&uid, &tid, attempt) {
+                Ok(attempt) => match api.create_collection(&uid, attempt) {
                     Ok(res) => done!(res),
                     Err(e) => {
-                        println!("transaction update fail: {}", e);
-                        done!(500 => "transaction update failed")
+                        println!("collection creation fail: {}", e);
+                        done!(500 => "collection creation failed")
                     }
                 },
             }
         },
-        "v1_update_single_transaction_handler",
+        "v1_create_collection_handler",
     );
 
-    router.delete(
-        "/v1/collectors/:uid/collections/_/transactions/:tid",
+    router.patch(
+        "/v1/collectors/:uid/collections/:cid",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
-            let tid = param!(r, "tid");
-            auth!(api, r, &uid);
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Owner);
 
-            match api.delete_transaction(&uid, &tid) {
-                Ok(res) => done!(res),
+            match serde_json::from_reader(&mut r.body) {
                 Err(e) => {
-                    println!("transaction removal fail: {}", e);
-                    done!(500 => "transaction removal failed")
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
                 }
+                Ok(attempt) => match api.update_collection(&uid, &cid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("collection update fail: {}", e);
+                        done!(500 => "collection update failed")
+                    }
+                },
             }
         },
-        "v1_delete_single_transaction_handler",
+        "v1_update_collection_handler",
     );
 
-    router.get(
-        "/v1/collectors/:uid/goals",
+    router.delete(
+        "/v1/collectors/:uid/collections/:cid",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid);
 
-            match api.retrieve_goals_for_collector(&uid) {
+            match api.delete_collection(&uid, &cid) {
                 Ok(res) => done!(res),
                 Err(e) => {
-                    println!("goals retrieval fail: {}", e);
-                    done!(500 => "goals retrieval failed")
+                    println!("collection removal fail: {}", e);
+                    done!(500 => "collection removal failed")
                 }
             }
         },
-        "v1_get_all_goals_handler",
+        "v1_delete_collection_handler",
     );
 
     router.post(
-        "/v1/collectors/:uid/goals",
+        "/v1/collectors/:uid/collections/:cid/transfers",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
-            auth!(api, r, &uid);
+            let cid = param!(r, "cid");
+            let actor = auth!(api, r, &uid, &cid, Role::Owner, Scope::WriteTransactions);
+            let cid = collection!(api, uid, cid);
 
             match serde_json::from_reader(&mut r.body) {
                 Err(e) => {
                     println!("error: {}", e);
                     done!(400 => "bad request")
                 }
-                Ok(attempt) => match api.create_goal(&uid, attempt) {
+                Ok(attempt) => match api.transfer_cards(&cid, &actor, attempt) {
                     Ok(res) => done!(res),
                     Err(e) => {
-                        println!("goal fail: {}", e);
-                        done!(500 => "goal creation failed")
+                        println!("transfer fail: {}", e);
+                        done!(500 => "transfer failed")
                     }
                 },
             }
         },
-        "v1_post_new_goal_handler",
+        "v1_transfer_cards_handler",
     );
 
     router.get(
-        "/v1/collectors/:uid/goals/:gid",
+        "/v1/collectors/:uid/collections/:cid/members",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
-            let gid = param!(r, "gid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Viewer, Scope::ReadCollection);
+            let cid = collection!(api, uid, cid);
 
-            match api.retrieve_goal(&uid, &gid) {
+            match api.retrieve_collection_members(&cid) {
                 Ok(res) => done!(res),
                 Err(e) => {
-                    println!("goal retrieval fail: {}", e);
-                    done!(500 => "goal retrieval failed")
+                    println!("collection members retrieval fail: {}", e);
+                    done!(500 => "collection members retrieval failed")
                 }
             }
         },
-        "v1_get_single_goal_handler",
+        "v1_get_collection_members_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/collections/:cid/members",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let cid = param!(r, "cid");
+            let actor = auth!(api, r, &uid, &cid, Role::Owner);
+            let cid = collection!(api, uid, cid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.invite_collection_member(&cid, &actor, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("collection member invitation fail: {}", e);
+                        done!(500 => "collection member invitation failed")
+                    }
+                },
+            }
+        },
+        "v1_invite_collection_member_handler",
     );
 
     router.patch(
-        "/v1/collectors/:uid/goals/:gid",
+        "/v1/collectors/:uid/collections/:cid/members/:mid",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
-            let gid = param!(r, "gid");
-            auth!(api, r, &uid);
+            let mid = param!(r, "mid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Owner);
+            let cid = collection!(api, uid, cid);
 
             match serde_json::from_reader(&mut r.body) {
                 Err(e) => {
                     println!("error: {}", e);
                     done!(400 => "bad request")
                 }
-                Ok(attempt) => match api.update_goal(&uid, &gid, attempt) {
+                Ok(attempt) => match api.update_collection_member(&cid, &mid, attempt) {
                     Ok(res) => done!(res),
                     Err(e) => {
-                        println!("goal update fail: {}", e);
-                        done!(500 => "goal update failed")
+                        println!("collection member update fail: {}", e);
+                        done!(500 => "collection member update failed")
                     }
                 },
             }
         },
-        "v1_update_single_goal_handler",
+        "v1_update_collection_member_handler",
     );
 
     router.delete(
-        "/v1/collectors/:uid/goals/:gid",
+        "/v1/collectors/:uid/collections/:cid/members/:mid",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
-            let gid = param!(r, "gid");
-            auth!(api, r, &uid);
+            let mid = param!(r, "mid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Owner);
+            let cid = collection!(api, uid, cid);
 
-            match api.delete_goal(&uid, &gid) {
+            match api.revoke_collection_member(&cid, &mid) {
                 Ok(res) => done!(res),
                 Err(e) => {
-                    println!("goal removal fail: {}", e);
-                    done!(500 => "goal removal failed")
+                    println!("collection member revocation fail: {}", e);
+                    done!(500 => "collection member revocation failed")
                 }
             }
         },
-        "v1_delete_single_goal_handler",
+        "v1_revoke_collection_member_handler",
     );
 
     router.get(
-        "/v1/collectors/:uid/decks",
+        "/v1/collectors/:uid/memberships",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
+            auth!(api, r, &uid);
 
-            match api.retrieve_decks_for_collector(&uid) {
+            match api.retrieve_memberships(&uid) {
                 Ok(res) => done!(res),
                 Err(e) => {
-                    println!("decks retrieval fail: {}", e);
-                    done!(500 => "decks retrieval failed")
+                    println!("memberships retrieval fail: {}", e);
+                    done!(500 => "memberships retrieval failed")
                 }
             }
         },
-        "v1_get_all_decks_handler",
+        "v1_get_memberships_handler",
     );
 
     router.post(
-        "/v1/collectors/:uid/decks",
+        "/v1/collectors/:uid/memberships/:cid/accept",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
+            let cid = param!(r, "cid");
             auth!(api, r, &uid);
 
-            match serde_json::from_reader(&mut r.body) {
+            match api.accept_membership(&uid, &cid) {
+                Ok(res) => done!(res),
                 Err(e) => {
-                    println!("error: {}", e);
-                    done!(400 => "bad request")
+                    println!("membership acceptance fail: {}", e);
+                    done!(500 => "membership acceptance failed")
                 }
-                Ok(attempt) => match api.create_deck(&uid, attempt) {
-                    Ok(res) => done!(res),
-                    Err(e) => {
-                        println!("deck fail: {}", e);
-                        done!(500 => "deck creation failed")
-                    }
-                },
             }
         },
-        "v1_post_new_deck_handler",
+        "v1_accept_membership_handler",
     );
 
-    router.get(
-        "/v1/collectors/:uid/decks/:did",
+    router.delete(
+        "/v1/collectors/:uid/memberships/:cid",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
-            let did = param!(r, "did");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid);
 
-            match api.retrieve_deck(&uid, &did) {
+            match api.leave_collection(&uid, &cid) {
                 Ok(res) => done!(res),
                 Err(e) => {
-                    println!("deck retrieval fail: {}", e);
-                    done!(500 => "deck retrieval failed")
+                    println!("membership removal fail: {}", e);
+                    done!(500 => "membership removal failed")
                 }
             }
         },
-        "v1_get_single_deck_handler",
+        "v1_leave_collection_handler",
     );
 
-    router.patch(
-        "/v1/collectors/:uid/decks/:did",
+    router.get(
+        "/v1/collectors/:uid/collections/:cid/transactions",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
-            let did = param!(r, "did");
-            auth!(api, r, &uid);
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Viewer, Scope::ReadCollection);
+            let cid = collection!(api, uid, cid);
 
-            match serde_json::from_reader(&mut r.body) {
+            match api.retrieve_transactions_for_collection(&cid, listing(r)) {
+                Ok(res) => done!(res),
                 Err(e) => {
-                    println!("error: {}", e);
-                    done!(400 => "bad request")
+                    println!("transactions retrieval fail: {}", e);
+                    done!(500 => "transactions retrieval failed")
                 }
-                Ok(attempt) => match api.update_deck(&uid, &did, attempt) {
-                    Ok(res) => done!(res),
-                    Err(e) => {
-                        println!("deck update fail: {}", e);
-                        done!(500 => "deck update failed")
-                    }
-                },
             }
         },
-        "v1_update_single_deck_handler",
+        "v1_get_all_transactions_handler",
     );
 
-    router.delete(
-        "/v1/collectors/:uid/decks/:did",
+    router.get(
+        "/v1/collectors/:uid/collections/:cid/transactions/page",
         |r: &mut Request| {
             let api = boot();
             let uid = param!(r, "uid");
-            let did = param!(r, "did");
-            auth!(api, r, &uid);
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Viewer, Scope::ReadCollection);
+            let cid = collection!(api, uid, cid);
 
-            match api.delete_deck(&uid, &did) {
+            match api.retrieve_transaction_page_for_collection(&cid, listing(r)) {
                 Ok(res) => done!(res),
                 Err(e) => {
-                    println!("deck removal fail: {}", e);
+                    println!("transaction page retrieval fail: {}", e);
+                    done!(500 => "transaction page retrieval failed")
+                }
+            }
+        },
+        "v1_get_transaction_page_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/collections/:cid/value",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Viewer, Scope::ReadCollection);
+            let cid = collection!(api, uid, cid);
+
+            match api.retrieve_collection_value(&cid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("collection valuation fail: {}", e);
+                    done!(500 => "collection valuation failed")
+                }
+            }
+        },
+        "v1_get_collection_value_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/collections/:cid/movers",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Viewer, Scope::ReadCollection);
+            let cid = collection!(api, uid, cid);
+            let days = match query(r, "days").map(|v| v.parse::<i64>()) {
+                None => 7,
+                Some(Ok(v)) if (1..=valuation::MAX_DAYS).contains(&v) => v,
+                Some(_) => return done!(400 => "bad request"),
+            };
+            let limit = match query(r, "limit").map(|v| v.parse::<usize>()) {
+                None => 25,
+                Some(Ok(v)) => v,
+                Some(Err(_)) => return done!(400 => "bad request"),
+            };
+
+            match api.retrieve_movers(&cid, days, limit) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("collection movers retrieval fail: {}", e);
+                    done!(500 => "collection movers retrieval failed")
+                }
+            }
+        },
+        "v1_get_collection_movers_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/collections/:cid/profit",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Viewer, Scope::ReadCollection);
+            let cid = collection!(api, uid, cid);
+            let allocate = query(r, "allocate").unwrap_or_default();
+            let method = query(r, "method").unwrap_or_default();
+
+            match api.retrieve_collection_profit(&cid, &allocate, &method) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("collection profit retrieval fail: {}", e);
+                    done!(500 => "collection profit retrieval failed")
+                }
+            }
+        },
+        "v1_get_collection_profit_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/collections/:cid/export",
+        |r: &mut Request| {
+            use iron::headers::{Charset, ContentDisposition, DispositionParam, DispositionType};
+
+            let api = boot();
+            let uid = param!(r, "uid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Viewer, Scope::ReadCollection);
+            let cid = collection!(api, uid, cid);
+
+            let arg = |name| query(r, name).unwrap_or_default();
+            let attempt = ExportAttempt {
+                format: arg("format"),
+                from: arg("from"),
+                to: arg("to"),
+                disposition: arg("disposition"),
+                allocation: arg("allocation"),
+                method: arg("method"),
+            };
+            match api.export_transactions(&cid, attempt) {
+                Ok(Ok(export)) => {
+                    let mut r = Response::with((status::Ok, export.body));
+                    if let Ok(mime) = export.content_type.parse::<Mime>() {
+                        r.headers.set(ContentType(mime));
+                    }
+                    r.headers.set(ContentDisposition {
+                        disposition: DispositionType::Attachment,
+                        parameters: vec![DispositionParam::Filename(
+                            Charset::Ext("UTF-8".to_string()),
+                            None,
+                            export.filename.into_bytes(),
+                        )],
+                    });
+                    Ok(r)
+                }
+                Ok(Err(res)) => done!(res),
+                Err(e) => {
+                    println!("transaction export fail: {}", e);
+                    done!(500 => "transaction export failed")
+                }
+            }
+        },
+        "v1_export_transactions_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/collections/:cid/graded",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Viewer, Scope::ReadCollection);
+            let cid = collection!(api, uid, cid);
+
+            match api.retrieve_graded_copies(&cid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("graded copy report fail: {}", e);
+                    done!(500 => "graded copy report failed")
+                }
+            }
+        },
+        "v1_get_graded_copies_handler",
+    );
+
+    router.put(
+        "/v1/collectors/:uid/collections/:cid/graded/:cert",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let cert = param!(r, "cert");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Editor, Scope::WriteTransactions);
+            let cid = collection!(api, uid, cid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.set_slab_value(&cid, &cert, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("slab valuation fail: {}", e);
+                        done!(500 => "slab valuation failed")
+                    }
+                },
+            }
+        },
+        "v1_set_slab_value_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/collections/:cid/graded/:cert",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let cert = param!(r, "cert");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Editor, Scope::WriteTransactions);
+            let cid = collection!(api, uid, cid);
+
+            match api.clear_slab_value(&cid, &cert) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("slab value removal fail: {}", e);
+                    done!(500 => "slab value removal failed")
+                }
+            }
+        },
+        "v1_clear_slab_value_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/collections/:cid/transactions",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let cid = param!(r, "cid");
+            let actor = auth!(api, r, &uid, &cid, Role::Editor, Scope::WriteTransactions);
+            let cid = collection!(api, uid, cid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.post_transaction(&cid, &actor, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("transaction fail: {}", e);
+                        done!(403 => "transaction creation failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_transaction_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/collections/:cid/transactions/:tid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Viewer, Scope::ReadCollection);
+            let cid = collection!(api, uid, cid);
+
+            match api.retrieve_transaction(&cid, &tid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("transaction retrieval fail: {}", e);
+                    done!(500 => "transaction retrieval failed")
+                }
+            }
+        },
+        "v1_get_single_transaction_handler",
+    );
+
+    router.patch(
+        "/v1/collectors/:uid/collections/:cid/transactions/:tid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            let cid = param!(r, "cid");
+            let actor = auth!(api, r, &uid, &cid, Role::Editor, Scope::WriteTransactions);
+            let cid = collection!(api, uid, cid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.update_transaction(&cid, &tid, &actor, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("transaction update fail: {}", e);
+                        done!(500 => "transaction update failed")
+                    }
+                },
+            }
+        },
+        "v1_update_single_transaction_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/collections/:cid/transactions/:tid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Editor, Scope::WriteTransactions);
+            let cid = collection!(api, uid, cid);
+
+            match api.delete_transaction(&cid, &tid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("transaction removal fail: {}", e);
+                    done!(500 => "transaction removal failed")
+                }
+            }
+        },
+        "v1_delete_single_transaction_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/collections/:cid/transactions/:tid/attachments",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Editor, Scope::WriteTransactions);
+            let cid = collection!(api, uid, cid);
+
+            let filename = match query(r, "filename") {
+                Some(filename) => filename,
+                None => return done!(400 => "bad request"),
+            };
+            let content_type = r.headers.get::<ContentType>().map(|ct| ct.to_string());
+            match api.attach_file(&cid, &tid, &filename, content_type, &mut r.body) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("attachment upload fail: {}", e);
+                    done!(500 => "attachment upload failed")
+                }
+            }
+        },
+        "v1_post_transaction_attachment_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/collections/:cid/transactions/:tid/attachments/:aid",
+        |r: &mut Request| {
+            use iron::headers::{Charset, ContentDisposition, DispositionParam, DispositionType};
+
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            let aid = param!(r, "aid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Viewer, Scope::ReadCollection);
+            let cid = collection!(api, uid, cid);
+
+            match api.retrieve_attachment(&cid, &tid, &aid) {
+                Ok(Ok((attachment, f))) => {
+                    let mut r = Response::with((status::Ok, f));
+                    r.headers.set(ContentType(
+                        attachment
+                            .content_type
+                            .parse::<Mime>()
+                            .unwrap_or(Mime(
+                                TopLevel::Application,
+                                SubLevel::Ext("octet-stream".to_string()),
+                                vec![],
+                            )),
+                    ));
+                    r.headers.set(ContentDisposition {
+                        disposition: DispositionType::Attachment,
+                        parameters: vec![DispositionParam::Filename(
+                            Charset::Ext("UTF-8".to_string()),
+                            None,
+                            attachment.filename.into_bytes(),
+                        )],
+                    });
+                    Ok(r)
+                }
+                Ok(Err(res)) => done!(res),
+                Err(e) => {
+                    println!("attachment retrieval fail: {}", e);
+                    done!(500 => "attachment retrieval failed")
+                }
+            }
+        },
+        "v1_get_transaction_attachment_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/collections/:cid/transactions/:tid/attachments/:aid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            let aid = param!(r, "aid");
+            let cid = param!(r, "cid");
+            auth!(api, r, &uid, &cid, Role::Editor, Scope::WriteTransactions);
+            let cid = collection!(api, uid, cid);
+
+            match api.delete_attachment(&cid, &tid, &aid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("attachment removal fail: {}", e);
+                    done!(500 => "attachment removal failed")
+                }
+            }
+        },
+        "v1_delete_transaction_attachment_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/goals",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid, "_", Role::Viewer, Scope::ReadCollection);
+
+            match api.retrieve_goals_for_collector(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("goals retrieval fail: {}", e);
+                    done!(500 => "goals retrieval failed")
+                }
+            }
+        },
+        "v1_get_all_goals_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/goals",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid, "_", Role::Editor);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_goal(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("goal fail: {}", e);
+                        done!(500 => "goal creation failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_goal_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/goals/:gid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let gid = param!(r, "gid");
+            auth!(api, r, &uid, "_", Role::Viewer, Scope::ReadCollection);
+
+            match api.retrieve_goal(&uid, &gid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("goal retrieval fail: {}", e);
+                    done!(500 => "goal retrieval failed")
+                }
+            }
+        },
+        "v1_get_single_goal_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/goals/:gid/missing",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let gid = param!(r, "gid");
+            auth!(api, r, &uid, "_", Role::Viewer, Scope::ReadCollection);
+
+            match api.retrieve_missing_cards_for_goal(&uid, &gid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("goal missing cards retrieval fail: {}", e);
+                    done!(500 => "goal missing cards retrieval failed")
+                }
+            }
+        },
+        "v1_get_goal_missing_cards_handler",
+    );
+
+    router.patch(
+        "/v1/collectors/:uid/goals/:gid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let gid = param!(r, "gid");
+            auth!(api, r, &uid, "_", Role::Editor);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.update_goal(&uid, &gid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("goal update fail: {}", e);
+                        done!(500 => "goal update failed")
+                    }
+                },
+            }
+        },
+        "v1_update_single_goal_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/goals/:gid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let gid = param!(r, "gid");
+            auth!(api, r, &uid, "_", Role::Editor);
+
+            match api.delete_goal(&uid, &gid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("goal removal fail: {}", e);
+                    done!(500 => "goal removal failed")
+                }
+            }
+        },
+        "v1_delete_single_goal_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/watchlists",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_watchlists_for_collector(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("watchlists retrieval fail: {}", e);
+                    done!(500 => "watchlists retrieval failed")
+                }
+            }
+        },
+        "v1_get_watchlists_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/watchlists",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_watchlist(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("watchlist fail: {}", e);
+                        done!(500 => "watchlist creation failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_watchlist_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/watchlists/:wid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let wid = param!(r, "wid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_watchlist(&uid, &wid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("watchlist retrieval fail: {}", e);
+                    done!(500 => "watchlist retrieval failed")
+                }
+            }
+        },
+        "v1_get_single_watchlist_handler",
+    );
+
+    router.patch(
+        "/v1/collectors/:uid/watchlists/:wid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let wid = param!(r, "wid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.update_watchlist(&uid, &wid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("watchlist update fail: {}", e);
+                        done!(500 => "watchlist update failed")
+                    }
+                },
+            }
+        },
+        "v1_update_single_watchlist_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/watchlists/:wid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let wid = param!(r, "wid");
+            auth!(api, r, &uid);
+
+            match api.delete_watchlist(&uid, &wid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("watchlist removal fail: {}", e);
+                    done!(500 => "watchlist removal failed")
+                }
+            }
+        },
+        "v1_delete_single_watchlist_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/alerts",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_alerts_for_collector(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("alerts retrieval fail: {}", e);
+                    done!(500 => "alerts retrieval failed")
+                }
+            }
+        },
+        "v1_get_alerts_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/wishlists",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+
+            match api.retrieve_wishlists_for_collector(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("wishlists retrieval fail: {}", e);
+                    done!(500 => "wishlists retrieval failed")
+                }
+            }
+        },
+        "v1_get_wishlists_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/wishlists",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_wishlist(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("wishlist fail: {}", e);
+                        done!(500 => "wishlist creation failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_wishlist_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/wishlists/:wid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let wid = param!(r, "wid");
+
+            match api.retrieve_wishlist(&uid, &wid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("wishlist retrieval fail: {}", e);
+                    done!(500 => "wishlist retrieval failed")
+                }
+            }
+        },
+        "v1_get_single_wishlist_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/wishlists/:wid/deals",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let wid = param!(r, "wid");
+
+            match api.retrieve_wishlist_deals(&uid, &wid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("wishlist deals retrieval fail: {}", e);
+                    done!(500 => "wishlist deals retrieval failed")
+                }
+            }
+        },
+        "v1_get_wishlist_deals_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/wishlists/:wid/satisfied",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let wid = param!(r, "wid");
+
+            match api.retrieve_wishlist_satisfaction(&uid, &wid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("wishlist satisfaction retrieval fail: {}", e);
+                    done!(500 => "wishlist satisfaction retrieval failed")
+                }
+            }
+        },
+        "v1_get_wishlist_satisfaction_handler",
+    );
+
+    router.patch(
+        "/v1/collectors/:uid/wishlists/:wid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let wid = param!(r, "wid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.update_wishlist(&uid, &wid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("wishlist update fail: {}", e);
+                        done!(500 => "wishlist update failed")
+                    }
+                },
+            }
+        },
+        "v1_update_single_wishlist_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/wishlists/:wid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let wid = param!(r, "wid");
+            auth!(api, r, &uid);
+
+            match api.delete_wishlist(&uid, &wid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("wishlist removal fail: {}", e);
+                    done!(500 => "wishlist removal failed")
+                }
+            }
+        },
+        "v1_delete_single_wishlist_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/trades",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_trades_for_collector(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("trades retrieval fail: {}", e);
+                    done!(500 => "trades retrieval failed")
+                }
+            }
+        },
+        "v1_get_trades_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/trades",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.propose_trade(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("trade proposal fail: {}", e);
+                        done!(500 => "trade proposal failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_trade_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/trade-matches",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+            let limit = match query(r, "limit").map(|v| v.parse::<usize>()) {
+                None => 25,
+                Some(Ok(v)) => v,
+                Some(Err(_)) => return done!(400 => "bad request"),
+            };
+
+            match api.retrieve_trade_matches(&uid, limit) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("trade matches retrieval fail: {}", e);
+                    done!(500 => "trade matches retrieval failed")
+                }
+            }
+        },
+        "v1_get_trade_matches_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/trades/:tid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_trade(&uid, &tid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("trade retrieval fail: {}", e);
+                    done!(500 => "trade retrieval failed")
+                }
+            }
+        },
+        "v1_get_single_trade_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/trades/:tid/counter",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.counter_trade(&uid, &tid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("trade counter fail: {}", e);
+                        done!(500 => "trade counter failed")
+                    }
+                },
+            }
+        },
+        "v1_counter_trade_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/trades/:tid/accept",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            auth!(api, r, &uid);
+
+            match api.accept_trade(&uid, &tid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("trade acceptance fail: {}", e);
+                    done!(500 => "trade acceptance failed")
+                }
+            }
+        },
+        "v1_accept_trade_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/trades/:tid/decline",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            auth!(api, r, &uid);
+
+            match api.decline_trade(&uid, &tid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("trade decline fail: {}", e);
+                    done!(500 => "trade decline failed")
+                }
+            }
+        },
+        "v1_decline_trade_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/trades/:tid/withdraw",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            auth!(api, r, &uid);
+
+            match api.withdraw_trade(&uid, &tid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("trade withdrawal fail: {}", e);
+                    done!(500 => "trade withdrawal failed")
+                }
+            }
+        },
+        "v1_withdraw_trade_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/locations",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_locations_for_collector(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("locations retrieval fail: {}", e);
+                    done!(500 => "locations retrieval failed")
+                }
+            }
+        },
+        "v1_get_locations_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/locations",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_location(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("location fail: {}", e);
+                        done!(500 => "location creation failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_location_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/locations/:lid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let lid = param!(r, "lid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_location(&uid, &lid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("location retrieval fail: {}", e);
+                    done!(500 => "location retrieval failed")
+                }
+            }
+        },
+        "v1_get_single_location_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/locations/:lid/cards",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let lid = param!(r, "lid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_location_contents(&uid, &lid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("location contents retrieval fail: {}", e);
+                    done!(500 => "location contents retrieval failed")
+                }
+            }
+        },
+        "v1_get_location_contents_handler",
+    );
+
+    router.patch(
+        "/v1/collectors/:uid/locations/:lid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let lid = param!(r, "lid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.update_location(&uid, &lid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("location update fail: {}", e);
+                        done!(500 => "location update failed")
+                    }
+                },
+            }
+        },
+        "v1_update_single_location_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/locations/:lid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let lid = param!(r, "lid");
+            auth!(api, r, &uid);
+
+            match api.delete_location(&uid, &lid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("location removal fail: {}", e);
+                    done!(500 => "location removal failed")
+                }
+            }
+        },
+        "v1_delete_single_location_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/moves",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.move_cards(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("card move fail: {}", e);
+                        done!(500 => "card move failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_move_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/whereis",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+            let card = match query(r, "card") {
+                Some(card) => card,
+                None => return done!(400 => "bad request"),
+            };
+
+            match api.retrieve_whereabouts(&uid, &card) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("whereabouts retrieval fail: {}", e);
+                    done!(500 => "whereabouts retrieval failed")
+                }
+            }
+        },
+        "v1_get_whereabouts_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/decks/:did/picks",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let did = param!(r, "did");
+            auth!(api, r, &uid, "_", Role::Viewer, Scope::ReadCollection);
+            let into = query(r, "into");
+
+            match api.retrieve_deck_picks(&uid, &did, into.as_deref()) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("pick list retrieval fail: {}", e);
+                    done!(500 => "pick list retrieval failed")
+                }
+            }
+        },
+        "v1_get_deck_picks_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/decks/:did/pull",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let did = param!(r, "did");
+            auth!(api, r, &uid, "_", Role::Editor, Scope::ManageDecks);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.pull_deck(&uid, &did, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("deck pull fail: {}", e);
+                        done!(500 => "deck pull failed")
+                    }
+                },
+            }
+        },
+        "v1_post_deck_pull_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/loans",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_loans_for_collector(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("loans retrieval fail: {}", e);
+                    done!(500 => "loans retrieval failed")
+                }
+            }
+        },
+        "v1_get_loans_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/loans",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_loan(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("loan fail: {}", e);
+                        done!(500 => "loan creation failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_loan_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/loans/overdue",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_overdue_loans(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("overdue loans retrieval fail: {}", e);
+                    done!(500 => "overdue loans retrieval failed")
+                }
+            }
+        },
+        "v1_get_overdue_loans_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/loans/:lid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let lid = param!(r, "lid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_loan(&uid, &lid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("loan retrieval fail: {}", e);
+                    done!(500 => "loan retrieval failed")
+                }
+            }
+        },
+        "v1_get_single_loan_handler",
+    );
+
+    router.patch(
+        "/v1/collectors/:uid/loans/:lid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let lid = param!(r, "lid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.update_loan(&uid, &lid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("loan update fail: {}", e);
+                        done!(500 => "loan update failed")
+                    }
+                },
+            }
+        },
+        "v1_update_single_loan_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/loans/:lid/return",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let lid = param!(r, "lid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.return_loan(&uid, &lid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("loan return fail: {}", e);
+                        done!(500 => "loan return failed")
+                    }
+                },
+            }
+        },
+        "v1_post_loan_return_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/loans/:lid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let lid = param!(r, "lid");
+            auth!(api, r, &uid);
+
+            match api.delete_loan(&uid, &lid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("loan removal fail: {}", e);
+                    done!(500 => "loan removal failed")
+                }
+            }
+        },
+        "v1_delete_single_loan_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/decks/:did/availability",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let did = param!(r, "did");
+            auth!(api, r, &uid, "_", Role::Viewer, Scope::ReadCollection);
+
+            match api.retrieve_deck_availability(&uid, &did) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("deck availability retrieval fail: {}", e);
+                    done!(500 => "deck availability retrieval failed")
+                }
+            }
+        },
+        "v1_get_deck_availability_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/sealed",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_sealed_products(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("sealed product retrieval fail: {}", e);
+                    done!(500 => "sealed product retrieval failed")
+                }
+            }
+        },
+        "v1_get_sealed_products_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/sealed",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_sealed_product(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("sealed product fail: {}", e);
+                        done!(500 => "sealed product creation failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_sealed_product_handler",
+    );
+
+    router.patch(
+        "/v1/collectors/:uid/sealed/:sid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let sid = param!(r, "sid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.update_sealed_product(&uid, &sid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("sealed product update fail: {}", e);
+                        done!(500 => "sealed product update failed")
+                    }
+                },
+            }
+        },
+        "v1_update_single_sealed_product_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/sealed/:sid/open",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let sid = param!(r, "sid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.open_sealed_product(&uid, &sid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("sealed product opening fail: {}", e);
+                        done!(500 => "sealed product opening failed")
+                    }
+                },
+            }
+        },
+        "v1_post_sealed_product_open_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/sealed/:sid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let sid = param!(r, "sid");
+            auth!(api, r, &uid);
+
+            match api.delete_sealed_product(&uid, &sid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("sealed product removal fail: {}", e);
+                    done!(500 => "sealed product removal failed")
+                }
+            }
+        },
+        "v1_delete_single_sealed_product_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/templates",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_templates(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("templates retrieval fail: {}", e);
+                    done!(500 => "templates retrieval failed")
+                }
+            }
+        },
+        "v1_get_templates_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/templates",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_template(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("template creation fail: {}", e);
+                        done!(500 => "template creation failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_template_handler",
+    );
+
+    router.patch(
+        "/v1/collectors/:uid/templates/:tid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.update_template(&uid, &tid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("template update fail: {}", e);
+                        done!(500 => "template update failed")
+                    }
+                },
+            }
+        },
+        "v1_update_single_template_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/templates/:tid/use",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.use_template(&uid, &tid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("template use fail: {}", e);
+                        done!(500 => "template use failed")
+                    }
+                },
+            }
+        },
+        "v1_post_template_use_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/templates/:tid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            auth!(api, r, &uid);
+
+            match api.delete_template(&uid, &tid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("template removal fail: {}", e);
+                    done!(500 => "template removal failed")
+                }
+            }
+        },
+        "v1_delete_single_template_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/recurring",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_recurring_transactions(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("recurring transactions retrieval fail: {}", e);
+                    done!(500 => "recurring transactions retrieval failed")
+                }
+            }
+        },
+        "v1_get_recurring_transactions_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/recurring",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_recurring_transaction(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("recurring transaction creation fail: {}", e);
+                        done!(500 => "recurring transaction creation failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_recurring_transaction_handler",
+    );
+
+    router.patch(
+        "/v1/collectors/:uid/recurring/:rid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let rid = param!(r, "rid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.update_recurring_transaction(&uid, &rid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("recurring transaction update fail: {}", e);
+                        done!(500 => "recurring transaction update failed")
+                    }
+                },
+            }
+        },
+        "v1_update_single_recurring_transaction_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/recurring/:rid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let rid = param!(r, "rid");
+            auth!(api, r, &uid);
+
+            match api.delete_recurring_transaction(&uid, &rid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("recurring transaction removal fail: {}", e);
+                    done!(500 => "recurring transaction removal failed")
+                }
+            }
+        },
+        "v1_delete_single_recurring_transaction_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/decks",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid, "_", Role::Viewer, Scope::ReadCollection);
+
+            match api.retrieve_decks_for_collector(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("decks retrieval fail: {}", e);
+                    done!(500 => "decks retrieval failed")
+                }
+            }
+        },
+        "v1_get_all_decks_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/decks",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid, "_", Role::Editor, Scope::ManageDecks);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_deck(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("deck fail: {}", e);
+                        done!(500 => "deck creation failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_deck_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/decks/:did",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let did = param!(r, "did");
+            auth!(api, r, &uid, "_", Role::Viewer, Scope::ReadCollection);
+
+            match api.retrieve_deck(&uid, &did) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("deck retrieval fail: {}", e);
+                    done!(500 => "deck retrieval failed")
+                }
+            }
+        },
+        "v1_get_single_deck_handler",
+    );
+
+    router.patch(
+        "/v1/collectors/:uid/decks/:did",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let did = param!(r, "did");
+            auth!(api, r, &uid, "_", Role::Editor, Scope::ManageDecks);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.update_deck(&uid, &did, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("deck update fail: {}", e);
+                        done!(500 => "deck update failed")
+                    }
+                },
+            }
+        },
+        "v1_update_single_deck_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/decks/:did",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let did = param!(r, "did");
+            auth!(api, r, &uid, "_", Role::Editor, Scope::ManageDecks);
+
+            match api.delete_deck(&uid, &did) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("deck removal fail: {}", e);
                     done!(500 => "deck removal failed")
                 }
             }
@@ -646,6 +2622,295 @@ pub fn run() {
         "v1_delete_single_deck_handler",
     );
 
+    router.get(
+        "/v1/collectors/:uid/shares",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_share_links(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("share links retrieval fail: {}", e);
+                    done!(500 => "share links retrieval failed")
+                }
+            }
+        },
+        "v1_get_share_links_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/shares",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_share_link(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("share link creation fail: {}", e);
+                        done!(500 => "share link creation failed")
+                    }
+                },
+            }
+        },
+        "v1_create_share_link_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/shares/:sid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let sid = param!(r, "sid");
+            auth!(api, r, &uid);
+
+            match api.revoke_share_link(&uid, &sid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("share link revocation fail: {}", e);
+                    done!(500 => "share link revocation failed")
+                }
+            }
+        },
+        "v1_revoke_share_link_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/tokens",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match api.retrieve_api_tokens(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("api tokens retrieval fail: {}", e);
+                    done!(500 => "api tokens retrieval failed")
+                }
+            }
+        },
+        "v1_get_api_tokens_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/tokens",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_api_token(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("api token creation fail: {}", e);
+                        done!(500 => "api token creation failed")
+                    }
+                },
+            }
+        },
+        "v1_create_api_token_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/tokens/:tid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let tid = param!(r, "tid");
+            auth!(api, r, &uid);
+
+            match api.revoke_api_token(&uid, &tid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("api token revocation fail: {}", e);
+                    done!(500 => "api token revocation failed")
+                }
+            }
+        },
+        "v1_revoke_api_token_handler",
+    );
+
+    // Share links are followed by people who need not have an account,
+    // so this route is deliberately left unguarded; the token is all
+    // the authorization there is.
+    router.get(
+        "/v1/shared/:token",
+        |r: &mut Request| {
+            let api = boot();
+            let token = param!(r, "token");
+
+            match api.retrieve_shared(&token) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("shared retrieval fail: {}", e);
+                    done!(500 => "shared retrieval failed")
+                }
+            }
+        },
+        "v1_get_shared_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/pods",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+
+            match api.retrieve_pods_for_collector(&uid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("pods retrieval fail: {}", e);
+                    done!(500 => "pods retrieval failed")
+                }
+            }
+        },
+        "v1_get_all_pods_handler",
+    );
+
+    router.post(
+        "/v1/collectors/:uid/pods",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            auth!(api, r, &uid);
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.create_pod(&uid, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("pod fail: {}", e);
+                        done!(500 => "pod creation failed")
+                    }
+                },
+            }
+        },
+        "v1_post_new_pod_handler",
+    );
+
+    router.get(
+        "/v1/collectors/:uid/pods/:pid",
+        |r: &mut Request| {
+            let api = boot();
+            let pid = param!(r, "pid");
+
+            match api.retrieve_pod(&pid, None) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("pod retrieval fail: {}", e);
+                    done!(500 => "pod retrieval failed")
+                }
+            }
+        },
+        "v1_get_single_pod_handler",
+    );
+
+    router.delete(
+        "/v1/collectors/:uid/pods/:pid",
+        |r: &mut Request| {
+            let api = boot();
+            let uid = param!(r, "uid");
+            let pid = param!(r, "pid");
+            auth!(api, r, &uid);
+
+            match api.delete_pod(&uid, &pid) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("pod removal fail: {}", e);
+                    done!(500 => "pod removal failed")
+                }
+            }
+        },
+        "v1_delete_single_pod_handler",
+    );
+
+    router.get(
+        "/v1/pods/:pid/seats/:seat",
+        |r: &mut Request| {
+            let api = boot();
+            let pid = param!(r, "pid");
+            let seat = match param!(r, "seat").parse::<usize>() {
+                Ok(seat) => seat,
+                Err(_) => return done!(400 => "bad request"),
+            };
+
+            match api.pod_seat_owner(&pid, seat) {
+                Ok(Some(uid)) => {
+                    auth!(api, r, &uid);
+                }
+                Ok(None) => return done!(404 => "no such seat"),
+                Err(e) => {
+                    println!("pod seat lookup fail: {}", e);
+                    return done!(500 => "pod retrieval failed");
+                }
+            }
+
+            match api.retrieve_pod(&pid, Some(seat)) {
+                Ok(res) => done!(res),
+                Err(e) => {
+                    println!("pod retrieval fail: {}", e);
+                    done!(500 => "pod retrieval failed")
+                }
+            }
+        },
+        "v1_get_pod_seat_handler",
+    );
+
+    router.post(
+        "/v1/pods/:pid/seats/:seat/picks",
+        |r: &mut Request| {
+            let api = boot();
+            let pid = param!(r, "pid");
+            let seat = match param!(r, "seat").parse::<usize>() {
+                Ok(seat) => seat,
+                Err(_) => return done!(400 => "bad request"),
+            };
+
+            match api.pod_seat_owner(&pid, seat) {
+                Ok(Some(uid)) => {
+                    auth!(api, r, &uid);
+                }
+                Ok(None) => return done!(400 => "bad request"),
+                Err(e) => {
+                    println!("pod seat lookup fail: {}", e);
+                    return done!(500 => "pick failed");
+                }
+            }
+
+            match serde_json::from_reader(&mut r.body) {
+                Err(e) => {
+                    println!("error: {}", e);
+                    done!(400 => "bad request")
+                }
+                Ok(attempt) => match api.pick(&pid, seat, attempt) {
+                    Ok(res) => done!(res),
+                    Err(e) => {
+                        println!("pick fail: {}", e);
+                        done!(500 => "pick failed")
+                    }
+                },
+            }
+        },
+        "v1_post_pod_pick_handler",
+    );
+
     let bind = match env::var("VCB_LISTEN") {
         Ok(v) => v,
         Err(_) => "localhost:3000".to_string(),
@@ -0,0 +1,33 @@
+use serde_json::json;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::api::{ExportAttempt, API};
+use crate::db::Database;
+
+pub fn run(collector: &str, attempt: ExportAttempt, output: Option<&str>) {
+    let db = Database::connect(
+        &env::var("VCB_DATABASE_URL").expect("VCB_DATABASE_URL must be set in environment"),
+        &env::var("VCB_REDIS_URL").expect("VCB_REDIS_URL must be set in environment"),
+        Path::new(&env::var("VCB_FS_ROOT").expect("VCB_FS_ROOT must be set in environment")),
+        0,
+    )
+    .unwrap();
+
+    let export = match API::new(db).export_transactions(collector, attempt) {
+        Ok(Ok(export)) => export,
+        Ok(Err(res)) => panic!("unable to export transactions: {}", json!(res)),
+        Err(e) => panic!("unable to export transactions: {}", e),
+    };
+
+    match output {
+        None | Some("-") => print!("{}", export.body),
+        Some(path) => {
+            if let Err(e) = fs::write(path, export.body) {
+                panic!("unable to write exported transactions to {}: {}", path, e);
+            }
+            println!("wrote exported transactions to {}", path);
+        }
+    }
+}
@@ -6,4 +6,25 @@ pub fn run() {
     println!("running database migrations against {}", url);
 
     Database::migrate(&url).unwrap();
+
+    let unmapped = Database::unmapped_dispositions(&url).unwrap();
+    if !unmapped.is_empty() {
+        println!();
+        println!(
+            "{} transaction(s) had dispositions that could not be mapped to a known kind;",
+            unmapped.len()
+        );
+        println!("they have been made corrections, and should be checked by hand:");
+        println!();
+        for txn in unmapped {
+            println!(
+                "  {}  {}  {}  {:?} (was {:?})",
+                txn.collection,
+                txn.id,
+                txn.dated,
+                txn.summary,
+                txn.unmapped_disposition().unwrap_or_default()
+            );
+        }
+    }
 }
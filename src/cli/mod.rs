@@ -1,5 +1,7 @@
 pub mod api;
+pub mod export;
 pub mod migrate;
 pub mod passwd;
 pub mod rescry;
 pub mod reconciler;
+pub mod recur;
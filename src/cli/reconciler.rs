@@ -2,23 +2,10 @@ use serde_json::json;
 use std::fs::{self, File};
 use std::path::Path;
 use std::io::prelude::*;
-use std::io;
 use std::time::Instant;
-use serde::{Serialize, Deserialize};
 
 use crate::prelude::*;
-use crate::card;
-
-#[derive(Serialize, Deserialize)]
-pub struct Aggregate(Vec<(u32, card::OwnedCard)>, Vec<Vec<card::Card>>);
-
-impl Persistable for Aggregate {
-    fn from_reader<T: io::Read>(src: &mut T) -> std::result::Result<Self, io::Error> {
-        let mut s = String::new();
-        src.read_to_string(&mut s)?;
-        Ok(serde_json::from_str(&s)?)
-    }
-}
+use crate::card::{self, Aggregate};
 
 pub fn run(path: &str) {
     let path = Path::new(path);
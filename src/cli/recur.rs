@@ -0,0 +1,27 @@
+use chrono::{naive::NaiveDate, Utc};
+use std::env;
+use std::path::Path;
+
+use crate::api::API;
+use crate::db::Database;
+
+pub fn run(as_of: Option<&str>) {
+    let on = match as_of {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .unwrap_or_else(|e| panic!("invalid --as-of date {:?}: {}", date, e)),
+        None => Utc::now().naive_utc().date(),
+    };
+
+    let db = Database::connect(
+        &env::var("VCB_DATABASE_URL").expect("VCB_DATABASE_URL must be set in environment"),
+        &env::var("VCB_REDIS_URL").expect("VCB_REDIS_URL must be set in environment"),
+        Path::new(&env::var("VCB_FS_ROOT").expect("VCB_FS_ROOT must be set in environment")),
+        0,
+    )
+    .unwrap();
+
+    match API::new(db).run_recurring_transactions(on) {
+        Ok(n) => println!("recorded {} recurring transaction(s) due by {}", n, on),
+        Err(e) => panic!("unable to run recurring transactions: {}", e),
+    }
+}
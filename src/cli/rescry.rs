@@ -1,11 +1,14 @@
+use chrono::Utc;
 use serde_json::json;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::prelude::*;
+use std::path::Path;
 use std::time::Instant;
 
 use crate::card;
+use crate::prelude::*;
 
-pub fn run(raw: Option<&str>, cards_json: Option<&str>, prices_json: Option<&str>, lookup_json: Option<&str>) {
+pub fn run(raw: Option<&str>, cards_json: Option<&str>, prices_json: Option<&str>, lookup_json: Option<&str>, history_json: Option<&str>) {
     let raw = match raw {
         Some(v) => v,
         None => "data/cache",
@@ -73,4 +76,25 @@ pub fn run(raw: Option<&str>, cards_json: Option<&str>, prices_json: Option<&str
     }
     let elapsed = now.elapsed().as_millis();
     println!("write lookup JSON data to {} in {}ms", lookup_json, elapsed);
+
+    if let Some(history_json) = history_json {
+        let now = Instant::now();
+        let mut history = if Path::new(history_json).exists() {
+            match card::History::from_file(history_json) {
+                Ok(history) => history,
+                Err(e) => panic!("unable to read price history JSON from {}: {}", history_json, e),
+            }
+        } else {
+            card::History::default()
+        };
+        history.record(Utc::now().naive_utc().date(), &pool.prices);
+
+        let tmp_file = Path::new(history_json).with_extension("json.tmp");
+        if let Err(e) = fs::write(&tmp_file, json!(history).to_string().as_bytes()) {
+            panic!("unable to write price history JSON output to {}: {}", tmp_file.to_str().unwrap(), e);
+        }
+        fs::rename(&tmp_file, history_json).unwrap();
+        let elapsed = now.elapsed().as_millis();
+        println!("appended {} price snapshot ({} snapshots total) to {} in {}ms", history.dates.last().unwrap(), history.dates.len(), history_json, elapsed);
+    }
 }
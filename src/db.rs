@@ -1,4 +1,8 @@
-use super::schema::{collections, collectors, decks, goals, transactions};
+use super::schema::{
+    account_tokens, alerts, api_tokens, attachments, collection_members, collections, collectors,
+    decks, goals, loans, locations, pods, recurring_transactions, sealed_products, share_links,
+    slab_values, stowage, trades, transaction_templates, transactions, watchlists, wishlists,
+};
 use bcrypt;
 use chrono::{naive::NaiveDate, DateTime, Utc};
 use diesel::pg::PgConnection;
@@ -6,16 +10,22 @@ use diesel::prelude::*;
 use redis;
 use serde::Serialize;
 use serde_json::json;
+use sha2::{Digest, Sha256};
 use std::fs::{self, File};
 use std::io::{self, Read, Seek, Write};
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 use crate::prelude::*;
+use crate::disposition::Disposition;
 use crate::{card, cdif};
 
 mod errors {
-    error_chain! {}
+    error_chain! {
+        foreign_links {
+            Database(diesel::result::Error);
+        }
+    }
 }
 pub use errors::Error;
 use errors::*;
@@ -58,6 +68,29 @@ impl FStore {
         Ok(())
     }
 
+    // Store binary contents, creating any parent directories.  Unlike
+    // overwrite, the whole file has to be in memory already.
+    pub fn store(&self, filename: &str, contents: &[u8]) -> Result<()> {
+        let pb = self.path_to(filename);
+
+        fs::create_dir_all(
+            pb.parent()
+                .chain_err(|| "failed to construct path for file storage")?,
+        )
+        .chain_err(|| "failed to create parent directories for file storage")?;
+
+        fs::write(pb, contents).chain_err(|| "failed to write to file")?;
+        Ok(())
+    }
+
+    pub fn remove(&self, filename: &str) -> Result<()> {
+        let pb = self.path_to(filename);
+        if pb.exists() {
+            fs::remove_file(pb).chain_err(|| "failed to remove file")?;
+        }
+        Ok(())
+    }
+
     pub fn get_as_reader(&self, filename: &str) -> Result<File> {
         let pb = self.path_to(filename);
         Ok(File::open(
@@ -124,6 +157,10 @@ pub struct Collector {
     pub username: String,
     pub email: String,
     pub password: String,
+    pub currency: String, // usd, eur or tix
+    pub tradeable: bool,  // opted in to trade matching
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub session_generation: i32, // bumped to end every session
 }
 
 #[derive(Insertable)]
@@ -139,19 +176,63 @@ pub struct CollectorUpdate<'a> {
     pub username: Option<&'a str>,
     pub email: Option<&'a str>,
     pub password: Option<&'a str>,
+    pub currency: Option<&'a str>,
+    pub tradeable: Option<bool>,
 }
 
-#[derive(Queryable)]
+// Every collector has at least one collection, which shares its id
+// with the collector (and is `_` in URLs); any others they create get
+// their own ids.
+#[derive(Identifiable, Queryable)]
 pub struct Collection {
     pub id: Uuid,
     pub collector: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Collection {
+    pub fn is_default(&self) -> bool {
+        self.id == self.collector
+    }
 }
 
 #[derive(Insertable)]
 #[table_name = "collections"]
-pub struct NewCollection {
-    pub id: Uuid,
+pub struct NewCollection<'a> {
+    pub collector: Uuid,
+    pub name: &'a str,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "collections"]
+pub struct UpdateCollection {
+    pub name: Option<String>,
+}
+
+// Someone other than its collector who has been invited into a
+// collection, with a role (see role.rs).  Invitations that haven't
+// been accepted yet don't grant anything.
+#[derive(Identifiable, Queryable)]
+#[primary_key(collection, collector)]
+pub struct CollectionMember {
+    pub collection: Uuid,
+    pub collector: Uuid,
+    pub role: String,
+    pub invited_by: Option<Uuid>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "collection_members"]
+pub struct NewCollectionMember<'a> {
+    pub collection: Uuid,
     pub collector: Uuid,
+    pub role: &'a str,
+    pub invited_by: Option<Uuid>,
 }
 
 #[derive(Identifiable, Queryable)]
@@ -161,13 +242,17 @@ pub struct Transaction {
     pub dated: NaiveDate,
     pub summary: String,
     pub notes: String,
-    pub disposition: String,
+    pub disposition: Disposition,
     pub gain: String,
     pub loss: String,
     pub metadata: serde_json::Value,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub paid: Option<i32>,
+    pub opened: Option<Uuid>,
+    pub transfer: Option<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
 }
 
 impl Transaction {
@@ -213,6 +298,15 @@ impl Transaction {
             _ => vec![],
         }
     }
+
+    // The free-form disposition this transaction had before dispositions
+    // were constrained, if it couldn't be mapped to a known kind.
+    pub fn unmapped_disposition(&self) -> Option<String> {
+        self.metadata
+            .get("unmapped_disposition")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    }
 }
 
 #[derive(Insertable)]
@@ -222,10 +316,11 @@ pub struct NewTransaction<'a> {
     pub summary: &'a str,
     pub notes: &'a str,
     pub dated: &'a NaiveDate,
-    pub disposition: &'a str,
+    pub disposition: Disposition,
     pub gain: &'a str,
     pub loss: &'a str,
     pub paid: Option<i32>,
+    pub created_by: Option<Uuid>,
 }
 
 #[derive(AsChangeset)]
@@ -234,10 +329,90 @@ pub struct UpdateTransaction {
     pub summary: Option<String>,
     pub notes: Option<String>,
     pub dated: Option<NaiveDate>,
-    pub disposition: Option<String>,
+    pub disposition: Option<Disposition>,
     pub gain: Option<String>,
     pub loss: Option<String>,
     pub paid: Option<Option<i32>>,
+    pub updated_by: Option<Uuid>,
+}
+
+// What to list out of a collection's transactions, and in what order.
+// Everything is optional; the default query lists every transaction,
+// oldest first.
+//
+// search   free text, matched against the words of the summary and notes.
+//
+// card     an oracle card name; matches transactions that gain or lose
+//          any printing of that card.
+//
+// set      a set code; matches transactions that gain or lose any cards
+//          from that set.
+//
+// after    a (dated, id) cursor; only transactions that sort after it
+//          (in whichever direction we are sorting) are listed.
+//
+#[derive(Default)]
+pub struct TransactionQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub dispositions: Vec<Disposition>,
+    pub search: Option<String>,
+    pub card: Option<String>,
+    pub set: Option<String>,
+    pub newest_first: bool,
+    pub after: Option<(NaiveDate, Uuid)>,
+    pub limit: Option<i64>,
+}
+
+// A case-insensitive (~*) regular expression matching a CDIF line for
+// any printing of the named card, i.e. `4x XLN Opt` or `1 XLN *65 Opt | foil`.
+fn card_line_pattern(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .flat_map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => vec![c],
+            _ => vec!['\\', c],
+        })
+        .collect();
+    format!(
+        "(^|\\n) *[0-9]+x? +[a-z0-9]+ +(\\*[0-9][a-z0-9]* +)?{} *(\\||#|\\n|$)",
+        name
+    )
+}
+
+fn find_unmapped_dispositions(pg: &PgConnection) -> Result<Vec<Transaction>> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::Bool;
+
+    transactions::table
+        .filter(sql::<Bool>("metadata ? 'unmapped_disposition'"))
+        .order((transactions::dsl::collection, transactions::dsl::dated))
+        .get_results::<Transaction>(pg)
+        .chain_err(|| "unable to retrieve transactions with unmapped dispositions")
+}
+
+// A file (receipt, invoice, photo) attached to a transaction.  The
+// contents live in the file store; see attachment_path.
+#[derive(Identifiable, Queryable)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub transaction: Uuid,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i32,
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "attachments"]
+pub struct NewAttachment<'a> {
+    pub transaction: Uuid,
+    pub filename: &'a str,
+    pub content_type: &'a str,
+    pub size: i32,
+    pub sha256: &'a str,
 }
 
 #[derive(Identifiable, Queryable)]
@@ -252,6 +427,8 @@ pub struct Goal {
     pub progress: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub deadline: Option<NaiveDate>,
+    pub budget: Option<i32>,
 }
 
 #[derive(Insertable)]
@@ -264,6 +441,8 @@ pub struct NewGoal<'a> {
     pub goal: &'a str,
     pub total: Option<i32>,
     pub progress: Option<i32>,
+    pub deadline: Option<NaiveDate>,
+    pub budget: Option<i32>,
 }
 
 #[derive(AsChangeset)]
@@ -275,6 +454,8 @@ pub struct UpdateGoal {
     pub goal: Option<String>,
     pub total: Option<Option<i32>>,
     pub progress: Option<Option<i32>>,
+    pub deadline: Option<Option<NaiveDate>>,
+    pub budget: Option<Option<i32>>,
 }
 
 #[derive(Identifiable, Queryable)]
@@ -316,702 +497,4070 @@ pub struct UpdateDeck {
     pub maybe: Option<String>,
 }
 
-use std::collections::HashMap;
-pub struct Session {
+#[derive(Identifiable, Queryable)]
+pub struct Pod {
     pub id: Uuid,
-    pub attrs: HashMap<String, String>,
+    pub collector: Uuid,
+    pub set_code: String,
+    pub state: serde_json::Value,
+    pub finished: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
-impl Session {
-    pub fn new(id: Option<Uuid>) -> Session {
-        Session {
-            id: match id {
-                Some(id) => id,
-                None => Uuid::new_v4(),
-            },
-            attrs: HashMap::new(),
-        }
-    }
+#[derive(Insertable)]
+#[table_name = "pods"]
+pub struct NewPod<'a> {
+    pub collector: Uuid,
+    pub set_code: &'a str,
+    pub state: serde_json::Value,
+}
 
-    pub fn set(&mut self, k: &str, v: &str) {
-        self.attrs.insert(k.to_string(), v.to_string());
-    }
+#[derive(AsChangeset)]
+#[table_name = "pods"]
+pub struct UpdatePod {
+    pub state: Option<serde_json::Value>,
+    pub finished: Option<bool>,
+}
 
-    pub fn get(&self, k: &str) -> String {
-        self.attrs[k].to_string()
-    }
+#[derive(Identifiable, Queryable)]
+pub struct Watchlist {
+    pub id: Uuid,
+    pub collector: Uuid,
+    pub card: String,
+    pub scope: String,
+    pub finish: String,
+    pub currency: String,
+    pub below: Option<i32>,
+    pub above: Option<i32>,
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
-pub struct Database {
-    pg: PgConnection,
-    rd: redis::Client,
-    fs: FStore,
-    idle: u32, // in seconds
+#[derive(Insertable)]
+#[table_name = "watchlists"]
+pub struct NewWatchlist<'a> {
+    pub collector: Uuid,
+    pub card: &'a str,
+    pub scope: &'a str,
+    pub finish: &'a str,
+    pub currency: &'a str,
+    pub below: Option<i32>,
+    pub above: Option<i32>,
+    pub notes: &'a str,
 }
 
-embed_migrations!("migrations/");
+#[derive(AsChangeset)]
+#[table_name = "watchlists"]
+pub struct UpdateWatchlist {
+    pub finish: Option<String>,
+    pub currency: Option<String>,
+    pub below: Option<Option<i32>>,
+    pub above: Option<Option<i32>>,
+    pub notes: Option<String>,
+}
 
-// Generate a random UUID, if one wasn't supplied.
-fn gen_uuid(id: Option<Uuid>) -> Uuid {
-    match id {
-        Some(id) => id,
-        None => Uuid::new_v4(),
-    }
+#[derive(Identifiable, Queryable)]
+pub struct Wishlist {
+    pub id: Uuid,
+    pub collector: Uuid,
+    pub name: String,
+    pub description: String,
+    pub cards: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
-impl Database {
-    pub fn migrate(pg: &str) -> Result<()> {
-        let pg = PgConnection::establish(pg).chain_err(|| "unable to connect to database")?;
-        embedded_migrations::run(&pg).chain_err(|| "failed to run database migrations")?;
-        Ok(())
-    }
+#[derive(Insertable)]
+#[table_name = "wishlists"]
+pub struct NewWishlist<'a> {
+    pub collector: Uuid,
+    pub name: &'a str,
+    pub description: &'a str,
+    pub cards: &'a str,
+}
 
-    // Connect to a DSN (must be PostgreSQL) and run migrations.
-    pub fn connect(pg: &str, rd: &str, fsroot: &Path, idle: u32) -> Result<Database> {
-        Ok(Database {
-            pg: PgConnection::establish(pg).chain_err(|| "unable to connect to database")?,
-            rd: redis::Client::open(rd).chain_err(|| "unable to connect to session store")?,
-            fs: FStore::new(fsroot),
-            idle: idle,
-        })
-    }
+#[derive(AsChangeset)]
+#[table_name = "wishlists"]
+pub struct UpdateWishlist {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub cards: Option<String>,
+}
 
-    #[cfg(test)]
-    // Start a transaction that will never end.
-    //
-    // This is only available in test runs, and is meant solely for being
-    // able to run SQL queries / statements until an error is encountered,
-    // with implicit (but unavoidable) transactional rollback.
-    //
-    pub fn testmode(&self) -> Result<()> {
-        self.pg
-            .begin_test_transaction()
-            .chain_err(|| "unable to initiate test transaction")?;
-        Ok(())
-    }
+#[derive(Identifiable, Queryable)]
+pub struct Trade {
+    pub id: Uuid,
+    pub proposer: Uuid,
+    pub recipient: Uuid,
+    pub offer: String,
+    pub request: String,
+    pub message: String,
+    pub status: String,
+    pub parent: Option<Uuid>,
+    pub proposer_transaction: Option<Uuid>,
+    pub recipient_transaction: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
 
-    pub fn get_file(&self, rel: &str) -> Result<std::fs::File> {
-        Ok(self
-            .fs
-            .get_as_reader(rel)
-            .chain_err(|| "unable to retrieve file from filesystem")?)
-    }
+#[derive(Insertable)]
+#[table_name = "trades"]
+pub struct NewTrade<'a> {
+    pub proposer: Uuid,
+    pub recipient: Uuid,
+    pub offer: &'a str,
+    pub request: &'a str,
+    pub message: &'a str,
+    pub parent: Option<Uuid>,
+}
 
-    pub fn put_file<R: Read>(&self, rel: &str, from: R) -> Result<std::fs::File> {
-        Ok(self
-            .fs
-            .overwrite(rel, from)
-            .chain_err(|| "unable to store file into filesystem")?)
-    }
+#[derive(Identifiable, Queryable, Clone)]
+pub struct Alert {
+    pub id: Uuid,
+    pub watchlist: Uuid,
+    pub collector: Uuid,
+    pub card: String,
+    pub direction: String,
+    pub threshold: i32,
+    pub price: i32,
+    pub currency: String,
+    pub dated: NaiveDate,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
 
-    // Persist a Session object to Redis.
-    //
-    // Session objects expire after 1h.  This is currently hard-coded.
-    //
-    pub fn set_session(&self, s: Session) -> Result<Session> {
-        let key = format!("session:{}", s.id);
+#[derive(Insertable)]
+#[table_name = "alerts"]
+pub struct NewAlert<'a> {
+    pub watchlist: Uuid,
+    pub collector: Uuid,
+    pub card: &'a str,
+    pub direction: &'a str,
+    pub threshold: i32,
+    pub price: i32,
+    pub currency: &'a str,
+    pub dated: NaiveDate,
+}
 
-        let mut cmd = redis::cmd("HMSET");
-        let mut cmd = cmd.arg(&key).arg("id").arg(s.id.to_string());
-        for (k, v) in &s.attrs {
-            cmd = cmd.arg(k).arg(v);
-        }
-        cmd.query::<bool>(
-            &mut self
-                .rd
-                .get_connection()
-                .chain_err(|| "unable to connect to session store")?,
-        )
-        .chain_err(|| "failed to insert session object into session store")?;
+#[derive(Identifiable, Queryable)]
+pub struct Loan {
+    pub id: Uuid,
+    pub collector: Uuid,
+    pub borrower: String,
+    pub borrower_collector: Option<Uuid>,
+    pub cards: String,
+    pub notes: String,
+    pub lent_on: NaiveDate,
+    pub due_on: Option<NaiveDate>,
+    pub returned_on: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
 
-        let mut cmd = redis::cmd("EXPIRE");
-        let cmd = cmd.arg(&key).arg(self.idle.to_string());
-        cmd.query::<bool>(
-            &mut self
-                .rd
-                .get_connection()
-                .chain_err(|| "unable to connect to session store")?,
-        )
-        .chain_err(|| "failed to set session expiration in session store")?;
+#[derive(Insertable)]
+#[table_name = "loans"]
+pub struct NewLoan<'a> {
+    pub collector: Uuid,
+    pub borrower: &'a str,
+    pub borrower_collector: Option<Uuid>,
+    pub cards: &'a str,
+    pub notes: &'a str,
+    pub lent_on: NaiveDate,
+    pub due_on: Option<NaiveDate>,
+}
 
-        Ok(s)
-    }
+#[derive(AsChangeset)]
+#[table_name = "loans"]
+pub struct UpdateLoan {
+    pub borrower: Option<String>,
+    pub cards: Option<String>,
+    pub notes: Option<String>,
+    pub due_on: Option<Option<NaiveDate>>,
+}
 
-    // Retrieve a Session object from Redis.
-    pub fn get_session(&self, id: Uuid) -> Result<Option<Session>> {
-        let key = format!("session:{}", id);
-        let mut cmd = redis::cmd("HGETALL");
-        let cmd = cmd.arg(key);
+// Sealed product (booster boxes, bundles, etc.) that a collector has
+// yet to open.  `quantity` is how many are still sealed; `paid` is what
+// each one cost, in pennies.
+#[derive(Identifiable, Queryable)]
+pub struct SealedProduct {
+    pub id: Uuid,
+    pub collector: Uuid,
+    pub kind: String,
+    pub name: String,
+    pub set_code: String,
+    pub packs: i32,
+    pub quantity: i32,
+    pub paid: Option<i32>,
+    pub acquired_on: NaiveDate,
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
 
-        let lst = cmd
-            .query::<HashMap<String, String>>(
-                &mut self
-                    .rd
-                    .get_connection()
-                    .chain_err(|| "unable to connect to session store")?,
-            )
-            .chain_err(|| "failed to retrieve session from session store")?;
-        Ok(if lst.contains_key("id") {
-            Some(Session { id: id, attrs: lst })
-        } else {
-            None
-        })
+#[derive(Insertable)]
+#[table_name = "sealed_products"]
+pub struct NewSealedProduct<'a> {
+    pub collector: Uuid,
+    pub kind: &'a str,
+    pub name: &'a str,
+    pub set_code: &'a str,
+    pub packs: i32,
+    pub quantity: i32,
+    pub paid: Option<i32>,
+    pub acquired_on: NaiveDate,
+    pub notes: &'a str,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "sealed_products"]
+pub struct UpdateSealedProduct {
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub set_code: Option<String>,
+    pub packs: Option<i32>,
+    pub quantity: Option<i32>,
+    pub paid: Option<Option<i32>>,
+    pub acquired_on: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+#[derive(Identifiable, Queryable)]
+pub struct TransactionTemplate {
+    pub id: Uuid,
+    pub collector: Uuid,
+    pub collection: Uuid,
+    pub name: String,
+    pub summary: String,
+    pub notes: String,
+    pub gain: String,
+    pub loss: String,
+    pub disposition: Disposition,
+    pub paid: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "transaction_templates"]
+pub struct NewTransactionTemplate<'a> {
+    pub collector: Uuid,
+    pub collection: Uuid,
+    pub name: &'a str,
+    pub summary: &'a str,
+    pub notes: &'a str,
+    pub gain: &'a str,
+    pub loss: &'a str,
+    pub disposition: Disposition,
+    pub paid: Option<i32>,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "transaction_templates"]
+pub struct UpdateTransactionTemplate {
+    pub collection: Option<Uuid>,
+    pub name: Option<String>,
+    pub summary: Option<String>,
+    pub notes: Option<String>,
+    pub gain: Option<String>,
+    pub loss: Option<String>,
+    pub disposition: Option<Disposition>,
+    pub paid: Option<Option<i32>>,
+}
+
+#[derive(Identifiable, Queryable)]
+pub struct RecurringTransaction {
+    pub id: Uuid,
+    pub collector: Uuid,
+    pub template: Uuid,
+    pub every: String,
+    pub starts_on: NaiveDate,
+    pub ends_on: Option<NaiveDate>,
+    pub vars: serde_json::Value,
+    pub occurrences: i32,
+    pub next_on: NaiveDate,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "recurring_transactions"]
+pub struct NewRecurringTransaction<'a> {
+    pub collector: Uuid,
+    pub template: Uuid,
+    pub every: &'a str,
+    pub starts_on: NaiveDate,
+    pub ends_on: Option<NaiveDate>,
+    pub vars: serde_json::Value,
+    pub next_on: NaiveDate,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "recurring_transactions"]
+pub struct UpdateRecurringTransaction {
+    pub every: Option<String>,
+    pub starts_on: Option<NaiveDate>,
+    pub ends_on: Option<Option<NaiveDate>>,
+    pub vars: Option<serde_json::Value>,
+    pub next_on: Option<NaiveDate>,
+}
+
+#[derive(Identifiable, Queryable)]
+pub struct Location {
+    pub id: Uuid,
+    pub collector: Uuid,
+    pub parent: Option<Uuid>,
+    pub name: String,
+    pub kind: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "locations"]
+pub struct NewLocation<'a> {
+    pub collector: Uuid,
+    pub parent: Option<Uuid>,
+    pub name: &'a str,
+    pub kind: &'a str,
+    pub description: &'a str,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "locations"]
+pub struct UpdateLocation {
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+// Copies of a print (with the given variants, space-separated) kept in
+// a location.  When passed to `stow`, the quantity is a change.
+#[derive(Queryable, Insertable, Clone)]
+#[table_name = "stowage"]
+pub struct Stowed {
+    pub location: Uuid,
+    pub pid: String,
+    pub var: String,
+    pub quantity: i32,
+}
+
+// A collector's own valuation of a graded copy, by certification number.
+#[derive(Queryable, Insertable, Clone)]
+#[table_name = "slab_values"]
+pub struct SlabValue {
+    pub collector: Uuid,
+    pub cert: String,
+    pub value: i32, // pennies
+    pub notes: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+// A read-only link to a deck, deck version or collection; see share.rs.
+// As with API tokens, only the hash of the token is kept.
+#[derive(Identifiable, Queryable)]
+pub struct ShareLink {
+    pub id: Uuid,
+    pub collector: Uuid,
+    pub hash: String,
+    pub kind: String,
+    pub deck: Option<Uuid>,
+    pub collection: Option<Uuid>,
+    pub query: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub hint: String,
+}
+
+impl ShareLink {
+    // Whether the link still lets anyone in: it hasn't been revoked,
+    // and hasn't expired.
+    pub fn is_live(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && !matches!(self.expires_at, Some(at) if at <= now)
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "share_links"]
+pub struct NewShareLink<'a> {
+    pub collector: Uuid,
+    pub hash: &'a str,
+    pub hint: &'a str,
+    pub kind: &'a str,
+    pub deck: Option<Uuid>,
+    pub collection: Option<Uuid>,
+    pub query: &'a str,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+// A collector's personal API token; see token.rs.  Only its hash is
+// kept, so there's no getting the token back out of one of these.
+#[derive(Identifiable, Queryable)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub collector: Uuid,
+    pub name: String,
+    pub hash: String,
+    pub hint: String,
+    pub scopes: Vec<String>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "api_tokens"]
+pub struct NewApiToken<'a> {
+    pub collector: Uuid,
+    pub name: &'a str,
+    pub hash: &'a str,
+    pub hint: &'a str,
+    pub scopes: Vec<String>,
+}
+
+// A token mailed out to a collector, to reset their password or verify
+// their email address; see account.rs.  The token itself is signed and
+// carries this row's id, which is all the database needs to know.
+#[derive(Identifiable, Queryable)]
+pub struct AccountToken {
+    pub id: Uuid,
+    pub collector: Uuid,
+    pub purpose: String, // reset-password or verify-email
+    pub email: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "account_tokens"]
+pub struct NewAccountToken<'a> {
+    pub collector: Uuid,
+    pub purpose: &'a str,
+    pub email: &'a str,
+    pub expires_at: DateTime<Utc>,
+}
+
+use std::collections::HashMap;
+pub struct Session {
+    pub id: Uuid,
+    pub attrs: HashMap<String, String>,
+}
+
+impl Session {
+    pub fn new(id: Option<Uuid>) -> Session {
+        Session {
+            id: match id {
+                Some(id) => id,
+                None => Uuid::new_v4(),
+            },
+            attrs: HashMap::new(),
+        }
+    }
+
+    pub fn set(&mut self, k: &str, v: &str) {
+        self.attrs.insert(k.to_string(), v.to_string());
+    }
+
+    pub fn get(&self, k: &str) -> String {
+        self.attrs[k].to_string()
+    }
+}
+
+pub struct Database {
+    pg: PgConnection,
+    rd: redis::Client,
+    fs: FStore,
+    idle: u32, // in seconds
+}
+
+embed_migrations!("migrations/");
+
+// Where the contents of a transaction attachment are kept: in the
+// directory of its collection, by content hash, so that the same file
+// attached twice is only stored once.
+fn attachment_path(collection: Uuid, sha256: &str) -> String {
+    format!("c/{}/_/attachments/{}", collection, sha256)
+}
+
+// Generate a random UUID, if one wasn't supplied.
+fn gen_uuid(id: Option<Uuid>) -> Uuid {
+    match id {
+        Some(id) => id,
+        None => Uuid::new_v4(),
+    }
+}
+
+impl Database {
+    pub fn migrate(pg: &str) -> Result<()> {
+        let pg = PgConnection::establish(pg).chain_err(|| "unable to connect to database")?;
+        embedded_migrations::run(&pg).chain_err(|| "failed to run database migrations")?;
+        Ok(())
+    }
+
+    // Find the transactions whose dispositions couldn't be mapped to
+    // a known kind when dispositions were constrained; these were made
+    // corrections, and are worth a second look.
+    pub fn unmapped_dispositions(pg: &str) -> Result<Vec<Transaction>> {
+        let pg = PgConnection::establish(pg).chain_err(|| "unable to connect to database")?;
+        find_unmapped_dispositions(&pg)
+    }
+
+    // Connect to a DSN (must be PostgreSQL) and run migrations.
+    pub fn connect(pg: &str, rd: &str, fsroot: &Path, idle: u32) -> Result<Database> {
+        Ok(Database {
+            pg: PgConnection::establish(pg).chain_err(|| "unable to connect to database")?,
+            rd: redis::Client::open(rd).chain_err(|| "unable to connect to session store")?,
+            fs: FStore::new(fsroot),
+            idle: idle,
+        })
+    }
+
+    #[cfg(test)]
+    // Start a transaction that will never end.
+    //
+    // This is only available in test runs, and is meant solely for being
+    // able to run SQL queries / statements until an error is encountered,
+    // with implicit (but unavoidable) transactional rollback.
+    //
+    pub fn testmode(&self) -> Result<()> {
+        self.pg
+            .begin_test_transaction()
+            .chain_err(|| "unable to initiate test transaction")?;
+        Ok(())
+    }
+
+    // Run a series of database changes as a single transaction; if the
+    // closure fails, none of them happen.  Files in the FStore aren't
+    // covered, so callers should only touch those once this returns.
+    pub fn atomically<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        self.pg.transaction(f)
+    }
+
+    pub fn get_file(&self, rel: &str) -> Result<std::fs::File> {
+        Ok(self
+            .fs
+            .get_as_reader(rel)
+            .chain_err(|| "unable to retrieve file from filesystem")?)
+    }
+
+    pub fn put_file<R: Read>(&self, rel: &str, from: R) -> Result<std::fs::File> {
+        Ok(self
+            .fs
+            .overwrite(rel, from)
+            .chain_err(|| "unable to store file into filesystem")?)
+    }
+
+    // Read the full card pool (cards.json), as published by rescry.
+    pub fn load_pool(&self) -> Result<card::Pool> {
+        let mut f = self
+            .fs
+            .get_as_reader("cards.json")
+            .chain_err(|| "failed to retrieve card pool")?;
+
+        card::Pool::from_reader(&mut f).chain_err(|| "failed to parse card pool")
+    }
+
+    // Read the card name -> print id lookup table (lookup.json).
+    pub fn load_lookup(&self) -> Result<card::Map> {
+        let mut f = self
+            .fs
+            .get_as_reader("lookup.json")
+            .chain_err(|| "failed to retrieve card name -> print id lookup table")?;
+
+        card::Map::from_reader(&mut f).chain_err(|| "failed to parse card lookup table")
+    }
+
+    // Read the current print id -> price table (prices.json).
+    pub fn load_prices(&self) -> Result<card::Prices> {
+        let mut f = self
+            .fs
+            .get_as_reader("prices.json")
+            .chain_err(|| "failed to retrieve card prices")?;
+
+        card::Prices::from_reader(&mut f).chain_err(|| "failed to parse card prices")
+    }
+
+    // Read the daily price history (history.json).  Until the first
+    // rescry with --history has been uploaded, there is no history.
+    pub fn load_history(&self) -> Result<card::History> {
+        if !self.fs.path_to("history.json").exists() {
+            return Ok(card::History::default());
+        }
+        let mut f = self
+            .fs
+            .get_as_reader("history.json")
+            .chain_err(|| "failed to retrieve card price history")?;
+
+        card::History::from_reader(&mut f).chain_err(|| "failed to parse card price history")
+    }
+
+    // Read a collection, reconciling any transactions that have been
+    // appended to it since the last time the reconciler ran.
+    pub fn load_collection(&self, id: Uuid) -> Result<card::Collection> {
+        let mut f = self
+            .fs
+            .get_as_reader(&format!("c/{}/_/collection.json", id))
+            .chain_err(|| "failed to retrieve collection")?;
+
+        Ok(card::Collection::from(
+            card::Aggregate::from_reader(&mut f).chain_err(|| "failed to parse collection")?,
+        ))
+    }
+
+    // Persist a Session object to Redis.
+    //
+    // Session objects expire after 1h.  This is currently hard-coded.
+    //
+    pub fn set_session(&self, s: Session) -> Result<Session> {
+        let key = format!("session:{}", s.id);
+
+        let mut cmd = redis::cmd("HMSET");
+        let mut cmd = cmd.arg(&key).arg("id").arg(s.id.to_string());
+        for (k, v) in &s.attrs {
+            cmd = cmd.arg(k).arg(v);
+        }
+        cmd.query::<bool>(
+            &mut self
+                .rd
+                .get_connection()
+                .chain_err(|| "unable to connect to session store")?,
+        )
+        .chain_err(|| "failed to insert session object into session store")?;
+
+        let mut cmd = redis::cmd("EXPIRE");
+        let cmd = cmd.arg(&key).arg(self.idle.to_string());
+        cmd.query::<bool>(
+            &mut self
+                .rd
+                .get_connection()
+                .chain_err(|| "unable to connect to session store")?,
+        )
+        .chain_err(|| "failed to set session expiration in session store")?;
+
+        Ok(s)
+    }
+
+    // Retrieve a Session object from Redis.
+    pub fn get_session(&self, id: Uuid) -> Result<Option<Session>> {
+        let key = format!("session:{}", id);
+        let mut cmd = redis::cmd("HGETALL");
+        let cmd = cmd.arg(key);
+
+        let lst = cmd
+            .query::<HashMap<String, String>>(
+                &mut self
+                    .rd
+                    .get_connection()
+                    .chain_err(|| "unable to connect to session store")?,
+            )
+            .chain_err(|| "failed to retrieve session from session store")?;
+        Ok(if lst.contains_key("id") {
+            Some(Session { id: id, attrs: lst })
+        } else {
+            None
+        })
+    }
+
+    // Forcibly expire a Session from Redis.
+    //
+    // After this call, attempts to retrieve the Session object via
+    // `get_session()` will return Ok(None), assuming no Redis errors
+    // (transport, connection, etc.) are encountered.
+    //
+    pub fn expire_session(&self, s: &Session) -> Result<()> {
+        let key = format!("session:{}", s.id);
+        let mut cmd = redis::cmd("DEL");
+        let cmd = cmd.arg(key);
+
+        cmd.query::<i32>(
+            &mut self
+                .rd
+                .get_connection()
+                .chain_err(|| "unable to connect to session store")?,
+        )
+        .chain_err(|| "failed to delete session key from session store")?;
+
+        Ok(())
+    }
+
+    // Insert a new Collector, and their 1:1 Collection.
+    //
+    // The caller may supply an explicit UUID as the first parameter, or
+    // None to automatically generate a v4 UUID.
+    //
+    pub fn create_collector(
+        &self,
+        id: Option<Uuid>,
+        new: NewCollector,
+        password: Option<&str>,
+    ) -> Result<Collector> {
+        let id = gen_uuid(id);
+        let crypted = match password {
+            Some(password) => bcrypt::hash(password, PWHASH_COST)
+                .chain_err(|| "failed to hash collector password")?,
+            None => "password-not-specified".to_string(),
+        };
+
+        let collector = diesel::insert_into(collectors::table)
+            .values((
+                &new,
+                collectors::dsl::id.eq(id),
+                collectors::dsl::password.eq(crypted),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert collector record into database")?;
+
+        diesel::insert_into(collections::table)
+            .values((
+                &NewCollection {
+                    collector: id,
+                    name: "Collection",
+                },
+                collections::dsl::id.eq(id),
+            ))
+            .get_result::<Collection>(&self.pg)
+            .chain_err(|| "failed to insert collection record for new collector into database")?;
+
+        self.fs
+            .create(
+                &format!("c/{}/_/collection.json", id.to_string()),
+                r#"[[],[[]]]"#,
+            )
+            .chain_err(|| "failed to create initial collection cache file")?;
+
+        Ok(collector)
+    }
+
+    pub fn authenticate_collector(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<Collector>> {
+        let mut found = collectors::dsl::collectors
+            .filter(collectors::dsl::username.eq(username))
+            .get_results::<Collector>(&self.pg)
+            .chain_err(|| "failed to retrieve collector record from database")?;
+
+        match found.len() {
+            1 => {
+                let collector = found.pop().unwrap();
+                match bcrypt::verify(&password, &collector.password) {
+                    Ok(true) => Ok(Some(collector)),
+                    _ => Ok(None),
+                }
+            }
+            _ => {
+                let _ = bcrypt::verify(&password, "");
+                Ok(None)
+            }
+        }
+    }
+
+    // Find a Collector by their UUID.
+    pub fn find_collector_by_uuid(&self, id: Uuid) -> Result<Option<Collector>> {
+        match collectors::dsl::collectors
+            .find(id)
+            .get_result::<Collector>(&self.pg)
+        {
+            Ok(collector) => Ok(Some(collector)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve collector from database",
+            )),
+        }
+    }
+
+    // Find a Collector by their username.
+    pub fn find_collector_by_username(&self, username: &str) -> Result<Option<Collector>> {
+        match collectors::dsl::collectors
+            .filter(collectors::dsl::username.eq(username))
+            .get_result::<Collector>(&self.pg)
+        {
+            Ok(collector) => Ok(Some(collector)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve collector from database",
+            )),
+        }
+    }
+
+    // Update a Collector with a blanket patch
+    //
+    // The CollectorUpdate object is just a collection of Option<...>
+    // fields, to make it possible to do partial updates flexibly.
+    //
+    // Returns the final Collector object, after updates are applied.
+    //
+    pub fn update_collector(&self, obj: &Collector, upd: CollectorUpdate) -> Result<Collector> {
+        let collector: Collector = diesel::update(obj)
+            .set(&upd)
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update collector record in database")?;
+
+        // a new email address has to be verified all over again.
+        if collector.email != obj.email && collector.email_verified_at.is_some() {
+            return diesel::update(&collector)
+                .set(collectors::dsl::email_verified_at.eq(None::<DateTime<Utc>>))
+                .get_result(&self.pg)
+                .chain_err(|| "failed to reset collector email verification in database");
+        }
+        Ok(collector)
+    }
+
+    // Change a collector's password (which is hashed here, rather than
+    // by the caller).
+    pub fn set_collector_password(&self, obj: &Collector, password: &str) -> Result<Collector> {
+        let crypted = bcrypt::hash(password, PWHASH_COST)
+            .chain_err(|| "failed to hash collector password")?;
+        diesel::update(obj)
+            .set(collectors::dsl::password.eq(crypted))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update collector password in database")
+    }
+
+    // End every session a collector has, by moving them on to the next
+    // generation; sessions from earlier ones are no longer honored.
+    pub fn end_collector_sessions(&self, obj: &Collector) -> Result<Collector> {
+        diesel::update(obj)
+            .set(collectors::dsl::session_generation.eq(collectors::dsl::session_generation + 1))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to end collector sessions in database")
+    }
+
+    // Mark a collector's (current) email address as verified.
+    pub fn verify_collector_email(&self, obj: &Collector) -> Result<Collector> {
+        diesel::update(obj)
+            .set(collectors::dsl::email_verified_at.eq(Some(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to mark collector email as verified in database")
+    }
+
+    // Find the collectors who have opted in to trade matching.
+    pub fn find_tradeable_collectors(&self) -> Result<Vec<Collector>> {
+        collectors::dsl::collectors
+            .filter(collectors::dsl::tradeable.eq(true))
+            .order(collectors::dsl::username)
+            .get_results::<Collector>(&self.pg)
+            .chain_err(|| "unable to retrieve tradeable collectors")
+    }
+
+    // Find a Collection by its UUID.
+    pub fn find_collection_by_uuid(&self, id: Uuid) -> Result<Option<Collection>> {
+        match collections::dsl::collections
+            .find(id)
+            .get_result::<Collection>(&self.pg)
+        {
+            Ok(collection) => Ok(Some(collection)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve collection from database",
+            )),
+        }
+    }
+
+    // Find a Collection by its UUID, and lock it (SELECT ... FOR UPDATE)
+    // until the end of the current transaction, so that anything that
+    // checks what's in it before changing it has it to itself.  Only
+    // useful inside atomically().
+    pub fn lock_collection(&self, id: Uuid) -> Result<Option<Collection>> {
+        match collections::dsl::collections
+            .find(id)
+            .for_update()
+            .get_result::<Collection>(&self.pg)
+        {
+            Ok(collection) => Ok(Some(collection)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to lock collection record in database",
+            )),
+        }
+    }
+
+    // Find all of a Collector's Collections, their default one first,
+    // and the rest by name.
+    pub fn find_collections_for_collector(&self, uid: Uuid) -> Result<Vec<Collection>> {
+        use diesel::dsl::sql;
+        use diesel::sql_types::Bool;
+
+        collections::dsl::collections
+            .filter(collections::dsl::collector.eq(uid))
+            .order((sql::<Bool>("id <> collector"), collections::dsl::name))
+            .get_results::<Collection>(&self.pg)
+            .chain_err(|| "unable to retrieve collections for collector uuid")
+    }
+
+    // Create a new (empty) Collection for a Collector, alongside the
+    // one they got when they signed up.
+    pub fn create_collection(&self, id: Option<Uuid>, new: NewCollection) -> Result<Collection> {
+        let id = gen_uuid(id);
+        let collection = diesel::insert_into(collections::table)
+            .values((&new, collections::dsl::id.eq(id)))
+            .get_result::<Collection>(&self.pg)
+            .chain_err(|| "failed to insert collection record into database")?;
+
+        self.fs
+            .create(&format!("c/{}/_/collection.json", id), r#"[[],[[]]]"#)
+            .chain_err(|| "failed to create initial collection cache file")?;
+
+        Ok(collection)
+    }
+
+    pub fn update_collection(&self, obj: &Collection, upd: UpdateCollection) -> Result<Collection> {
+        diesel::update(obj)
+            .set((&upd, collections::dsl::updated_at.eq(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update collection record in database")
+    }
+
+    // Delete a Collection, and its collection.json, so long as it has no
+    // transactions.  Its history is part of the rest of the collector's
+    // (cards transferred out of it carry their cost basis with them), so
+    // it can't be cascaded away; this returns false, and leaves it be.
+    pub fn delete_collection(&self, id: Uuid) -> Result<bool> {
+        let deleted = self.atomically(|| {
+            if self.lock_collection(id)?.is_none() {
+                return Ok(false);
+            }
+            let n: i64 = transactions::dsl::transactions
+                .filter(transactions::dsl::collection.eq(id))
+                .count()
+                .get_result(&self.pg)
+                .chain_err(|| "unable to count transactions for collection uuid")?;
+            if n > 0 {
+                return Ok(false);
+            }
+
+            diesel::delete(collections::dsl::collections.find(id))
+                .execute(&self.pg)
+                .chain_err(|| "failed to delete collection record from database")?;
+            Ok(true)
+        })?;
+
+        if deleted {
+            self.fs
+                .remove(&format!("c/{}/_/collection.json", id))
+                .chain_err(|| "failed to remove collection cache file")?;
+        }
+        Ok(deleted)
+    }
+
+    pub fn find_members_of_collection(&self, cid: Uuid) -> Result<Vec<CollectionMember>> {
+        collection_members::dsl::collection_members
+            .filter(collection_members::dsl::collection.eq(cid))
+            .order(collection_members::dsl::created_at)
+            .get_results::<CollectionMember>(&self.pg)
+            .chain_err(|| "unable to retrieve members of collection uuid")
+    }
+
+    // Find the collections a collector has been invited into, whether
+    // they've accepted those invitations or not.
+    pub fn find_memberships_for_collector(&self, uid: Uuid) -> Result<Vec<CollectionMember>> {
+        collection_members::dsl::collection_members
+            .filter(collection_members::dsl::collector.eq(uid))
+            .order(collection_members::dsl::created_at)
+            .get_results::<CollectionMember>(&self.pg)
+            .chain_err(|| "unable to retrieve collection memberships for collector uuid")
+    }
+
+    pub fn find_collection_member(&self, cid: Uuid, uid: Uuid) -> Result<Option<CollectionMember>> {
+        match collection_members::dsl::collection_members
+            .find((cid, uid))
+            .get_result::<CollectionMember>(&self.pg)
+        {
+            Ok(member) => Ok(Some(member)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve collection member from database",
+            )),
+        }
+    }
+
+    pub fn invite_collection_member(&self, new: NewCollectionMember) -> Result<CollectionMember> {
+        diesel::insert_into(collection_members::table)
+            .values(&new)
+            .get_result::<CollectionMember>(&self.pg)
+            .chain_err(|| "failed to insert collection member record into database")
+    }
+
+    pub fn accept_collection_membership(&self, obj: &CollectionMember) -> Result<CollectionMember> {
+        let now = Utc::now();
+        diesel::update(obj)
+            .set((
+                collection_members::dsl::accepted_at.eq(Some(now)),
+                collection_members::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to accept collection membership in database")
+    }
+
+    pub fn set_collection_member_role(
+        &self,
+        obj: &CollectionMember,
+        role: &str,
+    ) -> Result<CollectionMember> {
+        diesel::update(obj)
+            .set((
+                collection_members::dsl::role.eq(role),
+                collection_members::dsl::updated_at.eq(Utc::now()),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update collection member role in database")
+    }
+
+    pub fn delete_collection_member(&self, cid: Uuid, uid: Uuid) -> Result<()> {
+        diesel::delete(collection_members::dsl::collection_members.find((cid, uid)))
+            .execute(&self.pg)
+            .chain_err(|| "failed to delete collection member from database")?;
+        Ok(())
+    }
+
+    pub fn find_transactions_for_collection(&self, cid: Uuid) -> Result<Vec<Transaction>> {
+        Ok(transactions::dsl::transactions
+            .filter(transactions::dsl::collection.eq(cid))
+            .get_results::<Transaction>(&self.pg)
+            .chain_err(|| "unable to retrieve transactions for collection uuid")?)
+    }
+
+    // Find the Transactions in a Collection that match a query, sorted
+    // by date (and then by UUID, so that cursors are stable).
+    pub fn find_transactions_matching(
+        &self,
+        cid: Uuid,
+        q: &TransactionQuery,
+    ) -> Result<Vec<Transaction>> {
+        use diesel::dsl::sql;
+        use diesel::sql_types::{Bool, Jsonb, Text};
+        use transactions::dsl::{collection, dated, disposition, id};
+
+        let mut query = transactions::table.filter(collection.eq(cid)).into_boxed();
+        if let Some(from) = q.from {
+            query = query.filter(dated.ge(from));
+        }
+        if let Some(to) = q.to {
+            query = query.filter(dated.le(to));
+        }
+        if !q.dispositions.is_empty() {
+            query = query.filter(disposition.eq_any(q.dispositions.clone()));
+        }
+        if let Some(search) = &q.search {
+            query = query.filter(
+                sql::<Bool>("to_tsvector('simple', summary || ' ' || notes) @@ ")
+                    .sql("plainto_tsquery('simple', ")
+                    .bind::<Text, _>(search.to_string())
+                    .sql(")"),
+            );
+        }
+        if let Some(card) = &q.card {
+            let pattern = card_line_pattern(card);
+            query = query.filter(
+                sql::<Bool>("(gain ~* ")
+                    .bind::<Text, _>(pattern.to_string())
+                    .sql(" OR loss ~* ")
+                    .bind::<Text, _>(pattern)
+                    .sql(")"),
+            );
+        }
+        if let Some(set) = &q.set {
+            let set = set.trim().to_uppercase();
+            query = query.filter(
+                sql::<Bool>("(metadata @> ")
+                    .bind::<Jsonb, _>(json!({ "set_gain": [set] }))
+                    .sql(" OR metadata @> ")
+                    .bind::<Jsonb, _>(json!({ "set_loss": [set] }))
+                    .sql(")"),
+            );
+        }
+        match (q.after, q.newest_first) {
+            (Some((d, i)), false) => {
+                query = query.filter(dated.gt(d).or(dated.eq(d).and(id.gt(i))));
+            }
+            (Some((d, i)), true) => {
+                query = query.filter(dated.lt(d).or(dated.eq(d).and(id.lt(i))));
+            }
+            (None, _) => (),
+        }
+        query = if q.newest_first {
+            query.order((dated.desc(), id.desc()))
+        } else {
+            query.order((dated.asc(), id.asc()))
+        };
+        if let Some(limit) = q.limit {
+            query = query.limit(limit);
+        }
+
+        query
+            .get_results::<Transaction>(&self.pg)
+            .chain_err(|| "unable to retrieve matching transactions for collection uuid")
+    }
+
+    // Find a Transaction by its UUID.
+    pub fn find_transaction_by_uuid(&self, cid: Uuid, id: Uuid) -> Result<Option<Transaction>> {
+        match transactions::dsl::transactions
+            .find(id)
+            .filter(transactions::dsl::collection.eq(cid))
+            .get_result::<Transaction>(&self.pg)
+        {
+            Ok(transaction) => Ok(Some(transaction)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve transaction from database",
+            )),
+        }
+    }
+
+    // Create a new Transaction.
+    pub fn create_transaction(&self, id: Option<Uuid>, new: NewTransaction) -> Result<Transaction> {
+        let collection = new.collection;
+        let (txn, gain, loss) = self.insert_transaction(id, new)?;
+
+        // update the collection with new, resolved CDIF
+        self.apply_collection_credit(collection, gain)
+            .chain_err(|| "unable to apply new gains from transaction")?;
+
+        // update the collection again for any losses
+        self.apply_collection_debit(collection, loss)
+            .chain_err(|| "unable to apply new losses from transaction")?;
+
+        Ok(txn)
+    }
+
+    // Move cards from one Collection to another, as a pair of transfer
+    // transactions (a loss from one, and a gain to the other) that point
+    // at each other, so that deleting either one deletes both.
+    pub fn transfer_cards(
+        &self,
+        out: NewTransaction,
+        into: NewTransaction,
+    ) -> Result<(Transaction, Transaction)> {
+        let (out, into, loss, gain) = self.atomically(|| self.insert_transfer(out, into))?;
+
+        // the collection files live outside the database; only touch
+        // them once both halves are safely recorded.
+        self.apply_transfer(out.collection, loss, into.collection, gain)?;
+        Ok((out, into))
+    }
+
+    // Insert both halves of a transfer, pointing at each other, without
+    // touching either collection.  As with insert_transaction, what was
+    // lost and gained is handed back, for apply_transfer.  Only useful
+    // inside atomically().
+    pub fn insert_transfer(
+        &self,
+        out: NewTransaction,
+        into: NewTransaction,
+    ) -> Result<(Transaction, Transaction, cdif::File, cdif::File)> {
+        let (out, _, loss) = self.insert_transaction(None, out)?;
+        let (into, gain, _) = self.insert_transaction(None, into)?;
+
+        let out = diesel::update(&out)
+            .set(transactions::dsl::transfer.eq(Some(into.id)))
+            .get_result::<Transaction>(&self.pg)
+            .chain_err(|| "failed to pair transfer transactions in database")?;
+        let into = diesel::update(&into)
+            .set(transactions::dsl::transfer.eq(Some(out.id)))
+            .get_result::<Transaction>(&self.pg)
+            .chain_err(|| "failed to pair transfer transactions in database")?;
+        Ok((out, into, loss, gain))
+    }
+
+    pub fn apply_transfer(
+        &self,
+        from: Uuid,
+        loss: cdif::File,
+        to: Uuid,
+        gain: cdif::File,
+    ) -> Result<()> {
+        self.apply_collection_debit(from, loss)
+            .chain_err(|| "unable to apply transfer out of collection")?;
+        self.apply_collection_credit(to, gain)
+            .chain_err(|| "unable to apply transfer into collection")
+    }
+
+    // Insert a transaction record, without touching the collection.
+    // The parsed gains and losses are handed back so that the caller
+    // can apply them once it is ready to.
+    fn insert_transaction(
+        &self,
+        id: Option<Uuid>,
+        new: NewTransaction,
+    ) -> Result<(Transaction, cdif::File, cdif::File)> {
+        let gain = cdif::File::from_string(&new.gain)
+            .chain_err(|| "unable to parse gained cards from transaction")?;
+        let loss = cdif::File::from_string(&new.loss)
+            .chain_err(|| "unable to parse lost cards from transaction")?;
+
+        let mut meta = serde_json::Map::new();
+
+        let (total, unique) = gain.count();
+        meta.insert("total_gain".to_string(), json!(total as f64));
+        meta.insert("unique_gain".to_string(), json!(unique as f64));
+        meta.insert("set_gain".to_string(), json!(gain.unique_sets()));
+
+        let (total, unique) = loss.count();
+        meta.insert("total_loss".to_string(), json!(total as f64));
+        meta.insert("unique_loss".to_string(), json!(unique as f64));
+        meta.insert("set_loss".to_string(), json!(loss.unique_sets()));
+
+        let txn = diesel::insert_into(transactions::table)
+            .values((
+                &new,
+                transactions::dsl::metadata.eq(serde_json::Value::Object(meta)),
+                transactions::dsl::id.eq(gen_uuid(id)),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert transaction record into database")?;
+
+        Ok((txn, gain, loss))
+    }
+
+    pub fn update_transaction(
+        &self,
+        obj: &Transaction,
+        upd: UpdateTransaction,
+    ) -> Result<Transaction> {
+        let mut meta = match obj.metadata.as_object() {
+            Some(meta) => meta.clone(),
+            _ => serde_json::Map::new(),
+        };
+
+        let gain = match &upd.gain {
+            None => None,
+            Some(s) => {
+                let cdif = cdif::File::from_string(&s)
+                    .chain_err(|| "failed to parse updated gains during transaction update")?;
+
+                let (total, unique) = cdif.count();
+                meta.insert("total_gain".to_string(), json!(total as f64));
+                meta.insert("unique_gain".to_string(), json!(unique as f64));
+                meta.insert("set_gain".to_string(), json!(cdif.unique_sets()));
+
+                Some(cdif)
+            }
+        };
+
+        let loss = match &upd.loss {
+            None => None,
+            Some(s) => {
+                let cdif = cdif::File::from_string(&s)
+                    .chain_err(|| "failed to parse updated losses during transaction update")?;
+
+                let (total, unique) = cdif.count();
+                meta.insert("total_loss".to_string(), json!(total as f64));
+                meta.insert("unique_loss".to_string(), json!(unique as f64));
+                meta.insert("set_loss".to_string(), json!(cdif.unique_sets()));
+
+                Some(cdif)
+            }
+        };
+
+        // an explicit disposition settles any question of what it was
+        if upd.disposition.is_some() {
+            meta.remove("unmapped_disposition");
+        }
+
+        let txn = diesel::update(obj)
+            .set((
+                &upd,
+                transactions::dsl::metadata.eq(serde_json::Value::Object(meta)),
+                transactions::dsl::updated_at.eq(Utc::now()),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update transaction record in database")?;
+
+        match gain {
+            None => (),
+            Some(now) => {
+                let then = cdif::File::from_string(&obj.gain)
+                    .chain_err(|| "failed to parse previous gains during transaction update")?;
+
+                self.apply_collection_credit(obj.collection, cdif::File::diff(&then, &now))?;
+            }
+        };
+
+        match loss {
+            None => (),
+            Some(now) => {
+                let then = cdif::File::from_string(&obj.loss)
+                    .chain_err(|| "failed to parse previous losses during transaction update")?;
+
+                self.apply_collection_debit(obj.collection, cdif::File::diff(&then, &now))?;
+            }
+        };
+
+        Ok(txn)
+    }
+
+    // Delete a transaction, along with any files attached to it.  Half a
+    // transfer is no transfer at all, so the other half goes too.  The
+    // records all go in one database transaction; attached files are
+    // only removed (and the cards gained and lost given back) once that
+    // has committed, since that can't be undone.
+    pub fn delete_transaction(&self, id: Uuid) -> Result<()> {
+        let (deleted, mut orphaned) = self.pg.transaction::<_, Error, _>(|| {
+            let mut deleted = vec![];
+            let mut orphaned = vec![];
+            let mut next = Some(id);
+            while let Some(id) = next {
+                let txn = match transactions::dsl::transactions
+                    .find(id)
+                    .get_result::<Transaction>(&self.pg)
+                    .optional()
+                    .chain_err(|| "failed to retrieve transaction record from database")?
+                {
+                    Some(txn) => txn,
+                    None => break,
+                };
+                let (collection, opened, transfer) = (txn.collection, txn.opened, txn.transfer);
+                for attachment in self.find_attachments_for_transactions(&[id])? {
+                    orphaned.push((collection, attachment.sha256));
+                }
+
+                // attachment records go with the transaction (on delete
+                // cascade), and the other half of a transfer is unpaired
+                // (on delete set null), so this stops there.
+                diesel::delete(
+                    transactions::dsl::transactions.filter(transactions::dsl::id.eq(id)),
+                )
+                .execute(&self.pg)
+                .chain_err(|| "failed to delete transaction record from database")?;
+
+                // un-opening a sealed product puts it back on the shelf.
+                if let Some(opened) = opened {
+                    diesel::update(sealed_products::dsl::sealed_products.find(opened))
+                        .set((
+                            sealed_products::dsl::quantity.eq(sealed_products::dsl::quantity + 1),
+                            sealed_products::dsl::updated_at.eq(Utc::now()),
+                        ))
+                        .execute(&self.pg)
+                        .chain_err(|| "failed to return opened sealed product to inventory")?;
+                }
+                deleted.push(txn);
+                next = transfer;
+            }
+            Ok((deleted, orphaned))
+        })?;
+
+        // the files themselves have to be cleaned up by hand.
+        for txn in deleted {
+            let gain = cdif::File::from_string(&txn.gain)
+                .chain_err(|| "unable to parse gained cards from deleted transaction")?;
+            let loss = cdif::File::from_string(&txn.loss)
+                .chain_err(|| "unable to parse lost cards from deleted transaction")?;
+            self.apply_collection_debit(txn.collection, gain)?;
+            self.apply_collection_credit(txn.collection, loss)?;
+        }
+        orphaned.sort();
+        orphaned.dedup();
+        for (collection, sha256) in orphaned {
+            self.remove_attachment_contents(collection, &sha256)?;
+        }
+        Ok(())
+    }
+
+    fn collection_of(&self, attachment: &Attachment) -> Result<Uuid> {
+        transactions::dsl::transactions
+            .find(attachment.transaction)
+            .select(transactions::dsl::collection)
+            .get_result::<Uuid>(&self.pg)
+            .chain_err(|| "failed to retrieve transaction record for attachment")
+    }
+
+    pub fn find_attachments_for_transactions(&self, ids: &[Uuid]) -> Result<Vec<Attachment>> {
+        attachments::dsl::attachments
+            .filter(attachments::dsl::transaction.eq_any(ids))
+            .order((attachments::dsl::created_at, attachments::dsl::filename))
+            .get_results::<Attachment>(&self.pg)
+            .chain_err(|| "unable to retrieve attachments for transactions")
+    }
+
+    pub fn find_attachment_by_uuid(&self, tid: Uuid, id: Uuid) -> Result<Option<Attachment>> {
+        match attachments::dsl::attachments
+            .find(id)
+            .filter(attachments::dsl::transaction.eq(tid))
+            .get_result::<Attachment>(&self.pg)
+        {
+            Ok(attachment) => Ok(Some(attachment)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve attachment record from database",
+            )),
+        }
+    }
+
+    // Attach a file to a transaction, storing its contents and recording
+    // their SHA-256 digest.  Size limits are up to the caller.
+    pub fn create_attachment(
+        &self,
+        id: Option<Uuid>,
+        txn: &Transaction,
+        filename: &str,
+        content_type: &str,
+        contents: &[u8],
+    ) -> Result<Attachment> {
+        let sha256 = format!("{:x}", Sha256::digest(contents));
+        self.fs
+            .store(&attachment_path(txn.collection, &sha256), contents)
+            .chain_err(|| "failed to store attachment contents")?;
+
+        diesel::insert_into(attachments::table)
+            .values((
+                &NewAttachment {
+                    transaction: txn.id,
+                    filename,
+                    content_type,
+                    size: contents.len() as i32,
+                    sha256: &sha256,
+                },
+                attachments::dsl::id.eq(gen_uuid(id)),
+            ))
+            .get_result::<Attachment>(&self.pg)
+            .chain_err(|| "failed to insert attachment record into database")
+    }
+
+    pub fn get_attachment_contents(&self, attachment: &Attachment) -> Result<File> {
+        self
+            .fs
+            .get_as_reader(&attachment_path(
+                self.collection_of(attachment)?,
+                &attachment.sha256,
+            ))
+            .chain_err(|| "unable to retrieve attachment contents")
+    }
+
+    pub fn delete_attachment(&self, attachment: &Attachment) -> Result<()> {
+        let collection = self.collection_of(attachment)?;
+        diesel::delete(attachments::dsl::attachments.find(attachment.id))
+            .execute(&self.pg)
+            .chain_err(|| "failed to delete attachment record from database")?;
+        self.remove_attachment_contents(collection, &attachment.sha256)
+    }
+
+    // Remove the stored contents of a deleted attachment, unless another
+    // attachment in the same collection still needs them.
+    fn remove_attachment_contents(&self, collection: Uuid, sha256: &str) -> Result<()> {
+        let shared = attachments::dsl::attachments
+            .inner_join(transactions::table)
+            .filter(transactions::dsl::collection.eq(collection))
+            .filter(attachments::dsl::sha256.eq(sha256))
+            .count()
+            .get_result::<i64>(&self.pg)
+            .chain_err(|| "failed to count attachments sharing contents")?;
+        if shared == 0 {
+            self.fs
+                .remove(&attachment_path(collection, sha256))
+                .chain_err(|| "failed to remove attachment contents")?;
+        }
+        Ok(())
+    }
+
+    fn apply_collection_diff(&self, id: Uuid, cards: cdif::File, credit: bool) -> Result<()> {
+        let mut f = self
+            .fs
+            .get_as_reader("lookup.json")
+            .chain_err(|| "failed to retrieve card name -> print id lookup table")?;
+
+        let lookup = card::Map::from_reader(&mut f)
+            .chain_err(|| "failed to retrieve card name -> print id lookup table")?;
+
+        let mut delta = card::Pile::resolve(cards, lookup);
+        if !credit {
+            delta.invert();
+        }
+        self.fs
+            .append_to_json_list(
+                &format!("c/{}/_/collection.json", id.to_string()),
+                delta.cards,
+            )
+            .chain_err(|| "failed to append json object to collection json file")?;
+
+        Ok(())
+    }
+
+    pub fn apply_collection_credit(&self, id: Uuid, credit: cdif::File) -> Result<()> {
+        self.apply_collection_diff(id, credit, true)
+    }
+
+    pub fn apply_collection_debit(&self, id: Uuid, debit: cdif::File) -> Result<()> {
+        self.apply_collection_diff(id, debit, false)
+    }
+
+    pub fn find_goals_for_collector(&self, uid: Uuid) -> Result<Vec<Goal>> {
+        Ok(goals::dsl::goals
+            .filter(goals::dsl::collector.eq(uid))
+            .get_results::<Goal>(&self.pg)
+            .chain_err(|| "unable to retrieve goals for collector uuid")?)
+    }
+
+    pub fn find_goal_by_uuid(&self, uid: Uuid, id: Uuid) -> Result<Option<Goal>> {
+        match goals::dsl::goals
+            .find(id)
+            .filter(goals::dsl::collector.eq(uid))
+            .get_result::<Goal>(&self.pg)
+        {
+            Ok(goal) => Ok(Some(goal)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve goal record from database",
+            )),
+        }
+    }
+
+    pub fn create_goal(&self, id: Option<Uuid>, new: NewGoal) -> Result<Goal> {
+        let now = Utc::now();
+        let id = gen_uuid(id);
+        Ok(diesel::insert_into(goals::table)
+            .values((
+                &new,
+                goals::dsl::id.eq(id),
+                goals::dsl::created_at.eq(now),
+                goals::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert goal record into database")?)
+    }
+
+    pub fn update_goal(&self, obj: &Goal, upd: UpdateGoal) -> Result<Goal> {
+        Ok(diesel::update(obj)
+            .set((&upd, goals::dsl::updated_at.eq(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update goal record in database")?)
+    }
+
+    pub fn delete_goal(&self, id: Uuid) -> Result<()> {
+        diesel::delete(goals::dsl::goals.filter(goals::dsl::id.eq(id)))
+            .execute(&self.pg)
+            .chain_err(|| "failed to delete goal record from database")?;
+        Ok(())
+    }
+
+    pub fn find_decks_for_collector(&self, uid: Uuid) -> Result<Vec<Deck>> {
+        Ok(decks::dsl::decks
+            .filter(decks::dsl::collector.eq(uid))
+            .get_results::<Deck>(&self.pg)
+            .chain_err(|| "unable to retrieve decks for collector uuid")?)
+    }
+
+    pub fn find_deck_by_uuid(&self, uid: Uuid, id: Uuid) -> Result<Option<Deck>> {
+        match decks::dsl::decks
+            .find(id)
+            .filter(decks::dsl::collector.eq(uid))
+            .get_result::<Deck>(&self.pg)
+        {
+            Ok(deck) => Ok(Some(deck)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve deck record from database",
+            )),
+        }
+    }
+
+    pub fn create_deck(&self, id: Option<Uuid>, new: NewDeck) -> Result<Deck> {
+        let now = Utc::now();
+        let id = gen_uuid(id);
+        Ok(diesel::insert_into(decks::table)
+            .values((
+                &new,
+                decks::dsl::id.eq(id),
+                decks::dsl::lineage.eq(id),
+                decks::dsl::ordinal.eq(0),
+                decks::dsl::created_at.eq(now),
+                decks::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert deck record into database")?)
+    }
+
+    pub fn update_deck(&self, obj: &Deck, upd: UpdateDeck) -> Result<Deck> {
+        Ok(diesel::update(obj)
+            .set((&upd, decks::dsl::updated_at.eq(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update deck record in database")?)
+    }
+
+    pub fn delete_deck(&self, id: Uuid) -> Result<()> {
+        diesel::delete(decks::dsl::decks.filter(decks::dsl::id.eq(id)))
+            .execute(&self.pg)
+            .chain_err(|| "failed to delete deck record from database")?;
+        Ok(())
+    }
+
+    // Find the newest version of a deck, by its lineage.
+    pub fn find_latest_deck_in_lineage(&self, lineage: Uuid) -> Result<Option<Deck>> {
+        match decks::dsl::decks
+            .filter(decks::dsl::lineage.eq(lineage))
+            .order(decks::dsl::ordinal.desc())
+            .first::<Deck>(&self.pg)
+        {
+            Ok(deck) => Ok(Some(deck)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve deck record from database",
+            )),
+        }
+    }
+
+    //pub fn snapshot_deck
+
+    pub fn find_pods_for_collector(&self, uid: Uuid) -> Result<Vec<Pod>> {
+        pods::dsl::pods
+            .filter(pods::dsl::collector.eq(uid))
+            .get_results::<Pod>(&self.pg)
+            .chain_err(|| "unable to retrieve draft pods for collector uuid")
+    }
+
+    // Find a draft Pod by its UUID, regardless of who organized it.
+    pub fn find_pod_by_uuid(&self, id: Uuid) -> Result<Option<Pod>> {
+        match pods::dsl::pods.find(id).get_result::<Pod>(&self.pg) {
+            Ok(pod) => Ok(Some(pod)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve draft pod record from database",
+            )),
+        }
+    }
+
+    pub fn create_pod(&self, id: Option<Uuid>, new: NewPod) -> Result<Pod> {
+        let now = Utc::now();
+        diesel::insert_into(pods::table)
+            .values((
+                &new,
+                pods::dsl::id.eq(gen_uuid(id)),
+                pods::dsl::created_at.eq(now),
+                pods::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert draft pod record into database")
+    }
+
+    // Find a draft Pod by its UUID, and lock it (SELECT ... FOR UPDATE)
+    // until the end of the current transaction, so that drafters picking
+    // at the same time take turns, instead of overwriting each other's
+    // picks.  Only useful inside atomically().
+    pub fn lock_pod(&self, id: Uuid) -> Result<Option<Pod>> {
+        match pods::dsl::pods
+            .find(id)
+            .for_update()
+            .get_result::<Pod>(&self.pg)
+        {
+            Ok(pod) => Ok(Some(pod)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to lock draft pod record in database",
+            )),
+        }
+    }
+
+    pub fn update_pod(&self, obj: &Pod, upd: UpdatePod) -> Result<Pod> {
+        diesel::update(obj)
+            .set((&upd, pods::dsl::updated_at.eq(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update draft pod record in database")
+    }
+
+    pub fn delete_pod(&self, id: Uuid) -> Result<()> {
+        diesel::delete(pods::dsl::pods.filter(pods::dsl::id.eq(id)))
+            .execute(&self.pg)
+            .chain_err(|| "failed to delete draft pod record from database")?;
+        Ok(())
+    }
+
+    pub fn find_watchlists(&self) -> Result<Vec<Watchlist>> {
+        watchlists::dsl::watchlists
+            .get_results::<Watchlist>(&self.pg)
+            .chain_err(|| "unable to retrieve watchlists")
+    }
+
+    pub fn find_watchlists_for_collector(&self, uid: Uuid) -> Result<Vec<Watchlist>> {
+        watchlists::dsl::watchlists
+            .filter(watchlists::dsl::collector.eq(uid))
+            .order(watchlists::dsl::created_at)
+            .get_results::<Watchlist>(&self.pg)
+            .chain_err(|| "unable to retrieve watchlists for collector uuid")
+    }
+
+    pub fn find_watchlist_by_uuid(&self, uid: Uuid, id: Uuid) -> Result<Option<Watchlist>> {
+        match watchlists::dsl::watchlists
+            .find(id)
+            .filter(watchlists::dsl::collector.eq(uid))
+            .get_result::<Watchlist>(&self.pg)
+        {
+            Ok(watchlist) => Ok(Some(watchlist)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve watchlist record from database",
+            )),
+        }
+    }
+
+    pub fn create_watchlist(&self, id: Option<Uuid>, new: NewWatchlist) -> Result<Watchlist> {
+        let now = Utc::now();
+        diesel::insert_into(watchlists::table)
+            .values((
+                &new,
+                watchlists::dsl::id.eq(gen_uuid(id)),
+                watchlists::dsl::created_at.eq(now),
+                watchlists::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert watchlist record into database")
+    }
+
+    pub fn update_watchlist(&self, obj: &Watchlist, upd: UpdateWatchlist) -> Result<Watchlist> {
+        diesel::update(obj)
+            .set((&upd, watchlists::dsl::updated_at.eq(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update watchlist record in database")
+    }
+
+    pub fn delete_watchlist(&self, id: Uuid) -> Result<()> {
+        diesel::delete(watchlists::dsl::watchlists.filter(watchlists::dsl::id.eq(id)))
+            .execute(&self.pg)
+            .chain_err(|| "failed to delete watchlist record from database")?;
+        Ok(())
+    }
+
+    pub fn find_wishlists_for_collector(&self, uid: Uuid) -> Result<Vec<Wishlist>> {
+        wishlists::dsl::wishlists
+            .filter(wishlists::dsl::collector.eq(uid))
+            .order(wishlists::dsl::name)
+            .get_results::<Wishlist>(&self.pg)
+            .chain_err(|| "unable to retrieve wishlists for collector uuid")
+    }
+
+    pub fn find_wishlist_by_uuid(&self, uid: Uuid, id: Uuid) -> Result<Option<Wishlist>> {
+        match wishlists::dsl::wishlists
+            .find(id)
+            .filter(wishlists::dsl::collector.eq(uid))
+            .get_result::<Wishlist>(&self.pg)
+        {
+            Ok(wishlist) => Ok(Some(wishlist)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve wishlist record from database",
+            )),
+        }
+    }
+
+    pub fn create_wishlist(&self, id: Option<Uuid>, new: NewWishlist) -> Result<Wishlist> {
+        let now = Utc::now();
+        diesel::insert_into(wishlists::table)
+            .values((
+                &new,
+                wishlists::dsl::id.eq(gen_uuid(id)),
+                wishlists::dsl::created_at.eq(now),
+                wishlists::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert wishlist record into database")
+    }
+
+    pub fn update_wishlist(&self, obj: &Wishlist, upd: UpdateWishlist) -> Result<Wishlist> {
+        diesel::update(obj)
+            .set((&upd, wishlists::dsl::updated_at.eq(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update wishlist record in database")
+    }
+
+    pub fn delete_wishlist(&self, id: Uuid) -> Result<()> {
+        diesel::delete(wishlists::dsl::wishlists.filter(wishlists::dsl::id.eq(id)))
+            .execute(&self.pg)
+            .chain_err(|| "failed to delete wishlist record from database")?;
+        Ok(())
+    }
+
+    // Find every trade a collector is party to, most recent first.
+    pub fn find_trades_for_collector(&self, uid: Uuid) -> Result<Vec<Trade>> {
+        trades::dsl::trades
+            .filter(
+                trades::dsl::proposer
+                    .eq(uid)
+                    .or(trades::dsl::recipient.eq(uid)),
+            )
+            .order(trades::dsl::created_at.desc())
+            .get_results::<Trade>(&self.pg)
+            .chain_err(|| "unable to retrieve trades for collector uuid")
+    }
+
+    pub fn find_trade_by_uuid(&self, uid: Uuid, id: Uuid) -> Result<Option<Trade>> {
+        match trades::dsl::trades
+            .find(id)
+            .filter(
+                trades::dsl::proposer
+                    .eq(uid)
+                    .or(trades::dsl::recipient.eq(uid)),
+            )
+            .get_result::<Trade>(&self.pg)
+        {
+            Ok(trade) => Ok(Some(trade)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve trade record from database",
+            )),
+        }
+    }
+
+    pub fn create_trade(&self, id: Option<Uuid>, new: NewTrade) -> Result<Trade> {
+        let now = Utc::now();
+        diesel::insert_into(trades::table)
+            .values((
+                &new,
+                trades::dsl::id.eq(gen_uuid(id)),
+                trades::dsl::created_at.eq(now),
+                trades::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert trade record into database")
+    }
+
+    // Move a trade out of the proposed state.  Trades that have already
+    // been settled (accepted, declined, etc.) are left alone, and None
+    // is returned, so that two racing responses can't both win.
+    fn close_trade(&self, obj: &Trade, status: &str) -> Result<Option<Trade>> {
+        diesel::update(
+            trades::dsl::trades
+                .filter(trades::dsl::id.eq(obj.id))
+                .filter(trades::dsl::status.eq("proposed")),
+        )
+        .set((
+            trades::dsl::status.eq(status),
+            trades::dsl::updated_at.eq(Utc::now()),
+        ))
+        .get_result::<Trade>(&self.pg)
+        .optional()
+        .chain_err(|| "failed to update trade record in database")
+    }
+
+    pub fn decline_trade(&self, obj: &Trade) -> Result<Option<Trade>> {
+        self.close_trade(obj, "declined")
+    }
+
+    pub fn withdraw_trade(&self, obj: &Trade) -> Result<Option<Trade>> {
+        self.close_trade(obj, "withdrawn")
+    }
+
+    // Counter a proposed trade with a new one, in the other direction.
+    pub fn counter_trade(&self, obj: &Trade, id: Option<Uuid>, new: NewTrade) -> Result<Option<Trade>> {
+        self.pg.transaction(|| {
+            if self.close_trade(obj, "countered")?.is_none() {
+                return Ok(None);
+            }
+            Ok(Some(self.create_trade(id, new)?))
+        })
+    }
+
+    // Accept a proposed trade, writing the (mirrored) transactions for
+    // both collections.  Either all of it happens, or none of it does;
+    // the collection files are only touched once the database work has
+    // been committed, just as for any other transaction.
+    pub fn accept_trade(
+        &self,
+        obj: &Trade,
+        moves: Vec<(NewTransaction, NewTransaction)>,
+        proposer: NewTransaction,
+        recipient: NewTransaction,
+    ) -> Result<Option<Trade>> {
+        let (a, b) = (proposer.collection, recipient.collection);
+        let mut moved = vec![];
+        let accepted = self.pg.transaction::<_, Error, _>(|| {
+            if self.close_trade(obj, "accepted")?.is_none() {
+                return Ok(None);
+            }
+
+            // cards given up from anywhere but the collection the trade
+            // is posted to are transferred there first.
+            for (out, into) in moves {
+                let (out, into, loss, gain) = self.insert_transfer(out, into)?;
+                moved.push((out.collection, loss, into.collection, gain));
+            }
+
+            let (mine, a_gain, a_loss) = self.insert_transaction(None, proposer)?;
+            let (theirs, b_gain, b_loss) = self.insert_transaction(None, recipient)?;
+
+            let trade = diesel::update(obj)
+                .set((
+                    trades::dsl::proposer_transaction.eq(Some(mine.id)),
+                    trades::dsl::recipient_transaction.eq(Some(theirs.id)),
+                ))
+                .get_result::<Trade>(&self.pg)
+                .chain_err(|| "failed to link transactions to trade record in database")?;
+
+            Ok(Some((trade, a_gain, a_loss, b_gain, b_loss)))
+        })?;
+
+        let (trade, a_gain, a_loss, b_gain, b_loss) = match accepted {
+            Some(accepted) => accepted,
+            None => return Ok(None),
+        };
+        for (from, loss, to, gain) in moved {
+            self.apply_transfer(from, loss, to, gain)?;
+        }
+        self.apply_collection_credit(a, a_gain)
+            .chain_err(|| "unable to apply traded gains to proposer's collection")?;
+        self.apply_collection_debit(a, a_loss)
+            .chain_err(|| "unable to apply traded losses to proposer's collection")?;
+        self.apply_collection_credit(b, b_gain)
+            .chain_err(|| "unable to apply traded gains to recipient's collection")?;
+        self.apply_collection_debit(b, b_loss)
+            .chain_err(|| "unable to apply traded losses to recipient's collection")?;
+
+        Ok(Some(trade))
+    }
+
+    // Find a collector's price alerts, most recent first.
+    pub fn find_alerts_for_collector(&self, uid: Uuid) -> Result<Vec<Alert>> {
+        alerts::dsl::alerts
+            .filter(alerts::dsl::collector.eq(uid))
+            .order(alerts::dsl::created_at.desc())
+            .get_results::<Alert>(&self.pg)
+            .chain_err(|| "unable to retrieve alerts for collector uuid")
+    }
+
+    // Find the alerts that no notifier has (successfully) delivered.
+    pub fn find_undelivered_alerts(&self) -> Result<Vec<Alert>> {
+        alerts::dsl::alerts
+            .filter(alerts::dsl::delivered_at.is_null())
+            .order(alerts::dsl::created_at)
+            .get_results::<Alert>(&self.pg)
+            .chain_err(|| "unable to retrieve undelivered alerts")
+    }
+
+    // Record a triggered alert, unless the same alert has already been
+    // recorded for that day's prices, in which case we return None.
+    pub fn create_alert(&self, id: Option<Uuid>, new: NewAlert) -> Result<Option<Alert>> {
+        diesel::insert_into(alerts::table)
+            .values((
+                &new,
+                alerts::dsl::id.eq(gen_uuid(id)),
+                alerts::dsl::created_at.eq(Utc::now()),
+            ))
+            .on_conflict_do_nothing()
+            .get_result(&self.pg)
+            .optional()
+            .chain_err(|| "failed to insert alert record into database")
+    }
+
+    pub fn mark_alert_delivered(&self, obj: &Alert) -> Result<Alert> {
+        diesel::update(obj)
+            .set(alerts::dsl::delivered_at.eq(Some(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to mark alert record as delivered in database")
+    }
+
+    // Find every loan a collector has made, most recent first.
+    pub fn find_loans_for_collector(&self, uid: Uuid) -> Result<Vec<Loan>> {
+        loans::dsl::loans
+            .filter(loans::dsl::collector.eq(uid))
+            .order((loans::dsl::lent_on.desc(), loans::dsl::created_at.desc()))
+            .get_results::<Loan>(&self.pg)
+            .chain_err(|| "unable to retrieve loans for collector uuid")
+    }
+
+    // Find the loans a collector is still waiting to get cards back from.
+    pub fn find_open_loans_for_collector(&self, uid: Uuid) -> Result<Vec<Loan>> {
+        loans::dsl::loans
+            .filter(loans::dsl::collector.eq(uid))
+            .filter(loans::dsl::returned_on.is_null())
+            .order((loans::dsl::lent_on.desc(), loans::dsl::created_at.desc()))
+            .get_results::<Loan>(&self.pg)
+            .chain_err(|| "unable to retrieve open loans for collector uuid")
+    }
+
+    // Find the open loans that should have come back before a given day,
+    // most overdue first.
+    pub fn find_overdue_loans_for_collector(
+        &self,
+        uid: Uuid,
+        today: NaiveDate,
+    ) -> Result<Vec<Loan>> {
+        loans::dsl::loans
+            .filter(loans::dsl::collector.eq(uid))
+            .filter(loans::dsl::returned_on.is_null())
+            .filter(loans::dsl::due_on.lt(today))
+            .order(loans::dsl::due_on)
+            .get_results::<Loan>(&self.pg)
+            .chain_err(|| "unable to retrieve overdue loans for collector uuid")
+    }
+
+    pub fn find_loan_by_uuid(&self, uid: Uuid, id: Uuid) -> Result<Option<Loan>> {
+        match loans::dsl::loans
+            .find(id)
+            .filter(loans::dsl::collector.eq(uid))
+            .get_result::<Loan>(&self.pg)
+        {
+            Ok(loan) => Ok(Some(loan)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve loan record from database",
+            )),
+        }
+    }
+
+    pub fn create_loan(&self, id: Option<Uuid>, new: NewLoan) -> Result<Loan> {
+        let now = Utc::now();
+        diesel::insert_into(loans::table)
+            .values((
+                &new,
+                loans::dsl::id.eq(gen_uuid(id)),
+                loans::dsl::created_at.eq(now),
+                loans::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert loan record into database")
+    }
+
+    pub fn update_loan(&self, obj: &Loan, upd: UpdateLoan) -> Result<Loan> {
+        diesel::update(obj)
+            .set((&upd, loans::dsl::updated_at.eq(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update loan record in database")
+    }
+
+    // Close out a loan, as of the day the cards came back.  Returns None
+    // if the loan had already been closed.
+    pub fn return_loan(&self, obj: &Loan, on: NaiveDate) -> Result<Option<Loan>> {
+        diesel::update(
+            loans::dsl::loans
+                .filter(loans::dsl::id.eq(obj.id))
+                .filter(loans::dsl::returned_on.is_null()),
+        )
+        .set((
+            loans::dsl::returned_on.eq(Some(on)),
+            loans::dsl::updated_at.eq(Utc::now()),
+        ))
+        .get_result(&self.pg)
+        .optional()
+        .chain_err(|| "failed to mark loan record as returned in database")
+    }
+
+    pub fn delete_loan(&self, id: Uuid) -> Result<()> {
+        diesel::delete(loans::dsl::loans.filter(loans::dsl::id.eq(id)))
+            .execute(&self.pg)
+            .chain_err(|| "failed to delete loan record from database")?;
+        Ok(())
+    }
+
+    // Find every sealed product a collector has, most recent first.
+    pub fn find_sealed_products_for_collector(&self, uid: Uuid) -> Result<Vec<SealedProduct>> {
+        sealed_products::dsl::sealed_products
+            .filter(sealed_products::dsl::collector.eq(uid))
+            .order((
+                sealed_products::dsl::acquired_on.desc(),
+                sealed_products::dsl::created_at.desc(),
+            ))
+            .get_results::<SealedProduct>(&self.pg)
+            .chain_err(|| "unable to retrieve sealed products for collector uuid")
+    }
+
+    pub fn find_sealed_product_by_uuid(
+        &self,
+        uid: Uuid,
+        id: Uuid,
+    ) -> Result<Option<SealedProduct>> {
+        match sealed_products::dsl::sealed_products
+            .find(id)
+            .filter(sealed_products::dsl::collector.eq(uid))
+            .get_result::<SealedProduct>(&self.pg)
+        {
+            Ok(product) => Ok(Some(product)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve sealed product record from database",
+            )),
+        }
+    }
+
+    pub fn create_sealed_product(
+        &self,
+        id: Option<Uuid>,
+        new: NewSealedProduct,
+    ) -> Result<SealedProduct> {
+        let now = Utc::now();
+        diesel::insert_into(sealed_products::table)
+            .values((
+                &new,
+                sealed_products::dsl::id.eq(gen_uuid(id)),
+                sealed_products::dsl::created_at.eq(now),
+                sealed_products::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert sealed product record into database")
+    }
+
+    pub fn update_sealed_product(
+        &self,
+        obj: &SealedProduct,
+        upd: UpdateSealedProduct,
+    ) -> Result<SealedProduct> {
+        diesel::update(obj)
+            .set((&upd, sealed_products::dsl::updated_at.eq(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update sealed product record in database")
+    }
+
+    pub fn delete_sealed_product(&self, id: Uuid) -> Result<()> {
+        diesel::delete(
+            sealed_products::dsl::sealed_products.filter(sealed_products::dsl::id.eq(id)),
+        )
+        .execute(&self.pg)
+        .chain_err(|| "failed to delete sealed product record from database")?;
+        Ok(())
+    }
+
+    // Open one of a sealed product, recording what came out of it as
+    // a transaction.  Returns None if there were none left to open.
+    pub fn open_sealed_product(
+        &self,
+        obj: &SealedProduct,
+        new: NewTransaction,
+    ) -> Result<Option<Transaction>> {
+        self.pg.transaction(|| {
+            let opened = diesel::update(
+                sealed_products::dsl::sealed_products
+                    .filter(sealed_products::dsl::id.eq(obj.id))
+                    .filter(sealed_products::dsl::quantity.gt(0)),
+            )
+            .set((
+                sealed_products::dsl::quantity.eq(sealed_products::dsl::quantity - 1),
+                sealed_products::dsl::updated_at.eq(Utc::now()),
+            ))
+            .execute(&self.pg)
+            .chain_err(|| "failed to open sealed product record in database")?;
+            if opened == 0 {
+                return Ok(None);
+            }
+
+            let collection = new.collection;
+            let (txn, gain, loss) = self.insert_transaction(None, new)?;
+            let txn = diesel::update(&txn)
+                .set(transactions::dsl::opened.eq(Some(obj.id)))
+                .get_result::<Transaction>(&self.pg)
+                .chain_err(|| "failed to link transaction to sealed product in database")?;
+
+            self.apply_collection_credit(collection, gain)
+                .chain_err(|| "unable to apply opened product to collection")?;
+            self.apply_collection_debit(collection, loss)
+                .chain_err(|| "unable to apply opened product to collection")?;
+            Ok(Some(txn))
+        })
+    }
+
+    pub fn find_transaction_templates_for_collector(
+        &self,
+        uid: Uuid,
+    ) -> Result<Vec<TransactionTemplate>> {
+        transaction_templates::dsl::transaction_templates
+            .filter(transaction_templates::dsl::collector.eq(uid))
+            .order(transaction_templates::dsl::name)
+            .get_results::<TransactionTemplate>(&self.pg)
+            .chain_err(|| "unable to retrieve transaction templates for collector uuid")
+    }
+
+    pub fn find_transaction_template_by_uuid(
+        &self,
+        uid: Uuid,
+        id: Uuid,
+    ) -> Result<Option<TransactionTemplate>> {
+        match transaction_templates::dsl::transaction_templates
+            .find(id)
+            .filter(transaction_templates::dsl::collector.eq(uid))
+            .get_result::<TransactionTemplate>(&self.pg)
+        {
+            Ok(template) => Ok(Some(template)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve transaction template record from database",
+            )),
+        }
+    }
+
+    pub fn create_transaction_template(
+        &self,
+        id: Option<Uuid>,
+        new: NewTransactionTemplate,
+    ) -> Result<TransactionTemplate> {
+        let now = Utc::now();
+        diesel::insert_into(transaction_templates::table)
+            .values((
+                &new,
+                transaction_templates::dsl::id.eq(gen_uuid(id)),
+                transaction_templates::dsl::created_at.eq(now),
+                transaction_templates::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert transaction template record into database")
+    }
+
+    pub fn update_transaction_template(
+        &self,
+        obj: &TransactionTemplate,
+        upd: UpdateTransactionTemplate,
+    ) -> Result<TransactionTemplate> {
+        diesel::update(obj)
+            .set((&upd, transaction_templates::dsl::updated_at.eq(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update transaction template record in database")
+    }
+
+    // Removing a template stops anything that was recurring from it.
+    pub fn delete_transaction_template(&self, id: Uuid) -> Result<()> {
+        diesel::delete(
+            transaction_templates::dsl::transaction_templates
+                .filter(transaction_templates::dsl::id.eq(id)),
+        )
+        .execute(&self.pg)
+        .chain_err(|| "failed to delete transaction template record from database")?;
+        Ok(())
+    }
+
+    pub fn find_recurring_transactions_for_collector(
+        &self,
+        uid: Uuid,
+    ) -> Result<Vec<RecurringTransaction>> {
+        recurring_transactions::dsl::recurring_transactions
+            .filter(recurring_transactions::dsl::collector.eq(uid))
+            .order((
+                recurring_transactions::dsl::next_on,
+                recurring_transactions::dsl::created_at,
+            ))
+            .get_results::<RecurringTransaction>(&self.pg)
+            .chain_err(|| "unable to retrieve recurring transactions for collector uuid")
+    }
+
+    pub fn find_recurring_transaction_by_uuid(
+        &self,
+        uid: Uuid,
+        id: Uuid,
+    ) -> Result<Option<RecurringTransaction>> {
+        match recurring_transactions::dsl::recurring_transactions
+            .find(id)
+            .filter(recurring_transactions::dsl::collector.eq(uid))
+            .get_result::<RecurringTransaction>(&self.pg)
+        {
+            Ok(recurring) => Ok(Some(recurring)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve recurring transaction record from database",
+            )),
+        }
+    }
+
+    // Find every recurring transaction, for every collector, that has an
+    // occurrence due on or before the given date (and before it ends).
+    pub fn find_due_recurring_transactions(
+        &self,
+        on: NaiveDate,
+    ) -> Result<Vec<RecurringTransaction>> {
+        recurring_transactions::dsl::recurring_transactions
+            .filter(recurring_transactions::dsl::next_on.le(on))
+            .filter(
+                recurring_transactions::dsl::ends_on
+                    .is_null()
+                    .or(recurring_transactions::dsl::ends_on
+                        .ge(recurring_transactions::dsl::next_on.nullable())),
+            )
+            .order((
+                recurring_transactions::dsl::next_on,
+                recurring_transactions::dsl::created_at,
+            ))
+            .get_results::<RecurringTransaction>(&self.pg)
+            .chain_err(|| "unable to retrieve due recurring transactions")
+    }
+
+    pub fn create_recurring_transaction(
+        &self,
+        id: Option<Uuid>,
+        new: NewRecurringTransaction,
+    ) -> Result<RecurringTransaction> {
+        let now = Utc::now();
+        diesel::insert_into(recurring_transactions::table)
+            .values((
+                &new,
+                recurring_transactions::dsl::id.eq(gen_uuid(id)),
+                recurring_transactions::dsl::created_at.eq(now),
+                recurring_transactions::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert recurring transaction record into database")
+    }
+
+    pub fn update_recurring_transaction(
+        &self,
+        obj: &RecurringTransaction,
+        upd: UpdateRecurringTransaction,
+    ) -> Result<RecurringTransaction> {
+        diesel::update(obj)
+            .set((&upd, recurring_transactions::dsl::updated_at.eq(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update recurring transaction record in database")
+    }
+
+    pub fn delete_recurring_transaction(&self, id: Uuid) -> Result<()> {
+        diesel::delete(
+            recurring_transactions::dsl::recurring_transactions
+                .filter(recurring_transactions::dsl::id.eq(id)),
+        )
+        .execute(&self.pg)
+        .chain_err(|| "failed to delete recurring transaction record from database")?;
+        Ok(())
+    }
+
+    // Record the next occurrence of a recurring transaction, and move it
+    // on to the one after (`next_on`).  If someone else got there first,
+    // (i.e. two workers running at once), nothing is recorded, and None
+    // is returned.
+    pub fn record_recurring_transaction(
+        &self,
+        obj: &RecurringTransaction,
+        next_on: NaiveDate,
+        new: NewTransaction,
+    ) -> Result<Option<Transaction>> {
+        self.pg.transaction(|| {
+            let moved = diesel::update(
+                recurring_transactions::dsl::recurring_transactions
+                    .filter(recurring_transactions::dsl::id.eq(obj.id))
+                    .filter(recurring_transactions::dsl::occurrences.eq(obj.occurrences)),
+            )
+            .set((
+                recurring_transactions::dsl::occurrences
+                    .eq(recurring_transactions::dsl::occurrences + 1),
+                recurring_transactions::dsl::next_on.eq(next_on),
+                recurring_transactions::dsl::updated_at.eq(Utc::now()),
+            ))
+            .execute(&self.pg)
+            .chain_err(|| "failed to advance recurring transaction record in database")?;
+            if moved == 0 {
+                return Ok(None);
+            }
+
+            let collection = new.collection;
+            let (txn, gain, loss) = self.insert_transaction(None, new)?;
+            self.apply_collection_credit(collection, gain)
+                .chain_err(|| "unable to apply recurring transaction to collection")?;
+            self.apply_collection_debit(collection, loss)
+                .chain_err(|| "unable to apply recurring transaction to collection")?;
+            Ok(Some(txn))
+        })
+    }
+
+    pub fn find_locations_for_collector(&self, uid: Uuid) -> Result<Vec<Location>> {
+        locations::dsl::locations
+            .filter(locations::dsl::collector.eq(uid))
+            .order(locations::dsl::name)
+            .get_results::<Location>(&self.pg)
+            .chain_err(|| "unable to retrieve locations for collector uuid")
+    }
+
+    pub fn find_location_by_uuid(&self, uid: Uuid, id: Uuid) -> Result<Option<Location>> {
+        match locations::dsl::locations
+            .find(id)
+            .filter(locations::dsl::collector.eq(uid))
+            .get_result::<Location>(&self.pg)
+        {
+            Ok(location) => Ok(Some(location)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve location record from database",
+            )),
+        }
+    }
+
+    pub fn create_location(&self, id: Option<Uuid>, new: NewLocation) -> Result<Location> {
+        let now = Utc::now();
+        diesel::insert_into(locations::table)
+            .values((
+                &new,
+                locations::dsl::id.eq(gen_uuid(id)),
+                locations::dsl::created_at.eq(now),
+                locations::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert location record into database")
+    }
+
+    pub fn update_location(&self, obj: &Location, upd: UpdateLocation) -> Result<Location> {
+        diesel::update(obj)
+            .set((&upd, locations::dsl::updated_at.eq(Utc::now())))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to update location record in database")
+    }
+
+    // Remove a location, along with everything inside of it.  Cards kept
+    // there are not lost, just no longer stored anywhere in particular.
+    pub fn delete_location(&self, id: Uuid) -> Result<()> {
+        diesel::delete(locations::dsl::locations.filter(locations::dsl::id.eq(id)))
+            .execute(&self.pg)
+            .chain_err(|| "failed to delete location record from database")?;
+        Ok(())
+    }
+
+    // Find out what is kept where, across all of a collector's locations.
+    pub fn find_stowage_for_collector(&self, uid: Uuid) -> Result<Vec<Stowed>> {
+        stowage::dsl::stowage
+            .inner_join(locations::table)
+            .filter(locations::dsl::collector.eq(uid))
+            .select((
+                stowage::dsl::location,
+                stowage::dsl::pid,
+                stowage::dsl::var,
+                stowage::dsl::quantity,
+            ))
+            .order((stowage::dsl::location, stowage::dsl::pid, stowage::dsl::var))
+            .get_results::<Stowed>(&self.pg)
+            .chain_err(|| "unable to retrieve stowage for collector uuid")
+    }
+
+    // Put copies into (or take them out of) locations.  Locations never
+    // hold fewer than zero copies of anything; taking out more than is
+    // there just empties it out.
+    pub fn stow(&self, changes: &[Stowed]) -> Result<()> {
+        self.pg.transaction(|| {
+            for change in changes {
+                let key = (
+                    change.location,
+                    change.pid.to_string(),
+                    change.var.to_string(),
+                );
+                let have = stowage::dsl::stowage
+                    .find(key.clone())
+                    .select(stowage::dsl::quantity)
+                    .get_result::<i32>(&self.pg)
+                    .optional()
+                    .chain_err(|| "failed to retrieve stowage record from database")?;
+
+                let want = have.unwrap_or(0) + change.quantity;
+                if want <= 0 {
+                    diesel::delete(stowage::dsl::stowage.find(key))
+                        .execute(&self.pg)
+                        .chain_err(|| "failed to delete stowage record from database")?;
+                } else if have.is_some() {
+                    diesel::update(stowage::dsl::stowage.find(key))
+                        .set(stowage::dsl::quantity.eq(want))
+                        .execute(&self.pg)
+                        .chain_err(|| "failed to update stowage record in database")?;
+                } else {
+                    diesel::insert_into(stowage::table)
+                        .values(change)
+                        .execute(&self.pg)
+                        .chain_err(|| "failed to insert stowage record into database")?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    pub fn find_slab_values_for_collector(&self, uid: Uuid) -> Result<Vec<SlabValue>> {
+        slab_values::dsl::slab_values
+            .filter(slab_values::dsl::collector.eq(uid))
+            .order(slab_values::dsl::cert)
+            .get_results::<SlabValue>(&self.pg)
+            .chain_err(|| "unable to retrieve slab values for collector uuid")
+    }
+
+    // Set (or replace) the value of a graded copy.
+    pub fn set_slab_value(
+        &self,
+        uid: Uuid,
+        cert: &str,
+        value: i32,
+        notes: &str,
+    ) -> Result<SlabValue> {
+        let new = SlabValue {
+            collector: uid,
+            cert: cert.to_string(),
+            value,
+            notes: notes.to_string(),
+            updated_at: Utc::now(),
+        };
+        diesel::insert_into(slab_values::table)
+            .values(&new)
+            .on_conflict((slab_values::dsl::collector, slab_values::dsl::cert))
+            .do_update()
+            .set((
+                slab_values::dsl::value.eq(value),
+                slab_values::dsl::notes.eq(notes),
+                slab_values::dsl::updated_at.eq(new.updated_at),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to set slab value in database")
+    }
+
+    // Forget the value of a graded copy; it goes back to being unvalued.
+    pub fn clear_slab_value(&self, uid: Uuid, cert: &str) -> Result<()> {
+        diesel::delete(slab_values::dsl::slab_values.find((uid, cert.to_string())))
+            .execute(&self.pg)
+            .chain_err(|| "failed to delete slab value from database")?;
+        Ok(())
+    }
+
+    pub fn find_share_links_for_collector(&self, uid: Uuid) -> Result<Vec<ShareLink>> {
+        share_links::dsl::share_links
+            .filter(share_links::dsl::collector.eq(uid))
+            .order(share_links::dsl::created_at)
+            .get_results::<ShareLink>(&self.pg)
+            .chain_err(|| "unable to retrieve share links for collector uuid")
+    }
+
+    pub fn find_share_link_by_uuid(&self, uid: Uuid, id: Uuid) -> Result<Option<ShareLink>> {
+        match share_links::dsl::share_links
+            .find(id)
+            .filter(share_links::dsl::collector.eq(uid))
+            .get_result::<ShareLink>(&self.pg)
+        {
+            Ok(link) => Ok(Some(link)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve share link from database",
+            )),
+        }
+    }
+
+    // Find a share link by the hash of its token, whether it's still
+    // live or not.
+    pub fn find_share_link_by_hash(&self, hash: &str) -> Result<Option<ShareLink>> {
+        match share_links::dsl::share_links
+            .filter(share_links::dsl::hash.eq(hash))
+            .get_result::<ShareLink>(&self.pg)
+        {
+            Ok(link) => Ok(Some(link)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve share link from database",
+            )),
+        }
+    }
+
+    pub fn create_share_link(&self, id: Option<Uuid>, new: NewShareLink) -> Result<ShareLink> {
+        let id = gen_uuid(id);
+        diesel::insert_into(share_links::table)
+            .values((&new, share_links::dsl::id.eq(id)))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert share link into database")
+    }
+
+    // Revoke a share link.  Revoked links are kept around (rather than
+    // deleted) so that the collector can see what they have handed out.
+    pub fn revoke_share_link(&self, obj: &ShareLink) -> Result<ShareLink> {
+        let now = Utc::now();
+        diesel::update(obj)
+            .set((
+                share_links::dsl::revoked_at.eq(Some(now)),
+                share_links::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to revoke share link in database")
+    }
+
+    pub fn find_api_tokens_for_collector(&self, uid: Uuid) -> Result<Vec<ApiToken>> {
+        api_tokens::dsl::api_tokens
+            .filter(api_tokens::dsl::collector.eq(uid))
+            .order(api_tokens::dsl::created_at)
+            .get_results::<ApiToken>(&self.pg)
+            .chain_err(|| "unable to retrieve api tokens for collector uuid")
+    }
+
+    pub fn find_api_token_by_uuid(&self, uid: Uuid, id: Uuid) -> Result<Option<ApiToken>> {
+        match api_tokens::dsl::api_tokens
+            .find(id)
+            .filter(api_tokens::dsl::collector.eq(uid))
+            .get_result::<ApiToken>(&self.pg)
+        {
+            Ok(token) => Ok(Some(token)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve api token from database",
+            )),
+        }
+    }
+
+    // Find an API token by the hash of the token itself, whether it has
+    // been revoked or not.
+    pub fn find_api_token_by_hash(&self, hash: &str) -> Result<Option<ApiToken>> {
+        match api_tokens::dsl::api_tokens
+            .filter(api_tokens::dsl::hash.eq(hash))
+            .get_result::<ApiToken>(&self.pg)
+        {
+            Ok(token) => Ok(Some(token)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to retrieve api token from database",
+            )),
+        }
+    }
+
+    pub fn create_api_token(&self, id: Option<Uuid>, new: NewApiToken) -> Result<ApiToken> {
+        let id = gen_uuid(id);
+        diesel::insert_into(api_tokens::table)
+            .values((&new, api_tokens::dsl::id.eq(id)))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert api token into database")
+    }
+
+    // Note that an API token has just been used.
+    pub fn touch_api_token(&self, obj: &ApiToken) -> Result<()> {
+        diesel::update(obj)
+            .set(api_tokens::dsl::last_used_at.eq(Some(Utc::now())))
+            .execute(&self.pg)
+            .chain_err(|| "failed to update api token last-used timestamp in database")?;
+        Ok(())
+    }
+
+    pub fn revoke_api_token(&self, obj: &ApiToken) -> Result<ApiToken> {
+        let now = Utc::now();
+        diesel::update(obj)
+            .set((
+                api_tokens::dsl::revoked_at.eq(Some(now)),
+                api_tokens::dsl::updated_at.eq(now),
+            ))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to revoke api token in database")
+    }
+
+    // Revoke every one of a collector's API tokens that hasn't been
+    // already, i.e. once their password has been reset.
+    pub fn revoke_api_tokens_for_collector(&self, uid: Uuid) -> Result<usize> {
+        let now = Utc::now();
+        diesel::update(
+            api_tokens::dsl::api_tokens
+                .filter(api_tokens::dsl::collector.eq(uid))
+                .filter(api_tokens::dsl::revoked_at.is_null()),
+        )
+        .set((
+            api_tokens::dsl::revoked_at.eq(Some(now)),
+            api_tokens::dsl::updated_at.eq(now),
+        ))
+        .execute(&self.pg)
+        .chain_err(|| "failed to revoke api tokens in database")
+    }
+
+    pub fn create_account_token(
+        &self,
+        id: Option<Uuid>,
+        new: NewAccountToken,
+    ) -> Result<AccountToken> {
+        let id = gen_uuid(id);
+        diesel::insert_into(account_tokens::table)
+            .values((&new, account_tokens::dsl::id.eq(id)))
+            .get_result(&self.pg)
+            .chain_err(|| "failed to insert account token into database")
+    }
+
+    // Use up an account token, if it hasn't been already (and hasn't
+    // expired).  This is a single UPDATE, so that two requests racing
+    // to use the same token can't both succeed.
+    pub fn use_account_token(&self, id: Uuid, now: DateTime<Utc>) -> Result<Option<AccountToken>> {
+        match diesel::update(
+            account_tokens::dsl::account_tokens
+                .find(id)
+                .filter(account_tokens::dsl::used_at.is_null())
+                .filter(account_tokens::dsl::expires_at.gt(now)),
+        )
+        .set((
+            account_tokens::dsl::used_at.eq(Some(now)),
+            account_tokens::dsl::updated_at.eq(now),
+        ))
+        .get_result::<AccountToken>(&self.pg)
+        {
+            Ok(token) => Ok(Some(token)),
+            Err(diesel::NotFound) => Ok(None),
+            Err(e) => Err(Error::with_chain(
+                e,
+                "failed to use account token in database",
+            )),
+        }
+    }
+
+    // Use up all of a collector's outstanding tokens for some purpose,
+    // i.e. once their password has been reset, so that any other reset
+    // links they were sent stop working.
+    pub fn expire_account_tokens(&self, uid: Uuid, purpose: &str) -> Result<usize> {
+        let now = Utc::now();
+        diesel::update(
+            account_tokens::dsl::account_tokens
+                .filter(account_tokens::dsl::collector.eq(uid))
+                .filter(account_tokens::dsl::purpose.eq(purpose))
+                .filter(account_tokens::dsl::used_at.is_null()),
+        )
+        .set((
+            account_tokens::dsl::used_at.eq(Some(now)),
+            account_tokens::dsl::updated_at.eq(now),
+        ))
+        .execute(&self.pg)
+        .chain_err(|| "failed to expire account tokens in database")
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use std::collections::HashSet;
+    use tempdir::TempDir;
+    use uuid::Uuid;
+
+    pub fn connect() -> (TempDir, Database) {
+        use std::env;
+
+        let pg =
+            env::var("VCB_DATABASE_URL").expect("VCB_DATABASE_URL must be set in the environment.");
+
+        let rd = env::var("VCB_REDIS_URL").expect("VCB_REDIS_URL must be set in the environment.");
+
+        let fs = TempDir::new("vcb-test").expect("Failed to create temp directory");
+
+        let db = Database::connect(&pg, &rd, &fs.path(), 3600).unwrap();
+        db.testmode().unwrap();
+        db.fs
+            .create(
+                "lookup.json",
+                r#"
+{
+  "XLN * Opt": "xln-opt-fake-id",
+  "GRN * Radical Idea": "grn-rad-fake-id"
+}
+"#,
+            )
+            .unwrap();
+        (fs, db)
+    }
+
+    #[test]
+    fn should_be_able_to_create_a_session() {
+        let (_tmp, db) = connect();
+
+        let mut session = Session::new(None);
+        session.set("foo", "bar");
+        assert_eq!(session.get("foo"), "bar");
+
+        let session = db.set_session(session).unwrap();
+        assert_eq!(session.get("foo"), "bar");
+
+        let session = db.get_session(session.id).unwrap().unwrap();
+        assert_eq!(session.get("foo"), "bar");
+
+        assert!(db.expire_session(&session).is_ok());
+        assert!(db.get_session(session.id).unwrap().is_none());
     }
 
-    // Forcibly expire a Session from Redis.
-    //
-    // After this call, attempts to retrieve the Session object via
-    // `get_session()` will return Ok(None), assuming no Redis errors
-    // (transport, connection, etc.) are encountered.
-    //
-    pub fn expire_session(&self, s: &Session) -> Result<()> {
-        let key = format!("session:{}", s.id);
-        let mut cmd = redis::cmd("DEL");
-        let cmd = cmd.arg(key);
+    #[test]
+    fn should_be_able_to_create_a_collector() {
+        let (_tmp, db) = connect();
+
+        let id = Uuid::parse_str("a026089c-90a2-4180-b41a-2082e7b2ebcc").unwrap();
+        let jhunt = db.create_collector(
+            Some(id),
+            NewCollector {
+                username: "jhunt",
+                email: "james@example.com",
+            },
+            None,
+        );
+        assert!(jhunt.is_ok());
+
+        let found = db.find_collector_by_uuid(id);
+        assert!(found.is_ok());
+        let found = found.unwrap();
+        assert!(found.is_some());
+        let found = found.unwrap();
+        assert_eq!(found.username, "jhunt");
+        assert_eq!(found.email, "james@example.com");
+    }
+
+    #[test]
+    fn should_not_be_able_to_reuse_usernames() {
+        let (_tmp, db) = connect();
+
+        let jhunt = db.create_collector(
+            None,
+            NewCollector {
+                username: "jhunt",
+                email: "james@example.com",
+            },
+            None,
+        );
+        assert!(jhunt.is_ok());
+        let jhunt = jhunt.unwrap();
+
+        let found = db.find_collector_by_uuid(jhunt.id);
+        assert!(found.is_ok());
+        let found = found.unwrap();
+        assert!(found.is_some());
+        let found = found.unwrap();
+        assert_eq!(found.username, "jhunt");
+        assert_eq!(found.email, "james@example.com");
+
+        let err = db.create_collector(
+            None,
+            NewCollector {
+                username: &found.username,
+                email: "other-james@example.com",
+            },
+            None,
+        );
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn should_be_able_to_reuse_email_addresses() {
+        let (_tmp, db) = connect();
+
+        let jhunt = db.create_collector(
+            None,
+            NewCollector {
+                username: "jhunt",
+                email: "james@example.com",
+            },
+            None,
+        );
+        assert!(jhunt.is_ok());
+        let jhunt = jhunt.unwrap();
+
+        let found = db.find_collector_by_uuid(jhunt.id);
+        assert!(found.is_ok());
+        let found = found.unwrap();
+        assert!(found.is_some());
+        let found = found.unwrap();
+
+        let other = db.create_collector(
+            None,
+            NewCollector {
+                username: "other-jhunt",
+                email: &found.email,
+            },
+            None,
+        );
+        assert!(other.is_ok());
+    }
+
+    #[test]
+    #[ignore]
+    fn should_be_able_to_reuse_passwords() {
+        let (_tmp, db) = connect();
+
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                Some("sekrit"),
+            )
+            .unwrap();
+
+        db.find_collector_by_uuid(jhunt.id).unwrap();
+        let other = db.create_collector(
+            None,
+            NewCollector {
+                username: "other-jhunt",
+                email: "other-james@example.com",
+            },
+            Some("sekrit"),
+        );
+        assert!(other.is_ok());
+    }
+
+    #[test]
+    fn should_be_able_to_update_a_collectors_username() {
+        let (_tmp, db) = connect();
+
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
+
+        let found = db.find_collector_by_uuid(jhunt.id);
+        assert!(found.is_ok());
+        let found = found.unwrap();
+        assert!(found.is_some());
+        let found = found.unwrap();
+        assert_eq!("jhunt", found.username);
 
-        cmd.query::<i32>(
-            &mut self
-                .rd
-                .get_connection()
-                .chain_err(|| "unable to connect to session store")?,
-        )
-        .chain_err(|| "failed to delete session key from session store")?;
+        let updated = db.update_collector(
+            &jhunt,
+            CollectorUpdate {
+                username: Some("james"),
+                email: None,
+                password: None,
+                currency: None,
+                tradeable: None,
+            },
+        );
+        assert!(updated.is_ok());
+        let updated = updated.unwrap();
+        assert_eq!("james", updated.username);
 
-        Ok(())
+        let found = db.find_collector_by_uuid(updated.id);
+        assert!(found.is_ok());
+        let found = found.unwrap();
+        assert!(found.is_some());
+        let found = found.unwrap();
+        assert_eq!("james", found.username);
     }
 
-    // Insert a new Collector, and their 1:1 Collection.
-    //
-    // The caller may supply an explicit UUID as the first parameter, or
-    // None to automatically generate a v4 UUID.
-    //
-    pub fn create_collector(
-        &self,
-        id: Option<Uuid>,
-        new: NewCollector,
-        password: Option<&str>,
-    ) -> Result<Collector> {
-        let id = gen_uuid(id);
-        let crypted = match password {
-            Some(password) => bcrypt::hash(password, PWHASH_COST)
-                .chain_err(|| "failed to hash collector password")?,
-            None => "password-not-specified".to_string(),
-        };
-
-        let collector = diesel::insert_into(collectors::table)
-            .values((
-                &new,
-                collectors::dsl::id.eq(id),
-                collectors::dsl::password.eq(crypted),
-            ))
-            .get_result(&self.pg)
-            .chain_err(|| "failed to insert collector record into database")?;
+    #[test]
+    fn should_not_be_able_to_reuse_usernames_via_update() {
+        let (_tmp, db) = connect();
 
-        diesel::insert_into(collections::table)
-            .values(&NewCollection { id, collector: id })
-            .get_result::<Collection>(&self.pg)
-            .chain_err(|| "failed to insert collection record for new collector into database")?;
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
+        db.find_collector_by_uuid(jhunt.id).unwrap().unwrap();
 
-        self.fs
-            .create(
-                &format!("c/{}/_/collection.json", id.to_string()),
-                r#"[[],[[]]]"#,
+        let james = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "james",
+                    email: "other-james@example.com",
+                },
+                None,
             )
-            .chain_err(|| "failed to create initial collection cache file")?;
+            .unwrap();
+        db.find_collector_by_uuid(james.id).unwrap().unwrap();
 
-        Ok(collector)
+        let updated = db.update_collector(
+            &jhunt,
+            CollectorUpdate {
+                username: Some("james"),
+                email: None,
+                password: None,
+                currency: None,
+                tradeable: None,
+            },
+        );
+        assert!(updated.is_err());
     }
 
-    pub fn authenticate_collector(
-        &self,
-        username: &str,
-        password: &str,
-    ) -> Result<Option<Collector>> {
-        let mut found = collectors::dsl::collectors
-            .filter(collectors::dsl::username.eq(username))
-            .get_results::<Collector>(&self.pg)
-            .chain_err(|| "failed to retrieve collector record from database")?;
+    #[test]
+    fn should_be_able_to_update_a_collectors_email() {
+        let (_tmp, db) = connect();
 
-        match found.len() {
-            1 => {
-                let collector = found.pop().unwrap();
-                match bcrypt::verify(&password, &collector.password) {
-                    Ok(true) => Ok(Some(collector)),
-                    _ => Ok(None),
-                }
-            }
-            _ => {
-                let _ = bcrypt::verify(&password, "");
-                Ok(None)
-            }
-        }
-    }
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-    // Find a Collector by their UUID.
-    pub fn find_collector_by_uuid(&self, id: Uuid) -> Result<Option<Collector>> {
-        match collectors::dsl::collectors
-            .find(id)
-            .get_result::<Collector>(&self.pg)
-        {
-            Ok(collector) => Ok(Some(collector)),
-            Err(diesel::NotFound) => Ok(None),
-            Err(e) => Err(Error::with_chain(
-                e,
-                "failed to retrieve collector from database",
-            )),
-        }
-    }
+        let found = db.find_collector_by_uuid(jhunt.id);
+        assert!(found.is_ok());
+        let found = found.unwrap();
+        assert!(found.is_some());
+        let found = found.unwrap();
+        assert_eq!("jhunt", found.username);
 
-    // Update a Collector with a blanket patch
-    //
-    // The CollectorUpdate object is just a collection of Option<...>
-    // fields, to make it possible to do partial updates flexibly.
-    //
-    // Returns the final Collector object, after updates are applied.
-    //
-    pub fn update_collector(&self, obj: &Collector, upd: CollectorUpdate) -> Result<Collector> {
-        Ok(diesel::update(obj)
-            .set(&upd)
-            .get_result(&self.pg)
-            .chain_err(|| "failed to update collector record in database")?)
-    }
+        let updated = db.update_collector(
+            &jhunt,
+            CollectorUpdate {
+                username: None,
+                email: Some("jhunt@example.com"),
+                password: None,
+                currency: None,
+                tradeable: None,
+            },
+        );
+        assert!(updated.is_ok());
+        let updated = updated.unwrap();
+        assert_eq!("jhunt@example.com", updated.email);
 
-    // Find a Collection by its UUID.
-    pub fn find_collection_by_uuid(&self, id: Uuid) -> Result<Option<Collection>> {
-        match collections::dsl::collections
-            .find(id)
-            .get_result::<Collection>(&self.pg)
-        {
-            Ok(collection) => Ok(Some(collection)),
-            Err(diesel::NotFound) => Ok(None),
-            Err(e) => Err(Error::with_chain(
-                e,
-                "failed to retrieve collection from database",
-            )),
-        }
+        let found = db.find_collector_by_uuid(updated.id);
+        assert!(found.is_ok());
+        let found = found.unwrap();
+        assert!(found.is_some());
+        let found = found.unwrap();
+        assert_eq!("jhunt@example.com", found.email);
     }
 
-    pub fn find_transactions_for_collection(&self, cid: Uuid) -> Result<Vec<Transaction>> {
-        Ok(transactions::dsl::transactions
-            .filter(transactions::dsl::collection.eq(cid))
-            .get_results::<Transaction>(&self.pg)
-            .chain_err(|| "unable to retrieve transactions for collection uuid")?)
+    #[test]
+    fn should_be_able_to_update_a_collectors_currency() {
+        let (_tmp, db) = connect();
+
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
+        assert_eq!("usd", jhunt.currency);
+
+        let updated = db
+            .update_collector(
+                &jhunt,
+                CollectorUpdate {
+                    username: None,
+                    email: None,
+                    password: None,
+                    currency: Some("eur"),
+                    tradeable: None,
+                },
+            )
+            .unwrap();
+        assert_eq!("eur", updated.currency);
+        assert_eq!("jhunt", updated.username);
     }
 
-    // Find a Transaction by its UUID.
-    pub fn find_transaction_by_uuid(&self, cid: Uuid, id: Uuid) -> Result<Option<Transaction>> {
-        match transactions::dsl::transactions
-            .find(id)
-            .filter(transactions::dsl::collection.eq(cid))
-            .get_result::<Transaction>(&self.pg)
-        {
-            Ok(transaction) => Ok(Some(transaction)),
-            Err(diesel::NotFound) => Ok(None),
-            Err(e) => Err(Error::with_chain(
-                e,
-                "failed to retrieve transaction from database",
-            )),
-        }
+    #[test]
+    fn should_be_able_to_opt_a_collector_in_to_trade_matching() {
+        let (_tmp, db) = connect();
+
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
+        assert!(!jhunt.tradeable);
+        assert!(!db
+            .find_tradeable_collectors()
+            .unwrap()
+            .iter()
+            .any(|c| c.id == jhunt.id));
+
+        let updated = db
+            .update_collector(
+                &jhunt,
+                CollectorUpdate {
+                    username: None,
+                    email: None,
+                    password: None,
+                    currency: None,
+                    tradeable: Some(true),
+                },
+            )
+            .unwrap();
+        assert!(updated.tradeable);
+        assert!(db
+            .find_tradeable_collectors()
+            .unwrap()
+            .iter()
+            .any(|c| c.id == jhunt.id));
     }
 
-    // Create a new Transaction.
-    pub fn create_transaction(&self, id: Option<Uuid>, new: NewTransaction) -> Result<Transaction> {
-        let gain = cdif::File::from_string(&new.gain)
-            .chain_err(|| "unable to parse gained cards from transaction")?;
-        let loss = cdif::File::from_string(&new.loss)
-            .chain_err(|| "unable to parse lost cards from transaction")?;
+    #[test]
+    fn should_be_able_to_reuse_email_addresses_via_update() {
+        let (_tmp, db) = connect();
 
-        let mut meta = serde_json::Map::new();
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
+        db.find_collector_by_uuid(jhunt.id).unwrap().unwrap();
 
-        let (total, unique) = gain.count();
-        meta.insert("total_gain".to_string(), json!(total as f64));
-        meta.insert("unique_gain".to_string(), json!(unique as f64));
-        meta.insert("set_gain".to_string(), json!(gain.unique_sets()));
+        let james = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "james",
+                    email: "other-james@example.com",
+                },
+                None,
+            )
+            .unwrap();
+        db.find_collector_by_uuid(james.id).unwrap().unwrap();
 
-        let (total, unique) = loss.count();
-        meta.insert("total_loss".to_string(), json!(total as f64));
-        meta.insert("unique_loss".to_string(), json!(unique as f64));
-        meta.insert("set_loss".to_string(), json!(loss.unique_sets()));
+        let updated = db.update_collector(
+            &jhunt,
+            CollectorUpdate {
+                username: None,
+                email: Some("other-james@example.com"),
+                password: None,
+                currency: None,
+                tradeable: None,
+            },
+        );
+        assert!(updated.is_ok());
+        let updated = updated.unwrap();
+        assert_eq!("other-james@example.com", updated.email);
 
-        let txn = diesel::insert_into(transactions::table)
-            .values((
-                &new,
-                transactions::dsl::metadata.eq(serde_json::Value::Object(meta)),
-                transactions::dsl::id.eq(gen_uuid(id)),
-            ))
-            .get_result(&self.pg)
-            .chain_err(|| "failed to insert transaction record into database")?;
+        let found = db.find_collector_by_uuid(jhunt.id);
+        assert!(found.is_ok());
+        let found = found.unwrap();
+        assert!(found.is_some());
+        let found = found.unwrap();
+        assert_eq!("other-james@example.com", found.email);
 
-        // update the collection with new, resolved CDIF
-        self.apply_collection_credit(new.collection, gain)
-            .chain_err(|| "unable to apply new gains from transaction")?;
+        let found = db.find_collector_by_uuid(james.id);
+        assert!(found.is_ok());
+        let found = found.unwrap();
+        assert!(found.is_some());
+        let found = found.unwrap();
+        assert_eq!("other-james@example.com", found.email);
+    }
 
-        // update the collection again for any losses
-        self.apply_collection_debit(new.collection, loss)
-            .chain_err(|| "unable to apply new losses from transaction")?;
+    #[test]
+    fn should_create_a_collection_for_a_new_collector() {
+        let (_tmp, db) = connect();
 
-        Ok(txn)
+        let jhunt = db.create_collector(
+            None,
+            NewCollector {
+                username: "jhunt",
+                email: "james@example.com",
+            },
+            None,
+        );
+        assert!(jhunt.is_ok());
+        let jhunt = jhunt.unwrap();
+
+        let collection = db.find_collection_by_uuid(jhunt.id);
+        assert!(collection.is_ok());
+        let collection = collection.unwrap();
+        assert!(collection.is_some());
     }
 
-    pub fn update_transaction(
-        &self,
-        obj: &Transaction,
-        upd: UpdateTransaction,
-    ) -> Result<Transaction> {
-        let mut meta = match obj.metadata.as_object() {
-            Some(meta) => meta.clone(),
-            _ => serde_json::Map::new(),
-        };
+    fn vec_to_set(src: Vec<String>) -> HashSet<String> {
+        let mut dst = HashSet::new();
+        for x in src {
+            dst.insert(x);
+        }
+        dst
+    }
 
-        let gain = match &upd.gain {
-            None => None,
-            Some(s) => {
-                let cdif = cdif::File::from_string(&s)
-                    .chain_err(|| "failed to parse updated gains during transaction update")?;
+    #[test]
+    pub fn can_create_a_transaction() {
+        let (_tmp, db) = connect();
 
-                let (total, unique) = cdif.count();
-                meta.insert("total_gain".to_string(), json!(total as f64));
-                meta.insert("unique_gain".to_string(), json!(unique as f64));
-                meta.insert("set_gain".to_string(), json!(cdif.unique_sets()));
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-                Some(cdif)
-            }
-        };
+        let txn = db.create_transaction(
+            None,
+            NewTransaction {
+                summary: "opting for ixalan",
+                disposition: Disposition::Buy,
+                notes: "this oughta be good",
+                collection: jhunt.id,
+                dated: &NaiveDate::from_ymd_opt(2020, 01, 14).unwrap(),
+                gain: "1x XLN Opt\n",
+                loss: "",
+                paid: None,
+                created_by: None,
+            },
+        );
+        assert!(txn.is_ok());
+        let txn = txn.unwrap();
 
-        let loss = match &upd.loss {
-            None => None,
-            Some(s) => {
-                let cdif = cdif::File::from_string(&s)
-                    .chain_err(|| "failed to parse updated losses during transaction update")?;
+        assert_eq!(txn.collection, jhunt.id);
+        assert_eq!(txn.summary, "opting for ixalan");
+        assert_eq!(txn.notes, "this oughta be good");
+        assert_eq!(txn.disposition, Disposition::Buy);
+        assert_eq!(txn.dated, NaiveDate::from_ymd_opt(2020, 01, 14).unwrap());
+        assert_eq!(txn.gain, "1x XLN Opt\n");
+        assert_eq!(txn.loss, "");
+        assert_eq!(txn.total_card_gain(), 1);
+        assert_eq!(txn.unique_card_gain(), 1);
+        assert_eq!(txn.total_card_loss(), 0);
+        assert_eq!(txn.unique_card_loss(), 0);
 
-                let (total, unique) = cdif.count();
-                meta.insert("total_loss".to_string(), json!(total as f64));
-                meta.insert("unique_loss".to_string(), json!(unique as f64));
-                meta.insert("set_loss".to_string(), json!(cdif.unique_sets()));
+        assert_eq!(txn.set_loss().len(), 0);
+        assert_eq!(txn.set_gain().len(), 1);
+        let sets = vec_to_set(txn.set_gain());
+        assert!(sets.contains("XLN"));
 
-                Some(cdif)
-            }
-        };
+        let updated = db
+            .update_transaction(
+                &txn,
+                UpdateTransaction {
+                    summary: None,
+                    notes: None,
+                    dated: None,
+                    disposition: None,
+                    gain: Some("1x XLN Opt\n3x GRN Radical Idea\n".to_string()),
+                    loss: None,
+                    paid: None,
+                    updated_by: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(updated.collection, jhunt.id);
+        assert_eq!(
+            updated.dated,
+            NaiveDate::from_ymd_opt(2020, 01, 14).unwrap()
+        );
+        assert_eq!(updated.summary, "opting for ixalan");
+        assert_eq!(updated.notes, "this oughta be good");
+        assert_eq!(updated.disposition, Disposition::Buy);
+        assert_eq!(updated.gain, "1x XLN Opt\n3x GRN Radical Idea\n");
+        assert_eq!(updated.loss, "");
+        assert_eq!(updated.total_card_gain(), 4);
+        assert_eq!(updated.unique_card_gain(), 2);
+        assert_eq!(updated.total_card_loss(), 0);
+        assert_eq!(updated.unique_card_loss(), 0);
 
-        let txn = diesel::update(obj)
-            .set((
-                &upd,
-                transactions::dsl::metadata.eq(serde_json::Value::Object(meta)),
-                transactions::dsl::updated_at.eq(Utc::now()),
-            ))
-            .get_result(&self.pg)
-            .chain_err(|| "failed to update transaction record in database")?;
+        assert_eq!(updated.set_loss().len(), 0);
+        assert_eq!(updated.set_gain().len(), 2);
+        let sets = vec_to_set(updated.set_gain());
+        assert!(sets.contains("XLN"));
+        assert!(sets.contains("GRN"));
 
-        match gain {
-            None => (),
-            Some(now) => {
-                let then = cdif::File::from_string(&obj.gain)
-                    .chain_err(|| "failed to parse previous gains during transaction update")?;
+        let collection = db.load_collection(jhunt.id).unwrap();
+        let owned = collection.owned();
+        assert_eq!(owned.get("xln-opt-fake-id"), Some(&1));
+        assert_eq!(owned.get("grn-rad-fake-id"), Some(&3));
+    }
 
-                self.apply_collection_credit(obj.collection, cdif::File::diff(&then, &now))?;
-            }
-        };
+    #[test]
+    pub fn can_attach_files_to_a_transaction() {
+        let (_tmp, db) = connect();
 
-        match loss {
-            None => (),
-            Some(now) => {
-                let then = cdif::File::from_string(&obj.loss)
-                    .chain_err(|| "failed to parse previous losses during transaction update")?;
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-                self.apply_collection_debit(obj.collection, cdif::File::diff(&then, &now))?;
-            }
+        let dated = NaiveDate::from_ymd_opt(2020, 10, 1).unwrap();
+        let new = |summary| NewTransaction {
+            summary,
+            disposition: Disposition::Buy,
+            notes: "",
+            collection: jhunt.id,
+            dated: &dated,
+            gain: "1x XLN Opt\n",
+            loss: "",
+            paid: Some(25),
+            created_by: None,
         };
+        let one = db.create_transaction(None, new("one")).unwrap();
+        let two = db.create_transaction(None, new("two")).unwrap();
 
-        Ok(txn)
-    }
-
-    pub fn delete_transaction(&self, id: Uuid) -> Result<()> {
-        diesel::delete(transactions::dsl::transactions.filter(transactions::dsl::id.eq(id)))
-            .execute(&self.pg)
-            .chain_err(|| "failed to delete transaction record from database")?;
-        Ok(())
+        let receipt = db
+            .create_attachment(None, &one, "receipt.txt", "text/plain", b"1x XLN Opt $0.25")
+            .unwrap();
+        assert_eq!(receipt.size, 16);
+        assert_eq!(receipt.sha256.len(), 64);
+        let copy = db
+            .create_attachment(None, &two, "copy.txt", "text/plain", b"1x XLN Opt $0.25")
+            .unwrap();
+        assert_eq!(receipt.sha256, copy.sha256);
+
+        let path = attachment_path(jhunt.id, &receipt.sha256);
+        assert!(db.fs.path_to(&path).exists());
+        assert_eq!(
+            db.find_attachments_for_transactions(&[one.id, two.id])
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(db
+            .find_attachment_by_uuid(two.id, receipt.id)
+            .unwrap()
+            .is_none());
+
+        let mut s = String::new();
+        db.get_attachment_contents(&receipt)
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "1x XLN Opt $0.25");
+
+        // the contents are shared, so they stick around until both of the
+        // attachments are gone.
+        db.delete_attachment(&receipt).unwrap();
+        assert!(db.fs.path_to(&path).exists());
+        db.delete_transaction(two.id).unwrap();
+        assert!(!db.fs.path_to(&path).exists());
+        assert_eq!(
+            db.find_attachments_for_transactions(&[one.id, two.id])
+                .unwrap()
+                .len(),
+            0
+        );
     }
 
-    fn apply_collection_diff(&self, id: Uuid, cards: cdif::File, credit: bool) -> Result<()> {
-        let mut f = self
-            .fs
-            .get_as_reader("lookup.json")
-            .chain_err(|| "failed to retrieve card name -> print id lookup table")?;
+    #[test]
+    pub fn can_report_unmapped_dispositions() {
+        let (_tmp, db) = connect();
 
-        let lookup = card::Map::from_reader(&mut f)
-            .chain_err(|| "failed to retrieve card name -> print id lookup table")?;
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-        let mut delta = card::Pile::resolve(cards, lookup);
-        if !credit {
-            delta.invert();
-        }
-        self.fs
-            .append_to_json_list(
-                &format!("c/{}/_/collection.json", id.to_string()),
-                delta.cards,
+        let dated = NaiveDate::from_ymd_opt(2020, 10, 1).unwrap();
+        let txn = db
+            .create_transaction(
+                None,
+                NewTransaction {
+                    collection: jhunt.id,
+                    summary: "From the old spreadsheet",
+                    notes: "",
+                    dated: &dated,
+                    disposition: Disposition::Correction,
+                    gain: "1x XLN Opt\n",
+                    loss: "",
+                    paid: None,
+                    created_by: None,
+                },
             )
-            .chain_err(|| "failed to append json object to collection json file")?;
+            .unwrap();
+        assert!(find_unmapped_dispositions(&db.pg).unwrap().is_empty());
+
+        // as the migration would have left it
+        let mut meta = txn.metadata.as_object().unwrap().clone();
+        meta.insert("unmapped_disposition".to_string(), json!("boughten"));
+        let txn: Transaction = diesel::update(&txn)
+            .set(transactions::dsl::metadata.eq(serde_json::Value::Object(meta)))
+            .get_result(&db.pg)
+            .unwrap();
 
-        Ok(())
+        let unmapped = find_unmapped_dispositions(&db.pg).unwrap();
+        assert_eq!(unmapped.len(), 1);
+        assert_eq!(unmapped[0].unmapped_disposition(), Some("boughten".to_string()));
+
+        db.update_transaction(
+            &txn,
+            UpdateTransaction {
+                summary: None,
+                notes: None,
+                dated: None,
+                disposition: Some(Disposition::Buy),
+                gain: None,
+                loss: None,
+                paid: None,
+                updated_by: None,
+            },
+        )
+        .unwrap();
+        assert!(find_unmapped_dispositions(&db.pg).unwrap().is_empty());
     }
 
-    pub fn apply_collection_credit(&self, id: Uuid, credit: cdif::File) -> Result<()> {
-        self.apply_collection_diff(id, credit, true)
-    }
+    #[test]
+    pub fn can_search_and_page_through_transactions() {
+        let (_tmp, db) = connect();
 
-    pub fn apply_collection_debit(&self, id: Uuid, debit: cdif::File) -> Result<()> {
-        self.apply_collection_diff(id, debit, false)
-    }
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-    pub fn find_goals_for_collector(&self, uid: Uuid) -> Result<Vec<Goal>> {
-        Ok(goals::dsl::goals
-            .filter(goals::dsl::collector.eq(uid))
-            .get_results::<Goal>(&self.pg)
-            .chain_err(|| "unable to retrieve goals for collector uuid")?)
-    }
+        let days = [
+            NaiveDate::from_ymd_opt(2020, 10, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2020, 10, 2).unwrap(),
+            NaiveDate::from_ymd_opt(2020, 10, 3).unwrap(),
+        ];
+        let txn = |dated, summary, notes, disposition, gain, loss| {
+            db.create_transaction(
+                None,
+                NewTransaction {
+                    collection: jhunt.id,
+                    summary,
+                    notes,
+                    dated,
+                    disposition,
+                    gain,
+                    loss,
+                    paid: None,
+                    created_by: None,
+                },
+            )
+            .unwrap()
+        };
+        let one = txn(&days[0], "LGS pickups", "", Disposition::Buy, "4x XLN Opt\n", "");
+        let two = txn(
+            &days[1],
+            "Trade night",
+            "swapped with Dave",
+            Disposition::Trade,
+            "1x GRN Radical Idea\n",
+            "2x XLN Opt\n",
+        );
+        let three = txn(
+            &days[2],
+            "eBay sale",
+            "",
+            Disposition::Sell,
+            "",
+            "1x GRN Radical Idea\n",
+        );
+        let four = txn(
+            &days[2],
+            "Opt-ional",
+            "",
+            Disposition::Buy,
+            "1x GRN Radical Idea\n",
+            "",
+        );
 
-    pub fn find_goal_by_uuid(&self, uid: Uuid, id: Uuid) -> Result<Option<Goal>> {
-        match goals::dsl::goals
-            .find(id)
-            .filter(goals::dsl::collector.eq(uid))
-            .get_result::<Goal>(&self.pg)
-        {
-            Ok(goal) => Ok(Some(goal)),
-            Err(diesel::NotFound) => Ok(None),
-            Err(e) => Err(Error::with_chain(
-                e,
-                "failed to retrieve goal record from database",
-            )),
-        }
-    }
+        let ids = |q: &TransactionQuery| -> Vec<Uuid> {
+            db.find_transactions_matching(jhunt.id, q)
+                .unwrap()
+                .iter()
+                .map(|t| t.id)
+                .collect()
+        };
 
-    pub fn create_goal(&self, id: Option<Uuid>, new: NewGoal) -> Result<Goal> {
-        let now = Utc::now();
-        let id = gen_uuid(id);
-        Ok(diesel::insert_into(goals::table)
-            .values((
-                &new,
-                goals::dsl::id.eq(id),
-                goals::dsl::created_at.eq(now),
-                goals::dsl::updated_at.eq(now),
-            ))
-            .get_result(&self.pg)
-            .chain_err(|| "failed to insert goal record into database")?)
-    }
+        // everything, oldest first, ties broken by uuid
+        let mut last = [three.id, four.id];
+        last.sort();
+        let all = ids(&TransactionQuery::default());
+        assert_eq!(all, vec![one.id, two.id, last[0], last[1]]);
 
-    pub fn update_goal(&self, obj: &Goal, upd: UpdateGoal) -> Result<Goal> {
-        Ok(diesel::update(obj)
-            .set((&upd, goals::dsl::updated_at.eq(Utc::now())))
-            .get_result(&self.pg)
-            .chain_err(|| "failed to update goal record in database")?)
-    }
+        let newest = ids(&TransactionQuery {
+            newest_first: true,
+            ..TransactionQuery::default()
+        });
+        assert_eq!(newest, vec![last[1], last[0], two.id, one.id]);
+
+        assert_eq!(
+            ids(&TransactionQuery {
+                from: Some(days[1]),
+                to: Some(days[1]),
+                ..TransactionQuery::default()
+            }),
+            vec![two.id]
+        );
+        assert_eq!(
+            ids(&TransactionQuery {
+                dispositions: vec![Disposition::Buy, Disposition::Sell],
+                ..TransactionQuery::default()
+            })
+            .len(),
+            3
+        );
+        assert_eq!(
+            ids(&TransactionQuery {
+                search: Some("dave".to_string()),
+                ..TransactionQuery::default()
+            }),
+            vec![two.id]
+        );
+        assert_eq!(
+            ids(&TransactionQuery {
+                card: Some("opt".to_string()),
+                ..TransactionQuery::default()
+            }),
+            vec![one.id, two.id]
+        );
+        assert_eq!(
+            ids(&TransactionQuery {
+                set: Some("grn".to_string()),
+                ..TransactionQuery::default()
+            }),
+            vec![two.id, last[0], last[1]]
+        );
 
-    pub fn delete_goal(&self, id: Uuid) -> Result<()> {
-        diesel::delete(goals::dsl::goals.filter(goals::dsl::id.eq(id)))
-            .execute(&self.pg)
-            .chain_err(|| "failed to delete goal record from database")?;
-        Ok(())
+        // page through, two at a time
+        let page = |after| {
+            db.find_transactions_matching(
+                jhunt.id,
+                &TransactionQuery {
+                    after,
+                    limit: Some(2),
+                    ..TransactionQuery::default()
+                },
+            )
+            .unwrap()
+        };
+        let first = page(None);
+        assert_eq!(first.len(), 2);
+        let second = page(Some((first[1].dated, first[1].id)));
+        assert_eq!(second.len(), 2);
+        assert_eq!(second[0].id, last[0]);
+        assert_eq!(page(Some((second[1].dated, second[1].id))).len(), 0);
     }
 
-    pub fn find_decks_for_collector(&self, uid: Uuid) -> Result<Vec<Deck>> {
-        Ok(decks::dsl::decks
-            .filter(decks::dsl::collector.eq(uid))
-            .get_results::<Deck>(&self.pg)
-            .chain_err(|| "unable to retrieve decks for collector uuid")?)
-    }
+    #[test]
+    pub fn can_create_a_deck() {
+        let (_tmp, db) = connect();
 
-    pub fn find_deck_by_uuid(&self, uid: Uuid, id: Uuid) -> Result<Option<Deck>> {
-        match decks::dsl::decks
-            .find(id)
-            .filter(decks::dsl::collector.eq(uid))
-            .get_result::<Deck>(&self.pg)
-        {
-            Ok(deck) => Ok(Some(deck)),
-            Err(diesel::NotFound) => Ok(None),
-            Err(e) => Err(Error::with_chain(
-                e,
-                "failed to retrieve deck record from database",
-            )),
-        }
-    }
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-    pub fn create_deck(&self, id: Option<Uuid>, new: NewDeck) -> Result<Deck> {
-        let now = Utc::now();
-        let id = gen_uuid(id);
-        Ok(diesel::insert_into(decks::table)
-            .values((
-                &new,
-                decks::dsl::id.eq(id),
-                decks::dsl::lineage.eq(id),
-                decks::dsl::ordinal.eq(0),
-                decks::dsl::created_at.eq(now),
-                decks::dsl::updated_at.eq(now),
-            ))
-            .get_result(&self.pg)
-            .chain_err(|| "failed to insert deck record into database")?)
-    }
+        let deck = db.create_deck(
+            None,
+            NewDeck {
+                collector: jhunt.id,
+                code: "niv",
+                title: "Niv-Mizzet",
+                description: "Draw a card, 1 damage to you...",
+                main: "1x GRN Niv-Mizzet\n",
+                side: "1x RNA Niv-Mizzet, Parun\n",
+                maybe: "1x WAR Niv-Mizzet, Reborn\n",
+            },
+        );
+        assert!(deck.is_ok());
+        let deck = deck.unwrap();
 
-    pub fn update_deck(&self, obj: &Deck, upd: UpdateDeck) -> Result<Deck> {
-        Ok(diesel::update(obj)
-            .set((&upd, decks::dsl::updated_at.eq(Utc::now())))
-            .get_result(&self.pg)
-            .chain_err(|| "failed to update deck record in database")?)
+        assert_eq!(deck.collector, jhunt.id);
+        assert_eq!(deck.title, "Niv-Mizzet");
+        assert_eq!(deck.description, "Draw a card, 1 damage to you...");
+        assert_eq!(deck.main, "1x GRN Niv-Mizzet\n");
+        assert_eq!(deck.side, "1x RNA Niv-Mizzet, Parun\n");
+        assert_eq!(deck.maybe, "1x WAR Niv-Mizzet, Reborn\n");
+        assert_eq!(deck.lineage, deck.id);
+        assert_eq!(deck.ordinal, 0);
     }
 
-    pub fn delete_deck(&self, id: Uuid) -> Result<()> {
-        diesel::delete(decks::dsl::decks.filter(decks::dsl::id.eq(id)))
-            .execute(&self.pg)
-            .chain_err(|| "failed to delete deck record from database")?;
-        Ok(())
-    }
+    #[test]
+    pub fn can_create_a_goal_with_a_deadline_and_budget() {
+        let (_tmp, db) = connect();
 
-    //pub fn snapshot_deck
-}
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use std::collections::HashSet;
-    use tempdir::TempDir;
-    use uuid::Uuid;
+        let goal = db
+            .create_goal(
+                None,
+                NewGoal {
+                    collector: jhunt.id,
+                    name: "Finish Dominaria",
+                    ordinal: 1,
+                    target: "set:DOM",
+                    goal: "one-of-each",
+                    total: None,
+                    progress: None,
+                    deadline: Some(NaiveDate::from_ymd_opt(2020, 12, 31).unwrap()),
+                    budget: Some(25000),
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            goal.deadline,
+            Some(NaiveDate::from_ymd_opt(2020, 12, 31).unwrap())
+        );
+        assert_eq!(goal.budget, Some(25000));
+
+        let goal = db
+            .update_goal(
+                &goal,
+                UpdateGoal {
+                    name: None,
+                    ordinal: None,
+                    target: None,
+                    goal: None,
+                    total: Some(Some(269)),
+                    progress: Some(Some(12)),
+                    deadline: Some(None),
+                    budget: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(goal.total, Some(269));
+        assert_eq!(goal.progress, Some(12));
+        assert_eq!(goal.deadline, None);
+        assert_eq!(goal.budget, Some(25000));
+    }
 
-    fn connect() -> (TempDir, Database) {
-        use std::env;
+    #[test]
+    pub fn can_create_a_draft_pod() {
+        let (_tmp, db) = connect();
 
-        let pg =
-            env::var("VCB_DATABASE_URL").expect("VCB_DATABASE_URL must be set in the environment.");
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
+
+        let pod = db
+            .create_pod(
+                None,
+                NewPod {
+                    collector: jhunt.id,
+                    set_code: "MIR",
+                    state: json!({"set": "MIR"}),
+                },
+            )
+            .unwrap();
+        assert_eq!(pod.collector, jhunt.id);
+        assert_eq!(pod.set_code, "MIR");
+        assert!(!pod.finished);
+
+        let pod = db
+            .update_pod(
+                &pod,
+                UpdatePod {
+                    state: None,
+                    finished: Some(true),
+                },
+            )
+            .unwrap();
+        assert!(pod.finished);
+        assert_eq!(pod.state, json!({"set": "MIR"}));
+
+        // a failed pick leaves the pod as it was.
+        let picked: Result<Pod> = db.atomically(|| {
+            let pod = db.lock_pod(pod.id)?.unwrap();
+            db.update_pod(
+                &pod,
+                UpdatePod {
+                    state: Some(json!({"set": "VIS"})),
+                    finished: None,
+                },
+            )?;
+            Err("pick-failed".into())
+        });
+        assert!(picked.is_err());
+        let pod = db.find_pod_by_uuid(pod.id).unwrap().unwrap();
+        assert_eq!(pod.state, json!({"set": "MIR"}));
+        assert!(db.lock_pod(Uuid::new_v4()).unwrap().is_none());
+
+        assert_eq!(db.find_pods_for_collector(jhunt.id).unwrap().len(), 1);
+        db.delete_pod(pod.id).unwrap();
+        assert!(db.find_pod_by_uuid(pod.id).unwrap().is_none());
+    }
 
-        let rd = env::var("VCB_REDIS_URL").expect("VCB_REDIS_URL must be set in the environment.");
+    #[test]
+    pub fn can_create_a_watchlist_and_record_alerts() {
+        let (_tmp, db) = connect();
 
-        let fs = TempDir::new("vcb-test").expect("Failed to create temp directory");
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-        let db = Database::connect(&pg, &rd, &fs.path(), 3600).unwrap();
-        db.testmode().unwrap();
-        db.fs
-            .create(
-                "lookup.json",
-                r#"
-{
-  "XLN * Opt": "xln-opt-fake-id",
-  "GRN * Radical Idea": "grn-rad-fake-id"
-}
-"#,
+        let watch = db
+            .create_watchlist(
+                None,
+                NewWatchlist {
+                    collector: jhunt.id,
+                    card: "4644694d-52e6-4d00-8cad-748899eeea84",
+                    scope: "print",
+                    finish: "foil",
+                    currency: "usd",
+                    below: Some(100),
+                    above: None,
+                    notes: "",
+                },
             )
             .unwrap();
-        (fs, db)
+        assert_eq!(watch.below, Some(100));
+
+        let watch = db
+            .update_watchlist(
+                &watch,
+                UpdateWatchlist {
+                    finish: None,
+                    currency: None,
+                    below: None,
+                    above: Some(Some(2500)),
+                    notes: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(watch.below, Some(100));
+        assert_eq!(watch.above, Some(2500));
+
+        let alert = || NewAlert {
+            watchlist: watch.id,
+            collector: jhunt.id,
+            card: "4644694d-52e6-4d00-8cad-748899eeea84",
+            direction: "below",
+            threshold: 100,
+            price: 75,
+            currency: "usd",
+            dated: NaiveDate::from_ymd_opt(2020, 8, 23).unwrap(),
+        };
+        let recorded = db.create_alert(None, alert()).unwrap();
+        assert!(recorded.is_some());
+        assert!(db.create_alert(None, alert()).unwrap().is_none());
+
+        let pending = db.find_undelivered_alerts().unwrap();
+        assert_eq!(1, pending.len());
+        db.mark_alert_delivered(&pending[0]).unwrap();
+        assert!(db.find_undelivered_alerts().unwrap().is_empty());
+        assert_eq!(1, db.find_alerts_for_collector(jhunt.id).unwrap().len());
+
+        db.delete_watchlist(watch.id).unwrap();
+        assert!(db.find_watchlist_by_uuid(jhunt.id, watch.id).unwrap().is_none());
+        assert!(db.find_alerts_for_collector(jhunt.id).unwrap().is_empty());
     }
 
     #[test]
-    fn should_be_able_to_create_a_session() {
+    pub fn can_create_a_wishlist() {
         let (_tmp, db) = connect();
 
-        let mut session = Session::new(None);
-        session.set("foo", "bar");
-        assert_eq!(session.get("foo"), "bar");
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-        let session = db.set_session(session).unwrap();
-        assert_eq!(session.get("foo"), "bar");
+        let wishes = db
+            .create_wishlist(
+                None,
+                NewWishlist {
+                    collector: jhunt.id,
+                    name: "Mirage",
+                    description: "",
+                    cards: "1x MIR Afterlife | (printing: any) (max: 0.50)\n",
+                },
+            )
+            .unwrap();
 
-        let session = db.get_session(session.id).unwrap().unwrap();
-        assert_eq!(session.get("foo"), "bar");
+        let wishes = db
+            .update_wishlist(
+                &wishes,
+                UpdateWishlist {
+                    name: None,
+                    description: Some("things I still need".to_string()),
+                    cards: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(wishes.name, "Mirage");
+        assert_eq!(wishes.description, "things I still need");
+        assert_eq!(db.find_wishlists_for_collector(jhunt.id).unwrap().len(), 1);
 
-        assert!(db.expire_session(&session).is_ok());
-        assert!(db.get_session(session.id).unwrap().is_none());
+        db.delete_wishlist(wishes.id).unwrap();
+        assert!(db.find_wishlist_by_uuid(jhunt.id, wishes.id).unwrap().is_none());
     }
 
     #[test]
-    fn should_be_able_to_create_a_collector() {
+    pub fn can_lend_cards_and_get_them_back() {
         let (_tmp, db) = connect();
 
-        let id = Uuid::parse_str("a026089c-90a2-4180-b41a-2082e7b2ebcc").unwrap();
-        let jhunt = db.create_collector(
-            Some(id),
-            NewCollector {
-                username: "jhunt",
-                email: "james@example.com",
-            },
-            None,
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
+
+        let loan = db
+            .create_loan(
+                None,
+                NewLoan {
+                    collector: jhunt.id,
+                    borrower: "Dave, from game night",
+                    borrower_collector: None,
+                    cards: "4x MIR Afterlife\n",
+                    notes: "",
+                    lent_on: NaiveDate::from_ymd_opt(2020, 9, 1).unwrap(),
+                    due_on: Some(NaiveDate::from_ymd_opt(2020, 9, 14).unwrap()),
+                },
+            )
+            .unwrap();
+        assert_eq!(db.find_open_loans_for_collector(jhunt.id).unwrap().len(), 1);
+        assert_eq!(
+            db.find_overdue_loans_for_collector(
+                jhunt.id,
+                NaiveDate::from_ymd_opt(2020, 9, 14).unwrap()
+            )
+            .unwrap()
+            .len(),
+            0
+        );
+        assert_eq!(
+            db.find_overdue_loans_for_collector(
+                jhunt.id,
+                NaiveDate::from_ymd_opt(2020, 9, 15).unwrap()
+            )
+            .unwrap()
+            .len(),
+            1
         );
-        assert!(jhunt.is_ok());
 
-        let found = db.find_collector_by_uuid(id);
-        assert!(found.is_ok());
-        let found = found.unwrap();
-        assert!(found.is_some());
-        let found = found.unwrap();
-        assert_eq!(found.username, "jhunt");
-        assert_eq!(found.email, "james@example.com");
+        let loan = db
+            .return_loan(&loan, NaiveDate::from_ymd_opt(2020, 9, 20).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            loan.returned_on,
+            Some(NaiveDate::from_ymd_opt(2020, 9, 20).unwrap())
+        );
+        assert!(db
+            .return_loan(&loan, NaiveDate::from_ymd_opt(2020, 9, 21).unwrap())
+            .unwrap()
+            .is_none());
+        assert!(db.find_open_loans_for_collector(jhunt.id).unwrap().is_empty());
+        assert_eq!(db.find_loans_for_collector(jhunt.id).unwrap().len(), 1);
     }
 
     #[test]
-    fn should_not_be_able_to_reuse_usernames() {
+    pub fn can_open_sealed_product() {
         let (_tmp, db) = connect();
 
-        let jhunt = db.create_collector(
-            None,
-            NewCollector {
-                username: "jhunt",
-                email: "james@example.com",
-            },
-            None,
-        );
-        assert!(jhunt.is_ok());
-        let jhunt = jhunt.unwrap();
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-        let found = db.find_collector_by_uuid(jhunt.id);
-        assert!(found.is_ok());
-        let found = found.unwrap();
-        assert!(found.is_some());
-        let found = found.unwrap();
-        assert_eq!(found.username, "jhunt");
-        assert_eq!(found.email, "james@example.com");
+        let packs = db
+            .create_sealed_product(
+                None,
+                NewSealedProduct {
+                    collector: jhunt.id,
+                    kind: "booster-pack",
+                    name: "",
+                    set_code: "XLN",
+                    packs: 1,
+                    quantity: 1,
+                    paid: Some(399),
+                    acquired_on: NaiveDate::from_ymd_opt(2020, 10, 1).unwrap(),
+                    notes: "",
+                },
+            )
+            .unwrap();
+        assert_eq!(db.find_sealed_products_for_collector(jhunt.id).unwrap().len(), 1);
+
+        let dated = NaiveDate::from_ymd_opt(2020, 10, 2).unwrap();
+        let open = || NewTransaction {
+            summary: "cracked a pack",
+            disposition: Disposition::Open,
+            notes: "",
+            collection: jhunt.id,
+            dated: &dated,
+            gain: "1x XLN Opt\n",
+            loss: "",
+            paid: packs.paid,
+            created_by: None,
+        };
+        let txn = db.open_sealed_product(&packs, open()).unwrap().unwrap();
+        assert_eq!(txn.opened, Some(packs.id));
+        assert_eq!(txn.paid, Some(399));
+        assert_eq!(txn.disposition, Disposition::Open);
+
+        let packs = db
+            .find_sealed_product_by_uuid(jhunt.id, packs.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(packs.quantity, 0);
+        assert!(db.open_sealed_product(&packs, open()).unwrap().is_none());
 
-        let err = db.create_collector(
-            None,
-            NewCollector {
-                username: &found.username,
-                email: "other-james@example.com",
-            },
-            None,
-        );
-        assert!(err.is_err());
+        db.delete_transaction(txn.id).unwrap();
+        let packs = db
+            .find_sealed_product_by_uuid(jhunt.id, packs.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(packs.quantity, 1);
+
+        db.delete_sealed_product(packs.id).unwrap();
+        assert!(db
+            .find_sealed_product_by_uuid(jhunt.id, packs.id)
+            .unwrap()
+            .is_none());
     }
 
     #[test]
-    fn should_be_able_to_reuse_email_addresses() {
+    pub fn can_transfer_cards_between_collections() {
         let (_tmp, db) = connect();
 
-        let jhunt = db.create_collector(
-            None,
-            NewCollector {
-                username: "jhunt",
-                email: "james@example.com",
-            },
-            None,
-        );
-        assert!(jhunt.is_ok());
-        let jhunt = jhunt.unwrap();
-
-        let found = db.find_collector_by_uuid(jhunt.id);
-        assert!(found.is_ok());
-        let found = found.unwrap();
-        assert!(found.is_some());
-        let found = found.unwrap();
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-        let other = db.create_collector(
-            None,
-            NewCollector {
-                username: "other-jhunt",
-                email: &found.email,
-            },
-            None,
+        let binder = db
+            .create_collection(
+                None,
+                NewCollection {
+                    collector: jhunt.id,
+                    name: "Trade Binder",
+                },
+            )
+            .unwrap();
+        assert!(!binder.is_default());
+
+        let all = db.find_collections_for_collector(jhunt.id).unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(all[0].is_default());
+        assert_eq!(all[1].name, "Trade Binder");
+
+        let binder = db
+            .update_collection(
+                &binder,
+                UpdateCollection {
+                    name: Some("Trades".to_string()),
+                },
+            )
+            .unwrap();
+        assert_eq!(binder.name, "Trades");
+
+        let dated = NaiveDate::from_ymd_opt(2020, 11, 15).unwrap();
+        let (out, into) = db
+            .transfer_cards(
+                NewTransaction {
+                    summary: "moved to the binder",
+                    disposition: Disposition::Transfer,
+                    notes: "",
+                    collection: jhunt.id,
+                    dated: &dated,
+                    gain: "",
+                    loss: "1x XLN Opt\n",
+                    paid: None,
+                    created_by: None,
+                },
+                NewTransaction {
+                    summary: "moved to the binder",
+                    disposition: Disposition::Transfer,
+                    notes: "",
+                    collection: binder.id,
+                    dated: &dated,
+                    gain: "1x XLN Opt\n",
+                    loss: "",
+                    paid: None,
+                    created_by: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(out.transfer, Some(into.id));
+        assert_eq!(into.transfer, Some(out.id));
+        assert_eq!(
+            db.find_transactions_for_collection(binder.id)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(!db.delete_collection(binder.id).unwrap());
+        assert!(db.find_collection_by_uuid(binder.id).unwrap().is_some());
+
+        db.delete_transaction(into.id).unwrap();
+        assert!(db
+            .find_transactions_for_collection(jhunt.id)
+            .unwrap()
+            .is_empty());
+        assert!(db
+            .find_transactions_for_collection(binder.id)
+            .unwrap()
+            .is_empty());
+
+        assert!(db.delete_collection(binder.id).unwrap());
+        assert!(db.find_collection_by_uuid(binder.id).unwrap().is_none());
+        assert_eq!(
+            db.find_collections_for_collector(jhunt.id).unwrap().len(),
+            1
         );
-        assert!(!other.is_err());
     }
 
     #[test]
-    #[ignore]
-    fn should_be_able_to_reuse_passwords() {
+    pub fn can_share_a_collection() {
         let (_tmp, db) = connect();
 
         let jhunt = db
@@ -1021,24 +4570,81 @@ mod test {
                     username: "jhunt",
                     email: "james@example.com",
                 },
-                Some("sekrit"),
+                None,
+            )
+            .unwrap();
+        let other = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "other",
+                    email: "other@example.com",
+                },
+                None,
             )
             .unwrap();
+        assert_eq!(
+            db.find_collector_by_username("other").unwrap().unwrap().id,
+            other.id
+        );
+        assert!(db.find_collector_by_username("nobody").unwrap().is_none());
 
-        db.find_collector_by_uuid(jhunt.id).unwrap();
-        let other = db.create_collector(
-            None,
-            NewCollector {
-                username: "other-jhunt",
-                email: "other-james@example.com",
-            },
-            Some("sekrit"),
+        let member = db
+            .invite_collection_member(NewCollectionMember {
+                collection: jhunt.id,
+                collector: other.id,
+                role: "viewer",
+                invited_by: Some(jhunt.id),
+            })
+            .unwrap();
+        assert!(member.accepted_at.is_none());
+        assert_eq!(db.find_members_of_collection(jhunt.id).unwrap().len(), 1);
+        assert_eq!(
+            db.find_memberships_for_collector(other.id).unwrap()[0].collection,
+            jhunt.id
+        );
+
+        let member = db.accept_collection_membership(&member).unwrap();
+        assert!(member.accepted_at.is_some());
+        let member = db.set_collection_member_role(&member, "editor").unwrap();
+        assert_eq!(
+            db.find_collection_member(jhunt.id, other.id)
+                .unwrap()
+                .unwrap()
+                .role,
+            "editor"
         );
-        assert!(!other.is_err());
+
+        let txn = db
+            .create_transaction(
+                None,
+                NewTransaction {
+                    summary: "bought on their behalf",
+                    disposition: Disposition::Buy,
+                    notes: "",
+                    collection: jhunt.id,
+                    dated: &NaiveDate::from_ymd_opt(2020, 11, 22).unwrap(),
+                    gain: "1x XLN Opt\n",
+                    loss: "",
+                    paid: None,
+                    created_by: Some(other.id),
+                },
+            )
+            .unwrap();
+        assert_eq!(txn.created_by, Some(other.id));
+        assert_eq!(txn.updated_by, None);
+
+        db.delete_collection_member(member.collection, member.collector)
+            .unwrap();
+        assert!(db
+            .find_collection_member(jhunt.id, other.id)
+            .unwrap()
+            .is_none());
+        assert!(db.find_members_of_collection(jhunt.id).unwrap().is_empty());
     }
 
     #[test]
-    fn should_be_able_to_update_a_collectors_username() {
+    pub fn can_hand_out_and_revoke_share_links() {
         let (_tmp, db) = connect();
 
         let jhunt = db
@@ -1051,36 +4657,88 @@ mod test {
                 None,
             )
             .unwrap();
+        let deck = db
+            .create_deck(
+                None,
+                NewDeck {
+                    collector: jhunt.id,
+                    code: "opt",
+                    title: "Opt Tribal",
+                    description: "",
+                    main: "4x XLN Opt\n",
+                    side: "",
+                    maybe: "",
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            db.find_latest_deck_in_lineage(deck.lineage)
+                .unwrap()
+                .unwrap()
+                .id,
+            deck.id
+        );
 
-        let found = db.find_collector_by_uuid(jhunt.id);
-        assert!(found.is_ok());
-        let found = found.unwrap();
-        assert!(found.is_some());
-        let found = found.unwrap();
-        assert_eq!("jhunt", found.username);
-
-        let updated = db.update_collector(
-            &jhunt,
-            CollectorUpdate {
-                username: Some("james"),
-                email: None,
-                password: None,
-            },
+        let link = db
+            .create_share_link(
+                None,
+                NewShareLink {
+                    collector: jhunt.id,
+                    hash: "bf1ad3bdea3d3a05d2be2d5c3c8a9c0f3a1a4e2f1c3b9e5d7a6f8c0b2d4e6f81",
+                    hint: "IJ01",
+                    kind: "deck",
+                    deck: Some(deck.id),
+                    collection: None,
+                    query: "",
+                    expires_at: None,
+                },
+            )
+            .unwrap();
+        assert!(link.is_live(Utc::now()));
+        assert_eq!(
+            db.find_share_link_by_hash(
+                "bf1ad3bdea3d3a05d2be2d5c3c8a9c0f3a1a4e2f1c3b9e5d7a6f8c0b2d4e6f81"
+            )
+            .unwrap()
+            .unwrap()
+            .id,
+            link.id
+        );
+        assert!(db.find_share_link_by_hash("nope").unwrap().is_none());
+
+        let expiring = db
+            .create_share_link(
+                None,
+                NewShareLink {
+                    collector: jhunt.id,
+                    hash: "0c5e1f7a9b3d2c4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e1f2a4b6c8d0e",
+                    hint: "ij01",
+                    kind: "collection",
+                    deck: None,
+                    collection: Some(jhunt.id),
+                    query: "set:XLN",
+                    expires_at: Some(Utc::now() + chrono::Duration::days(1)),
+                },
+            )
+            .unwrap();
+        assert!(expiring.is_live(Utc::now()));
+        assert!(!expiring.is_live(Utc::now() + chrono::Duration::days(2)));
+        assert_eq!(
+            db.find_share_links_for_collector(jhunt.id).unwrap().len(),
+            2
         );
-        assert!(updated.is_ok());
-        let updated = updated.unwrap();
-        assert_eq!("james", updated.username);
 
-        let found = db.find_collector_by_uuid(updated.id);
-        assert!(found.is_ok());
-        let found = found.unwrap();
-        assert!(found.is_some());
-        let found = found.unwrap();
-        assert_eq!("james", found.username);
+        let link = db.revoke_share_link(&link).unwrap();
+        assert!(link.revoked_at.is_some());
+        assert!(!link.is_live(Utc::now()));
+        assert!(db
+            .find_share_link_by_uuid(jhunt.id, link.id)
+            .unwrap()
+            .is_some());
     }
 
     #[test]
-    fn should_not_be_able_to_reuse_usernames_via_update() {
+    pub fn can_issue_and_revoke_api_tokens() {
         let (_tmp, db) = connect();
 
         let jhunt = db
@@ -1093,33 +4751,46 @@ mod test {
                 None,
             )
             .unwrap();
-        db.find_collector_by_uuid(jhunt.id).unwrap().unwrap();
 
-        let james = db
-            .create_collector(
+        let token = db
+            .create_api_token(
                 None,
-                NewCollector {
-                    username: "james",
-                    email: "other-james@example.com",
+                NewApiToken {
+                    collector: jhunt.id,
+                    name: "nightly import",
+                    hash: "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae",
+                    hint: "wxyz",
+                    scopes: vec!["read-collection".to_string(), "manage-decks".to_string()],
                 },
-                None,
             )
             .unwrap();
-        db.find_collector_by_uuid(james.id).unwrap().unwrap();
+        assert_eq!(token.name, "nightly import");
+        assert_eq!(token.scopes, vec!["read-collection", "manage-decks"]);
+        assert!(token.last_used_at.is_none());
 
-        let updated = db.update_collector(
-            &jhunt,
-            CollectorUpdate {
-                username: Some("james"),
-                email: None,
-                password: None,
-            },
-        );
-        assert!(updated.is_err());
+        let found = db
+            .find_api_token_by_hash(
+                "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae",
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, token.id);
+        assert!(db.find_api_token_by_hash("nope").unwrap().is_none());
+
+        db.touch_api_token(&found).unwrap();
+        let found = db
+            .find_api_token_by_uuid(jhunt.id, token.id)
+            .unwrap()
+            .unwrap();
+        assert!(found.last_used_at.is_some());
+
+        let revoked = db.revoke_api_token(&found).unwrap();
+        assert!(revoked.revoked_at.is_some());
+        assert_eq!(db.find_api_tokens_for_collector(jhunt.id).unwrap().len(), 1);
     }
 
     #[test]
-    fn should_be_able_to_update_a_collectors_email() {
+    pub fn can_use_account_tokens_once() {
         let (_tmp, db) = connect();
 
         let jhunt = db
@@ -1129,39 +4800,71 @@ mod test {
                     username: "jhunt",
                     email: "james@example.com",
                 },
-                None,
+                Some("sekrit"),
             )
             .unwrap();
+        assert!(jhunt.email_verified_at.is_none());
 
-        let found = db.find_collector_by_uuid(jhunt.id);
-        assert!(found.is_ok());
-        let found = found.unwrap();
-        assert!(found.is_some());
-        let found = found.unwrap();
-        assert_eq!("jhunt", found.username);
-
-        let updated = db.update_collector(
-            &jhunt,
-            CollectorUpdate {
-                username: None,
-                email: Some("jhunt@example.com"),
-                password: None,
-            },
-        );
-        assert!(updated.is_ok());
-        let updated = updated.unwrap();
-        assert_eq!("jhunt@example.com", updated.email);
+        let now = Utc::now();
+        let token = |purpose, expires_at| {
+            db.create_account_token(
+                None,
+                NewAccountToken {
+                    collector: jhunt.id,
+                    purpose,
+                    email: "james@example.com",
+                    expires_at,
+                },
+            )
+            .unwrap()
+        };
+        let reset = token("reset-password", now + chrono::Duration::hours(1));
+        let other = token("reset-password", now + chrono::Duration::hours(1));
+        let stale = token("reset-password", now - chrono::Duration::hours(1));
+        let verify = token("verify-email", now + chrono::Duration::days(2));
+
+        let used = db.use_account_token(reset.id, now).unwrap().unwrap();
+        assert!(used.used_at.is_some());
+        assert!(db.use_account_token(reset.id, now).unwrap().is_none());
+        assert!(db.use_account_token(stale.id, now).unwrap().is_none());
+        assert!(db.use_account_token(Uuid::new_v4(), now).unwrap().is_none());
+
+        let expired = db
+            .expire_account_tokens(jhunt.id, "reset-password")
+            .unwrap();
+        assert_eq!(expired, 2);
+        assert!(db.use_account_token(other.id, now).unwrap().is_none());
+        assert!(db.use_account_token(verify.id, now).unwrap().is_some());
 
-        let found = db.find_collector_by_uuid(updated.id);
-        assert!(found.is_ok());
-        let found = found.unwrap();
-        assert!(found.is_some());
-        let found = found.unwrap();
-        assert_eq!("jhunt@example.com", found.email);
+        let jhunt = db.set_collector_password(&jhunt, "new sekrit").unwrap();
+        assert!(db
+            .authenticate_collector("jhunt", "sekrit")
+            .unwrap()
+            .is_none());
+        assert!(db
+            .authenticate_collector("jhunt", "new sekrit")
+            .unwrap()
+            .is_some());
+
+        let jhunt = db.verify_collector_email(&jhunt).unwrap();
+        assert!(jhunt.email_verified_at.is_some());
+        let jhunt = db
+            .update_collector(
+                &jhunt,
+                CollectorUpdate {
+                    username: None,
+                    email: Some("jhunt@example.com"),
+                    password: None,
+                    currency: None,
+                    tradeable: None,
+                },
+            )
+            .unwrap();
+        assert!(jhunt.email_verified_at.is_none());
     }
 
     #[test]
-    fn should_be_able_to_reuse_email_addresses_via_update() {
+    pub fn can_recur_a_templated_transaction() {
         let (_tmp, db) = connect();
 
         let jhunt = db
@@ -1174,78 +4877,163 @@ mod test {
                 None,
             )
             .unwrap();
-        db.find_collector_by_uuid(jhunt.id).unwrap().unwrap();
 
-        let james = db
-            .create_collector(
+        let subs = db
+            .create_collection(
                 None,
-                NewCollector {
-                    username: "james",
-                    email: "other-james@example.com",
+                NewCollection {
+                    collector: jhunt.id,
+                    name: "Subscriptions",
                 },
+            )
+            .unwrap();
+        let t = db
+            .create_transaction_template(
                 None,
+                NewTransactionTemplate {
+                    collector: jhunt.id,
+                    collection: subs.id,
+                    name: "monthly opt",
+                    summary: "{set} subscription",
+                    notes: "",
+                    gain: "1x {set} Opt\n",
+                    loss: "",
+                    disposition: Disposition::Buy,
+                    paid: Some(25),
+                },
             )
             .unwrap();
-        db.find_collector_by_uuid(james.id).unwrap().unwrap();
+        assert_eq!(
+            db.find_transaction_templates_for_collector(jhunt.id)
+                .unwrap()
+                .len(),
+            1
+        );
 
-        let updated = db.update_collector(
-            &jhunt,
-            CollectorUpdate {
-                username: None,
-                email: Some("other-james@example.com"),
-                password: None,
-            },
+        let r = db
+            .create_recurring_transaction(
+                None,
+                NewRecurringTransaction {
+                    collector: jhunt.id,
+                    template: t.id,
+                    every: "monthly",
+                    starts_on: NaiveDate::from_ymd_opt(2020, 1, 31).unwrap(),
+                    ends_on: None,
+                    vars: json!({"set": "XLN"}),
+                    next_on: NaiveDate::from_ymd_opt(2020, 1, 31).unwrap(),
+                },
+            )
+            .unwrap();
+        assert!(db
+            .find_due_recurring_transactions(NaiveDate::from_ymd_opt(2020, 1, 30).unwrap())
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            db.find_due_recurring_transactions(NaiveDate::from_ymd_opt(2020, 3, 1).unwrap())
+                .unwrap()
+                .len(),
+            1
         );
-        assert!(updated.is_ok());
-        let updated = updated.unwrap();
-        assert_eq!("other-james@example.com", updated.email);
 
-        let found = db.find_collector_by_uuid(jhunt.id);
-        assert!(found.is_ok());
-        let found = found.unwrap();
-        assert!(found.is_some());
-        let found = found.unwrap();
-        assert_eq!("other-james@example.com", found.email);
+        let dated = r.next_on;
+        let new = || NewTransaction {
+            summary: "XLN subscription",
+            disposition: Disposition::Buy,
+            notes: "",
+            collection: t.collection,
+            dated: &dated,
+            gain: "1x XLN Opt\n",
+            loss: "",
+            paid: Some(25),
+            created_by: None,
+        };
+        let next_on = NaiveDate::from_ymd_opt(2020, 2, 29).unwrap();
+        let txn = db
+            .record_recurring_transaction(&r, next_on, new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(txn.dated, NaiveDate::from_ymd_opt(2020, 1, 31).unwrap());
+        assert_eq!(txn.total_card_gain(), 1);
+        assert_eq!(txn.collection, subs.id);
 
-        let found = db.find_collector_by_uuid(james.id);
-        assert!(found.is_ok());
-        let found = found.unwrap();
-        assert!(found.is_some());
-        let found = found.unwrap();
-        assert_eq!("other-james@example.com", found.email);
+        // a second worker, with a stale view of things, records nothing
+        assert!(db
+            .record_recurring_transaction(&r, next_on, new())
+            .unwrap()
+            .is_none());
+
+        let r = db
+            .find_recurring_transaction_by_uuid(jhunt.id, r.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(r.occurrences, 1);
+        assert_eq!(r.next_on, next_on);
+        assert!(db
+            .find_due_recurring_transactions(NaiveDate::from_ymd_opt(2020, 2, 28).unwrap())
+            .unwrap()
+            .is_empty());
+
+        let r = db
+            .update_recurring_transaction(
+                &r,
+                UpdateRecurringTransaction {
+                    every: None,
+                    starts_on: None,
+                    ends_on: Some(Some(NaiveDate::from_ymd_opt(2020, 2, 1).unwrap())),
+                    vars: None,
+                    next_on: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            r.ends_on,
+            Some(NaiveDate::from_ymd_opt(2020, 2, 1).unwrap())
+        );
+        assert!(db
+            .find_due_recurring_transactions(NaiveDate::from_ymd_opt(2020, 3, 1).unwrap())
+            .unwrap()
+            .is_empty());
+
+        db.delete_transaction_template(t.id).unwrap();
+        assert!(db
+            .find_recurring_transactions_for_collector(jhunt.id)
+            .unwrap()
+            .is_empty());
     }
 
     #[test]
-    fn should_create_a_collection_for_a_new_collector() {
+    pub fn can_value_graded_copies() {
         let (_tmp, db) = connect();
 
-        let jhunt = db.create_collector(
-            None,
-            NewCollector {
-                username: "jhunt",
-                email: "james@example.com",
-            },
-            None,
-        );
-        assert!(jhunt.is_ok());
-        let jhunt = jhunt.unwrap();
-
-        let collection = db.find_collection_by_uuid(jhunt.id);
-        assert!(collection.is_ok());
-        let collection = collection.unwrap();
-        assert!(collection.is_some());
-    }
+        let jhunt = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "jhunt",
+                    email: "james@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-    fn vec_to_set(src: Vec<String>) -> HashSet<String> {
-        let mut dst = HashSet::new();
-        for x in src {
-            dst.insert(x);
-        }
-        dst
+        db.set_slab_value(jhunt.id, "12345678", 150000, "PSA 9 comps")
+            .unwrap();
+        db.set_slab_value(jhunt.id, "00042", 2500, "").unwrap();
+        let v = db.set_slab_value(jhunt.id, "12345678", 175000, "").unwrap();
+        assert_eq!(v.value, 175000);
+        assert_eq!(v.notes, "");
+
+        let values = db.find_slab_values_for_collector(jhunt.id).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].cert, "00042");
+        assert_eq!(values[1].value, 175000);
+
+        db.clear_slab_value(jhunt.id, "00042").unwrap();
+        assert_eq!(db.find_slab_values_for_collector(jhunt.id).unwrap().len(), 1);
     }
 
     #[test]
-    pub fn can_create_a_transaction() {
+    pub fn can_stow_cards_in_nested_locations() {
         let (_tmp, db) = connect();
 
         let jhunt = db
@@ -1259,74 +5047,74 @@ mod test {
             )
             .unwrap();
 
-        let txn = db.create_transaction(
-            None,
-            NewTransaction {
-                summary: "opting for ixalan",
-                disposition: "buy",
-                notes: "this oughta be good",
-                collection: jhunt.id,
-                dated: &NaiveDate::from_ymd(2020, 01, 14),
-                gain: "1x XLN Opt\n",
-                loss: "",
-                paid: None,
-            },
-        );
-        assert!(txn.is_ok());
-        let txn = txn.unwrap();
-
-        assert_eq!(txn.collection, jhunt.id);
-        assert_eq!(txn.summary, "opting for ixalan");
-        assert_eq!(txn.notes, "this oughta be good");
-        assert_eq!(txn.disposition, "buy");
-        assert_eq!(txn.dated, NaiveDate::from_ymd(2020, 01, 14));
-        assert_eq!(txn.gain, "1x XLN Opt\n");
-        assert_eq!(txn.loss, "");
-        assert_eq!(txn.total_card_gain(), 1);
-        assert_eq!(txn.unique_card_gain(), 1);
-        assert_eq!(txn.total_card_loss(), 0);
-        assert_eq!(txn.unique_card_loss(), 0);
-
-        assert_eq!(txn.set_loss().len(), 0);
-        assert_eq!(txn.set_gain().len(), 1);
-        let sets = vec_to_set(txn.set_gain());
-        assert!(sets.contains("XLN"));
-
-        let updated = db
-            .update_transaction(
-                &txn,
-                UpdateTransaction {
-                    summary: None,
-                    notes: None,
-                    dated: None,
-                    disposition: None,
-                    gain: Some("1x XLN Opt\n3x GRN Radical Idea\n".to_string()),
-                    loss: None,
-                    paid: None,
+        let binder = db
+            .create_location(
+                None,
+                NewLocation {
+                    collector: jhunt.id,
+                    parent: None,
+                    name: "binder-3",
+                    kind: "binder",
+                    description: "",
+                },
+            )
+            .unwrap();
+        let page = db
+            .create_location(
+                None,
+                NewLocation {
+                    collector: jhunt.id,
+                    parent: Some(binder.id),
+                    name: "page-1",
+                    kind: "page",
+                    description: "",
                 },
             )
             .unwrap();
-        assert_eq!(updated.collection, jhunt.id);
-        assert_eq!(updated.dated, NaiveDate::from_ymd(2020, 01, 14));
-        assert_eq!(updated.summary, "opting for ixalan");
-        assert_eq!(updated.notes, "this oughta be good");
-        assert_eq!(updated.disposition, "buy");
-        assert_eq!(updated.gain, "1x XLN Opt\n3x GRN Radical Idea\n");
-        assert_eq!(updated.loss, "");
-        assert_eq!(updated.total_card_gain(), 4);
-        assert_eq!(updated.unique_card_gain(), 2);
-        assert_eq!(updated.total_card_loss(), 0);
-        assert_eq!(updated.unique_card_loss(), 0);
 
-        assert_eq!(updated.set_loss().len(), 0);
-        assert_eq!(updated.set_gain().len(), 2);
-        let sets = vec_to_set(updated.set_gain());
-        assert!(sets.contains("XLN"));
-        assert!(sets.contains("GRN"));
+        // names need only be unique among siblings
+        let location = |parent: Option<Uuid>, name: &'static str| NewLocation {
+            collector: jhunt.id,
+            parent,
+            name,
+            kind: if parent.is_some() { "page" } else { "binder" },
+            description: "",
+        };
+        let other = db
+            .create_location(None, location(None, "binder-4"))
+            .unwrap();
+        db.create_location(None, location(Some(other.id), "page-1"))
+            .unwrap();
+        assert!(db
+            .atomically(|| db.create_location(None, location(Some(binder.id), "page-1")))
+            .is_err());
+        assert!(db
+            .atomically(|| db.create_location(None, location(None, "binder-3")))
+            .is_err());
+        assert_eq!(db.find_locations_for_collector(jhunt.id).unwrap().len(), 4);
+
+        let stowed = |location: Uuid, quantity: i32| Stowed {
+            location,
+            pid: "mir-afterlife".to_string(),
+            var: "foil".to_string(),
+            quantity,
+        };
+        db.stow(&[stowed(binder.id, 3), stowed(page.id, 2)]).unwrap();
+        db.stow(&[stowed(binder.id, -1), stowed(page.id, -5)]).unwrap();
+
+        let stowage = db.find_stowage_for_collector(jhunt.id).unwrap();
+        assert_eq!(stowage.len(), 1);
+        assert_eq!(stowage[0].location, binder.id);
+        assert_eq!(stowage[0].quantity, 2);
+
+        // removing the binder takes its pages (and their cards) with it
+        db.delete_location(binder.id).unwrap();
+        assert!(db.find_location_by_uuid(jhunt.id, page.id).unwrap().is_none());
+        assert!(db.find_stowage_for_collector(jhunt.id).unwrap().is_empty());
     }
 
     #[test]
-    pub fn can_create_a_deck() {
+    pub fn can_settle_a_trade_between_collectors() {
         let (_tmp, db) = connect();
 
         let jhunt = db
@@ -1339,30 +5127,88 @@ mod test {
                 None,
             )
             .unwrap();
+        let other = db
+            .create_collector(
+                None,
+                NewCollector {
+                    username: "other",
+                    email: "other@example.com",
+                },
+                None,
+            )
+            .unwrap();
 
-        let deck = db.create_deck(
-            None,
-            NewDeck {
-                collector: jhunt.id,
-                code: "niv",
-                title: "Niv-Mizzet",
-                description: "Draw a card, 1 damage to you...",
-                main: "1x GRN Niv-Mizzet\n",
-                side: "1x RNA Niv-Mizzet, Parun\n",
-                maybe: "1x WAR Niv-Mizzet, Reborn\n",
-            },
-        );
-        assert!(deck.is_ok());
-        let deck = deck.unwrap();
-
-        assert_eq!(deck.collector, jhunt.id);
-        assert_eq!(deck.title, "Niv-Mizzet");
-        assert_eq!(deck.description, "Draw a card, 1 damage to you...");
-        assert_eq!(deck.main, "1x GRN Niv-Mizzet\n");
-        assert_eq!(deck.side, "1x RNA Niv-Mizzet, Parun\n");
-        assert_eq!(deck.maybe, "1x WAR Niv-Mizzet, Reborn\n");
-        assert_eq!(deck.lineage, deck.id);
-        assert_eq!(deck.ordinal, 0);
+        let proposal = || NewTrade {
+            proposer: jhunt.id,
+            recipient: other.id,
+            offer: "4x XLN Opt\n",
+            request: "1x GRN Radical Idea\n",
+            message: "opts for ideas?",
+            parent: None,
+        };
+        let trade = db.create_trade(None, proposal()).unwrap();
+        assert_eq!(trade.status, "proposed");
+        assert_eq!(db.find_trades_for_collector(other.id).unwrap().len(), 1);
+
+        // countering closes the original, and proposes the reverse
+        let counter = db
+            .counter_trade(
+                &trade,
+                None,
+                NewTrade {
+                    proposer: other.id,
+                    recipient: jhunt.id,
+                    offer: "1x GRN Radical Idea\n",
+                    request: "8x XLN Opt\n",
+                    message: "make it eight",
+                    parent: Some(trade.id),
+                },
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(counter.parent, Some(trade.id));
+        let trade = db.find_trade_by_uuid(jhunt.id, trade.id).unwrap().unwrap();
+        assert_eq!(trade.status, "countered");
+        assert!(db.decline_trade(&trade).unwrap().is_none());
+
+        let dated = NaiveDate::from_ymd_opt(2020, 9, 6).unwrap();
+        let accepted = db
+            .accept_trade(
+                &counter,
+                vec![],
+                NewTransaction {
+                    collection: other.id,
+                    summary: "Traded with jhunt",
+                    notes: "",
+                    dated: &dated,
+                    disposition: Disposition::Trade,
+                    gain: "8x XLN Opt\n",
+                    loss: "1x GRN Radical Idea\n",
+                    paid: None,
+                    created_by: None,
+                },
+                NewTransaction {
+                    collection: jhunt.id,
+                    summary: "Traded with other",
+                    notes: "",
+                    dated: &dated,
+                    disposition: Disposition::Trade,
+                    gain: "1x GRN Radical Idea\n",
+                    loss: "8x XLN Opt\n",
+                    paid: None,
+                    created_by: None,
+                },
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(accepted.status, "accepted");
+        assert!(accepted.proposer_transaction.is_some());
+        assert!(accepted.recipient_transaction.is_some());
+        assert_eq!(db.find_transactions_for_collection(jhunt.id).unwrap().len(), 1);
+        assert_eq!(db.find_transactions_for_collection(other.id).unwrap().len(), 1);
+
+        let collection = db.load_collection(other.id).unwrap();
+        assert_eq!(collection.owned().get("xln-opt-fake-id"), Some(&8));
     }
 
     #[test]
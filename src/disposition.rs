@@ -0,0 +1,217 @@
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Varchar;
+use std::io::Write;
+
+use crate::cdif;
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// What kind of transaction this is, which governs what it has to look
+// like (see check) and how it figures into cost basis.
+//
+// buy          cards bought, for `paid`.
+// sell         cards sold, for `paid`.
+// trade        cards traded away for other cards (plus `paid`, if any).
+// open         cards pulled from opened (or cracked) sealed product.
+// gift         cards given away, or received, for nothing.
+// loss         cards lost, stolen or damaged beyond keeping.
+// correction   a manual fix-up of the collection; anything goes.
+// import       the initial import of a pre-existing collection.
+// transfer     cards moved from one of a collector's collections to
+//              another; always one half of a pair (see db.rs).
+//
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, AsExpression, FromSqlRow)]
+#[sql_type = "Varchar"]
+pub enum Disposition {
+    Buy,
+    Sell,
+    Trade,
+    Open,
+    Gift,
+    Loss,
+    Correction,
+    Import,
+    Transfer,
+}
+
+pub const ALL: [Disposition; 9] = [
+    Disposition::Buy,
+    Disposition::Sell,
+    Disposition::Trade,
+    Disposition::Open,
+    Disposition::Gift,
+    Disposition::Loss,
+    Disposition::Correction,
+    Disposition::Import,
+    Disposition::Transfer,
+];
+
+impl Disposition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Disposition::Buy => "buy",
+            Disposition::Sell => "sell",
+            Disposition::Trade => "trade",
+            Disposition::Open => "open",
+            Disposition::Gift => "gift",
+            Disposition::Loss => "loss",
+            Disposition::Correction => "correction",
+            Disposition::Import => "import",
+            Disposition::Transfer => "transfer",
+        }
+    }
+
+    // Parse a disposition, as given to the API.  Case and surrounding
+    // whitespace are forgiven; anything else is not.
+    pub fn parse(s: &str) -> Result<Disposition> {
+        let d = s.trim().to_lowercase();
+        match ALL.iter().find(|k| k.as_str() == d) {
+            Some(k) => Ok(*k),
+            None => Err(format!("invalid-disposition: {}", s.trim()).into()),
+        }
+    }
+
+    // Check that a transaction's gains, losses and payment make sense
+    // for its kind, returning a failure code suitable for the API if not.
+    pub fn check(&self, gain: &cdif::File, loss: &cdif::File, paid: Option<i32>) -> Result<()> {
+        let (gains, loses) = (!gain.lines.is_empty(), !loss.lines.is_empty());
+        let fail = |what: &str| Err(format!("{}-{}", self.as_str(), what).into());
+        match self {
+            Disposition::Buy | Disposition::Import if !gains => fail("without-gain"),
+            Disposition::Buy | Disposition::Import if loses => fail("with-loss"),
+            Disposition::Sell | Disposition::Loss if !loses => fail("without-loss"),
+            Disposition::Sell | Disposition::Loss if gains => fail("with-gain"),
+            Disposition::Trade if !gains => fail("without-gain"),
+            Disposition::Trade if !loses => fail("without-loss"),
+            Disposition::Open if !gains => fail("without-gain"),
+            Disposition::Gift if !gains && !loses => fail("without-cards"),
+            Disposition::Transfer if gains == loses => fail("without-one-side"),
+            Disposition::Gift | Disposition::Loss | Disposition::Transfer if paid.is_some() => {
+                fail("with-payment")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Display for Disposition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl ToSql<Varchar, Pg> for Disposition {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <str as ToSql<Varchar, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Varchar, Pg> for Disposition {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let s = <String as FromSql<Varchar, Pg>>::from_sql(bytes)?;
+        Disposition::parse(&s).map_err(|e| e.to_string().into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    fn file(s: &str) -> cdif::File {
+        cdif::File::from_string(s).unwrap()
+    }
+
+    #[test]
+    fn should_parse_dispositions_strictly() {
+        assert_eq!(Disposition::Buy, Disposition::parse("buy").unwrap());
+        assert_eq!(Disposition::Sell, Disposition::parse(" Sell ").unwrap());
+        assert_eq!(
+            Disposition::Correction,
+            Disposition::parse("CORRECTION").unwrap()
+        );
+        for d in ALL.iter() {
+            assert_eq!(*d, Disposition::parse(&d.to_string()).unwrap());
+        }
+
+        assert_eq!(
+            "invalid-disposition: bought",
+            Disposition::parse("bought").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "invalid-disposition: ",
+            Disposition::parse("").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn should_validate_transactions_by_kind() {
+        let some = file("1x XLN Opt\n");
+        let none = file("");
+        let check = |d: Disposition, gain: &cdif::File, loss: &cdif::File, paid| {
+            d.check(gain, loss, paid).map_err(|e| e.to_string())
+        };
+
+        assert!(check(Disposition::Buy, &some, &none, Some(25)).is_ok());
+        assert_eq!(
+            Err("buy-without-gain".to_string()),
+            check(Disposition::Buy, &none, &none, Some(25))
+        );
+        assert_eq!(
+            Err("buy-with-loss".to_string()),
+            check(Disposition::Buy, &some, &some, Some(25))
+        );
+
+        assert!(check(Disposition::Sell, &none, &some, Some(25)).is_ok());
+        assert_eq!(
+            Err("sell-without-loss".to_string()),
+            check(Disposition::Sell, &none, &none, Some(25))
+        );
+        assert_eq!(
+            Err("sell-with-gain".to_string()),
+            check(Disposition::Sell, &some, &some, None)
+        );
+
+        assert!(check(Disposition::Trade, &some, &some, None).is_ok());
+        assert_eq!(
+            Err("trade-without-loss".to_string()),
+            check(Disposition::Trade, &some, &none, None)
+        );
+
+        assert!(check(Disposition::Gift, &none, &some, None).is_ok());
+        assert!(check(Disposition::Gift, &some, &none, None).is_ok());
+        assert_eq!(
+            Err("gift-with-payment".to_string()),
+            check(Disposition::Gift, &some, &none, Some(100))
+        );
+        assert_eq!(
+            Err("loss-with-payment".to_string()),
+            check(Disposition::Loss, &none, &some, Some(100))
+        );
+
+        assert!(check(Disposition::Open, &some, &none, None).is_ok());
+        assert!(check(Disposition::Import, &some, &none, None).is_ok());
+        assert!(check(Disposition::Correction, &none, &none, None).is_ok());
+
+        assert!(check(Disposition::Transfer, &none, &some, None).is_ok());
+        assert!(check(Disposition::Transfer, &some, &none, None).is_ok());
+        assert_eq!(
+            Err("transfer-without-one-side".to_string()),
+            check(Disposition::Transfer, &some, &some, None)
+        );
+        assert_eq!(
+            Err("transfer-without-one-side".to_string()),
+            check(Disposition::Transfer, &none, &none, None)
+        );
+        assert_eq!(
+            Err("transfer-with-payment".to_string()),
+            check(Disposition::Transfer, &none, &some, Some(100))
+        );
+    }
+}
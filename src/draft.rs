@@ -0,0 +1,539 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::card;
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// A table seats two to eight, and drafts one to five packs apiece.
+pub const SEATS: std::ops::RangeInclusive<usize> = 2..=8;
+pub const ROUNDS: std::ops::RangeInclusive<usize> = 1..=5;
+
+// Build a single booster pack for the given set, using the same slot
+// layout as the browser-side pack opener: one rare (or mythic), three
+// uncommons, ten non-basic commons, and a basic land.
+//
+// Returns the print ids of the cards in the pack.
+//
+pub fn booster<R: Rng>(pool: &card::Pool, code: &str, rng: &mut R) -> Result<Vec<String>> {
+    let set = match pool.sets.get(&code.to_uppercase()) {
+        Some(set) => set,
+        None => return Err(format!("no-such-set: {}", code).into()),
+    };
+
+    let basic = |print: &card::PrintCard| match pool.cards.get(&print.oid) {
+        Some(oracle) => oracle.type_line.contains("Basic Land"),
+        None => false,
+    };
+
+    let slot = |f: &dyn Fn(&card::PrintCard) -> bool| -> Vec<&card::PrintCard> {
+        set.cards.iter().filter(|c| f(c)).collect()
+    };
+    let slots = vec![
+        (
+            1,
+            slot(&|c| c.flags.rarity == "rare" || c.flags.rarity == "mythic"),
+        ),
+        (3, slot(&|c| c.flags.rarity == "uncommon")),
+        (10, slot(&|c| c.flags.rarity == "common" && !basic(c))),
+        (1, slot(&|c| basic(c))),
+    ];
+
+    let mut pack = vec![];
+    for (n, cards) in slots {
+        for print in cards.choose_multiple(rng, n) {
+            pack.push(print.id.to_string());
+        }
+    }
+    Ok(pack)
+}
+
+// Everything a bot drafter gets to look at when making a pick.
+pub struct Context<'a> {
+    pub pool: &'a card::Pool,
+    pub prices: &'a card::Prices,
+    prints: HashMap<&'a str, (&'a card::Set, &'a card::PrintCard)>,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(pool: &'a card::Pool, prices: &'a card::Prices) -> Self {
+        Self {
+            pool,
+            prices,
+            prints: pool.prints(),
+        }
+    }
+
+    pub fn print(&self, pid: &str) -> Option<&'a card::PrintCard> {
+        self.prints.get(pid).map(|(_, print)| *print)
+    }
+
+    pub fn oracle(&self, pid: &str) -> Option<&'a card::OracleCard> {
+        match self.print(pid) {
+            Some(print) => self.pool.cards.get(&print.oid),
+            None => None,
+        }
+    }
+
+    pub fn rarity(&self, pid: &str) -> u32 {
+        match self.print(pid) {
+            Some(print) => match print.flags.rarity.as_str() {
+                "common" => 1,
+                "uncommon" => 2,
+                "rare" => 3,
+                "mythic" => 4,
+                _ => 0,
+            },
+            None => 0,
+        }
+    }
+
+    pub fn price(&self, pid: &str) -> f64 {
+        self.prices.get(pid).and_then(|q| q.usd).unwrap_or(0.0)
+    }
+
+    // Format the CDIF identity of a print card, for deck construction.
+    pub fn cdif_id(&self, pid: &str) -> Option<String> {
+        match self.prints.get(pid) {
+            Some((set, print)) => self.pool.cdif_id(set, print),
+            None => None,
+        }
+    }
+}
+
+// A Strategy decides which card a bot drafter takes from the pack in
+// front of it, given the cards it has already picked.  Implementations
+// return the index (into `pack`) of the card to take.
+pub trait Strategy {
+    fn pick(&self, pack: &[String], picks: &[String], ctx: &Context) -> usize;
+}
+
+// Pick the highest-scoring card from the pack; ties go to the card
+// closest to the front of the pack.
+fn best_by<F: Fn(&str) -> f64>(pack: &[String], score: F) -> usize {
+    let mut best = 0;
+    let mut high = f64::MIN;
+    for (i, pid) in pack.iter().enumerate() {
+        let s = score(pid);
+        if s > high {
+            best = i;
+            high = s;
+        }
+    }
+    best
+}
+
+// Always take the rarest card in the pack.
+pub struct RarityFirst;
+
+impl Strategy for RarityFirst {
+    fn pick(&self, pack: &[String], _: &[String], ctx: &Context) -> usize {
+        best_by(pack, |pid| ctx.rarity(pid) as f64)
+    }
+}
+
+// Take the most expensive card in the pack, according to prices.json.
+pub struct PriceGreedy;
+
+impl Strategy for PriceGreedy {
+    fn pick(&self, pack: &[String], _: &[String], ctx: &Context) -> usize {
+        best_by(pack, |pid| ctx.price(pid))
+    }
+}
+
+// Draft like a person: take the best cards for the first few picks,
+// then settle into the two colors (by color identity) that the picks
+// so far lean towards, and stay there.
+pub struct ColorCommitment;
+
+impl ColorCommitment {
+    const COMMIT_AFTER: usize = 4;
+
+    fn colors(picks: &[String], ctx: &Context) -> Vec<String> {
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for pid in picks {
+            if let Some(oracle) = ctx.oracle(pid) {
+                for color in &oracle.color_identity {
+                    *seen.entry(color).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut seen: Vec<(&str, usize)> = seen.into_iter().collect();
+        seen.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        seen.iter().take(2).map(|(c, _)| c.to_string()).collect()
+    }
+}
+
+impl Strategy for ColorCommitment {
+    fn pick(&self, pack: &[String], picks: &[String], ctx: &Context) -> usize {
+        if picks.len() < Self::COMMIT_AFTER {
+            return best_by(pack, |pid| ctx.rarity(pid) as f64);
+        }
+
+        let colors = Self::colors(picks, ctx);
+        best_by(pack, |pid| {
+            let rarity = ctx.rarity(pid) as f64;
+            let identity = match ctx.oracle(pid) {
+                Some(oracle) => &oracle.color_identity,
+                None => return rarity,
+            };
+            let off = identity.iter().filter(|c| !colors.contains(c)).count() as f64;
+            if identity.is_empty() {
+                rarity + 1.0
+            } else if off == 0.0 {
+                rarity + 4.0
+            } else {
+                rarity - 2.0 * off
+            }
+        })
+    }
+}
+
+// Look up a bot Strategy by the name it is stored under in the pod.
+pub fn strategy(name: &str) -> Option<Box<dyn Strategy>> {
+    match name {
+        "rarity" => Some(Box::new(RarityFirst)),
+        "colors" => Some(Box::new(ColorCommitment)),
+        "price" => Some(Box::new(PriceGreedy)),
+        _ => None,
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Seat {
+    pub name: String,
+    pub collector: Option<String>, // uuid, for human drafters
+    pub bot: Option<String>,       // strategy name, for bot drafters
+    pub picked: bool,
+    pub pack: Vec<String>,
+    pub picks: Vec<String>,
+}
+
+impl Seat {
+    pub fn human(name: &str, collector: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            collector: collector.map(|s| s.to_string()),
+            bot: None,
+            picked: false,
+            pack: vec![],
+            picks: vec![],
+        }
+    }
+
+    pub fn bot(name: &str, strategy: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            collector: None,
+            bot: Some(strategy.to_string()),
+            picked: false,
+            pack: vec![],
+            picks: vec![],
+        }
+    }
+
+    pub fn is_bot(&self) -> bool {
+        self.bot.is_some()
+    }
+}
+
+// A Pod is a table of drafters (humans and bots), passing packs of a
+// single set around.  Packs go left in odd rounds and right in even
+// rounds, and every seat must pick before any pack is passed.
+//
+// The whole Pod serializes to JSON, so that it can be persisted
+// between picks.
+//
+#[derive(Serialize, Deserialize)]
+pub struct Pod {
+    pub set: String,
+    pub rounds: usize,
+    pub round: usize,
+    pub seats: Vec<Seat>,
+}
+
+impl Pod {
+    pub fn new(set: &str, seats: Vec<Seat>, rounds: usize) -> Result<Self> {
+        if seats.len() < *SEATS.start() {
+            return Err("not-enough-drafters".into());
+        }
+        if seats.len() > *SEATS.end() {
+            return Err("too-many-drafters".into());
+        }
+        if !ROUNDS.contains(&rounds) {
+            return Err("invalid-draft-rounds".into());
+        }
+        for seat in &seats {
+            if let Some(name) = &seat.bot {
+                if strategy(name).is_none() {
+                    return Err(format!("unknown-bot-strategy: {}", name).into());
+                }
+            }
+        }
+
+        Ok(Self {
+            set: set.to_uppercase(),
+            rounds,
+            round: 0,
+            seats,
+        })
+    }
+
+    // Crack a fresh pack for every seat, starting the next round.
+    pub fn open<R: Rng>(&mut self, pool: &card::Pool, rng: &mut R) -> Result<()> {
+        if self.round >= self.rounds {
+            return Err("draft-finished".into());
+        }
+        self.round += 1;
+        for seat in &mut self.seats {
+            seat.pack = booster(pool, &self.set, rng)?;
+            seat.picked = false;
+        }
+        Ok(())
+    }
+
+    pub fn finished(&self) -> bool {
+        self.round >= self.rounds && self.seats.iter().all(|s| s.pack.is_empty())
+    }
+
+    // Which (human) seats are holding up the table?
+    pub fn waiting_on(&self) -> Vec<usize> {
+        self.seats
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| !s.is_bot() && !s.picked && !s.pack.is_empty())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn pick(&mut self, seat: usize, pid: &str) -> Result<()> {
+        if self.finished() {
+            return Err("draft-finished".into());
+        }
+        let seat = match self.seats.get_mut(seat) {
+            Some(seat) => seat,
+            None => return Err("no-such-seat".into()),
+        };
+        if seat.picked {
+            return Err("already-picked".into());
+        }
+        match seat.pack.iter().position(|c| c == pid) {
+            Some(i) => {
+                seat.picks.push(seat.pack.remove(i));
+                seat.picked = true;
+                Ok(())
+            }
+            None => Err("card-not-in-pack".into()),
+        }
+    }
+
+    fn bots_pick(&mut self, ctx: &Context) {
+        for seat in &mut self.seats {
+            if seat.picked || seat.pack.is_empty() {
+                continue;
+            }
+            if let Some(bot) = seat.bot.as_ref().and_then(|name| strategy(name)) {
+                let i = bot.pick(&seat.pack, &seat.picks, ctx);
+                seat.picks.push(seat.pack.remove(i));
+                seat.picked = true;
+            }
+        }
+    }
+
+    fn pass(&mut self) {
+        let mut packs: Vec<Vec<String>> = self
+            .seats
+            .iter_mut()
+            .map(|s| std::mem::take(&mut s.pack))
+            .collect();
+
+        if self.round % 2 == 1 {
+            packs.rotate_right(1); // seat i passes left, to seat i+1
+        } else {
+            packs.rotate_left(1); // seat i passes right, to seat i-1
+        }
+
+        for (seat, pack) in self.seats.iter_mut().zip(packs) {
+            seat.pack = pack;
+            seat.picked = false;
+        }
+    }
+
+    // Let the bots make their picks, and keep the packs moving around
+    // the table until a human has to pick, or the draft is over.
+    pub fn advance<R: Rng>(&mut self, ctx: &Context, rng: &mut R) -> Result<()> {
+        loop {
+            if self.round == 0 {
+                self.open(ctx.pool, rng)?;
+            }
+
+            self.bots_pick(ctx);
+            if !self.waiting_on().is_empty() {
+                return Ok(());
+            }
+
+            self.pass();
+            if self.seats.iter().all(|s| s.pack.is_empty()) {
+                if self.round >= self.rounds {
+                    return Ok(());
+                }
+                self.open(ctx.pool, rng)?;
+            }
+        }
+    }
+
+    // Render a seat's picks as a CDIF deck list.
+    pub fn deck(&self, seat: usize, ctx: &Context) -> Result<String> {
+        let seat = match self.seats.get(seat) {
+            Some(seat) => seat,
+            None => return Err("no-such-seat".into()),
+        };
+
+        let mut counts: Vec<(String, u32)> = vec![];
+        for pid in &seat.picks {
+            let id = match ctx.cdif_id(pid) {
+                Some(id) => id,
+                None => return Err(format!("unable to find print card {}", pid).into()),
+            };
+            match counts.iter_mut().find(|(k, _)| *k == id) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((id, 1)),
+            }
+        }
+
+        Ok(counts
+            .iter()
+            .map(|(id, n)| format!("{}x {}\n", n, id))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cdif;
+    use crate::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn fixtures() -> (card::Pool, card::Prices) {
+        (
+            card::Pool::from_file("test/cards.json").expect("reading card pool"),
+            card::Prices::from_file("test/prices.json").expect("reading prices"),
+        )
+    }
+
+    #[test]
+    fn should_build_boosters_by_rarity() {
+        let (pool, prices) = fixtures();
+        let ctx = Context::new(&pool, &prices);
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let pack = booster(&pool, "mir", &mut rng).unwrap();
+        assert_eq!(pack.len(), 15);
+
+        let rarities: Vec<u32> = pack.iter().map(|pid| ctx.rarity(pid)).collect();
+        assert!(rarities[0] >= 3, "first slot should be rare or mythic");
+        assert_eq!(&rarities[1..4], &[2, 2, 2]);
+        assert_eq!(&rarities[4..14], &[1; 10]);
+
+        assert!(booster(&pool, "XYZZY", &mut rng).is_err());
+    }
+
+    #[test]
+    fn bots_should_pick_according_to_their_strategy() {
+        let (pool, prices) = fixtures();
+        let ctx = Context::new(&pool, &prices);
+        let mut rng = StdRng::seed_from_u64(7);
+        let pack = booster(&pool, "MIR", &mut rng).unwrap();
+
+        let pid = &pack[RarityFirst.pick(&pack, &[], &ctx)];
+        assert!(ctx.rarity(pid) >= 3);
+
+        let pid = &pack[PriceGreedy.pick(&pack, &[], &ctx)];
+        for other in &pack {
+            assert!(ctx.price(pid) >= ctx.price(other));
+        }
+    }
+
+    #[test]
+    fn should_refuse_to_seat_unknown_bots() {
+        assert!(Pod::new(
+            "MIR",
+            vec![Seat::bot("a", "rarity"), Seat::bot("b", "telepathy")],
+            3
+        )
+        .is_err());
+        assert!(Pod::new("MIR", vec![Seat::bot("a", "rarity")], 3).is_err());
+    }
+
+    #[test]
+    fn should_keep_tables_and_drafts_to_a_sensible_size() {
+        let table = |n| (0..n).map(|_| Seat::bot("bot", "rarity")).collect();
+        let made = |seats, rounds| match Pod::new("MIR", seats, rounds) {
+            Ok(_) => "ok".to_string(),
+            Err(e) => e.to_string(),
+        };
+
+        assert_eq!("not-enough-drafters", made(table(1), 3));
+        assert_eq!("ok", made(table(2), 3));
+        assert_eq!("ok", made(table(8), 3));
+        assert_eq!("too-many-drafters", made(table(9), 3));
+        assert_eq!("invalid-draft-rounds", made(table(8), 0));
+        assert_eq!("ok", made(table(8), 1));
+        assert_eq!("ok", made(table(8), 5));
+        assert_eq!("invalid-draft-rounds", made(table(8), 6));
+    }
+
+    #[test]
+    fn should_pass_packs_around_the_table() {
+        let (pool, prices) = fixtures();
+        let ctx = Context::new(&pool, &prices);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let mut pod = Pod::new(
+            "MIR",
+            vec![
+                Seat::human("jhunt", None),
+                Seat::bot("Bot 1", "rarity"),
+                Seat::bot("Bot 2", "colors"),
+            ],
+            3,
+        )
+        .unwrap();
+        pod.advance(&ctx, &mut rng).unwrap();
+        assert_eq!(pod.round, 1);
+        assert_eq!(pod.waiting_on(), vec![0]);
+        assert_eq!(pod.seats[1].picks.len(), 1);
+
+        // round one passes left, so the first bot gets our pack
+        let ours = pod.seats[0].pack.clone();
+        assert!(pod.pick(0, "not-a-card").is_err());
+        pod.pick(0, &ours[0]).unwrap();
+        assert!(pod.pick(0, &ours[1]).is_err());
+        pod.advance(&ctx, &mut rng).unwrap();
+        assert_eq!(pod.seats[1].pack.len(), 14 - 1);
+        assert!(ours[1..].iter().all(|c| pod.seats[1].pack.contains(c)
+            || pod.seats[1].picks.contains(c)));
+
+        while !pod.finished() {
+            let pid = pod.seats[0].pack[0].clone();
+            pod.pick(0, &pid).unwrap();
+            pod.advance(&ctx, &mut rng).unwrap();
+        }
+        for seat in &pod.seats {
+            assert_eq!(seat.picks.len(), 45);
+        }
+        assert!(pod.pick(0, "anything").is_err());
+
+        let deck = cdif::File::from_string(&pod.deck(2, &ctx).unwrap()).unwrap();
+        assert_eq!(deck.count().0, 45);
+    }
+}
@@ -0,0 +1,391 @@
+use chrono::NaiveDate;
+
+use crate::disposition::Disposition;
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// Transactions can be exported for bookkeeping (and the tax man) as:
+//
+// csv   one row per transaction, with a header row, for spreadsheets.
+//
+// ofx   an Open Financial Exchange (2.x, XML) bank statement, with one
+//       statement transaction per collection transaction, for importing
+//       into accounting software.
+//
+// qif   a Quicken Interchange Format cash account, for everything that
+//       doesn't understand OFX.
+//
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Csv,
+    Ofx,
+    Qif,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Result<Format> {
+        match s.to_lowercase().as_str() {
+            "" | "csv" => Ok(Format::Csv),
+            "ofx" => Ok(Format::Ofx),
+            "qif" => Ok(Format::Qif),
+            _ => Err(format!("invalid-export-format: {}", s).into()),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv",
+            Format::Ofx => "application/x-ofx",
+            Format::Qif => "application/qif",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Ofx => "ofx",
+            Format::Qif => "qif",
+        }
+    }
+}
+
+// Which transactions to export: those dated between `from` and `to`
+// (inclusive, either of which can be left open), with one of the given
+// dispositions (or any disposition, if none are given).
+#[derive(Default, Debug)]
+pub struct Filter {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub dispositions: Vec<Disposition>,
+}
+
+impl Filter {
+    // Parse a filter from (optional) query string values; dispositions
+    // are comma-separated, as in `disposition=buy,sell`.
+    pub fn parse(from: &str, to: &str, dispositions: &str) -> Result<Filter> {
+        let date = |s: &str, what: &str| -> Result<Option<NaiveDate>> {
+            match s.trim() {
+                "" => Ok(None),
+                s => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .map(Some)
+                    .map_err(|_| format!("invalid-{}-date: {}", what, s).into()),
+            }
+        };
+        let filter = Filter {
+            from: date(from, "from")?,
+            to: date(to, "to")?,
+            dispositions: dispositions
+                .split(',')
+                .filter(|d| !d.trim().is_empty())
+                .map(|d| Disposition::parse(d).map_err(|e| Error::from(e.to_string())))
+                .collect::<Result<_>>()?,
+        };
+        match (filter.from, filter.to) {
+            (Some(from), Some(to)) if from > to => Err("invalid-date-range".into()),
+            _ => Ok(filter),
+        }
+    }
+
+    pub fn matches(&self, dated: NaiveDate, disposition: Disposition) -> bool {
+        self.from.is_none_or(|from| dated >= from)
+            && self.to.is_none_or(|to| dated <= to)
+            && (self.dispositions.is_empty() || self.dispositions.contains(&disposition))
+    }
+}
+
+// A single transaction, as exported.  All amounts are in pennies.
+pub struct Row {
+    pub id: String,
+    pub dated: NaiveDate,
+    pub disposition: Disposition,
+    pub summary: String,
+    pub notes: String,
+    pub paid: Option<i32>,
+    pub cards_gained: u32,
+    pub unique_gained: u32,
+    pub cards_lost: u32,
+    pub unique_lost: u32,
+    pub basis: Option<i64>, // of the cards sold; sells only
+}
+
+impl Row {
+    // Money in (positive) or out (negative) of the collector's pocket.
+    // Sells bring money in; everything else that has a payment on it
+    // (buys, and whatever was paid on top of a trade) sends it out.
+    pub fn amount(&self) -> i64 {
+        let paid = self.paid.unwrap_or(0) as i64;
+        match self.disposition {
+            Disposition::Sell => paid,
+            _ => -paid,
+        }
+    }
+
+    // Realized gain (or loss) on a sale: proceeds less cost basis.
+    pub fn realized(&self) -> Option<i64> {
+        self.basis
+            .map(|basis| self.paid.unwrap_or(0) as i64 - basis)
+    }
+}
+
+fn money(pennies: i64) -> String {
+    let sign = if pennies < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, pennies.abs() / 100, pennies.abs() % 100)
+}
+
+fn field(s: &str) -> String {
+    if s.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn csv(rows: &[Row]) -> String {
+    let mut out = String::from(concat!(
+        "date,disposition,summary,notes,paid,amount,",
+        "cards_gained,unique_gained,cards_lost,unique_lost,",
+        "cost_basis,realized,id\r\n"
+    ));
+    for row in rows {
+        let opt = |v: Option<i64>| v.map(money).unwrap_or_default();
+        let fields = vec![
+            row.dated.format("%Y-%m-%d").to_string(),
+            field(row.disposition.as_str()),
+            field(&row.summary),
+            field(&row.notes),
+            opt(row.paid.map(|p| p as i64)),
+            money(row.amount()),
+            row.cards_gained.to_string(),
+            row.unique_gained.to_string(),
+            row.cards_lost.to_string(),
+            row.unique_lost.to_string(),
+            opt(row.basis),
+            opt(row.realized()),
+            row.id.to_string(),
+        ];
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// What a transaction was, in a few words, for ledgers that only have
+// room for a payee and a memo.
+fn memo(row: &Row) -> String {
+    let mut memo = format!(
+        "{}: {} cards in, {} out",
+        row.disposition, row.cards_gained, row.cards_lost
+    );
+    if let (Some(basis), Some(realized)) = (row.basis, row.realized()) {
+        memo.push_str(&format!(
+            "; cost basis {}, realized {}",
+            money(basis),
+            money(realized)
+        ));
+    }
+    if !row.notes.is_empty() {
+        memo.push_str("; ");
+        memo.push_str(&row.notes);
+    }
+    memo.replace(&['\n', '\r'][..], " ")
+}
+
+fn ofx(rows: &[Row], currency: &str) -> String {
+    let date = |d: NaiveDate| d.format("%Y%m%d").to_string();
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n",
+        "<?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" ",
+        "OLDFILEUID=\"NONE\" NEWFILEUID=\"NONE\"?>\n",
+        "<OFX>\n<BANKMSGSRSV1>\n<STMTTRNRS>\n<TRNUID>0</TRNUID>\n",
+        "<STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>\n",
+        "<STMTRS>\n",
+    ));
+    out.push_str(&format!(
+        "<CURDEF>{}</CURDEF>\n",
+        xml(&currency.to_uppercase())
+    ));
+    out.push_str(concat!(
+        "<BANKACCTFROM><BANKID>VCB</BANKID><ACCTID>collection</ACCTID>",
+        "<ACCTTYPE>CHECKING</ACCTTYPE></BANKACCTFROM>\n",
+    ));
+
+    out.push_str("<BANKTRANLIST>\n");
+    if let (Some(first), Some(last)) = (
+        rows.iter().map(|r| r.dated).min(),
+        rows.iter().map(|r| r.dated).max(),
+    ) {
+        out.push_str(&format!(
+            "<DTSTART>{}</DTSTART><DTEND>{}</DTEND>\n",
+            date(first),
+            date(last)
+        ));
+    }
+    for row in rows {
+        let amount = row.amount();
+        out.push_str("<STMTTRN>\n");
+        out.push_str(&format!(
+            "<TRNTYPE>{}</TRNTYPE>\n",
+            match amount {
+                a if a > 0 => "CREDIT",
+                a if a < 0 => "DEBIT",
+                _ => "OTHER",
+            }
+        ));
+        out.push_str(&format!("<DTPOSTED>{}</DTPOSTED>\n", date(row.dated)));
+        out.push_str(&format!("<TRNAMT>{}</TRNAMT>\n", money(amount)));
+        out.push_str(&format!("<FITID>{}</FITID>\n", xml(&row.id)));
+        // OFX names are limited to 32 characters.
+        out.push_str(&format!(
+            "<NAME>{}</NAME>\n",
+            xml(&row.summary.chars().take(32).collect::<String>())
+        ));
+        out.push_str(&format!("<MEMO>{}</MEMO>\n", xml(&memo(row))));
+        out.push_str("</STMTTRN>\n");
+    }
+    out.push_str("</BANKTRANLIST>\n");
+    out.push_str("</STMTRS>\n</STMTTRNRS>\n</BANKMSGSRSV1>\n</OFX>\n");
+    out
+}
+
+fn qif(rows: &[Row]) -> String {
+    let line = |s: &str| s.replace(&['\n', '\r'][..], " ");
+    let mut out = String::from("!Type:Cash\n");
+    for row in rows {
+        out.push_str(&format!("D{}\n", row.dated.format("%m/%d/%Y")));
+        out.push_str(&format!("T{}\n", money(row.amount())));
+        out.push_str(&format!("P{}\n", line(&row.summary)));
+        out.push_str(&format!("M{}\n", line(&memo(row))));
+        out.push_str(&format!("L{}\n", row.disposition));
+        out.push_str(&format!("N{}\n", row.id));
+        out.push_str("^\n");
+    }
+    out
+}
+
+// Render exported transactions, in date order, in the given format.
+// The currency is the collector's preferred one, i.e. `usd`.
+pub fn render(format: Format, rows: &[Row], currency: &str) -> String {
+    match format {
+        Format::Csv => csv(rows),
+        Format::Ofx => ofx(rows, currency),
+        Format::Qif => qif(rows),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn day(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, m, d).unwrap()
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row {
+                id: "b1".to_string(),
+                dated: day(3, 1),
+                disposition: Disposition::Buy,
+                summary: "Collection, from a friend".to_string(),
+                notes: "".to_string(),
+                paid: Some(12000),
+                cards_gained: 300,
+                unique_gained: 120,
+                cards_lost: 0,
+                unique_lost: 0,
+                basis: None,
+            },
+            Row {
+                id: "s1".to_string(),
+                dated: day(11, 15),
+                disposition: Disposition::Sell,
+                summary: "TCGplayer order #1234".to_string(),
+                notes: "shipped \"first class\"".to_string(),
+                paid: Some(4550),
+                cards_gained: 0,
+                unique_gained: 0,
+                cards_lost: 4,
+                unique_lost: 2,
+                basis: Some(5005),
+            },
+        ]
+    }
+
+    #[test]
+    fn should_filter_by_date_range_and_disposition() {
+        let f = Filter::parse("2020-03-01", "2020-03-31", "sell, Buy").unwrap();
+        assert!(f.matches(day(3, 1), Disposition::Buy));
+        assert!(f.matches(day(3, 31), Disposition::Sell));
+        assert!(!f.matches(day(4, 1), Disposition::Sell));
+        assert!(!f.matches(day(3, 2), Disposition::Trade));
+
+        let f = Filter::parse("", "", "").unwrap();
+        assert!(f.matches(day(1, 1), Disposition::Gift));
+
+        assert_eq!(
+            "invalid-from-date: 2020-13-01",
+            Filter::parse("2020-13-01", "", "").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "invalid-date-range",
+            Filter::parse("2020-04-01", "2020-03-01", "")
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "invalid-disposition: bought",
+            Filter::parse("", "", "buy,bought").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn should_export_csv_with_basis_for_sells() {
+        let out = render(Format::Csv, &rows(), "usd");
+        let lines: Vec<&str> = out.split("\r\n").collect();
+        assert_eq!(4, lines.len());
+        assert!(lines[0].starts_with("date,disposition,summary,notes,paid,amount,"));
+        assert_eq!(
+            r#"2020-03-01,buy,"Collection, from a friend",,120.00,-120.00,300,120,0,0,,,b1"#,
+            lines[1]
+        );
+        assert_eq!(
+            concat!(
+                r#"2020-11-15,sell,TCGplayer order #1234,"shipped ""first class""","#,
+                "45.50,45.50,0,0,4,2,50.05,-4.55,s1"
+            ),
+            lines[2]
+        );
+        assert_eq!("", lines[3]);
+    }
+
+    #[test]
+    fn should_export_ofx_and_qif_ledgers() {
+        let out = render(Format::Ofx, &rows(), "eur");
+        assert!(out.contains("<CURDEF>EUR</CURDEF>"));
+        assert!(out.contains("<DTSTART>20200301</DTSTART><DTEND>20201115</DTEND>"));
+        assert!(out.contains(
+            "<TRNTYPE>DEBIT</TRNTYPE>\n<DTPOSTED>20200301</DTPOSTED>\n<TRNAMT>-120.00</TRNAMT>"
+        ));
+        assert!(out.contains("<TRNTYPE>CREDIT</TRNTYPE>"));
+        assert!(out.contains(concat!(
+            "<MEMO>sell: 0 cards in, 4 out; cost basis 50.05, realized -4.55; ",
+            "shipped &quot;first class&quot;</MEMO>"
+        )));
+
+        let out = render(Format::Qif, &rows(), "usd");
+        assert!(out.starts_with("!Type:Cash\nD03/01/2020\nT-120.00\nPCollection, from a friend\n"));
+        assert!(out.contains("D11/15/2020\nT45.50\nPTCGplayer order #1234\n"));
+        assert_eq!(2, out.matches("^\n").count());
+    }
+}
@@ -0,0 +1,410 @@
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{card, cdif};
+use crate::query::{Query, Subject};
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// What it means to have "finished" a goal, for each card that the
+// goal's target selects.
+//
+// Goals created before the server started evaluating them use either
+// `owned` (which is the same as one-of-each) or an arbitrary success
+// query, which is checked print-by-print against the collection.
+//
+pub enum Kind {
+    OneOfEach,
+    Playset,
+    EveryArt,
+    EveryFoil,
+    Query(Query),
+}
+
+impl Kind {
+    pub fn parse(goal: &str) -> Result<Kind> {
+        Ok(match goal.trim().to_lowercase().as_str() {
+            "" | "owned" | "one-of-each" => Kind::OneOfEach,
+            "playset" => Kind::Playset,
+            "every-art" => Kind::EveryArt,
+            "every-foil" => Kind::EveryFoil,
+            _ => Kind::Query(Query::parse(goal).chain_err(|| "invalid-goal-query")?),
+        })
+    }
+
+    fn need(&self) -> u32 {
+        match self {
+            Kind::Playset => 4,
+            _ => 1,
+        }
+    }
+}
+
+// Targets are either a bare set code, or a full query.
+pub fn target(pool: &card::Pool, target: &str) -> Result<Query> {
+    let code = target.trim().to_uppercase();
+    if pool.sets.contains_key(&code) {
+        return Ok(Query::Set(code));
+    }
+    Query::parse(target).chain_err(|| "invalid-goal-target")
+}
+
+// Check that a goal's target and success criteria are well-formed,
+// without needing the card pool on hand.
+pub fn validate(target: &str, goal: &str) -> Result<()> {
+    Query::parse(target).chain_err(|| "invalid-goal-target")?;
+    Kind::parse(goal)?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct Missing {
+    pub id: String,   // print id
+    pub card: String, // CDIF identifier, i.e. "MIR *1 Afterlife"
+    pub need: u32,
+}
+
+pub struct Progress {
+    pub total: i32,
+    pub progress: i32,
+    pub missing: Vec<Missing>,
+    pub targets: HashSet<String>, // print ids selected by the target
+    pub finish: card::Finish,     // how the missing cards are finished
+}
+
+struct Unit<'a> {
+    set: &'a card::Set,
+    print: &'a card::PrintCard,
+    have: u32,
+}
+
+pub fn evaluate(
+    pool: &card::Pool,
+    collection: &card::Collection,
+    target: &str,
+    goal: &str,
+) -> Result<Progress> {
+    let query = self::target(pool, target)?;
+    let kind = Kind::parse(goal)?;
+    let owned = collection.owned();
+    let foils = collection.foils();
+
+    let mut sets: Vec<&card::Set> = pool.sets.values().collect();
+    sets.sort_by(|a, b| (&a.released_at, &a.code).cmp(&(&b.released_at, &b.code)));
+
+    let mut units: Vec<Unit> = vec![];
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    let mut targets = HashSet::new();
+    for set in sets {
+        for print in &set.cards {
+            let oracle = match pool.cards.get(&print.oid) {
+                Some(oracle) => oracle,
+                None => continue,
+            };
+            let card = Subject {
+                set,
+                print,
+                oracle,
+                owned: *owned.get(print.id.as_str()).unwrap_or(&0),
+            };
+            if !query.matches(&card) {
+                continue;
+            }
+            targets.insert(print.id.to_string());
+
+            let (key, have) = match &kind {
+                Kind::OneOfEach | Kind::Playset => (print.oid.as_str(), card.owned),
+                Kind::EveryArt => (print.illustration.as_str(), card.owned),
+                Kind::EveryFoil => (
+                    print.id.as_str(),
+                    *foils.get(print.id.as_str()).unwrap_or(&0),
+                ),
+                Kind::Query(q) => (print.id.as_str(), q.matches(&card) as u32),
+            };
+            match index.get(key) {
+                Some(&i) => units[i].have += have,
+                None => {
+                    index.insert(key, units.len());
+                    units.push(Unit { set, print, have });
+                }
+            }
+        }
+    }
+
+    let need = kind.need();
+    let mut missing = vec![];
+    for unit in &units {
+        if unit.have < need {
+            missing.push(Missing {
+                id: unit.print.id.to_string(),
+                card: pool
+                    .cdif_id(unit.set, unit.print)
+                    .unwrap_or_else(|| unit.print.id.to_string()),
+                need: need - unit.have,
+            });
+        }
+    }
+
+    Ok(Progress {
+        total: units.len() as i32,
+        progress: (units.len() - missing.len()) as i32,
+        missing,
+        targets,
+        finish: match kind {
+            Kind::EveryFoil => card::Finish::Foil,
+            _ => card::Finish::Nonfoil,
+        },
+    })
+}
+
+// A transaction that brought in cards toward a goal.
+pub struct Contribution {
+    pub dated: NaiveDate,
+    pub cards: u32, // how many of the gained cards count toward the goal
+    pub total: u32, // how many cards were gained, all told
+    pub paid: Option<i32>,
+}
+
+impl Contribution {
+    // Work out how much of a transaction's gains went toward a goal.
+    // Transactions that gained nothing the goal targets contribute nothing.
+    pub fn of(
+        progress: &Progress,
+        lookup: &card::Map,
+        gain: &cdif::File,
+        dated: NaiveDate,
+        paid: Option<i32>,
+    ) -> Option<Contribution> {
+        let mut cards = 0;
+        let mut total = 0;
+        for line in gain.lines.values() {
+            if line.quantity <= 0 {
+                continue;
+            }
+            total += line.quantity as u32;
+
+            let pid = lookup
+                .get(&format!("{} *{} {}", line.set, line.number, line.oracle))
+                .or_else(|| lookup.get(&format!("{} * {}", line.set, line.oracle)));
+            if let Some(pid) = pid {
+                if progress.targets.contains(pid) {
+                    cards += line.quantity as u32;
+                }
+            }
+        }
+
+        if cards == 0 {
+            return None;
+        }
+        Some(Contribution {
+            dated,
+            cards,
+            total,
+            paid,
+        })
+    }
+
+    // Transactions that bought more than just cards for this goal only
+    // count the goal's share of what was paid.
+    fn spent(&self) -> i32 {
+        match self.paid {
+            Some(paid) => (paid as f64 * self.cards as f64 / self.total as f64).round() as i32,
+            None => 0,
+        }
+    }
+}
+
+// Prices are quoted in (fractional) dollars, euros, or tickets; budgets
+// and payments are tracked in pennies (or cents) of the same currency.
+fn pennies(price: f64) -> i32 {
+    (price * 100.0).round() as i32
+}
+
+pub struct Plan {
+    pub remaining_cost: i32, // pennies, for the priced missing cards
+    pub unpriced: u32,       // missing cards we have no price for
+    pub spent: i32,          // pennies
+    pub projected: Option<NaiveDate>,
+}
+
+// Figure out what it will take to finish a goal: how much the missing
+// cards cost at current prices, how much has been spent so far, and
+// when the goal should be finished, if acquisitions keep up the same
+// pace they've had since the first contributing transaction.
+pub fn plan(
+    progress: &Progress,
+    prices: &card::Prices,
+    currency: card::Currency,
+    history: &[Contribution],
+    today: NaiveDate,
+) -> Plan {
+    let mut remaining_cost = 0;
+    let mut unpriced = 0;
+    let mut remaining = 0;
+    for missing in &progress.missing {
+        remaining += missing.need;
+        match prices
+            .get(&missing.id)
+            .and_then(|q| q.price(progress.finish, currency))
+        {
+            Some(each) => remaining_cost += pennies(each) * missing.need as i32,
+            None => unpriced += missing.need,
+        }
+    }
+
+    let spent = history.iter().map(|c| c.spent()).sum();
+
+    let acquired: u32 = history.iter().map(|c| c.cards).sum();
+    let projected = match history.iter().map(|c| c.dated).min() {
+        Some(first) if remaining > 0 && acquired > 0 => {
+            let days = (today - first).num_days().max(1) as f64;
+            let rate = acquired as f64 / days;
+            Some(today + Duration::days((remaining as f64 / rate).ceil() as i64))
+        }
+        _ => None,
+    };
+
+    Plan {
+        remaining_cost,
+        unpriced,
+        spent,
+        projected,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    fn pool() -> card::Pool {
+        card::Pool::from_file("test/cards.json").expect("reading card pool")
+    }
+
+    fn collection(pool: &card::Pool, cards: Vec<(u32, &str, Vec<&str>)>) -> card::Collection {
+        let mut collection = card::Collection::new();
+        for (n, name, var) in cards {
+            let (set, number) = name.split_at(3);
+            let print = pool.sets[set]
+                .cards
+                .iter()
+                .find(|c| c.number == number.trim())
+                .expect("finding print in test pool");
+            collection.cards.push((
+                n,
+                card::OwnedCard {
+                    pid: print.id.to_string(),
+                    var: var.iter().map(|v| v.to_string()).collect(),
+                    grade: None,
+                },
+            ));
+        }
+        collection
+    }
+
+    #[test]
+    fn should_evaluate_one_of_each_goals() {
+        let pool = pool();
+        let empty = card::Collection::new();
+
+        let p = evaluate(&pool, &empty, "mir", "owned").unwrap();
+        assert!(p.total > 0);
+        assert_eq!(0, p.progress);
+        assert_eq!(p.total as usize, p.missing.len());
+
+        let some = collection(&pool, vec![(1, "MIR 1", vec![]), (3, "MIR 2", vec![])]);
+        let p = evaluate(&pool, &some, "MIR", "one-of-each").unwrap();
+        assert_eq!(2, p.progress);
+        assert!(!p.missing.iter().any(|m| m.card == "MIR *1 Afterlife"));
+    }
+
+    #[test]
+    fn should_evaluate_playset_goals() {
+        let pool = pool();
+        let some = collection(&pool, vec![(1, "MIR 1", vec![]), (4, "MIR 2", vec![])]);
+
+        let p = evaluate(&pool, &some, "set:MIR and name:afterlife", "playset").unwrap();
+        assert_eq!(1, p.total);
+        assert_eq!(0, p.progress);
+        assert_eq!(3, p.missing[0].need);
+        assert_eq!("MIR *1 Afterlife", p.missing[0].card);
+
+        let p = evaluate(&pool, &some, "MIR", "playset").unwrap();
+        assert_eq!(1, p.progress);
+    }
+
+    #[test]
+    fn should_evaluate_foil_goals() {
+        let pool = pool();
+        let some = collection(
+            &pool,
+            vec![(2, "MIR 1", vec!["NM"]), (1, "MIR 2", vec!["NM", "foil"])],
+        );
+
+        let p = evaluate(&pool, &some, "MIR", "every-foil").unwrap();
+        assert_eq!(1, p.progress);
+        assert!(p.missing.iter().any(|m| m.card == "MIR *1 Afterlife"));
+    }
+
+    #[test]
+    fn should_evaluate_success_queries() {
+        let pool = pool();
+        let some = collection(&pool, vec![(1, "MIR 1", vec![])]);
+
+        let p = evaluate(&pool, &some, "set:MIR and type:instant", "owned").unwrap();
+        let q = evaluate(&pool, &some, "set:MIR and type:instant", "own:1+").unwrap();
+        assert_eq!(p.total, q.total);
+        assert_eq!(1, q.progress);
+
+        assert!(evaluate(&pool, &some, "set:MIR and", "owned").is_err());
+        assert!(evaluate(&pool, &some, "MIR", "in:cube").is_err());
+    }
+
+    #[test]
+    fn should_plan_goal_completion() {
+        let pool = pool();
+        let some = collection(&pool, vec![(1, "MIR 1", vec![])]);
+        let p = evaluate(&pool, &some, "set:MIR and name:afterlife", "playset").unwrap();
+        let pid = p.missing[0].id.to_string();
+
+        let mut lookup = card::Map::new();
+        lookup.insert("MIR *1 Afterlife".to_string(), pid.to_string());
+        let mut prices = card::Prices::new();
+        prices.insert(
+            pid.to_string(),
+            card::Quote {
+                usd: Some(0.25),
+                eur: Some(0.20),
+                ..card::Quote::default()
+            },
+        );
+
+        let day = |d| NaiveDate::from_ymd_opt(2020, 8, d).unwrap();
+        let gain = cdif::File::from_string("1x MIR *1 Afterlife\n3x MIR Ashen Powder\n").unwrap();
+        let history = vec![
+            Contribution::of(&p, &lookup, &gain, day(1), Some(400)).unwrap(),
+        ];
+        let other = cdif::File::from_string("2x MIR Ashen Powder\n").unwrap();
+        assert!(Contribution::of(&p, &lookup, &other, day(2), Some(100)).is_none());
+
+        let plan = plan(&p, &prices, card::Currency::Eur, &history, day(11));
+        assert_eq!(60, plan.remaining_cost);
+
+        let plan = super::plan(&p, &prices, card::Currency::Usd, &history, day(11));
+        assert_eq!(75, plan.remaining_cost);
+        assert_eq!(0, plan.unpriced);
+        assert_eq!(100, plan.spent);
+        // one card in ten days; three more to go
+        assert_eq!(Some(day(11) + Duration::days(30)), plan.projected);
+
+        let plan = super::plan(&p, &card::Prices::new(), card::Currency::Usd, &[], day(11));
+        assert_eq!(0, plan.remaining_cost);
+        assert_eq!(3, plan.unpriced);
+        assert_eq!(None, plan.projected);
+    }
+}
@@ -0,0 +1,347 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::card::{self, Grade};
+use crate::cdif;
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// Graded (slabbed) copies are tracked through CDIF local variants:
+//
+//   1x LEA Black Lotus | (grader: PSA) (grade: 9) (cert: 12345678)
+//   1x LEA Mox Pearl | (grader: BGS) (grade: 9.5) (subgrades: 9.5/9.5/10/9) (cert: 0012345)
+//
+// grader      the grading service; one of PSA, BGS, CGC or SGC.
+//
+// grade       the overall grade, from 1 to 10 (half grades allowed).
+//
+// subgrades   optional, slash-separated centering, corners, edges and
+//             surface grades, as given by BGS and CGC.
+//
+// cert        the certification number printed on the slab.  Each slab
+//             is its own copy, so a collector can only hold one slab
+//             with a given certification number.
+//
+pub const GRADERS: [&str; 4] = ["PSA", "BGS", "CGC", "SGC"];
+
+fn valid_grade(s: &str) -> bool {
+    match s.parse::<f64>() {
+        Ok(g) => (1.0..=10.0).contains(&g) && (g * 2.0).fract() == 0.0,
+        Err(_) => false,
+    }
+}
+
+fn graded(line: &cdif::Line) -> bool {
+    line.lvars
+        .iter()
+        .any(|(k, _)| Grade::VARS.contains(&k.as_str()))
+}
+
+// Check the grading details of a single line, returning the grade if
+// the line is graded at all.
+fn grade_of(line: &cdif::Line) -> Result<Option<Grade>> {
+    if !graded(line) {
+        return Ok(None);
+    }
+    let get = |k: &str| {
+        line.lvars
+            .iter()
+            .find(|(key, _)| key == k)
+            .map(|(_, v)| v.trim())
+            .filter(|v| !v.is_empty())
+    };
+    match get("grader") {
+        Some(g) if GRADERS.contains(&g.to_uppercase().as_str()) => (),
+        Some(g) => return Err(format!("invalid-grader: {}", g).into()),
+        None => return Err("missing-grader".into()),
+    }
+    match get("grade") {
+        Some(g) if valid_grade(g) => (),
+        Some(g) => return Err(format!("invalid-grade: {}", g).into()),
+        None => return Err("missing-grade".into()),
+    }
+    if let Some(sub) = get("subgrades") {
+        if !sub.split('/').all(|g| valid_grade(g.trim())) {
+            return Err(format!("invalid-subgrades: {}", sub).into());
+        }
+    }
+    if get("cert").is_none() {
+        return Err("missing-cert".into());
+    }
+    if line.quantity != 1 {
+        return Err(format!("graded-quantity-not-one: {}", line.id()).into());
+    }
+    Ok(Grade::from_lvars(&line.lvars))
+}
+
+// Check the graded copies in a transaction, before it is applied to
+// a collection.  Gained slabs must not share a certification number
+// with each other, or with any slab the collector already holds.  When
+// a transaction is being edited, `previous` is what it gained before
+// the edit; those slabs are already in the collection, but are about
+// to be replaced.
+pub fn check(
+    gain: &cdif::File,
+    loss: &cdif::File,
+    collection: &card::Collection,
+    previous: Option<&cdif::File>,
+) -> Result<()> {
+    for line in loss.lines.values() {
+        grade_of(line)?;
+    }
+
+    let replaced: HashSet<String> = previous
+        .map(|f| {
+            f.lines
+                .values()
+                .filter_map(|l| Grade::from_lvars(&l.lvars))
+                .map(|g| g.cert)
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut held: HashSet<String> = collection
+        .cards
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .filter_map(|(_, c)| c.grade.as_ref())
+        .map(|g| g.cert.to_string())
+        .filter(|cert| !replaced.contains(cert))
+        .collect();
+
+    let mut lines: Vec<&cdif::Line> = gain.lines.values().collect();
+    lines.sort_by_key(|l| l.id());
+    for line in lines {
+        if let Some(grade) = grade_of(line)? {
+            if !held.insert(grade.cert.to_string()) {
+                return Err(format!("duplicate-cert: {}", grade.cert).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct Slab {
+    pub card: String,
+    pub pid: String,
+    pub set: String,
+    pub grade: Grade,
+    pub raw: Option<i64>,   // market price of a raw copy, in pennies
+    pub value: Option<i64>, // manual valuation, in pennies
+}
+
+#[derive(Serialize)]
+pub struct Slabs {
+    pub slabs: Vec<Slab>,
+    pub value: i64, // total of the manual valuations
+    pub raw: i64,   // what the same cards would fetch raw
+    pub unvalued: usize,
+}
+
+// Report on the graded copies in a collection, separately from the
+// rest of it.  Graded copies are worth whatever the collector says they
+// are worth (`values`, by certification number, in pennies); the raw
+// price is given for comparison, and is never counted as the value of
+// a slab.
+pub fn report(
+    pool: &card::Pool,
+    prices: &card::Prices,
+    collection: &card::Collection,
+    values: &HashMap<String, i64>,
+    currency: card::Currency,
+) -> Slabs {
+    let prints = pool.prints();
+    let mut slabs: Vec<Slab> = collection
+        .cards
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .filter_map(|(_, c)| c.grade.as_ref().map(|g| (c, g)))
+        .map(|(c, grade)| {
+            let (card, set) = match prints.get(c.pid.as_str()) {
+                Some((set, print)) => (
+                    pool.cards
+                        .get(&print.oid)
+                        .map(|o| o.name.to_string())
+                        .unwrap_or_default(),
+                    set.code.to_string(),
+                ),
+                None => (String::new(), String::new()),
+            };
+            Slab {
+                card,
+                set,
+                pid: c.pid.to_string(),
+                grade: grade.clone(),
+                raw: prices
+                    .get(&c.pid)
+                    .and_then(|q| q.price(card::Finish::of(&c.var), currency))
+                    .map(|p| (p * 100.0).round() as i64),
+                value: values.get(&grade.cert).cloned(),
+            }
+        })
+        .collect();
+    slabs.sort_by(|a, b| {
+        b.value
+            .cmp(&a.value)
+            .then_with(|| a.card.cmp(&b.card))
+            .then_with(|| a.grade.cert.cmp(&b.grade.cert))
+    });
+
+    Slabs {
+        value: slabs.iter().filter_map(|s| s.value).sum(),
+        raw: slabs.iter().filter_map(|s| s.raw).sum(),
+        unvalued: slabs.iter().filter(|s| s.value.is_none()).count(),
+        slabs,
+    }
+}
+
+// Whether a collection holds a slab with the given certification number.
+pub fn holds(collection: &card::Collection, cert: &str) -> bool {
+    collection
+        .cards
+        .iter()
+        .any(|(n, c)| *n > 0 && c.grade.as_ref().is_some_and(|g| g.cert == cert))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    fn file(s: &str) -> cdif::File {
+        cdif::File::from_string(s).unwrap()
+    }
+
+    fn slab(lookup: &card::Map, card: &str, grade: &str, cert: &str) -> (u32, card::OwnedCard) {
+        (
+            1,
+            card::OwnedCard {
+                pid: lookup[card].to_string(),
+                var: vec![],
+                grade: Some(Grade {
+                    grader: "PSA".to_string(),
+                    grade: grade.to_string(),
+                    subgrades: vec![],
+                    cert: cert.to_string(),
+                }),
+            },
+        )
+    }
+
+    #[test]
+    fn should_validate_grading_details() {
+        let none = card::Collection::new();
+        let check = |gain: &str| {
+            super::check(&file(gain), &file(""), &none, None).map_err(|e| e.to_string())
+        };
+
+        assert!(check("1x MIR Afterlife\n").is_ok());
+        assert!(check("1x MIR Afterlife | (grader: psa) (grade: 8.5) (cert: 1)\n").is_ok());
+        assert!(check(
+            "1x MIR Afterlife | (grader: BGS) (grade: 9.5) (subgrades: 9.5/9/10/9.5) (cert: 2)\n"
+        )
+        .is_ok());
+
+        assert_eq!(
+            Err("invalid-grader: ACME".to_string()),
+            check("1x MIR Afterlife | (grader: ACME) (grade: 9) (cert: 1)\n")
+        );
+        assert_eq!(
+            Err("invalid-grade: 11".to_string()),
+            check("1x MIR Afterlife | (grader: PSA) (grade: 11) (cert: 1)\n")
+        );
+        assert_eq!(
+            Err("invalid-subgrades: 9/x".to_string()),
+            check("1x MIR Afterlife | (grader: BGS) (grade: 9) (subgrades: 9/x) (cert: 1)\n")
+        );
+        assert_eq!(
+            Err("missing-cert".to_string()),
+            check("1x MIR Afterlife | (grader: PSA) (grade: 9)\n")
+        );
+        assert_eq!(
+            Err(
+                "graded-quantity-not-one: MIR Afterlife | (grader: PSA) (grade: 9) (cert: 1)"
+                    .to_string()
+            ),
+            check("2x MIR Afterlife | (grader: PSA) (grade: 9) (cert: 1)\n")
+        );
+    }
+
+    #[test]
+    fn should_keep_certification_numbers_unique() {
+        let (_, lookup) = card::test_pool();
+        let mine = card::Collection {
+            cards: vec![slab(&lookup, "MIR * Afterlife", "9", "111")],
+        };
+
+        let gain = file("1x AVR Archangel | (grader: PSA) (grade: 10) (cert: 111)\n");
+        assert_eq!(
+            "duplicate-cert: 111",
+            check(&gain, &file(""), &mine, None)
+                .unwrap_err()
+                .to_string()
+        );
+
+        let gain = file(concat!(
+            "1x AVR Archangel | (grader: PSA) (grade: 10) (cert: 222)\n",
+            "1x VIS Archangel | (grader: CGC) (grade: 10) (cert: 222)\n",
+        ));
+        assert_eq!(
+            "duplicate-cert: 222",
+            check(&gain, &file(""), &mine, None)
+                .unwrap_err()
+                .to_string()
+        );
+
+        // editing the transaction that brought the slab in is fine
+        let previous = file("1x MIR Afterlife | (grader: PSA) (grade: 8) (cert: 111)\n");
+        let gain = file("1x MIR Afterlife | (grader: PSA) (grade: 9) (cert: 111)\n");
+        assert!(check(&gain, &file(""), &mine, Some(&previous)).is_ok());
+    }
+
+    #[test]
+    fn should_report_graded_copies_separately() {
+        let (pool, lookup) = card::test_pool();
+        let mut prices = card::Prices::new();
+        prices.insert(
+            lookup["MIR * Afterlife"].to_string(),
+            card::Quote {
+                usd: Some(0.50),
+                ..card::Quote::default()
+            },
+        );
+        let mine = card::Collection {
+            cards: vec![
+                (
+                    3,
+                    card::OwnedCard {
+                        pid: lookup["MIR * Afterlife"].to_string(),
+                        var: vec![],
+                        grade: None,
+                    },
+                ),
+                slab(&lookup, "MIR * Afterlife", "10", "111"),
+                slab(&lookup, "AVR * Archangel", "9", "222"),
+            ],
+        };
+        let mut values = HashMap::new();
+        values.insert("111".to_string(), 25000);
+
+        let report = report(&pool, &prices, &mine, &values, card::Currency::Usd);
+        assert_eq!(2, report.slabs.len());
+        assert_eq!("Afterlife", report.slabs[0].card);
+        assert_eq!(Some(25000), report.slabs[0].value);
+        assert_eq!(Some(50), report.slabs[0].raw);
+        assert_eq!("Archangel", report.slabs[1].card);
+        assert_eq!(None, report.slabs[1].value);
+        assert_eq!(25000, report.value);
+        assert_eq!(1, report.unvalued);
+
+        assert!(holds(&mine, "222"));
+        assert!(!holds(&mine, "333"));
+    }
+}
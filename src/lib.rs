@@ -3,13 +3,35 @@
 #[macro_use] extern crate error_chain;
 #[macro_use] extern crate hyper;
 
+pub mod account;
 pub mod api;
+pub mod basis;
 pub mod card;
 pub mod cdif;
 pub mod db;
+pub mod disposition;
+pub mod draft;
+pub mod export;
+pub mod goal;
+pub mod grading;
+pub mod loan;
+pub mod mail;
+pub mod matching;
+pub mod notify;
 pub mod schema;
 pub mod scryfall;
+pub mod sealed;
+pub mod share;
 pub mod prelude;
+pub mod query;
+pub mod role;
+pub mod storage;
+pub mod template;
+pub mod token;
+pub mod trade;
+pub mod valuation;
+pub mod watchlist;
+pub mod wishlist;
 
 pub mod cli;
 
@@ -207,4 +229,49 @@ mod test {
         assert_eq!(vec!["W"], card.color_identity);
         assert_eq!(vec!["W"], card.colors);
     }
+
+    #[test]
+    pub fn it_should_carry_every_price_variant_through_the_pool() {
+        let mut pool = card::Pool::new();
+        for (_, set) in scryfall::sets("test/scryfall/sets") {
+            pool.add_set(&set);
+        }
+
+        // MIR Afterlife
+        let quote = &pool.prices["4644694d-52e6-4d00-8cad-748899eeea84"];
+        assert!(quote.usd.is_some());
+        assert!(quote.eur.is_some());
+        assert_eq!(quote.usd, quote.price(card::Finish::Nonfoil, card::Currency::Usd));
+        assert!(quote.usd_foil.is_none());
+        assert_eq!(None, quote.price(card::Finish::Foil, card::Currency::Usd));
+        assert_eq!(None, quote.price(card::Finish::Etched, card::Currency::Eur));
+
+        assert_eq!(card::Finish::Foil, card::Finish::of(&["NM".to_string(), "foil".to_string()]));
+        assert_eq!(card::Finish::Etched, card::Finish::of(&["etched".to_string()]));
+        assert_eq!(card::Finish::Foil, card::Finish::of(&["Foil".to_string()]));
+        assert_eq!(card::Finish::Nonfoil, card::Finish::of(&[]));
+    }
+
+    #[test]
+    pub fn it_should_pad_price_history_from_before_etched_and_eur_foil_prices() {
+        let mut history: card::History = serde_json::from_str(
+            r#"{"dates":["2020-08-01"],"prints":{"x":{"usd":[1.0],"usd_foil":[null],"eur":[null],"tix":[null]}}}"#,
+        )
+        .unwrap();
+
+        let mut prices = card::Prices::new();
+        prices.insert(
+            "x".to_string(),
+            card::Quote {
+                usd_etched: Some(4.0),
+                ..card::Quote::default()
+            },
+        );
+        history.record(chrono::NaiveDate::from_ymd_opt(2020, 8, 2).unwrap(), &prices);
+
+        let series = &history.prints["x"];
+        assert_eq!(vec![Some(1.0), None], series.usd);
+        assert_eq!(vec![None, Some(4.0)], series.usd_etched);
+        assert_eq!(vec![None, None], series.eur_foil);
+    }
 }
@@ -0,0 +1,187 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::trade;
+use crate::valuation::{self, Holdings};
+use crate::{card, cdif, matching};
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// Take the cards out on loan away from what a collector holds.  Lent
+// cards are still owned, they just aren't around to be played with.
+pub fn on_hand(lookup: &card::Map, collection: &card::Collection, loans: &[&str]) -> Holdings {
+    let mut holdings = valuation::holdings(collection);
+    for cards in loans {
+        // loans were checked when they were made; anything that no longer
+        // resolves can't be taking up space in the collection either.
+        if let Ok(lines) = trade::resolve(lookup, cards) {
+            for line in lines {
+                if let Some(n) = holdings.get_mut(&line.key) {
+                    *n -= line.quantity;
+                }
+            }
+        }
+    }
+    holdings
+}
+
+// Check that a collector can lend out a pile of cards, given what they
+// already have out on loan, returning a failure code suitable for the
+// API if not.
+pub fn check(
+    lookup: &card::Map,
+    cards: &str,
+    collection: &card::Collection,
+    loans: &[&str],
+) -> Result<()> {
+    let lines = trade::resolve(lookup, cards).map_err(|e| Error::from(e.to_string()))?;
+    if lines.is_empty() {
+        return Err("empty-loan".into());
+    }
+    if !trade::shortfall(&on_hand(lookup, collection, loans), &lines).is_empty() {
+        return Err("loan-not-owned".into());
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct Availability {
+    pub card: String, // oracle name
+    pub oid: String,
+    pub need: u32,
+    pub owned: u32,
+    pub lent: u32,
+    pub available: u32,
+    pub short: u32,
+}
+
+// How much of a deck can be put together from the cards a collector has
+// on hand (in any printing), and how much is out on loan.
+pub fn availability(
+    pool: &card::Pool,
+    lookup: &card::Map,
+    collection: &card::Collection,
+    deck: &cdif::File,
+    loans: &[&str],
+) -> Vec<Availability> {
+    let prints = pool.prints();
+    let by_oracle = |holdings: &Holdings| {
+        let mut total: HashMap<&str, i64> = HashMap::new();
+        for ((pid, _), n) in holdings {
+            if let Some((_, print)) = prints.get(pid.as_str()) {
+                *total.entry(print.oid.as_str()).or_insert(0) += n;
+            }
+        }
+        total
+    };
+    let owned = by_oracle(&valuation::holdings(collection));
+    let on_hand = by_oracle(&on_hand(lookup, collection, loans));
+
+    let mut report: Vec<Availability> =
+        matching::deck_needs(pool, lookup, std::slice::from_ref(deck))
+            .into_iter()
+            .map(|(oid, need)| {
+                let have = *owned.get(oid.as_str()).unwrap_or(&0).max(&0) as u32;
+                let available = *on_hand.get(oid.as_str()).unwrap_or(&0).max(&0) as u32;
+                Availability {
+                    card: pool
+                        .cards
+                        .get(&oid)
+                        .map(|c| c.name.to_string())
+                        .unwrap_or_default(),
+                    oid,
+                    need,
+                    owned: have,
+                    lent: have.saturating_sub(available),
+                    available,
+                    short: need.saturating_sub(available),
+                }
+            })
+            .collect();
+    report.sort_by(|a, b| a.card.cmp(&b.card));
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    fn collection(lookup: &card::Map, cards: &[(u32, &str)]) -> card::Collection {
+        card::Collection {
+            cards: cards
+                .iter()
+                .map(|(n, card)| {
+                    (
+                        *n,
+                        card::OwnedCard {
+                            pid: lookup[*card].to_string(),
+                            var: vec![],
+                            grade: None,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn should_not_lend_cards_that_are_already_out() {
+        let (_, lookup) = card::test_pool();
+        let mine = collection(&lookup, &[(4, "MIR * Afterlife")]);
+
+        assert!(check(&lookup, "3x MIR Afterlife\n", &mine, &[]).is_ok());
+        assert!(check(
+            &lookup,
+            "1x MIR Afterlife\n",
+            &mine,
+            &["3x MIR Afterlife\n"]
+        )
+        .is_ok());
+        assert_eq!(
+            "loan-not-owned",
+            check(
+                &lookup,
+                "2x MIR Afterlife\n",
+                &mine,
+                &["3x MIR Afterlife\n"]
+            )
+            .unwrap_err()
+            .to_string()
+        );
+        assert_eq!(
+            "empty-loan",
+            check(&lookup, "", &mine, &[]).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn should_flag_lent_cards_as_unavailable_for_decks() {
+        let (pool, lookup) = card::test_pool();
+        let mine = collection(
+            &lookup,
+            &[
+                (2, "AVR * Archangel"),
+                (1, "VIS * Archangel"),
+                (4, "MIR * Afterlife"),
+            ],
+        );
+        let deck = cdif::File::from_string("4x AVR Archangel\n2x MIR Afterlife\n").unwrap();
+
+        let report = availability(&pool, &lookup, &mine, &deck, &["2x AVR Archangel\n"]);
+        assert_eq!(2, report.len());
+        assert_eq!("Afterlife", report[0].card);
+        assert_eq!(0, report[0].short);
+
+        assert_eq!("Archangel", report[1].card);
+        assert_eq!(4, report[1].need);
+        assert_eq!(3, report[1].owned);
+        assert_eq!(2, report[1].lent);
+        assert_eq!(1, report[1].available);
+        assert_eq!(3, report[1].short);
+    }
+}
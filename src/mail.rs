@@ -0,0 +1,157 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::notify::{self, Notifier};
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// Mail that has to reach a collector's inbox, like a password reset
+// link.  Unlike notifications, these can't go to a webhook instead;
+// the whole point is proving that someone can read mail sent to the
+// address.
+//
+pub struct Mail {
+    pub to: String, // email address
+    pub username: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub trait Mailer {
+    fn send(&self, mail: &Mail) -> Result<()>;
+}
+
+// Build the mailer configured via $VCB_MAILER:
+//
+//   log                   print who mail would have gone to
+//   smtp://host:port      send email through a (local) relay
+//   eml:/some/dir         write each message to an .eml file in a
+//                         directory, for exercising things offline
+//
+// When unset, mail is logged (but never its body, which has tokens in
+// it).  Emails are sent from the address in $VCB_MAIL_FROM.
+//
+pub fn from_env() -> Result<Box<dyn Mailer>> {
+    let spec = env::var("VCB_MAILER").unwrap_or_else(|_| "log".to_string());
+    let from = env::var("VCB_MAIL_FROM").unwrap_or_else(|_| "vault@localhost".to_string());
+    if spec == "log" {
+        Ok(Box::new(Log {}))
+    } else if let Some(relay) = spec.strip_prefix("smtp://") {
+        Ok(Box::new(notify::Smtp {
+            relay: relay.trim_end_matches('/').to_string(),
+            from,
+        }))
+    } else if let Some(dir) = spec.strip_prefix("eml:") {
+        Ok(Box::new(Eml {
+            dir: PathBuf::from(dir),
+            from,
+        }))
+    } else {
+        Err(format!("unrecognized mailer '{}'", spec).into())
+    }
+}
+
+pub struct Log {}
+
+impl Mailer for Log {
+    fn send(&self, mail: &Mail) -> Result<()> {
+        println!("mail {} <{}>: {}", mail.username, mail.to, mail.subject);
+        Ok(())
+    }
+}
+
+impl Mailer for notify::Smtp {
+    fn send(&self, mail: &Mail) -> Result<()> {
+        self.notify(&notify::Message {
+            to: mail.to.to_string(),
+            username: mail.username.to_string(),
+            subject: mail.subject.to_string(),
+            body: mail.body.to_string(),
+            data: serde_json::Value::Null,
+        })
+        .chain_err(|| "unable to send mail")
+    }
+}
+
+pub struct Eml {
+    pub dir: PathBuf,
+    pub from: String,
+}
+
+impl Eml {
+    pub fn render(&self, mail: &Mail) -> String {
+        let mut eml = format!(
+            "From: {}\r\nTo: {} <{}>\r\nSubject: {}\r\nDate: {}\r\n\r\n",
+            self.from,
+            mail.username,
+            mail.to,
+            mail.subject,
+            chrono::Utc::now().to_rfc2822()
+        );
+        for line in mail.body.lines() {
+            eml.push_str(line);
+            eml.push_str("\r\n");
+        }
+        eml
+    }
+}
+
+impl Mailer for Eml {
+    fn send(&self, mail: &Mail) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .chain_err(|| format!("unable to create mail directory {}", self.dir.display()))?;
+
+        // timestamp first, so that a directory listing is in the order
+        // the mail was sent.
+        let file = self.dir.join(format!(
+            "{}-{}.eml",
+            chrono::Utc::now().format("%Y%m%d%H%M%S%.6f"),
+            Uuid::new_v4()
+        ));
+        fs::write(&file, self.render(mail))
+            .chain_err(|| format!("unable to write mail to {}", file.display()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn should_write_mail_to_eml_files() {
+        let dir = TempDir::new("vcb-mail").expect("creating temporary directory");
+        let mailer = Eml {
+            dir: dir.path().join("outbox"),
+            from: "vault@example.com".to_string(),
+        };
+        mailer
+            .send(&Mail {
+                to: "jhunt@example.com".to_string(),
+                username: "jhunt".to_string(),
+                subject: "Reset your password".to_string(),
+                body: "Your token:\nabc.123\n".to_string(),
+            })
+            .expect("sending mail");
+
+        let files: Vec<_> = fs::read_dir(dir.path().join("outbox"))
+            .expect("listing outbox")
+            .map(|f| f.unwrap().path())
+            .collect();
+        assert_eq!(1, files.len());
+        assert_eq!("eml", files[0].extension().unwrap());
+
+        let eml = fs::read_to_string(&files[0]).expect("reading mail");
+        assert!(eml.starts_with(
+            "From: vault@example.com\r\nTo: jhunt <jhunt@example.com>\r\n\
+             Subject: Reset your password\r\n"
+        ));
+        assert!(eml.ends_with("\r\n\r\nYour token:\r\nabc.123\r\n"));
+    }
+}
@@ -0,0 +1,463 @@
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::valuation::Holdings;
+use crate::{card, cdif, goal, wishlist};
+
+// How many copies of a card collectors keep for themselves before any
+// are considered surplus, unless one of their decks calls for more.
+pub const PLAYSET: u32 = 4;
+
+// Something a collector is after: copies of a card (in any printing),
+// copies from a particular set, or copies of one exact print.
+pub struct Want {
+    pub oid: String,
+    pub set: Option<String>,
+    pub pid: Option<String>,
+    pub finish: Option<card::Finish>,
+    pub quantity: u32,
+}
+
+// Copies of a print that a collector can part with.
+pub struct Spare {
+    pub pid: String,
+    pub oid: String,
+    pub set: String,
+    pub finish: card::Finish,
+    pub quantity: u32,
+}
+
+impl Want {
+    fn accepts(&self, spare: &Spare) -> bool {
+        spare.oid == self.oid
+            && self.set.as_ref().is_none_or(|set| *set == spare.set)
+            && self.pid.as_ref().is_none_or(|pid| *pid == spare.pid)
+            && self.finish.is_none_or(|finish| finish == spare.finish)
+    }
+}
+
+// How many copies of each card (by oracle id) a collector's decks call
+// for.  Decks are built one at a time, so this is the most that any one
+// deck (main and sideboard) needs, not the sum across all of them.
+pub fn deck_needs(
+    pool: &card::Pool,
+    lookup: &card::Map,
+    decks: &[cdif::File],
+) -> HashMap<String, u32> {
+    let prints = pool.prints();
+    let mut needs = HashMap::new();
+    for deck in decks {
+        let mut need: HashMap<&str, u32> = HashMap::new();
+        for line in deck.lines.values() {
+            let print = lookup
+                .get(&format!("{} *{} {}", line.set, line.number, line.oracle))
+                .and_then(|pid| prints.get(pid.as_str()));
+            if let Some((_, print)) = print {
+                *need.entry(print.oid.as_str()).or_insert(0) += line.quantity.max(0) as u32;
+            }
+        }
+        for (oid, n) in need {
+            let most = needs.entry(oid.to_string()).or_insert(0);
+            *most = (*most).max(n);
+        }
+    }
+    needs
+}
+
+fn is_basic_land(pool: &card::Pool, oid: &str) -> bool {
+    pool.cards
+        .get(oid)
+        .is_some_and(|oracle| oracle.type_line.starts_with("Basic"))
+}
+
+// (print id, finish) -> (set code, copies held)
+type Copies<'a> = BTreeMap<(&'a str, card::Finish), (&'a str, u32)>;
+
+// Work out which copies a collector holds are surplus: anything beyond
+// a playset, or beyond what the collector's decks need.  Holdings should
+// be what is on hand, since cards out on loan can't be traded away.
+// When deciding which copies to keep, we go by print id (and then
+// finish), so that the answer is the same from one day to the next.
+// Basic lands are never worth matching on.
+pub fn surplus(pool: &card::Pool, holdings: &Holdings, needs: &HashMap<String, u32>) -> Vec<Spare> {
+    let prints = pool.prints();
+    let mut held: BTreeMap<&str, Copies> = BTreeMap::new();
+    for ((pid, finish), n) in holdings.iter().filter(|(_, n)| **n > 0) {
+        if let Some((set, print)) = prints.get(pid.as_str()) {
+            let copies = held
+                .entry(print.oid.as_str())
+                .or_default()
+                .entry((print.id.as_str(), *finish))
+                .or_insert((set.code.as_str(), 0));
+            copies.1 += *n as u32;
+        }
+    }
+
+    let mut spares = vec![];
+    for (oid, copies) in held {
+        if is_basic_land(pool, oid) {
+            continue;
+        }
+        let mut keep = PLAYSET.max(*needs.get(oid).unwrap_or(&0));
+        for ((pid, finish), (set, n)) in copies {
+            let kept = keep.min(n);
+            keep -= kept;
+            if n > kept {
+                spares.push(Spare {
+                    pid: pid.to_string(),
+                    oid: oid.to_string(),
+                    set: set.to_string(),
+                    finish,
+                    quantity: n - kept,
+                });
+            }
+        }
+    }
+    spares
+}
+
+// Work out what a collector is looking for: cards their decks need but
+// they don't have enough of, cards missing from their goals, and wishes
+// their collection doesn't already satisfy.
+pub fn wants(
+    pool: &card::Pool,
+    collection: &card::Collection,
+    needs: &HashMap<String, u32>,
+    goals: &[goal::Progress],
+    wishes: &[wishlist::Wish],
+) -> Vec<Want> {
+    let prints = pool.prints();
+    let mut held: HashMap<&str, u32> = HashMap::new();
+    for (n, owned) in &collection.cards {
+        if let Some((_, print)) = prints.get(owned.pid.as_str()) {
+            *held.entry(print.oid.as_str()).or_insert(0) += n;
+        }
+    }
+
+    let mut wants = vec![];
+    for (oid, need) in needs {
+        let have = *held.get(oid.as_str()).unwrap_or(&0);
+        if have < *need && !is_basic_land(pool, oid) {
+            wants.push(Want {
+                oid: oid.to_string(),
+                set: None,
+                pid: None,
+                finish: None,
+                quantity: need - have,
+            });
+        }
+    }
+
+    for progress in goals {
+        for missing in &progress.missing {
+            if let Some((_, print)) = prints.get(missing.id.as_str()) {
+                wants.push(Want {
+                    oid: print.oid.to_string(),
+                    set: None,
+                    pid: Some(missing.id.to_string()),
+                    finish: match progress.finish {
+                        card::Finish::Nonfoil => None,
+                        finish => Some(finish),
+                    },
+                    quantity: missing.need,
+                });
+            }
+        }
+    }
+
+    for (wish, status) in wishes
+        .iter()
+        .zip(wishlist::satisfaction(pool, collection, wishes))
+    {
+        if status.satisfied {
+            continue;
+        }
+        wants.push(Want {
+            oid: wish.oid.to_string(),
+            set: match wish.printing {
+                wishlist::Printing::Set => Some(wish.set.to_string()),
+                _ => None,
+            },
+            pid: match wish.printing {
+                wishlist::Printing::Exact => Some(wish.id.to_string()),
+                _ => None,
+            },
+            finish: wish.finish,
+            quantity: status.want - status.have,
+        });
+    }
+
+    // deck wants come out of a hash map; keep the order stable
+    wants.sort_by(|a, b| (&a.oid, &a.pid, &a.set).cmp(&(&b.oid, &b.pid, &b.set)));
+    wants
+}
+
+// Everything we need to know about a collector to match them up with
+// other collectors.
+pub struct Trader {
+    pub id: String, // uuid
+    pub username: String,
+    pub wants: Vec<Want>,
+    pub spares: Vec<Spare>,
+}
+
+#[derive(Serialize)]
+pub struct Match {
+    pub id: String,   // print id
+    pub card: String, // CDIF identifier, i.e. "MIR *1 Afterlife"
+    pub finish: card::Finish,
+    pub quantity: u32,
+    pub price: Option<i64>, // pennies, per card
+}
+
+#[derive(Serialize)]
+pub struct Partner {
+    pub collector: String, // uuid
+    pub username: String,
+    pub get: Vec<Match>,  // what they can give us
+    pub give: Vec<Match>, // what we can give them
+    pub get_value: i64,   // pennies
+    pub give_value: i64,  // pennies
+    pub mutual: i64,      // pennies; the lesser of the two values
+}
+
+// Match up one collector's wants with another's spares.  Each spare
+// copy can only satisfy one want.
+fn pair(
+    pool: &card::Pool,
+    prices: &card::Prices,
+    currency: card::Currency,
+    wants: &[Want],
+    spares: &[Spare],
+) -> (Vec<Match>, i64) {
+    let prints = pool.prints();
+    let mut left: Vec<u32> = spares.iter().map(|s| s.quantity).collect();
+    let mut taken: BTreeMap<usize, u32> = BTreeMap::new();
+
+    for want in wants {
+        let mut need = want.quantity;
+        for (i, spare) in spares.iter().enumerate() {
+            if need == 0 {
+                break;
+            }
+            if left[i] == 0 || !want.accepts(spare) {
+                continue;
+            }
+            let n = need.min(left[i]);
+            left[i] -= n;
+            need -= n;
+            *taken.entry(i).or_insert(0) += n;
+        }
+    }
+
+    let mut value = 0;
+    let mut matches = vec![];
+    for (i, n) in taken {
+        let spare = &spares[i];
+        let price = prices
+            .get(&spare.pid)
+            .and_then(|q| q.price(spare.finish, currency))
+            .map(|p| (p * 100.0).round() as i64);
+        value += price.unwrap_or(0) * n as i64;
+        matches.push(Match {
+            id: spare.pid.to_string(),
+            card: prints
+                .get(spare.pid.as_str())
+                .and_then(|(set, print)| pool.cdif_id(set, print))
+                .unwrap_or_else(|| spare.pid.to_string()),
+            finish: spare.finish,
+            quantity: n,
+            price,
+        });
+    }
+    (matches, value)
+}
+
+// Find the collectors we can trade with, and rank them by how much
+// value would change hands in both directions.  A partner who has lots
+// we want, but wants nothing of ours, ranks below one where both of us
+// come away with something.
+pub fn rank(
+    pool: &card::Pool,
+    prices: &card::Prices,
+    currency: card::Currency,
+    me: &Trader,
+    others: &[Trader],
+    limit: usize,
+) -> Vec<Partner> {
+    let mut partners = vec![];
+    for other in others.iter().filter(|o| o.id != me.id) {
+        let (get, get_value) = pair(pool, prices, currency, &me.wants, &other.spares);
+        let (give, give_value) = pair(pool, prices, currency, &other.wants, &me.spares);
+        if get.is_empty() && give.is_empty() {
+            continue;
+        }
+        partners.push(Partner {
+            collector: other.id.to_string(),
+            username: other.username.to_string(),
+            get,
+            give,
+            get_value,
+            give_value,
+            mutual: get_value.min(give_value),
+        });
+    }
+
+    partners.sort_by_key(|p| {
+        (
+            Reverse(p.mutual),
+            Reverse(p.get_value + p.give_value),
+            p.username.to_string(),
+        )
+    });
+    partners.truncate(limit);
+    partners
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+    use crate::{loan, valuation};
+
+    fn print<'a>(pool: &'a card::Pool, set: &str, number: &str) -> &'a card::PrintCard {
+        pool.sets[set]
+            .cards
+            .iter()
+            .find(|c| c.number == number)
+            .expect("finding print in test pool")
+    }
+
+    fn collection(pool: &card::Pool, cards: &[(u32, &str, &str, &[&str])]) -> card::Collection {
+        card::Collection {
+            cards: cards
+                .iter()
+                .map(|(n, set, number, var)| {
+                    (
+                        *n,
+                        card::OwnedCard {
+                            pid: print(pool, set, number).id.to_string(),
+                            var: var.iter().map(|v| v.to_string()).collect(),
+                            grade: None,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn deck(cards: &str) -> cdif::File {
+        cdif::File::from_string(cards).unwrap()
+    }
+
+    #[test]
+    fn should_only_count_the_hungriest_deck() {
+        let (pool, lookup) = card::test_pool();
+        let decks = vec![
+            deck("4x AVR Archangel\n2x MIR *1 Afterlife\n"),
+            deck("3x VIS Archangel\n3x AVR Archangel\n"),
+        ];
+        let needs = deck_needs(&pool, &lookup, &decks);
+        assert_eq!(Some(&6), needs.get(&print(&pool, "AVR", "5").oid));
+        assert_eq!(Some(&2), needs.get(&print(&pool, "MIR", "1").oid));
+    }
+
+    #[test]
+    fn should_find_surplus_beyond_a_playset_or_deck() {
+        let (pool, lookup) = card::test_pool();
+        let mine = collection(
+            &pool,
+            &[
+                (5, "AVR", "5", &[]),
+                (2, "VIS", "1", &[]),
+                (7, "MIR", "1", &[]),
+            ],
+        );
+        let needs = deck_needs(&pool, &lookup, &[deck("6x MIR Afterlife\n")]);
+
+        let spares = surplus(&pool, &valuation::holdings(&mine), &needs);
+        let total = |oid: &str| -> u32 {
+            spares
+                .iter()
+                .filter(|s| s.oid == oid)
+                .map(|s| s.quantity)
+                .sum()
+        };
+        assert_eq!(3, total(&print(&pool, "AVR", "5").oid));
+        assert_eq!(1, total(&print(&pool, "MIR", "1").oid));
+
+        // copies out on loan can't be traded away
+        let on_hand = loan::on_hand(&lookup, &mine, &["2x AVR Archangel\n"]);
+        let spares = surplus(&pool, &on_hand, &needs);
+        let total = |oid: &str| -> u32 {
+            spares
+                .iter()
+                .filter(|s| s.oid == oid)
+                .map(|s| s.quantity)
+                .sum()
+        };
+        assert_eq!(1, total(&print(&pool, "AVR", "5").oid));
+        assert_eq!(1, total(&print(&pool, "MIR", "1").oid));
+    }
+
+    #[test]
+    fn should_rank_partners_by_mutual_value() {
+        let (pool, lookup) = card::test_pool();
+        let angel = print(&pool, "AVR", "5");
+        let afterlife = print(&pool, "MIR", "1");
+
+        let mut prices = card::Prices::new();
+        for (pid, usd) in &[(&angel.id, 2.00), (&afterlife.id, 0.50)] {
+            prices.insert(
+                pid.to_string(),
+                card::Quote {
+                    usd: Some(*usd),
+                    ..card::Quote::default()
+                },
+            );
+        }
+
+        let trader = |id: &str, cards: &[(u32, &str, &str, &[&str])], decks: &[&str]| {
+            let collection = collection(&pool, cards);
+            let decks: Vec<cdif::File> = decks.iter().map(|d| deck(d)).collect();
+            let needs = deck_needs(&pool, &lookup, &decks);
+            Trader {
+                id: id.to_string(),
+                username: id.to_string(),
+                wants: wants(&pool, &collection, &needs, &[], &[]),
+                spares: surplus(&pool, &valuation::holdings(&collection), &needs),
+            }
+        };
+
+        // I need two Afterlifes, and have Archangels to spare
+        let me = trader("me", &[(6, "AVR", "5", &[])], &["2x MIR Afterlife\n"]);
+        // they have Afterlifes to spare, and want Archangels
+        let both = trader("both", &[(8, "MIR", "1", &[])], &["6x AVR Archangel\n"]);
+        // they have Afterlifes to spare, but want nothing of mine
+        let one = trader("one", &[(9, "MIR", "1", &[])], &[]);
+        // nothing to offer, nothing wanted
+        let none = trader("none", &[(1, "AVR", "5", &[])], &[]);
+
+        let partners = rank(
+            &pool,
+            &prices,
+            card::Currency::Usd,
+            &me,
+            &[one, none, both],
+            10,
+        );
+        assert_eq!(2, partners.len());
+
+        assert_eq!("both", partners[0].username);
+        assert_eq!(2, partners[0].get[0].quantity);
+        assert_eq!(100, partners[0].get_value);
+        assert_eq!(2, partners[0].give[0].quantity);
+        assert_eq!(400, partners[0].give_value);
+        assert_eq!(100, partners[0].mutual);
+
+        assert_eq!("one", partners[1].username);
+        assert_eq!(0, partners[1].mutual);
+        assert!(partners[1].give.is_empty());
+    }
+}
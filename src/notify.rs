@@ -0,0 +1,252 @@
+use serde::Serialize;
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// Something worth telling a collector about, like a price alert.  The
+// subject and body are for humans; the data is for machines (webhooks
+// get it verbatim, as JSON).
+//
+#[derive(Serialize)]
+pub struct Message {
+    pub to: String, // email address
+    pub username: String,
+    pub subject: String,
+    pub body: String,
+    pub data: serde_json::Value,
+}
+
+pub trait Notifier {
+    fn notify(&self, msg: &Message) -> Result<()>;
+}
+
+// Build the notifier configured via $VCB_NOTIFIER:
+//
+//   log                   print notifications to standard output
+//   smtp://host:port      send email through a (local) relay
+//   http://host/path      POST notifications as JSON to a webhook
+//
+// When unset, notifications are logged.  Emails are sent from the
+// address in $VCB_MAIL_FROM.
+//
+pub fn from_env() -> Result<Box<dyn Notifier>> {
+    let spec = env::var("VCB_NOTIFIER").unwrap_or_else(|_| "log".to_string());
+    if spec == "log" {
+        Ok(Box::new(Log {}))
+    } else if let Some(relay) = spec.strip_prefix("smtp://") {
+        Ok(Box::new(Smtp {
+            relay: relay.trim_end_matches('/').to_string(),
+            from: env::var("VCB_MAIL_FROM").unwrap_or_else(|_| "vault@localhost".to_string()),
+        }))
+    } else if spec.starts_with("http://") {
+        Ok(Box::new(Webhook { url: spec }))
+    } else {
+        Err(format!("unrecognized notifier '{}'", spec).into())
+    }
+}
+
+pub struct Log {}
+
+impl Notifier for Log {
+    fn notify(&self, msg: &Message) -> Result<()> {
+        println!("notify {} <{}>: {}", msg.username, msg.to, msg.subject);
+        Ok(())
+    }
+}
+
+pub struct Smtp {
+    pub relay: String, // host:port
+    pub from: String,
+}
+
+// Read an SMTP reply (which may span several lines) and make sure it
+// has the status code we were expecting.
+fn reply<R: BufRead>(r: &mut R, want: &str) -> Result<()> {
+    loop {
+        let mut line = String::new();
+        r.read_line(&mut line)
+            .chain_err(|| "unable to read reply from smtp relay")?;
+        if !line.starts_with(want) {
+            return Err(format!("unexpected reply from smtp relay: {}", line.trim_end()).into());
+        }
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok(());
+        }
+    }
+}
+
+impl Smtp {
+    fn send(&self, msg: &Message) -> Result<()> {
+        let stream = TcpStream::connect(&self.relay)
+            .chain_err(|| format!("unable to connect to smtp relay {}", self.relay))?;
+        let mut r = BufReader::new(
+            stream
+                .try_clone()
+                .chain_err(|| "unable to set up smtp connection")?,
+        );
+        let mut w = stream;
+
+        let mut say = |line: &str, want: &str, r: &mut BufReader<TcpStream>| -> Result<()> {
+            write!(w, "{}\r\n", line).chain_err(|| "unable to write to smtp relay")?;
+            reply(r, want)
+        };
+
+        reply(&mut r, "220")?;
+        say("HELO localhost", "250", &mut r)?;
+        say(&format!("MAIL FROM:<{}>", self.from), "250", &mut r)?;
+        say(&format!("RCPT TO:<{}>", msg.to), "250", &mut r)?;
+        say("DATA", "354", &mut r)?;
+
+        let mut data = format!(
+            "From: {}\r\nTo: {} <{}>\r\nSubject: {}\r\nDate: {}\r\n\r\n",
+            self.from,
+            msg.username,
+            msg.to,
+            msg.subject,
+            chrono::Utc::now().to_rfc2822()
+        );
+        for line in msg.body.lines() {
+            // dot-stuffing, so a line with a lone "." doesn't end DATA
+            if line.starts_with('.') {
+                data.push('.');
+            }
+            data.push_str(line);
+            data.push_str("\r\n");
+        }
+        data.push('.');
+        say(&data, "250", &mut r)?;
+        say("QUIT", "221", &mut r)
+    }
+}
+
+impl Notifier for Smtp {
+    fn notify(&self, msg: &Message) -> Result<()> {
+        self.send(msg)
+            .chain_err(|| format!("unable to email {}", msg.to))
+    }
+}
+
+pub struct Webhook {
+    pub url: String,
+}
+
+impl Notifier for Webhook {
+    fn notify(&self, msg: &Message) -> Result<()> {
+        use hyper::header::ContentType;
+
+        let body = serde_json::to_string(msg).chain_err(|| "unable to encode notification")?;
+        let res = hyper::Client::new()
+            .post(&self.url)
+            .header(ContentType::json())
+            .body(&body)
+            .send()
+            .chain_err(|| format!("unable to post notification to {}", self.url))?;
+
+        if !res.status.is_success() {
+            return Err(format!("webhook {} responded {}", self.url, res.status).into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn message() -> Message {
+        Message {
+            to: "jhunt@example.com".to_string(),
+            username: "jhunt".to_string(),
+            subject: "Lightning Bolt is under $1.00".to_string(),
+            body: "It's only 99 cents.\n.\nBuy now!".to_string(),
+            data: serde_json::json!({"price": 99}),
+        }
+    }
+
+    #[test]
+    fn should_send_email_through_an_smtp_relay() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let relay = listener.local_addr().unwrap().to_string();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut r = BufReader::new(stream.try_clone().unwrap());
+            let mut w = stream;
+            let mut seen = vec![];
+            let mut data = false;
+
+            write!(w, "220 relay ESMTP\r\n").unwrap();
+            loop {
+                let mut line = String::new();
+                if r.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end().to_string();
+                seen.push(line.to_string());
+                if data {
+                    if line == "." {
+                        data = false;
+                        write!(w, "250 queued\r\n").unwrap();
+                    }
+                } else if line == "DATA" {
+                    data = true;
+                    write!(w, "354 go ahead\r\n").unwrap();
+                } else if line == "QUIT" {
+                    write!(w, "221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    write!(w, "250-ok\r\n250 ok\r\n").unwrap();
+                }
+            }
+            seen
+        });
+
+        let smtp = Smtp {
+            relay,
+            from: "vault@example.com".to_string(),
+        };
+        smtp.notify(&message()).unwrap();
+
+        let seen = server.join().unwrap();
+        assert!(seen.contains(&"MAIL FROM:<vault@example.com>".to_string()));
+        assert!(seen.contains(&"RCPT TO:<jhunt@example.com>".to_string()));
+        assert!(seen.contains(&"Subject: Lightning Bolt is under $1.00".to_string()));
+        assert!(seen.contains(&"..".to_string()));
+        assert_eq!(Some(&"QUIT".to_string()), seen.last());
+    }
+
+    #[test]
+    fn should_post_notifications_to_a_webhook() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = vec![0; 4096];
+            let mut req = String::new();
+            while !req.contains("\"price\":99") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                req.push_str(&String::from_utf8_lossy(&buf[..n]));
+            }
+            write!(stream, "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n").unwrap();
+            req
+        });
+
+        Webhook { url }.notify(&message()).unwrap();
+        let req = server.join().unwrap();
+        assert!(req.starts_with("POST /hook HTTP/1.1"));
+        assert!(req.contains("\"to\":\"jhunt@example.com\""));
+    }
+}
@@ -0,0 +1,518 @@
+use regex::Regex;
+
+use crate::card;
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// A single card, as seen by a query: the print, the set it was printed
+// in, its oracle card, and how many copies the collector owns.
+pub struct Subject<'a> {
+    pub set: &'a card::Set,
+    pub print: &'a card::PrintCard,
+    pub oracle: &'a card::OracleCard,
+    pub owned: u32,
+}
+
+pub enum Range {
+    Lt(f64),
+    Le(f64),
+    Gt(f64),
+    Ge(f64),
+    Eq(f64),
+}
+
+impl Range {
+    fn parse(s: &str) -> Result<Self> {
+        let re = Regex::new(r"^([<>]?=?)?([0-9]+(\.[0-9]+)?)(\+)?$").unwrap();
+        let m = match re.captures(s) {
+            Some(m) => m,
+            None => return Err(format!("bad-range: {}", s).into()),
+        };
+
+        let n = m[2].parse::<f64>().unwrap();
+        Ok(match (m.get(1).map(|op| op.as_str()), m.get(4)) {
+            (Some(""), Some(_)) | (None, Some(_)) => Range::Ge(n),
+            (_, Some(_)) => return Err(format!("bad-range: {}", s).into()),
+            (Some("<"), _) => Range::Lt(n),
+            (Some("<="), _) => Range::Le(n),
+            (Some(">"), _) => Range::Gt(n),
+            (Some(">="), _) => Range::Ge(n),
+            _ => Range::Eq(n),
+        })
+    }
+
+    fn matches(&self, v: f64) -> bool {
+        match self {
+            Range::Lt(n) => v < *n,
+            Range::Le(n) => v <= *n,
+            Range::Gt(n) => v > *n,
+            Range::Ge(n) => v >= *n,
+            Range::Eq(n) => (v - n).abs() < f64::EPSILON,
+        }
+    }
+}
+
+// Color predicates either name an exact color combination (a guild,
+// shard, or wedge name, or "none"), or list the colors that must all
+// be present (i.e. `@wu` matches anything that is at least white and
+// blue).
+pub enum Colors {
+    Exactly(String),
+    AtLeast(String),
+}
+
+impl Colors {
+    fn parse(s: &str) -> Self {
+        let s = s.to_uppercase();
+        let exact = match s.as_str() {
+            "NONE" => "",
+            "WHITE" => "W",
+            "BLUE" => "U",
+            "BLACK" => "B",
+            "RED" => "R",
+            "GREEN" => "G",
+            "AZORIUS" => "WU",
+            "DIMIR" => "UB",
+            "RAKDOS" => "BR",
+            "GRUUL" => "RG",
+            "SELESNYA" => "WG",
+            "ORZHOV" => "WB",
+            "IZZET" => "UR",
+            "GOLGARI" => "BG",
+            "BOROS" => "WR",
+            "SIMIC" => "UG",
+            "BANT" => "WUG",
+            "ESPER" => "WUB",
+            "GRIXIS" => "UBR",
+            "JUND" => "BRG",
+            "NAYA" => "WRG",
+            "ABZAN" => "WBG",
+            "JESKAI" => "WUR",
+            "SULTAI" => "UBG",
+            "MARDU" => "WBR",
+            "TEMUR" => "URG",
+            _ => return Colors::AtLeast(s),
+        };
+        Colors::Exactly(exact.to_string())
+    }
+
+    fn matches(&self, colors: &[String]) -> bool {
+        let has = |c: char| colors.iter().any(|have| have.contains(c));
+        match self {
+            Colors::Exactly(want) => "WUBRG".chars().all(|c| want.contains(c) == has(c)),
+            Colors::AtLeast(want) => want.chars().all(has),
+        }
+    }
+}
+
+// A parsed query, in the same (subset of the) query language that the
+// web interface uses for searching the vault.
+//
+// Qualifiers that depend on state the server does not have (decks, the
+// `unique:` de-duplicator, etc.) are rejected at parse time.
+//
+pub enum Query {
+    Set(String),
+    Card(String),
+    Type(Regex),
+    Name(Regex),
+    Oracle(Regex),
+    Flavor(Regex),
+    Artist(Regex),
+    Rarity(String),
+    Layout(String),
+    Border(String),
+    Legal(String),
+    Color(Colors),
+    NumColors(Range),
+    Cmc(Range),
+    Own(Range),
+    FullArt(bool),
+    Oversized(bool),
+    Variant(bool),
+    Spotlight(bool),
+    Reserved(bool),
+    Reprint(bool),
+    Vanilla(bool),
+
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Qualifier(String),
+    Identifier(String),
+    Str(String),
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let keyword = Regex::new(r"(?i)^(and|or|not|owned|have|need|vanilla|reprint|reserved|spotlight|fullart|oversized|variant)\b").unwrap();
+    let qualifier = Regex::new(r"^([a-zA-Z0-9_-]+):\s*").unwrap();
+    let identifier = Regex::new(r"^([^\s()]+)\s*").unwrap();
+
+    let mut tok = vec![];
+    let mut s = s.trim_start();
+    while !s.is_empty() {
+        let c = s.chars().next().unwrap();
+        match c {
+            '"' | '\'' => match s[1..].find(c) {
+                Some(i) => {
+                    tok.push(Token::Str(s[1..i + 1].to_string()));
+                    s = &s[i + 2..];
+                }
+                None => return Err("unterminated quoted string".into()),
+            },
+            '(' => {
+                tok.push(Token::Open);
+                s = &s[1..];
+            }
+            ')' => {
+                tok.push(Token::Close);
+                s = &s[1..];
+            }
+            '@' | '+' | '=' => {
+                tok.push(Token::Qualifier(
+                    match c {
+                        '@' => "COLOR",
+                        '+' => "ORACLE",
+                        _ => "RARITY",
+                    }
+                    .to_string(),
+                ));
+                s = &s[1..];
+            }
+            '!' => {
+                tok.push(Token::Not);
+                s = &s[1..];
+            }
+            _ => {
+                if let Some(m) = keyword.captures(s) {
+                    if !s[m[0].len()..].starts_with(':') {
+                        let kw = m[1].to_uppercase();
+                        match kw.as_str() {
+                            "AND" => tok.push(Token::And),
+                            "OR" => tok.push(Token::Or),
+                            "NOT" => tok.push(Token::Not),
+                            "OWNED" | "HAVE" | "NEED" => {
+                                tok.push(Token::Qualifier("OWN".to_string()));
+                                tok.push(Token::Identifier(
+                                    if kw == "NEED" { "0" } else { "1+" }.to_string(),
+                                ));
+                            }
+                            _ => {
+                                tok.push(Token::Qualifier(kw));
+                                tok.push(Token::Identifier("y".to_string()));
+                            }
+                        }
+                        s = s[m[0].len()..].trim_start();
+                        continue;
+                    }
+                }
+
+                if let Some(m) = qualifier.captures(s) {
+                    tok.push(Token::Qualifier(match m[1].to_uppercase().as_str() {
+                        "POWER" | "ATTACK" => "P".to_string(),
+                        "TOUGHNESS" | "DEFENSE" => "T".to_string(),
+                        q => q.to_string(),
+                    }));
+                    s = &s[m[0].len()..];
+                } else if let Some(m) = identifier.captures(s) {
+                    // collapse adjacent identifiers into a single, multi-word name
+                    match tok.last_mut() {
+                        Some(Token::Identifier(last)) => {
+                            last.push(' ');
+                            last.push_str(&m[1]);
+                        }
+                        _ => tok.push(Token::Identifier(m[1].to_string())),
+                    }
+                    s = &s[m[0].len()..];
+                } else {
+                    return Err(format!("unrecognized query fragment: [{}]", s).into());
+                }
+            }
+        }
+        s = s.trim_start();
+    }
+    Ok(tok)
+}
+
+fn pattern(v: &str, strict: bool) -> Result<Regex> {
+    Regex::new(&format!(r"{}\b{}\b", if strict { "" } else { "(?i)" }, v))
+        .chain_err(|| format!("bad pattern: {}", v))
+}
+
+fn boolish(v: &str) -> bool {
+    matches!(v.to_lowercase().as_str(), "y" | "yes" | "1")
+}
+
+fn rarity(v: &str) -> String {
+    match v.to_lowercase().as_str() {
+        "c" | "common" => "common",
+        "u" | "uncommon" => "uncommon",
+        "r" | "rare" => "rare",
+        "m" | "mythic" => "mythic",
+        _ => "",
+    }
+    .to_string()
+}
+
+fn qualified(q: &str, v: &str, strict: bool) -> Result<Query> {
+    Ok(match q {
+        "SET" => Query::Set(v.to_uppercase()),
+        "CARD" => Query::Card(v.to_lowercase()),
+        "TYPE" => Query::Type(pattern(v, strict)?),
+        "NAME" => Query::Name(pattern(v, strict)?),
+        "ORACLE" => Query::Oracle(pattern(v, strict)?),
+        "FLAVOR" => Query::Flavor(pattern(v, strict)?),
+        "ARTIST" => Query::Artist(pattern(v, strict)?),
+        "RARITY" => Query::Rarity(rarity(v)),
+        "LAYOUT" => Query::Layout(v.to_lowercase()),
+        "BORDER" => Query::Border(v.to_lowercase()),
+        "LEGAL" => Query::Legal(v.to_lowercase().replace('-', "_")),
+        "COLOR" => Query::Color(Colors::parse(v)),
+        "COLORS" => Query::NumColors(Range::parse(v)?),
+        "CMC" => Query::Cmc(Range::parse(v)?),
+        "OWN" => Query::Own(Range::parse(v)?),
+        "FULLART" => Query::FullArt(boolish(v)),
+        "OVERSIZED" => Query::Oversized(boolish(v)),
+        "VARIANT" => Query::Variant(boolish(v)),
+        "SPOTLIGHT" => Query::Spotlight(boolish(v)),
+        "RESERVED" => Query::Reserved(boolish(v)),
+        "REPRINT" => Query::Reprint(boolish(v)),
+        "VANILLA" => Query::Vanilla(boolish(v)),
+        _ => return Err(format!("unsupported query qualifier: {}", q.to_lowercase()).into()),
+    })
+}
+
+fn precedence(t: &Token) -> u8 {
+    match t {
+        Token::Not => 2,
+        _ => 1,
+    }
+}
+
+fn reduce(op: Token, data: &mut Vec<Query>) -> Result<()> {
+    match op {
+        Token::Not => match data.pop() {
+            Some(a) => data.push(Query::Not(Box::new(a))),
+            None => return Err("stack underflow (data) for NOT op".into()),
+        },
+        Token::And | Token::Or => {
+            if data.len() < 2 {
+                return Err(format!("stack underflow (data) for {:?} op", op).into());
+            }
+            let b = Box::new(data.pop().unwrap());
+            let a = Box::new(data.pop().unwrap());
+            data.push(if op == Token::And {
+                Query::And(a, b)
+            } else {
+                Query::Or(a, b)
+            });
+        }
+        _ => return Err("mismatched parentheses".into()),
+    }
+    Ok(())
+}
+
+impl Query {
+    pub fn parse(s: &str) -> Result<Query> {
+        let mut tok = tokenize(s)?.into_iter();
+        let mut data: Vec<Query> = vec![];
+        let mut ops: Vec<Token> = vec![];
+
+        while let Some(t) = tok.next() {
+            match t {
+                Token::Identifier(v) => data.push(Query::Name(pattern(&v, false)?)),
+                Token::Str(v) => data.push(Query::Name(pattern(&v, true)?)),
+                Token::Qualifier(q) => match tok.next() {
+                    Some(Token::Identifier(v)) => data.push(qualified(&q, &v, false)?),
+                    Some(Token::Str(v)) => data.push(qualified(&q, &v, true)?),
+                    _ => return Err(format!("bad value for {} qualifier", q).into()),
+                },
+                Token::And | Token::Or | Token::Not => {
+                    while let Some(op) = ops.last() {
+                        if *op == Token::Open || t == Token::Not || precedence(op) < precedence(&t) {
+                            break;
+                        }
+                        reduce(ops.pop().unwrap(), &mut data)?;
+                    }
+                    ops.push(t);
+                }
+                Token::Open => ops.push(t),
+                Token::Close => loop {
+                    match ops.pop() {
+                        Some(Token::Open) => break,
+                        Some(op) => reduce(op, &mut data)?,
+                        None => return Err("mismatched parentheses".into()),
+                    }
+                },
+            }
+        }
+        while let Some(op) = ops.pop() {
+            reduce(op, &mut data)?;
+        }
+
+        if data.len() != 1 {
+            return Err("syntax error".into());
+        }
+        Ok(data.pop().unwrap())
+    }
+
+    pub fn matches(&self, card: &Subject) -> bool {
+        let flags = &card.print.flags;
+        match self {
+            Query::Set(code) => *code == card.set.code,
+            Query::Card(id) => {
+                *id == card.print.id || *id == card.print.oid || *id == card.print.number
+            }
+            Query::Type(re) => re.is_match(&card.oracle.type_line.replace('—', "-")),
+            Query::Name(re) => re.is_match(&card.oracle.name),
+            Query::Oracle(re) => re.is_match(&card.oracle.text),
+            Query::Flavor(re) => re.is_match(&card.print.flavor),
+            Query::Artist(re) => re.is_match(&card.print.artist),
+            Query::Rarity(r) => *r == flags.rarity,
+            Query::Layout(l) => *l == card.print.layout,
+            Query::Border(b) => *b == card.print.border,
+            Query::Legal(format) => {
+                let legal = &card.oracle.legal;
+                match format.as_str() {
+                    "brawl" => legal.brawl,
+                    "edh" | "commander" => legal.commander,
+                    "duel" => legal.duel,
+                    "frontier" => legal.frontier,
+                    "future" => legal.future,
+                    "historic" => legal.historic,
+                    "legacy" => legal.legacy,
+                    "modern" => legal.modern,
+                    "old_school" => legal.old_school,
+                    "pauper" => legal.pauper,
+                    "penny" => legal.penny,
+                    "pioneer" => legal.pioneer,
+                    "standard" => legal.standard,
+                    "vintage" => legal.vintage,
+                    _ => false,
+                }
+            }
+            Query::Color(c) => c.matches(&card.oracle.colors),
+            Query::NumColors(r) => r.matches(card.oracle.colors.len() as f64),
+            Query::Cmc(r) => r.matches(card.oracle.cmc as f64),
+            Query::Own(r) => r.matches(card.owned as f64),
+            Query::FullArt(b) => *b == flags.full_art,
+            Query::Oversized(b) => *b == flags.oversized,
+            Query::Variant(b) => *b == flags.variation,
+            Query::Spotlight(b) => *b == flags.story_spotlight,
+            Query::Reserved(b) => *b == flags.reserved,
+            Query::Reprint(b) => *b == flags.reprint,
+            Query::Vanilla(b) => *b == card.oracle.text.is_empty(),
+
+            Query::Not(a) => !a.matches(card),
+            Query::And(a, b) => a.matches(card) && b.matches(card),
+            Query::Or(a, b) => a.matches(card) || b.matches(card),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    fn pool() -> card::Pool {
+        card::Pool::from_file("test/cards.json").expect("reading card pool")
+    }
+
+    fn search(pool: &card::Pool, q: &str) -> Vec<String> {
+        let q = Query::parse(q).unwrap();
+        let mut found = vec![];
+        for set in pool.sets.values() {
+            for print in &set.cards {
+                let card = Subject {
+                    set,
+                    print,
+                    oracle: &pool.cards[&print.oid],
+                    owned: 0,
+                };
+                if q.matches(&card) {
+                    found.push(format!("{} {}", set.code, card.oracle.name));
+                }
+            }
+        }
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn should_tokenize_queries() {
+        assert_eq!(
+            tokenize("set:MIR and =rare").unwrap(),
+            vec![
+                Token::Qualifier("SET".to_string()),
+                Token::Identifier("MIR".to_string()),
+                Token::And,
+                Token::Qualifier("RARITY".to_string()),
+                Token::Identifier("rare".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokenize("!(owned)").unwrap(),
+            vec![
+                Token::Not,
+                Token::Open,
+                Token::Qualifier("OWN".to_string()),
+                Token::Identifier("1+".to_string()),
+                Token::Close,
+            ]
+        );
+        assert_eq!(
+            tokenize("archangel avacyn").unwrap(),
+            vec![Token::Identifier("archangel avacyn".to_string())]
+        );
+        assert!(tokenize("'unterminated").is_err());
+    }
+
+    #[test]
+    fn should_reject_bad_queries() {
+        assert!(Query::parse("set:MIR and").is_err());
+        assert!(Query::parse("(set:MIR").is_err());
+        assert!(Query::parse("set:MIR)").is_err());
+        assert!(Query::parse("in:cube").is_err());
+        assert!(Query::parse("cmc:lots").is_err());
+    }
+
+    #[test]
+    fn should_match_cards_in_the_pool() {
+        let pool = pool();
+
+        assert_eq!(
+            search(&pool, "set:mir and name:afterlife"),
+            vec!["MIR Afterlife"]
+        );
+        assert_eq!(
+            search(&pool, "\"Archangel Avacyn\""),
+            vec!["SOI Archangel Avacyn // Avacyn, the Purifier"]
+        );
+
+        let all = search(&pool, "set:MIR").len();
+        let rare = search(&pool, "set:MIR and =rare").len();
+        let other = search(&pool, "set:MIR and !=rare").len();
+        assert!(rare > 0);
+        assert_eq!(all, rare + other);
+
+        for found in search(&pool, "set:MIR and @white and cmc:<=1") {
+            assert!(found.starts_with("MIR "));
+        }
+        assert!(!search(&pool, "set:MIR and (@w or @u) and type:instant").is_empty());
+        assert_eq!(search(&pool, "owned").len(), 0);
+    }
+}
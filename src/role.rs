@@ -0,0 +1,81 @@
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// What someone can do with a collection that has been shared with them.
+// Each role can do everything the ones before it can:
+//
+// viewer   see the collection, its transactions and their attachments,
+//          and export them.
+// editor   post, change and remove transactions (and attachments), and
+//          look after the collector's decks and goals.
+// owner    everything the collector themselves can do with it, short of
+//          deleting it: inviting and revoking members, and transfers.
+//
+// The collector a collection belongs to is always its owner.
+//
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl Role {
+    pub fn parse(s: &str) -> Result<Role> {
+        match s.trim().to_lowercase().as_str() {
+            "viewer" => Ok(Role::Viewer),
+            "editor" => Ok(Role::Editor),
+            "owner" => Ok(Role::Owner),
+            _ => Err(format!("invalid-role: {}", s.trim()).into()),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Owner => "owner",
+        }
+    }
+
+    // Whether someone with this role can do something that needs
+    // (at least) the other.
+    pub fn allows(&self, need: Role) -> bool {
+        *self >= need
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_parse_roles() {
+        assert_eq!(Role::Viewer, Role::parse("viewer").unwrap());
+        assert_eq!(Role::Editor, Role::parse(" Editor ").unwrap());
+        assert_eq!(Role::Owner, Role::parse("OWNER").unwrap());
+        assert_eq!(
+            "invalid-role: admin",
+            Role::parse("admin").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn should_rank_roles() {
+        assert!(Role::Owner.allows(Role::Editor));
+        assert!(Role::Owner.allows(Role::Viewer));
+        assert!(Role::Editor.allows(Role::Editor));
+        assert!(Role::Editor.allows(Role::Viewer));
+        assert!(!Role::Editor.allows(Role::Owner));
+        assert!(!Role::Viewer.allows(Role::Editor));
+    }
+}
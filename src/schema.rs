@@ -1,7 +1,78 @@
+table! {
+    account_tokens (id) {
+        id -> Uuid,
+        collector -> Uuid,
+        purpose -> Varchar,
+        email -> Text,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    alerts (id) {
+        id -> Uuid,
+        watchlist -> Uuid,
+        collector -> Uuid,
+        card -> Varchar,
+        direction -> Varchar,
+        threshold -> Int4,
+        price -> Int4,
+        currency -> Varchar,
+        dated -> Date,
+        delivered_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    api_tokens (id) {
+        id -> Uuid,
+        collector -> Uuid,
+        name -> Text,
+        hash -> Varchar,
+        hint -> Varchar,
+        scopes -> Array<Text>,
+        last_used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    attachments (id) {
+        id -> Uuid,
+        transaction -> Uuid,
+        filename -> Text,
+        content_type -> Varchar,
+        size -> Int4,
+        sha256 -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    collection_members (collection, collector) {
+        collection -> Uuid,
+        collector -> Uuid,
+        role -> Varchar,
+        invited_by -> Nullable<Uuid>,
+        accepted_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 table! {
     collections (id) {
         id -> Uuid,
         collector -> Uuid,
+        name -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -11,6 +82,10 @@ table! {
         username -> Varchar,
         email -> Varchar,
         password -> Varchar,
+        currency -> Varchar,
+        tradeable -> Bool,
+        email_verified_at -> Nullable<Timestamptz>,
+        session_generation -> Int4,
     }
 }
 
@@ -43,6 +118,152 @@ table! {
         progress -> Nullable<Int4>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        deadline -> Nullable<Date>,
+        budget -> Nullable<Int4>,
+    }
+}
+
+table! {
+    loans (id) {
+        id -> Uuid,
+        collector -> Uuid,
+        borrower -> Text,
+        borrower_collector -> Nullable<Uuid>,
+        cards -> Text,
+        notes -> Text,
+        lent_on -> Date,
+        due_on -> Nullable<Date>,
+        returned_on -> Nullable<Date>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    locations (id) {
+        id -> Uuid,
+        collector -> Uuid,
+        parent -> Nullable<Uuid>,
+        name -> Varchar,
+        kind -> Varchar,
+        description -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    pods (id) {
+        id -> Uuid,
+        collector -> Uuid,
+        set_code -> Varchar,
+        state -> Jsonb,
+        finished -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    recurring_transactions (id) {
+        id -> Uuid,
+        collector -> Uuid,
+        template -> Uuid,
+        every -> Varchar,
+        starts_on -> Date,
+        ends_on -> Nullable<Date>,
+        vars -> Jsonb,
+        occurrences -> Int4,
+        next_on -> Date,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    sealed_products (id) {
+        id -> Uuid,
+        collector -> Uuid,
+        kind -> Varchar,
+        name -> Text,
+        set_code -> Varchar,
+        packs -> Int4,
+        quantity -> Int4,
+        paid -> Nullable<Int4>,
+        acquired_on -> Date,
+        notes -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    share_links (id) {
+        id -> Uuid,
+        collector -> Uuid,
+        hash -> Varchar,
+        kind -> Varchar,
+        deck -> Nullable<Uuid>,
+        collection -> Nullable<Uuid>,
+        query -> Text,
+        expires_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        hint -> Varchar,
+    }
+}
+
+table! {
+    slab_values (collector, cert) {
+        collector -> Uuid,
+        cert -> Varchar,
+        value -> Int4,
+        notes -> Text,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    stowage (location, pid, var) {
+        location -> Uuid,
+        pid -> Varchar,
+        var -> Varchar,
+        quantity -> Int4,
+    }
+}
+
+table! {
+    trades (id) {
+        id -> Uuid,
+        proposer -> Uuid,
+        recipient -> Uuid,
+        offer -> Text,
+        request -> Text,
+        message -> Text,
+        status -> Varchar,
+        parent -> Nullable<Uuid>,
+        proposer_transaction -> Nullable<Uuid>,
+        recipient_transaction -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    transaction_templates (id) {
+        id -> Uuid,
+        collector -> Uuid,
+        collection -> Uuid,
+        name -> Text,
+        summary -> Text,
+        notes -> Text,
+        gain -> Text,
+        loss -> Text,
+        disposition -> Varchar,
+        paid -> Nullable<Int4>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -60,18 +281,88 @@ table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         paid -> Nullable<Int4>,
+        opened -> Nullable<Uuid>,
+        transfer -> Nullable<Uuid>,
+        created_by -> Nullable<Uuid>,
+        updated_by -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    watchlists (id) {
+        id -> Uuid,
+        collector -> Uuid,
+        card -> Varchar,
+        scope -> Varchar,
+        finish -> Varchar,
+        currency -> Varchar,
+        below -> Nullable<Int4>,
+        above -> Nullable<Int4>,
+        notes -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    wishlists (id) {
+        id -> Uuid,
+        collector -> Uuid,
+        name -> Text,
+        description -> Text,
+        cards -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
+joinable!(account_tokens -> collectors (collector));
+joinable!(alerts -> collectors (collector));
+joinable!(alerts -> watchlists (watchlist));
+joinable!(api_tokens -> collectors (collector));
+joinable!(attachments -> transactions (transaction));
+joinable!(collection_members -> collections (collection));
 joinable!(collections -> collectors (collector));
 joinable!(decks -> collectors (collector));
 joinable!(goals -> collectors (collector));
+joinable!(locations -> collectors (collector));
+joinable!(pods -> collectors (collector));
+joinable!(recurring_transactions -> collectors (collector));
+joinable!(recurring_transactions -> transaction_templates (template));
+joinable!(sealed_products -> collectors (collector));
+joinable!(share_links -> collections (collection));
+joinable!(share_links -> collectors (collector));
+joinable!(share_links -> decks (deck));
+joinable!(slab_values -> collectors (collector));
+joinable!(stowage -> locations (location));
+joinable!(transaction_templates -> collections (collection));
+joinable!(transaction_templates -> collectors (collector));
 joinable!(transactions -> collections (collection));
+joinable!(transactions -> sealed_products (opened));
+joinable!(watchlists -> collectors (collector));
+joinable!(wishlists -> collectors (collector));
 
 allow_tables_to_appear_in_same_query!(
+    account_tokens,
+    alerts,
+    api_tokens,
+    attachments,
+    collection_members,
     collections,
     collectors,
     decks,
     goals,
+    loans,
+    locations,
+    pods,
+    recurring_transactions,
+    sealed_products,
+    share_links,
+    slab_values,
+    stowage,
+    trades,
+    transaction_templates,
     transactions,
+    watchlists,
+    wishlists,
 );
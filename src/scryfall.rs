@@ -131,7 +131,9 @@ pub struct ImageURIs {
 #[derive(Deserialize)]
 pub struct Prices {
     pub eur: Option<String>,
+    pub eur_foil: Option<String>,
     pub usd_foil: Option<String>,
+    pub usd_etched: Option<String>,
     pub tix: Option<String>,
     pub usd: Option<String>,
 }
@@ -0,0 +1,233 @@
+use crate::card;
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// The kinds of sealed product we keep track of.  Boxes and bundles are
+// made up of packs; opening one credits the collection with whatever
+// came out of all of its packs at once.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    BoosterPack,
+    BoosterBox,
+    Bundle,
+    CollectorPack,
+    CollectorBox,
+}
+
+impl Kind {
+    pub fn parse(s: &str) -> Option<Kind> {
+        match s.trim().to_lowercase().as_str() {
+            "booster-pack" | "booster" | "pack" => Some(Kind::BoosterPack),
+            "booster-box" | "box" => Some(Kind::BoosterBox),
+            "bundle" | "fat-pack" => Some(Kind::Bundle),
+            "collector-pack" | "collector-booster" => Some(Kind::CollectorPack),
+            "collector-box" => Some(Kind::CollectorBox),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::BoosterPack => "booster-pack",
+            Kind::BoosterBox => "booster-box",
+            Kind::Bundle => "bundle",
+            Kind::CollectorPack => "collector-pack",
+            Kind::CollectorBox => "collector-box",
+        }
+    }
+
+    // How many packs come in one, unless we're told otherwise (set
+    // booster boxes, for instance, only have 30).
+    pub fn packs(&self) -> i32 {
+        match self {
+            Kind::BoosterPack | Kind::CollectorPack => 1,
+            Kind::BoosterBox => 36,
+            Kind::Bundle => 10,
+            Kind::CollectorBox => 12,
+        }
+    }
+
+    // What goes into each pack, by rarity.  These are approximations of
+    // the usual play booster and collector booster layouts; the rare
+    // slot turns up a mythic one time in eight, when there are any.
+    fn slots(&self) -> &'static [(&'static str, f64, card::Finish)] {
+        match self {
+            Kind::BoosterPack | Kind::BoosterBox | Kind::Bundle => &[
+                ("common", 10.0, card::Finish::Nonfoil),
+                ("uncommon", 3.0, card::Finish::Nonfoil),
+                ("rare", 1.0, card::Finish::Nonfoil),
+            ],
+            Kind::CollectorPack | Kind::CollectorBox => &[
+                ("common", 5.0, card::Finish::Foil),
+                ("uncommon", 4.0, card::Finish::Foil),
+                ("rare", 2.0, card::Finish::Nonfoil),
+                ("rare", 1.0, card::Finish::Foil),
+            ],
+        }
+    }
+}
+
+// Check the details of a sealed product, returning a failure code
+// suitable for the API if they don't add up.  Products have to come
+// from a set we know about, so that they can be valued.
+pub fn check(
+    pool: &card::Pool,
+    kind: &str,
+    set: &str,
+    packs: i32,
+    quantity: i32,
+    paid: Option<i32>,
+) -> Result<Kind> {
+    let kind = Kind::parse(kind).ok_or_else(|| Error::from("invalid-sealed-kind"))?;
+    if !pool.sets.contains_key(&set.trim().to_uppercase()) {
+        return Err(format!("unknown-set: {}", set.trim()).into());
+    }
+    if packs < 1 {
+        return Err("invalid-sealed-packs".into());
+    }
+    if quantity < 0 {
+        return Err("invalid-sealed-quantity".into());
+    }
+    if paid.is_some_and(|p| p < 0) {
+        return Err("invalid-sealed-paid".into());
+    }
+    Ok(kind)
+}
+
+// The expected value of a single pack of a kind of product, from the
+// given set, in pennies: the average market price of each rarity (of
+// those cards that have one), times how many of that rarity are in
+// a pack.  None if nothing in the set has a price.
+pub fn pack_ev(
+    pool: &card::Pool,
+    prices: &card::Prices,
+    set: &str,
+    kind: Kind,
+    currency: card::Currency,
+) -> Option<i64> {
+    let set = pool.sets.get(&set.trim().to_uppercase())?;
+    let average = |rarity: &str, finish: card::Finish| {
+        let each: Vec<f64> = set
+            .cards
+            .iter()
+            .filter(|print| print.flags.rarity == rarity)
+            .filter_map(|print| prices.get(&print.id))
+            .filter_map(|quote| quote.price(finish, currency))
+            .collect();
+        match each.len() {
+            0 => None,
+            n => Some(each.iter().sum::<f64>() / n as f64),
+        }
+    };
+
+    let mut ev = None;
+    for (rarity, n, finish) in kind.slots() {
+        let value = match *rarity {
+            "rare" => match (average("rare", *finish), average("mythic", *finish)) {
+                (Some(rare), Some(mythic)) => Some(rare * 7.0 / 8.0 + mythic / 8.0),
+                (rare, mythic) => rare.or(mythic),
+            },
+            rarity => average(rarity, *finish),
+        };
+        if let Some(value) = value {
+            ev = Some(ev.unwrap_or(0.0) + value * n);
+        }
+    }
+    ev.map(|ev| (ev * 100.0).round() as i64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    fn priced() -> (card::Pool, card::Prices) {
+        let pool = card::Pool::from_file("test/cards.json").expect("reading card pool");
+        let mut prices = card::Prices::new();
+        for print in &pool.sets["AVR"].cards {
+            let usd = match print.flags.rarity.as_str() {
+                "common" => 0.10,
+                "uncommon" => 0.25,
+                "rare" => 1.00,
+                _ => 5.00,
+            };
+            prices.insert(
+                print.id.to_string(),
+                card::Quote {
+                    usd: Some(usd),
+                    usd_foil: Some(usd * 2.0),
+                    ..card::Quote::default()
+                },
+            );
+        }
+        (pool, prices)
+    }
+
+    #[test]
+    fn should_check_sealed_product_details() {
+        let (pool, _) = priced();
+        assert_eq!(
+            Kind::BoosterBox,
+            check(&pool, "box", "avr", 36, 1, Some(9999)).unwrap()
+        );
+        assert_eq!(
+            Kind::Bundle,
+            check(&pool, "Bundle", "AVR", 10, 0, None).unwrap()
+        );
+
+        let fail = |kind, set, packs, quantity, paid| {
+            check(&pool, kind, set, packs, quantity, paid)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!("invalid-sealed-kind", fail("crate", "AVR", 1, 1, None));
+        assert_eq!("unknown-set: XYZ", fail("bundle", "XYZ", 10, 1, None));
+        assert_eq!("invalid-sealed-packs", fail("bundle", "AVR", 0, 1, None));
+        assert_eq!(
+            "invalid-sealed-quantity",
+            fail("bundle", "AVR", 10, -1, None)
+        );
+        assert_eq!(
+            "invalid-sealed-paid",
+            fail("bundle", "AVR", 10, 1, Some(-1))
+        );
+    }
+
+    #[test]
+    fn should_work_out_pack_ev_from_prices() {
+        let (pool, prices) = priced();
+        let ev = |kind| pack_ev(&pool, &prices, "avr", kind, card::Currency::Usd);
+
+        // 10 x $0.10 + 3 x $0.25 + (7/8 x $1 + 1/8 x $5)
+        assert_eq!(Some(325), ev(Kind::BoosterPack));
+        assert_eq!(Some(325), ev(Kind::BoosterBox));
+
+        // 5 x $0.20 + 4 x $0.50 + 2 x $1.50 + 1 x $3.00
+        assert_eq!(Some(900), ev(Kind::CollectorPack));
+
+        assert_eq!(
+            None,
+            pack_ev(
+                &pool,
+                &prices,
+                "MIR",
+                Kind::BoosterPack,
+                card::Currency::Usd
+            )
+        );
+        assert_eq!(
+            None,
+            pack_ev(
+                &pool,
+                &prices,
+                "AVR",
+                Kind::BoosterPack,
+                card::Currency::Eur
+            )
+        );
+    }
+}
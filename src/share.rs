@@ -0,0 +1,160 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::card;
+use crate::query::{Query, Subject};
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// What a share link lets someone without an account look at:
+//
+// deck           a deck, as it stands whenever the link is followed
+//                (i.e. the newest version in its lineage).
+// deck-version   one particular version of a deck, as it was.
+// collection     one of the collector's collections, optionally
+//                narrowed down to the cards that match a query.
+//
+// Share links are read-only, and only ever let out the cards
+// themselves; never notes, what was paid, or who the collector is
+// beyond their username.
+//
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Deck,
+    DeckVersion,
+    Collection,
+}
+
+impl Kind {
+    pub fn parse(s: &str) -> Result<Kind> {
+        match s.trim().to_lowercase().as_str() {
+            "deck" => Ok(Kind::Deck),
+            "deck-version" => Ok(Kind::DeckVersion),
+            "collection" => Ok(Kind::Collection),
+            _ => Err(format!("invalid-share-kind: {}", s.trim()).into()),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Deck => "deck",
+            Kind::DeckVersion => "deck-version",
+            Kind::Collection => "collection",
+        }
+    }
+}
+
+// Share tokens are all that stands between a link and what it shares,
+// so they are long and random: 32 letters and digits, a little over
+// 190 bits' worth.
+pub fn token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .collect()
+}
+
+#[derive(Serialize)]
+pub struct Card {
+    pub card: String, // CDIF identifier, i.e. "MIR *1 Afterlife"
+    pub pid: String,
+    pub quantity: u32,
+    pub var: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grade: Option<card::Grade>,
+}
+
+// The cards in a shared collection: all of them, or just those that
+// match the share link's query, if it has one.
+pub fn cards(pool: &card::Pool, collection: &card::Collection, query: Option<&Query>) -> Vec<Card> {
+    let prints = pool.prints();
+    let mut cards = vec![];
+    for (quantity, owned) in &collection.cards {
+        let (set, print) = match prints.get(owned.pid.as_str()) {
+            Some(found) if *quantity > 0 => *found,
+            _ => continue,
+        };
+        let oracle = match pool.cards.get(&print.oid) {
+            Some(oracle) => oracle,
+            None => continue,
+        };
+        let subject = Subject {
+            set,
+            print,
+            oracle,
+            owned: *quantity,
+        };
+        if let Some(q) = query {
+            if !q.matches(&subject) {
+                continue;
+            }
+        }
+        cards.push(Card {
+            card: format!("{} *{} {}", set.code, print.number, oracle.name),
+            pid: owned.pid.to_string(),
+            quantity: *quantity,
+            var: owned.var.clone(),
+            grade: owned.grade.clone(),
+        });
+    }
+    cards.sort_by(|a, b| a.card.cmp(&b.card));
+    cards
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn should_parse_share_kinds() {
+        assert_eq!(Kind::Deck, Kind::parse("deck").unwrap());
+        assert_eq!(Kind::DeckVersion, Kind::parse(" Deck-Version ").unwrap());
+        assert_eq!(Kind::Collection, Kind::parse("COLLECTION").unwrap());
+        assert_eq!(
+            "invalid-share-kind: binder",
+            Kind::parse("binder").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn should_generate_unguessable_tokens() {
+        let (a, b) = (token(), token());
+        assert_eq!(32, a.len());
+        assert!(a.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn should_only_share_cards_matching_the_query() {
+        let pool = card::Pool::from_file("test/cards.json").expect("reading card pool");
+        let owned = |pid: &str| card::OwnedCard {
+            pid: pid.to_string(),
+            var: vec![],
+            grade: None,
+        };
+        let collection = card::Collection {
+            cards: vec![
+                (2, owned("7a8be765-0949-491c-875c-0385fb83e4b9")), // AVR
+                (1, owned("3741e62a-4b86-46ff-a7df-a8ceaf3b9a0c")), // BFZ
+                (0, owned("16c5dfed-4dee-4e48-a445-89f03d7794e6")), // AVR, gone
+            ],
+        };
+
+        let all = cards(&pool, &collection, None);
+        assert_eq!(2, all.len());
+        assert_eq!("AVR *1 Angel of Glory's Rise", all[0].card);
+        assert_eq!(2, all[0].quantity);
+        assert_eq!("BFZ *1 Bane of Bala Ged", all[1].card);
+
+        let q = Query::parse("set:BFZ").unwrap();
+        let some = cards(&pool, &collection, Some(&q));
+        assert_eq!(1, some.len());
+        assert_eq!("3741e62a-4b86-46ff-a7df-a8ceaf3b9a0c", some[0].pid);
+    }
+}
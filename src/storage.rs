@@ -0,0 +1,929 @@
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::valuation::{Holdings, Key};
+use crate::{card, cdif};
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// The kinds of places cards are kept.  Boxes are split into rows, and
+// binders into pages; deck boxes hold a deck's worth of cards, and
+// nothing else.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    Box,
+    Row,
+    Binder,
+    Page,
+    Deckbox,
+}
+
+impl Kind {
+    pub fn parse(s: &str) -> Option<Kind> {
+        match s.to_lowercase().as_str() {
+            "box" => Some(Kind::Box),
+            "row" => Some(Kind::Row),
+            "binder" => Some(Kind::Binder),
+            "page" => Some(Kind::Page),
+            "deckbox" | "deck-box" => Some(Kind::Deckbox),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Box => "box",
+            Kind::Row => "row",
+            Kind::Binder => "binder",
+            Kind::Page => "page",
+            Kind::Deckbox => "deckbox",
+        }
+    }
+
+    // The kind of location this kind must be kept in, if any.
+    pub fn parent(&self) -> Option<Kind> {
+        match self {
+            Kind::Row => Some(Kind::Box),
+            Kind::Page => Some(Kind::Binder),
+            _ => None,
+        }
+    }
+}
+
+// Location names have to survive a round-trip through CDIF, as the
+// value of a `(loc: ...)` variant.
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+// Check that a new location, of the given kind, can live where it has
+// been put (inside a location of the parent kind, if any), returning a
+// failure code suitable for the API if not.
+pub fn check(name: &str, kind: &str, parent: Option<&str>) -> Result<Kind> {
+    if !valid_name(name) {
+        return Err("invalid-location-name".into());
+    }
+    let kind = Kind::parse(kind).ok_or_else(|| Error::from("invalid-location-kind"))?;
+    match (kind.parent(), parent.map(Kind::parse)) {
+        (None, None) => Ok(kind),
+        (Some(want), Some(Some(have))) if want == have => Ok(kind),
+        _ => Err("invalid-location-parent".into()),
+    }
+}
+
+pub struct Location {
+    pub id: String, // uuid
+    pub parent: Option<String>,
+    pub name: String,
+    pub kind: Kind,
+}
+
+// The full path to a location, from the outermost box or binder in,
+// i.e. `box-1/row-3`.
+pub fn path(locations: &[Location], id: &str) -> String {
+    let mut names = vec![];
+    let mut next = Some(id);
+    while let Some(id) = next {
+        match locations.iter().find(|l| l.id == id) {
+            Some(l) if names.len() < locations.len() => {
+                names.push(l.name.as_str());
+                next = l.parent.as_deref();
+            }
+            _ => break,
+        }
+    }
+    names.reverse();
+    names.join("/")
+}
+
+// Compare names the way a person would, with runs of digits compared
+// as numbers, so that `page-2` comes before `page-10` (and `XLN *2`
+// before `XLN *10`, which is how binders are usually filled).
+pub fn natural(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let (x, y) = match (a.peek(), b.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => (*x, *y),
+        };
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (m, n) = (digits(&mut a), digits(&mut b));
+            match m.len().cmp(&n.len()).then_with(|| m.cmp(&n)) {
+                Ordering::Equal => continue,
+                other => return other,
+            }
+        }
+        match x.cmp(&y) {
+            Ordering::Equal => {
+                a.next();
+                b.next();
+            }
+            other => return other,
+        }
+    }
+}
+
+// Take a run of digits off the front of a name, as a number (in decimal,
+// without leading zeroes, so that longer is bigger).
+fn digits(s: &mut Peekable<Chars>) -> String {
+    let mut n = String::new();
+    while let Some(c) = s.next_if(|c| c.is_ascii_digit()) {
+        n.push(c);
+    }
+    n.trim_start_matches('0').to_string()
+}
+
+// Order location paths by where they sit in the tree: each location
+// before the ones inside of it, and siblings by name (naturally).
+pub fn by_position(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.split('/'), b.split('/'));
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => match natural(x, y) {
+                Ordering::Equal => continue,
+                other => return other,
+            },
+        }
+    }
+}
+
+// Map the full path of every location to its id.  Names are only unique
+// among siblings, so `(loc: ...)` variants (and moves) go by path.
+pub fn paths(locations: &[Location]) -> HashMap<String, String> {
+    locations
+        .iter()
+        .map(|l| (path(locations, &l.id), l.id.to_string()))
+        .collect()
+}
+
+// Copies of one print (with one set of variants) kept in one location.
+// When handed around as a change, the quantity is how many copies to
+// add to (or, if negative, take out of) the location.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stowed {
+    pub location: String, // uuid
+    pub pid: String,
+    pub var: Vec<String>,
+    pub quantity: i32,
+}
+
+// Variants are stored as a single, space-separated string.
+pub fn variant(var: &[String]) -> String {
+    var.join(" ")
+}
+
+pub fn variants(var: &str) -> Vec<String> {
+    var.split_whitespace().map(|v| v.to_string()).collect()
+}
+
+fn located<'a>(
+    lookup: &'a card::Map,
+    paths: &HashMap<String, String>,
+    line: &cdif::Line,
+) -> Result<Option<(&'a str, String)>> {
+    let loc = match line.lvars.iter().find(|(k, _)| k == "loc") {
+        Some((_, loc)) => loc,
+        None => return Ok(None),
+    };
+    let id = paths
+        .get(loc)
+        .ok_or_else(|| Error::from(format!("unknown-location: {}", loc)))?;
+    match lookup.get(&format!("{} *{} {}", line.set, line.number, line.oracle)) {
+        Some(pid) => Ok(Some((pid.as_str(), id.to_string()))),
+        None => Err(format!("unknown-card: {} {}", line.set, line.oracle).into()),
+    }
+}
+
+// Work out what a transaction does to the contents of each location.
+// Gained cards tagged with `(loc: ...)` are put there, and lost cards so
+// tagged are taken out of there.  Untagged cards are left unstored (or,
+// for losses, are taken care of later on, when storage is synced back
+// up with the collection).  Locations are looked up by path.
+pub fn changes(
+    lookup: &card::Map,
+    paths: &HashMap<String, String>,
+    gain: &cdif::File,
+    loss: &cdif::File,
+) -> Result<Vec<Stowed>> {
+    let mut changes = vec![];
+    for (file, sign) in &[(gain, 1), (loss, -1)] {
+        for line in file.lines.values() {
+            if let Some((pid, location)) = located(lookup, paths, line)? {
+                changes.push(Stowed {
+                    location,
+                    pid: pid.to_string(),
+                    var: line.gvars.clone(),
+                    quantity: sign * line.quantity,
+                });
+            }
+        }
+    }
+    Ok(changes)
+}
+
+// Work out the changes needed to move some cards from one location to
+// another.  Either end may be left off, to move cards into storage, or
+// out of it.
+pub fn shift(
+    lookup: &card::Map,
+    from: Option<&str>,
+    to: Option<&str>,
+    cards: &cdif::File,
+) -> Result<Vec<Stowed>> {
+    let mut changes = vec![];
+    for line in cards.lines.values() {
+        let pid = lookup
+            .get(&format!("{} *{} {}", line.set, line.number, line.oracle))
+            .ok_or_else(|| Error::from(format!("unknown-card: {} {}", line.set, line.oracle)))?;
+        for (location, sign) in &[(from, -1), (to, 1)] {
+            if let Some(location) = location {
+                changes.push(Stowed {
+                    location: location.to_string(),
+                    pid: pid.to_string(),
+                    var: line.gvars.clone(),
+                    quantity: sign * line.quantity,
+                });
+            }
+        }
+    }
+    Ok(changes)
+}
+
+type Copy<'a> = (&'a str, &'a [String]);
+
+fn owned(collection: &card::Collection) -> HashMap<Copy<'_>, i32> {
+    let mut owned = HashMap::new();
+    for (n, card) in &collection.cards {
+        *owned
+            .entry((card.pid.as_str(), card.var.as_slice()))
+            .or_insert(0) += *n as i32;
+    }
+    owned
+}
+
+// Check that a set of changes (a move, usually) doesn't take out more
+// copies of a card than a location holds, and doesn't store more copies
+// of a card than are in the collection.
+pub fn verify(collection: &card::Collection, stowed: &[Stowed], changes: &[Stowed]) -> Result<()> {
+    let mut held: HashMap<(&str, Copy), i32> = HashMap::new();
+    let mut stored: HashMap<Copy, i32> = HashMap::new();
+    for s in stowed.iter().chain(changes) {
+        let copy = (s.pid.as_str(), s.var.as_slice());
+        *held.entry((s.location.as_str(), copy)).or_insert(0) += s.quantity;
+        *stored.entry(copy).or_insert(0) += s.quantity;
+    }
+
+    if held.values().any(|n| *n < 0) {
+        return Err("not-stowed".into());
+    }
+    let owned = owned(collection);
+    if stored
+        .iter()
+        .any(|(copy, n)| *n > *owned.get(copy).unwrap_or(&0))
+    {
+        return Err("not-owned".into());
+    }
+    Ok(())
+}
+
+// Bring storage back in line with the collection, by taking copies the
+// collector no longer owns out of the locations that hold them.  Copies
+// are taken from locations in a fixed (if arbitrary) order, so that two
+// syncs against the same collection agree.
+pub fn sync(collection: &card::Collection, stowed: &[Stowed]) -> Vec<Stowed> {
+    let mut by: BTreeMap<Copy, Vec<&Stowed>> = BTreeMap::new();
+    for s in stowed {
+        by.entry((s.pid.as_str(), s.var.as_slice()))
+            .or_default()
+            .push(s);
+    }
+
+    let owned = owned(collection);
+    let mut changes = vec![];
+    for (copy, mut places) in by {
+        let mut excess =
+            places.iter().map(|s| s.quantity).sum::<i32>() - *owned.get(&copy).unwrap_or(&0);
+        places.sort_by(|a, b| b.location.cmp(&a.location));
+        for s in places {
+            if excess <= 0 {
+                break;
+            }
+            let n = excess.min(s.quantity);
+            changes.push(Stowed {
+                location: s.location.to_string(),
+                pid: s.pid.to_string(),
+                var: s.var.clone(),
+                quantity: -n,
+            });
+            excess -= n;
+        }
+    }
+    changes
+}
+
+#[derive(Serialize)]
+pub struct Place {
+    pub location: String, // uuid
+    pub path: String,
+    pub quantity: i32,
+}
+
+#[derive(Serialize)]
+pub struct Copies {
+    pub card: String, // CDIF identifier, i.e. "MIR *1 Afterlife"
+    pub pid: String,
+    pub var: Vec<String>,
+    pub owned: i32,
+    pub unstored: i32,
+    pub places: Vec<Place>,
+}
+
+#[derive(Serialize)]
+pub struct Whereabouts {
+    pub card: String, // oracle name
+    pub oid: String,
+    pub owned: i32,
+    pub copies: Vec<Copies>,
+}
+
+// Where are all of the copies of a card (by name, in any printing)?
+// Returns None if there is no such card.
+pub fn whereis(
+    pool: &card::Pool,
+    collection: &card::Collection,
+    stowed: &[Stowed],
+    locations: &[Location],
+    name: &str,
+) -> Option<Whereabouts> {
+    let oracle = pool
+        .cards
+        .values()
+        .find(|c| c.name.eq_ignore_ascii_case(name))?;
+    let prints = pool.prints();
+
+    let mut copies = vec![];
+    for (copy, n) in owned(collection) {
+        let (set, print) = match prints.get(copy.0) {
+            Some((set, print)) if print.oid == oracle.id => (set, print),
+            _ => continue,
+        };
+
+        let mut places: Vec<Place> = stowed
+            .iter()
+            .filter(|s| s.pid == copy.0 && s.var == copy.1)
+            .map(|s| Place {
+                location: s.location.to_string(),
+                path: path(locations, &s.location),
+                quantity: s.quantity,
+            })
+            .collect();
+        places.sort_by(|a, b| by_position(&a.path, &b.path));
+
+        copies.push(Copies {
+            card: pool.cdif_id(set, print).unwrap_or_default(),
+            pid: copy.0.to_string(),
+            var: copy.1.to_vec(),
+            owned: n,
+            unstored: (n - places.iter().map(|p| p.quantity).sum::<i32>()).max(0),
+            places,
+        });
+    }
+    copies.sort_by(|a, b| a.card.cmp(&b.card).then_with(|| a.var.cmp(&b.var)));
+
+    Some(Whereabouts {
+        card: oracle.name.to_string(),
+        oid: oracle.id.to_string(),
+        owned: copies.iter().map(|c| c.owned).sum(),
+        copies,
+    })
+}
+
+#[derive(Serialize)]
+pub struct Content {
+    pub card: String, // CDIF identifier
+    pub pid: String,
+    pub var: Vec<String>,
+    pub quantity: i32,
+}
+
+// What's in a location, in CDIF order (by set, then collector number).
+pub fn contents(pool: &card::Pool, stowed: &[Stowed], location: &str) -> Vec<Content> {
+    let prints = pool.prints();
+    let mut contents: Vec<Content> = stowed
+        .iter()
+        .filter(|s| s.location == location)
+        .map(|s| Content {
+            card: prints
+                .get(s.pid.as_str())
+                .and_then(|(set, print)| pool.cdif_id(set, print))
+                .unwrap_or_else(|| s.pid.to_string()),
+            pid: s.pid.to_string(),
+            var: s.var.clone(),
+            quantity: s.quantity,
+        })
+        .collect();
+    contents.sort_by(|a, b| natural(&a.card, &b.card).then_with(|| a.var.cmp(&b.var)));
+    contents
+}
+
+#[derive(Serialize)]
+pub struct Stop {
+    pub location: String, // uuid
+    pub path: String,
+    pub picks: Vec<Content>,
+}
+
+#[derive(Serialize)]
+pub struct Short {
+    pub card: String, // oracle name
+    pub oid: String,
+    pub quantity: u32,
+}
+
+#[derive(Serialize)]
+pub struct PickList {
+    pub stops: Vec<Stop>,
+    pub short: Vec<Short>, // copies that aren't in storage anywhere
+}
+
+// Set aside stowed copies that are out on loan.  Storage doesn't know
+// which copies were lent, so we assume unstored copies went out first,
+// and then take the rest from the end of the walk, leaving the first
+// locations as they are.
+fn present(stowed: &[Stowed], locations: &[Location], on_hand: &Holdings) -> Vec<Stowed> {
+    let mut present = stowed.to_vec();
+    present
+        .sort_by(|a, b| by_position(&path(locations, &a.location), &path(locations, &b.location)));
+
+    let mut left: HashMap<Key, i64> = HashMap::new();
+    for s in &mut present {
+        let key = (s.pid.to_string(), card::Finish::of(&s.var));
+        let left = left
+            .entry(key)
+            .or_insert_with_key(|key| *on_hand.get(key).unwrap_or(&0));
+        let n = (s.quantity as i64).min(*left).max(0);
+        *left -= n;
+        s.quantity = n as i32;
+    }
+    present.retain(|s| s.quantity > 0);
+    present
+}
+
+// Plan out where to pull a deck's cards from.  Any printing of a card
+// will do.  Copies already in the deck box we're filling (if any) don't
+// need pulling, and copies sleeved up in other deck boxes are left be.
+//
+// Locations are chosen greedily: whichever can supply the most of what
+// is still needed goes first, so that the fewest binders and boxes get
+// opened.  The resulting stops are then ordered by path, so that pages
+// and rows can be walked in order.  Copies out on loan (anything stowed
+// beyond what the collector has on hand) are never picked.
+//
+pub fn pick(
+    pool: &card::Pool,
+    needs: &HashMap<String, u32>,
+    stowed: &[Stowed],
+    locations: &[Location],
+    into: Option<&str>,
+    on_hand: &Holdings,
+) -> PickList {
+    let present = present(stowed, locations, on_hand);
+    let stowed = present.as_slice();
+    let prints = pool.prints();
+    let oid = |pid: &str| prints.get(pid).map(|(_, print)| print.oid.as_str());
+
+    let mut remaining: BTreeMap<&str, u32> =
+        needs.iter().map(|(oid, n)| (oid.as_str(), *n)).collect();
+    for s in stowed.iter().filter(|s| Some(s.location.as_str()) == into) {
+        if let Some(want) = oid(&s.pid).and_then(|oid| remaining.get_mut(oid)) {
+            *want = want.saturating_sub(s.quantity.max(0) as u32);
+        }
+    }
+
+    let sources: HashSet<&str> = locations
+        .iter()
+        .filter(|l| l.kind != Kind::Deckbox && Some(l.id.as_str()) != into)
+        .map(|l| l.id.as_str())
+        .collect();
+    let mut candidates: BTreeMap<String, (&str, Vec<&Stowed>)> = BTreeMap::new();
+    for s in stowed {
+        if sources.contains(s.location.as_str()) {
+            candidates
+                .entry(path(locations, &s.location))
+                .or_insert_with(|| (s.location.as_str(), vec![]))
+                .1
+                .push(s);
+        }
+    }
+
+    let mut stops = vec![];
+    loop {
+        let supply = |held: &[&Stowed], remaining: &BTreeMap<&str, u32>| {
+            let mut have: HashMap<&str, u32> = HashMap::new();
+            for s in held {
+                if let Some(oid) = oid(&s.pid) {
+                    *have.entry(oid).or_insert(0) += s.quantity.max(0) as u32;
+                }
+            }
+            have.iter()
+                .map(|(oid, n)| (*n).min(*remaining.get(oid).unwrap_or(&0)))
+                .sum::<u32>()
+        };
+
+        // ties go to the location that comes first, in the tree
+        let best = candidates
+            .iter()
+            .map(|(path, (_, held))| (supply(held, &remaining), path))
+            .filter(|(n, _)| *n > 0)
+            .max_by(|a, b| a.0.cmp(&b.0).then_with(|| by_position(b.1, a.1)))
+            .map(|(_, path)| path.to_string());
+        let path = match best {
+            Some(path) => path,
+            None => break,
+        };
+
+        let (location, held) = candidates.remove(&path).unwrap_or_default();
+        let mut picks = contents(
+            pool,
+            &held.into_iter().cloned().collect::<Vec<_>>(),
+            location,
+        );
+        for p in &mut picks {
+            let want = match oid(&p.pid).and_then(|oid| remaining.get_mut(oid)) {
+                Some(want) => want,
+                None => continue,
+            };
+            let n = (*want).min(p.quantity.max(0) as u32);
+            *want -= n;
+            p.quantity = n as i32;
+        }
+        picks.retain(|p| p.quantity > 0);
+        stops.push(Stop {
+            location: location.to_string(),
+            path,
+            picks,
+        });
+    }
+    stops.sort_by(|a, b| by_position(&a.path, &b.path));
+
+    let mut short: Vec<Short> = remaining
+        .into_iter()
+        .filter(|(_, n)| *n > 0)
+        .map(|(oid, n)| Short {
+            card: pool
+                .cards
+                .get(oid)
+                .map(|c| c.name.to_string())
+                .unwrap_or_default(),
+            oid: oid.to_string(),
+            quantity: n,
+        })
+        .collect();
+    short.sort_by(|a, b| a.card.cmp(&b.card));
+
+    PickList { stops, short }
+}
+
+// Turn a pick list into the changes needed to move everything on it
+// into a (deck box) location.
+pub fn pulled(list: &PickList, into: &str) -> Vec<Stowed> {
+    let mut changes = vec![];
+    for stop in &list.stops {
+        for p in &stop.picks {
+            for (location, sign) in &[(stop.location.as_str(), -1), (into, 1)] {
+                changes.push(Stowed {
+                    location: location.to_string(),
+                    pid: p.pid.to_string(),
+                    var: p.var.clone(),
+                    quantity: sign * p.quantity,
+                });
+            }
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    fn lookup() -> card::Map {
+        let mut lookup = card::Map::new();
+        lookup.insert("XLN * Opt".to_string(), "opt".to_string());
+        lookup.insert("GRN * Radical Idea".to_string(), "idea".to_string());
+        lookup
+    }
+
+    fn paths() -> HashMap<String, String> {
+        let mut paths = HashMap::new();
+        paths.insert("binder-3".to_string(), "b3".to_string());
+        paths.insert("box-1/row-2".to_string(), "r2".to_string());
+        paths
+    }
+
+    fn collection(cards: &[(u32, &str, &[&str])]) -> card::Collection {
+        card::Collection {
+            cards: cards
+                .iter()
+                .map(|(n, pid, var)| {
+                    (
+                        *n,
+                        card::OwnedCard {
+                            pid: pid.to_string(),
+                            var: var.iter().map(|v| v.to_string()).collect(),
+                            grade: None,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn stowed(location: &str, pid: &str, var: &[&str], quantity: i32) -> Stowed {
+        Stowed {
+            location: location.to_string(),
+            pid: pid.to_string(),
+            var: var.iter().map(|v| v.to_string()).collect(),
+            quantity,
+        }
+    }
+
+    #[test]
+    fn should_check_the_location_hierarchy() {
+        assert_eq!(Kind::Box, check("box-1", "box", None).unwrap());
+        assert_eq!(Kind::Row, check("box-1.row-2", "row", Some("box")).unwrap());
+        assert_eq!(Kind::Page, check("p1", "page", Some("binder")).unwrap());
+        assert_eq!(Kind::Deckbox, check("elves", "deck-box", None).unwrap());
+
+        for (name, kind, parent, err) in &[
+            ("row-2", "row", None, "invalid-location-parent"),
+            ("row-2", "row", Some("binder"), "invalid-location-parent"),
+            ("box-2", "box", Some("box"), "invalid-location-parent"),
+            ("shoe", "shoebox", None, "invalid-location-kind"),
+            ("binder 3", "binder", None, "invalid-location-name"),
+            ("binder)", "binder", None, "invalid-location-name"),
+        ] {
+            assert_eq!(
+                *err,
+                check(name, kind, *parent).unwrap_err().to_string(),
+                "{} ({})",
+                name,
+                kind
+            );
+        }
+
+        let locations = vec![
+            Location {
+                id: "b1".to_string(),
+                parent: None,
+                name: "box-1".to_string(),
+                kind: Kind::Box,
+            },
+            Location {
+                id: "r2".to_string(),
+                parent: Some("b1".to_string()),
+                name: "row-2".to_string(),
+                kind: Kind::Row,
+            },
+        ];
+        assert_eq!("box-1/row-2", path(&locations, "r2"));
+        assert_eq!("box-1", path(&locations, "b1"));
+    }
+
+    #[test]
+    fn should_order_locations_by_their_place_in_the_tree() {
+        let mut paths = vec![
+            "binder-10",
+            "binder-2/page-10",
+            "binder-2",
+            "binder-2/page-2",
+            "binder-2-spare",
+            "box-1/row-002",
+            "box-1/row-10",
+        ];
+        paths.sort_by(|a, b| by_position(a, b));
+        assert_eq!(
+            vec![
+                "binder-2",
+                "binder-2/page-2",
+                "binder-2/page-10",
+                "binder-2-spare",
+                "binder-10",
+                "box-1/row-002",
+                "box-1/row-10",
+            ],
+            paths
+        );
+
+        let mut cards = vec![
+            "XLN *10 Opt",
+            "GRN *2 Radical Idea",
+            "XLN *2 Ixalan",
+            "XLN *1 Opt",
+        ];
+        cards.sort_by(|a, b| natural(a, b));
+        assert_eq!(
+            vec![
+                "GRN *2 Radical Idea",
+                "XLN *1 Opt",
+                "XLN *2 Ixalan",
+                "XLN *10 Opt"
+            ],
+            cards
+        );
+    }
+
+    #[test]
+    fn should_resolve_locations_by_path() {
+        let location = |id: &str, parent: Option<&str>, name: &str| Location {
+            id: id.to_string(),
+            parent: parent.map(|p| p.to_string()),
+            name: name.to_string(),
+            kind: Kind::Page,
+        };
+        let locations = vec![
+            location("b1", None, "binder-1"),
+            location("b2", None, "binder-2"),
+            location("b1p1", Some("b1"), "page-1"),
+            location("b2p1", Some("b2"), "page-1"),
+        ];
+
+        let paths = super::paths(&locations);
+        assert_eq!(4, paths.len());
+        assert_eq!("b1p1", paths["binder-1/page-1"]);
+        assert_eq!("b2p1", paths["binder-2/page-1"]);
+        assert!(!paths.contains_key("page-1"));
+    }
+
+    #[test]
+    fn should_stow_tagged_cards_from_transactions() {
+        let gain = cdif::File::from_string(
+            "2x XLN Opt | foil (loc: binder-3)\n1x XLN Opt\n3x GRN Radical Idea | (loc: box-1/row-2)\n",
+        )
+        .unwrap();
+        let loss = cdif::File::from_string("1x XLN Opt | (loc: binder-3)\n").unwrap();
+
+        let mut got = changes(&lookup(), &paths(), &gain, &loss).unwrap();
+        got.sort_by_key(|s| s.quantity);
+        assert_eq!(
+            vec![
+                stowed("b3", "opt", &[], -1),
+                stowed("b3", "opt", &["foil"], 2),
+                stowed("r2", "idea", &[], 3),
+            ],
+            got
+        );
+
+        let gain = cdif::File::from_string("1x XLN Opt | (loc: shoebox)\n").unwrap();
+        let loss = cdif::File::from_string("").unwrap();
+        assert_eq!(
+            "unknown-location: shoebox",
+            changes(&lookup(), &paths(), &gain, &loss)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn should_verify_moves_and_sync_with_the_collection() {
+        let mine = collection(&[(4, "opt", &[]), (1, "opt", &["foil"])]);
+        let held = vec![stowed("b3", "opt", &[], 3), stowed("r2", "opt", &[], 1)];
+
+        let cards = cdif::File::from_string("2x XLN Opt\n").unwrap();
+        let moved = shift(&lookup(), Some("b3"), Some("r2"), &cards).unwrap();
+        assert!(verify(&mine, &held, &moved).is_ok());
+
+        let cards = cdif::File::from_string("2x XLN Opt\n").unwrap();
+        let moved = shift(&lookup(), Some("r2"), Some("b3"), &cards).unwrap();
+        assert_eq!(
+            "not-stowed",
+            verify(&mine, &held, &moved).unwrap_err().to_string()
+        );
+
+        let cards = cdif::File::from_string("1x XLN Opt\n1x XLN Opt | foil\n").unwrap();
+        let moved = shift(&lookup(), None, Some("r2"), &cards).unwrap();
+        assert_eq!(
+            "not-owned",
+            verify(&mine, &held, &moved).unwrap_err().to_string()
+        );
+
+        // sold two of the non-foil copies, without saying where from
+        let mine = collection(&[(2, "opt", &[]), (1, "opt", &["foil"])]);
+        assert_eq!(
+            vec![stowed("r2", "opt", &[], -1), stowed("b3", "opt", &[], -1)],
+            sync(&mine, &held)
+        );
+    }
+
+    #[test]
+    fn should_plan_a_pick_list_for_a_deck() {
+        let pool = card::Pool::from_file("test/cards.json").expect("reading card pool");
+        let prints = pool.prints();
+        let pid = |set: &str, number: &str| {
+            pool.sets[set]
+                .cards
+                .iter()
+                .find(|p| p.number == number)
+                .unwrap()
+                .id
+                .to_string()
+        };
+        let (avr, vis, mir) = (pid("AVR", "5"), pid("VIS", "1"), pid("MIR", "1"));
+        let oid = |pid: &str| prints[pid].1.oid.to_string();
+
+        let location = |id: &str, parent: Option<&str>, kind: Kind| Location {
+            id: id.to_string(),
+            parent: parent.map(|p| p.to_string()),
+            name: id.to_string(),
+            kind,
+        };
+        let locations = vec![
+            location("binder-1", None, Kind::Binder),
+            location("p1", Some("binder-1"), Kind::Page),
+            location("box-1", None, Kind::Box),
+            location("angels", None, Kind::Deckbox),
+            location("spirits", None, Kind::Deckbox),
+        ];
+        let held = vec![
+            stowed("p1", &avr, &[], 2),
+            stowed("p1", &mir, &[], 1),
+            stowed("box-1", &vis, &["foil"], 3),
+            stowed("spirits", &mir, &[], 2),
+            stowed("angels", &mir, &[], 1),
+        ];
+
+        let mut needs = HashMap::new();
+        needs.insert(oid(&avr), 6);
+        needs.insert(oid(&mir), 2);
+
+        let mut on_hand = Holdings::new();
+        for s in &held {
+            *on_hand
+                .entry((s.pid.to_string(), card::Finish::of(&s.var)))
+                .or_insert(0) += s.quantity as i64;
+        }
+
+        let list = pick(&pool, &needs, &held, &locations, Some("angels"), &on_hand);
+        assert_eq!(2, list.stops.len());
+        assert_eq!("binder-1/p1", list.stops[0].path);
+        assert_eq!(
+            vec![2, 1],
+            list.stops[0]
+                .picks
+                .iter()
+                .map(|p| p.quantity)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("box-1", list.stops[1].path);
+        assert_eq!(3, list.stops[1].picks[0].quantity);
+        assert_eq!(vec!["foil".to_string()], list.stops[1].picks[0].var);
+
+        assert_eq!(1, list.short.len());
+        assert_eq!("Archangel", list.short[0].card);
+        assert_eq!(1, list.short[0].quantity);
+
+        let changes = pulled(&list, "angels");
+        assert_eq!(6, changes.len());
+        assert_eq!(
+            6,
+            changes
+                .iter()
+                .filter(|s| s.location == "angels")
+                .map(|s| s.quantity)
+                .sum::<i32>()
+        );
+
+        // with one of the binder's Archangels out on loan, there's one
+        // fewer to pick
+        *on_hand
+            .get_mut(&(avr.to_string(), card::Finish::Nonfoil))
+            .unwrap() -= 1;
+        let list = pick(&pool, &needs, &held, &locations, Some("angels"), &on_hand);
+        assert_eq!("binder-1/p1", list.stops[0].path);
+        assert_eq!(
+            vec![1, 1],
+            list.stops[0]
+                .picks
+                .iter()
+                .map(|p| p.quantity)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, list.short[0].quantity);
+    }
+}
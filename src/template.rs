@@ -0,0 +1,253 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::HashMap;
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// Transaction templates are transactions-in-waiting: a summary, notes,
+// and gain / loss CDIF, any of which can have {placeholders} in them,
+// filled in each time the template is used.  For example:
+//
+//     summary: {set} prerelease kit
+//     gain:    6x {set} Booster Pack
+//
+// Placeholders are named with lowercase letters, digits and underscores.
+// A few are always bound, from the date of the transaction:
+//
+//     {date}    the date, as YYYY-MM-DD
+//     {year}    the year, i.e. 2020
+//     {month}   the month, as two digits, i.e. 09
+//
+// and anything else has to be given a value when the template is used.
+// Literal braces are written doubled: {{ and }}.
+//
+const BUILTIN: [&str; 3] = ["date", "year", "month"];
+
+enum Part<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+fn parse(s: &str) -> Result<Vec<Part<'_>>> {
+    let mut parts = vec![];
+    let mut rest = s;
+    while let Some(i) = rest.find(['{', '}']) {
+        parts.push(Part::Text(&rest[..i]));
+        let (brace, after) = (&rest[i..=i], &rest[i + 1..]);
+        if after.starts_with(brace) {
+            parts.push(Part::Text(brace));
+            rest = &after[1..];
+            continue;
+        }
+        if brace == "}" {
+            return Err("unopened-placeholder".into());
+        }
+
+        let end = after
+            .find('}')
+            .ok_or_else(|| Error::from("unclosed-placeholder"))?;
+        let name = &after[..end];
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err(format!("invalid-placeholder: {}", name).into());
+        }
+        parts.push(Part::Placeholder(name));
+        rest = &after[end + 1..];
+    }
+    parts.push(Part::Text(rest));
+    Ok(parts)
+}
+
+// The names of all the placeholders in a bit of template text, in the
+// order they first appear, or a failure code if it can't be parsed.
+pub fn placeholders(s: &str) -> Result<Vec<String>> {
+    let mut names: Vec<String> = vec![];
+    for part in parse(s)? {
+        if let Part::Placeholder(name) = part {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    Ok(names)
+}
+
+// The names of the placeholders across several bits of template text
+// that have to be given values when the template is used, i.e. those
+// that aren't built-in.
+pub fn required(texts: &[&str]) -> Result<Vec<String>> {
+    let mut names: Vec<String> = vec![];
+    for text in texts {
+        for name in placeholders(text)? {
+            if !BUILTIN.contains(&name.as_str()) && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    Ok(names)
+}
+
+// Fill in the placeholders in a bit of template text, for a transaction
+// on the given date.  Values given explicitly win out over the built-in
+// date placeholders; placeholders with no value at all are a failure.
+pub fn render(s: &str, vars: &HashMap<String, String>, dated: NaiveDate) -> Result<String> {
+    let mut out = String::new();
+    for part in parse(s)? {
+        match part {
+            Part::Text(text) => out.push_str(text),
+            Part::Placeholder(name) => match (vars.get(name), name) {
+                (Some(value), _) => out.push_str(value),
+                (None, "date") => out.push_str(&dated.format("%Y-%m-%d").to_string()),
+                (None, "year") => out.push_str(&dated.year().to_string()),
+                (None, "month") => out.push_str(&format!("{:02}", dated.month())),
+                (None, _) => return Err(format!("unbound-placeholder: {}", name).into()),
+            },
+        }
+    }
+    Ok(out)
+}
+
+// How often a recurring transaction recurs.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Every {
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Every {
+    pub fn parse(s: &str) -> Option<Every> {
+        match s.trim().to_lowercase().as_str() {
+            "weekly" | "week" => Some(Every::Week),
+            "monthly" | "month" => Some(Every::Month),
+            "quarterly" | "quarter" => Some(Every::Quarter),
+            "yearly" | "year" | "annually" => Some(Every::Year),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Every::Week => "weekly",
+            Every::Month => "monthly",
+            Every::Quarter => "quarterly",
+            Every::Year => "yearly",
+        }
+    }
+
+    // The date of the nth occurrence (counting from zero) of something
+    // that started on the given date.  Occurrences are always worked out
+    // from the start, so that something starting on the 31st comes back
+    // around on the 31st, after a month that has fewer days.
+    pub fn nth(&self, starts_on: NaiveDate, n: i32) -> NaiveDate {
+        let months = match self {
+            Every::Week => return starts_on + Duration::weeks(n as i64),
+            Every::Month => n,
+            Every::Quarter => n * 3,
+            Every::Year => n * 12,
+        };
+
+        let m = starts_on.year() * 12 + starts_on.month0() as i32 + months;
+        let (year, month) = (m.div_euclid(12), m.rem_euclid(12) as u32 + 1);
+        (1..=starts_on.day())
+            .rev()
+            .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+            .unwrap_or(starts_on)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn should_render_placeholders() {
+        let dated = date(2020, 9, 25);
+        let render = |s, v: &[(&str, &str)]| render(s, &vars(v), dated).map_err(|e| e.to_string());
+
+        assert_eq!(
+            Ok("6x ZNR Booster Pack\n".to_string()),
+            render("6x {set} Booster Pack\n", &[("set", "ZNR")])
+        );
+        assert_eq!(
+            Ok("Secret Lair (2020-09) {drop} #1".to_string()),
+            render("Secret Lair ({year}-{month}) {{drop}} #{n}", &[("n", "1")])
+        );
+        assert_eq!(
+            Ok("bought 2020-09-25".to_string()),
+            render("bought {date}", &[])
+        );
+        assert_eq!(
+            Ok("1999".to_string()),
+            render("{year}", &[("year", "1999")])
+        );
+
+        assert_eq!(
+            Err("unbound-placeholder: set".to_string()),
+            render("{set} prerelease", &[])
+        );
+        assert_eq!(
+            Err("unclosed-placeholder".to_string()),
+            render("{set prerelease", &[])
+        );
+        assert_eq!(
+            Err("unopened-placeholder".to_string()),
+            render("set} prerelease", &[])
+        );
+        assert_eq!(
+            Err("invalid-placeholder: Set".to_string()),
+            render("{Set} prerelease", &[])
+        );
+    }
+
+    #[test]
+    fn should_list_placeholders() {
+        assert_eq!(
+            vec!["set", "date"],
+            placeholders("{set} on {date}, {{really}}, {set}").unwrap()
+        );
+        assert!(placeholders("no placeholders here").unwrap().is_empty());
+        assert!(placeholders("{}").is_err());
+
+        assert_eq!(
+            vec!["set", "n"],
+            required(&["{set} on {date}", "{n}x {set} Booster Pack"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn should_work_out_recurrences_from_the_start() {
+        let starts = date(2020, 1, 31);
+        assert_eq!(date(2020, 1, 31), Every::Month.nth(starts, 0));
+        assert_eq!(date(2020, 2, 29), Every::Month.nth(starts, 1));
+        assert_eq!(date(2020, 3, 31), Every::Month.nth(starts, 2));
+        assert_eq!(date(2021, 1, 31), Every::Month.nth(starts, 12));
+        assert_eq!(date(2020, 4, 30), Every::Quarter.nth(starts, 1));
+        assert_eq!(date(2020, 2, 14), Every::Week.nth(starts, 2));
+
+        let leap = date(2020, 2, 29);
+        assert_eq!(date(2021, 2, 28), Every::Year.nth(leap, 1));
+        assert_eq!(date(2024, 2, 29), Every::Year.nth(leap, 4));
+
+        assert_eq!(Some(Every::Month), Every::parse("Monthly"));
+        assert_eq!(None, Every::parse("fortnightly"));
+    }
+}
@@ -0,0 +1,124 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// Personal API tokens let a collector's scripts in without logging in
+// (and keeping a session alive).  Each token is only good for what it
+// was scoped to when it was made:
+//
+// read-collection      see collections and their transactions'
+//                      attachments, and export them.
+// write-transactions   post, change and remove transactions (including
+//                      transfers), their attachments, and slab values.
+// manage-decks         create, change and remove decks, and pull cards
+//                      for them.
+//
+// Everything else (collector settings, sharing, other tokens, ...)
+// still needs a real session.
+//
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scope {
+    ReadCollection,
+    WriteTransactions,
+    ManageDecks,
+}
+
+pub const ALL: [Scope; 3] = [
+    Scope::ReadCollection,
+    Scope::WriteTransactions,
+    Scope::ManageDecks,
+];
+
+impl Scope {
+    pub fn parse(s: &str) -> Result<Scope> {
+        let scope = s.trim().to_lowercase();
+        match ALL.iter().find(|k| k.as_str() == scope) {
+            Some(k) => Ok(*k),
+            None => Err(format!("invalid-scope: {}", s.trim()).into()),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ReadCollection => "read-collection",
+            Scope::WriteTransactions => "write-transactions",
+            Scope::ManageDecks => "manage-decks",
+        }
+    }
+}
+
+// Tokens all start with this, so that the guards can tell them apart
+// from session ids (which are UUIDs).
+pub const PREFIX: &str = "vcb_";
+
+pub fn generate() -> String {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .collect();
+    format!("{}{}", PREFIX, secret)
+}
+
+pub fn is_token(s: &str) -> bool {
+    s.starts_with(PREFIX)
+}
+
+// Only the hash of a token is ever stored.  Tokens are long and random
+// enough that a plain (unsalted) SHA-256 is fine, and it lets us look
+// them up by hash, which bcrypt wouldn't.
+pub fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+// The last few characters of a token, for telling tokens apart once
+// the token itself is gone.
+pub fn hint(token: &str) -> String {
+    token.chars().skip(token.len().saturating_sub(4)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_parse_scopes() {
+        assert_eq!(
+            Scope::ReadCollection,
+            Scope::parse("read-collection").unwrap()
+        );
+        assert_eq!(Scope::ManageDecks, Scope::parse(" Manage-Decks ").unwrap());
+        for s in ALL.iter() {
+            assert_eq!(*s, Scope::parse(s.as_str()).unwrap());
+        }
+        assert_eq!(
+            "invalid-scope: admin",
+            Scope::parse("admin").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn should_generate_and_hash_tokens() {
+        let (a, b) = (generate(), generate());
+        assert_eq!(44, a.len());
+        assert!(is_token(&a));
+        assert!(!is_token("e0a5b5c2-3b4e-4a5e-9c1e-0d1f2a3b4c5d"));
+        assert_ne!(a, b);
+
+        assert_eq!(64, hash(&a).len());
+        assert_eq!(hash(&a), hash(&a));
+        assert_ne!(hash(&a), hash(&b));
+        assert_eq!(
+            "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae",
+            hash("foo")
+        );
+
+        assert_eq!("wxyz", hint("vcb_abcwxyz"));
+        assert_eq!("ab", hint("ab"));
+    }
+}
@@ -0,0 +1,361 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::disposition::Disposition;
+use crate::prelude::*;
+use crate::valuation::{Holdings, Key};
+use crate::{card, cdif};
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// One line of a pile of cards changing hands.
+pub struct Line {
+    pub card: String, // CDIF identifier, i.e. "MIR *1 Afterlife | foil"
+    pub key: Key,
+    pub quantity: i64,
+}
+
+// Resolve a pile of cards (as CDIF) to print ids.  Unlike transactions,
+// trades must only contain cards we know about; we can't check that
+// anyone owns a card we can't find.
+pub fn resolve(lookup: &card::Map, pile: &str) -> Result<Vec<Line>> {
+    let file = cdif::File::from_string(pile).chain_err(|| "invalid-cdif")?;
+    let mut lines = vec![];
+    for line in file.lines.values() {
+        let id = format!("{} *{} {}", line.set, line.number, line.oracle);
+        match lookup.get(&id) {
+            Some(pid) => lines.push(Line {
+                card: line.id(),
+                key: (pid.to_string(), card::Finish::of(&line.gvars)),
+                quantity: line.quantity as i64,
+            }),
+            None => return Err(format!("unknown-card: {} {}", line.set, line.oracle).into()),
+        }
+    }
+    lines.sort_by(|a, b| a.card.cmp(&b.card));
+    Ok(lines)
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Shortfall {
+    pub id: String, // print id
+    pub finish: card::Finish,
+    pub want: i64,
+    pub have: i64,
+}
+
+// Find the cards in a pile that the holder doesn't have enough of.
+// Copies are matched on print and finish; condition and other variants
+// don't come into it.
+pub fn shortfall(holdings: &Holdings, pile: &[Line]) -> Vec<Shortfall> {
+    let mut want: BTreeMap<&Key, i64> = BTreeMap::new();
+    for line in pile {
+        *want.entry(&line.key).or_insert(0) += line.quantity;
+    }
+
+    want.into_iter()
+        .filter_map(|(key, want)| {
+            let have = *holdings.get(key).unwrap_or(&0);
+            if have < want {
+                Some(Shortfall {
+                    id: key.0.to_string(),
+                    finish: key.1,
+                    want,
+                    have,
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+pub struct Priced {
+    pub card: String,
+    pub quantity: i64,
+    pub price: Option<i64>, // pennies, per card
+}
+
+#[derive(Serialize)]
+pub struct Side {
+    pub cards: Vec<Priced>,
+    pub value: i64,    // pennies
+    pub unpriced: i64, // cards we have no market price for
+}
+
+pub fn value(prices: &card::Prices, pile: &[Line], currency: card::Currency) -> Side {
+    let mut side = Side {
+        cards: vec![],
+        value: 0,
+        unpriced: 0,
+    };
+    for line in pile {
+        let price = prices
+            .get(&line.key.0)
+            .and_then(|q| q.price(line.key.1, currency))
+            .map(|p| (p * 100.0).round() as i64);
+        match price {
+            Some(p) => side.value += p * line.quantity,
+            None => side.unpriced += line.quantity,
+        }
+        side.cards.push(Priced {
+            card: line.card.to_string(),
+            quantity: line.quantity,
+            price,
+        });
+    }
+    side
+}
+
+// Both sides of a trade, at current market prices.  The difference is
+// from the proposer's point of view: positive when they are giving up
+// more than they are getting.
+#[derive(Serialize)]
+pub struct Balance {
+    pub offer: Side,
+    pub request: Side,
+    pub difference: i64, // pennies
+}
+
+pub fn balance(
+    prices: &card::Prices,
+    offer: &[Line],
+    request: &[Line],
+    currency: card::Currency,
+) -> Balance {
+    let offer = value(prices, offer, currency);
+    let request = value(prices, request, currency);
+    let difference = offer.value - request.value;
+    Balance {
+        offer,
+        request,
+        difference,
+    }
+}
+
+// Check that both sides of a proposed trade have what they are giving
+// up on hand (i.e. not out on loan), returning a failure code suitable
+// for the API if not.
+pub fn check(
+    lookup: &card::Map,
+    offer: &str,
+    request: &str,
+    proposer: &Holdings,
+    recipient: &Holdings,
+) -> Result<()> {
+    let offer = resolve(lookup, offer)?;
+    let request = resolve(lookup, request)?;
+    if offer.is_empty() && request.is_empty() {
+        return Err("empty-trade".into());
+    }
+    if !shortfall(proposer, &offer).is_empty() {
+        return Err("offer-not-owned".into());
+    }
+    if !shortfall(recipient, &request).is_empty() {
+        return Err("request-not-owned".into());
+    }
+    Ok(())
+}
+
+// Trades are posted to each collector's default collection, but what
+// they give up can be in any of their collections.  Copies are taken
+// from the default collection (the first of `held`) as far as it goes,
+// and then from each of the others in turn; this works out what has to
+// be moved into the default collection from each of the others first.
+pub fn sources(pile: &[Line], held: &[(Uuid, Holdings)]) -> Vec<(Uuid, String)> {
+    let mut left: Vec<Holdings> = held.iter().map(|(_, h)| h.clone()).collect();
+    let mut moved = vec![String::new(); held.len()];
+    for line in pile {
+        let mut need = line.quantity;
+        for (i, holdings) in left.iter_mut().enumerate() {
+            let have = holdings.entry(line.key.clone()).or_insert(0);
+            let n = need.min(*have);
+            if n <= 0 {
+                continue;
+            }
+            *have -= n;
+            need -= n;
+            if i > 0 {
+                moved[i].push_str(&format!("{}x {}\n", n, line.card));
+            }
+        }
+    }
+
+    held.iter()
+        .map(|(id, _)| *id)
+        .zip(moved)
+        .filter(|(_, cards)| !cards.is_empty())
+        .collect()
+}
+
+// How an accepted trade goes down in the books.  With nothing coming
+// back the other way, it's a gift; trades proper need both sides.
+pub fn disposition(offer: &str, request: &str) -> Disposition {
+    let empty = |pile: &str| match cdif::File::from_string(pile) {
+        Ok(f) => f.lines.is_empty(),
+        Err(_) => true,
+    };
+    if empty(offer) || empty(request) {
+        Disposition::Gift
+    } else {
+        Disposition::Trade
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{loan, valuation};
+
+    fn lookup() -> card::Map {
+        let mut lookup = card::Map::new();
+        lookup.insert("XLN * Opt".to_string(), "opt".to_string());
+        lookup.insert("GRN * Radical Idea".to_string(), "idea".to_string());
+        lookup
+    }
+
+    fn collection(cards: &[(u32, &str, &[&str])]) -> card::Collection {
+        card::Collection {
+            cards: cards
+                .iter()
+                .map(|(n, pid, var)| {
+                    (
+                        *n,
+                        card::OwnedCard {
+                            pid: pid.to_string(),
+                            var: var.iter().map(|v| v.to_string()).collect(),
+                            grade: None,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn should_resolve_trade_piles() {
+        let lines = resolve(&lookup(), "2x XLN Opt | foil\n1x GRN Radical Idea\n").unwrap();
+        assert_eq!(2, lines.len());
+        assert_eq!("GRN Radical Idea", lines[0].card);
+        assert_eq!(("opt".to_string(), card::Finish::Foil), lines[1].key);
+        assert_eq!(2, lines[1].quantity);
+
+        assert!(resolve(&lookup(), "1x XLN Lightning Bolt\n").is_err());
+    }
+
+    #[test]
+    fn should_find_cards_that_are_not_owned() {
+        let mine = collection(&[(3, "opt", &["NM"]), (1, "opt", &["foil"])]);
+        let holdings = valuation::holdings(&mine);
+
+        let pile = resolve(&lookup(), "3x XLN Opt\n1x XLN Opt | foil\n").unwrap();
+        assert!(shortfall(&holdings, &pile).is_empty());
+
+        let pile = resolve(&lookup(), "2x XLN Opt | foil\n1x GRN Radical Idea\n").unwrap();
+        assert_eq!(
+            vec![
+                Shortfall {
+                    id: "idea".to_string(),
+                    finish: card::Finish::Nonfoil,
+                    want: 1,
+                    have: 0,
+                },
+                Shortfall {
+                    id: "opt".to_string(),
+                    finish: card::Finish::Foil,
+                    want: 2,
+                    have: 1,
+                },
+            ],
+            shortfall(&holdings, &pile)
+        );
+    }
+
+    #[test]
+    fn should_check_and_balance_both_sides_of_a_trade() {
+        let mine = valuation::holdings(&collection(&[(4, "opt", &[])]));
+        let theirs = valuation::holdings(&collection(&[(1, "idea", &[])]));
+
+        assert!(check(&lookup(), "4x XLN Opt\n", "1x GRN Radical Idea\n", &mine, &theirs).is_ok());
+        assert_eq!(
+            "offer-not-owned",
+            check(&lookup(), "5x XLN Opt\n", "", &mine, &theirs)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "request-not-owned",
+            check(&lookup(), "", "2x GRN Radical Idea\n", &mine, &theirs)
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(check(&lookup(), "", "", &mine, &theirs).is_err());
+
+        assert_eq!(
+            Disposition::Trade,
+            disposition("4x XLN Opt\n", "1x GRN Radical Idea\n")
+        );
+        assert_eq!(Disposition::Gift, disposition("4x XLN Opt\n", ""));
+        assert_eq!(Disposition::Gift, disposition("", "1x GRN Radical Idea\n"));
+
+        // cards out on loan aren't there to be traded
+        let lent = loan::on_hand(
+            &lookup(),
+            &collection(&[(4, "opt", &[])]),
+            &["1x XLN Opt\n"],
+        );
+        assert_eq!(
+            "offer-not-owned",
+            check(&lookup(), "4x XLN Opt\n", "", &lent, &theirs)
+                .unwrap_err()
+                .to_string()
+        );
+
+        let mut prices = card::Prices::new();
+        prices.insert(
+            "opt".to_string(),
+            card::Quote {
+                usd: Some(0.25),
+                ..card::Quote::default()
+            },
+        );
+        let offer = resolve(&lookup(), "4x XLN Opt\n").unwrap();
+        let request = resolve(&lookup(), "1x GRN Radical Idea\n").unwrap();
+        let balance = balance(&prices, &offer, &request, card::Currency::Usd);
+        assert_eq!(100, balance.offer.value);
+        assert_eq!(0, balance.request.value);
+        assert_eq!(1, balance.request.unpriced);
+        assert_eq!(100, balance.difference);
+    }
+
+    #[test]
+    fn should_take_cards_from_other_collections_when_the_default_runs_short() {
+        let (main, binder, box_) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let held = vec![
+            (main, valuation::holdings(&collection(&[(1, "opt", &[])]))),
+            (binder, valuation::holdings(&collection(&[(2, "opt", &[])]))),
+            (
+                box_,
+                valuation::holdings(&collection(&[(5, "opt", &[]), (1, "idea", &[])])),
+            ),
+        ];
+
+        let pile = resolve(&lookup(), "1x XLN Opt\n").unwrap();
+        assert!(sources(&pile, &held).is_empty());
+
+        let pile = resolve(&lookup(), "4x XLN Opt\n1x GRN Radical Idea\n").unwrap();
+        assert_eq!(
+            vec![
+                (binder, "2x XLN Opt\n".to_string()),
+                (box_, "1x GRN Radical Idea\n1x XLN Opt\n".to_string()),
+            ],
+            sources(&pile, &held)
+        );
+    }
+}
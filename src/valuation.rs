@@ -0,0 +1,406 @@
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::{card, cdif};
+
+// A print id, and how the copies of that print are finished (foils
+// and etched foils are priced separately).
+pub type Key = (String, card::Finish);
+
+// How many copies of each print a collector holds.
+pub type Holdings = HashMap<Key, i64>;
+
+pub fn holdings(collection: &card::Collection) -> Holdings {
+    let mut holdings = Holdings::new();
+    for (n, card) in &collection.cards {
+        *holdings
+            .entry((card.pid.to_string(), card::Finish::of(&card.var)))
+            .or_insert(0) += *n as i64;
+    }
+    holdings
+}
+
+// Holdings of raw (ungraded) copies only.  Graded copies are valued on
+// their own, and have nothing to do with the market price of the print.
+pub fn raw_holdings(collection: &card::Collection) -> Holdings {
+    let mut holdings = Holdings::new();
+    for (n, card) in collection.cards.iter().filter(|(_, c)| c.grade.is_none()) {
+        *holdings
+            .entry((card.pid.to_string(), card::Finish::of(&card.var)))
+            .or_insert(0) += *n as i64;
+    }
+    holdings
+}
+
+// A dated change to a collection, i.e. a transaction, with its gains
+// and losses resolved to print ids.  Cards that cannot be found in the
+// lookup table are ignored, as are graded copies (see raw_holdings).
+pub struct Change {
+    pub dated: NaiveDate,
+    pub delta: Holdings,
+}
+
+// Resolve the cards in a CDIF file to print ids, returning the key,
+// set code and quantity of each.  Cards that cannot be found in the
+// lookup table are skipped.
+pub fn resolve(lookup: &card::Map, file: &cdif::File) -> Vec<(Key, String, i64)> {
+    let mut cards = vec![];
+    for line in file.lines.values() {
+        let key = format!("{} *{} {}", line.set, line.number, line.oracle);
+        if let Some(pid) = lookup.get(&key) {
+            cards.push((
+                (pid.to_string(), card::Finish::of(&line.gvars)),
+                line.set.to_string(),
+                line.quantity as i64,
+            ));
+        }
+    }
+    cards
+}
+
+impl Change {
+    pub fn resolve(
+        lookup: &card::Map,
+        dated: NaiveDate,
+        gain: &cdif::File,
+        loss: &cdif::File,
+    ) -> Change {
+        let mut delta = Holdings::new();
+        for (file, sign) in &[(gain, 1), (loss, -1)] {
+            for line in file.lines.values() {
+                if card::Grade::from_lvars(&line.lvars).is_some() {
+                    continue;
+                }
+                let key = format!("{} *{} {}", line.set, line.number, line.oracle);
+                if let Some(pid) = lookup.get(&key) {
+                    *delta
+                        .entry((pid.to_string(), card::Finish::of(&line.gvars)))
+                        .or_insert(0) += sign * line.quantity as i64;
+                }
+            }
+        }
+        Change { dated, delta }
+    }
+}
+
+fn pennies(price: f64) -> i64 {
+    (price * 100.0).round() as i64
+}
+
+// The price of a single card, as of the i'th snapshot in the history,
+// in pennies (or cents, or hundredths of a ticket) of the currency.
+pub fn price_at(
+    history: &card::History,
+    i: usize,
+    key: &Key,
+    currency: card::Currency,
+) -> Option<i64> {
+    history
+        .quote(&key.0, i)
+        .and_then(|q| q.price(key.1, currency))
+        .map(pennies)
+}
+
+fn value_at(
+    history: &card::History,
+    i: usize,
+    holdings: &Holdings,
+    currency: card::Currency,
+) -> i64 {
+    holdings
+        .iter()
+        .filter(|(_, n)| **n > 0)
+        .map(|(key, n)| price_at(history, i, key, currency).unwrap_or(0) * n)
+        .sum()
+}
+
+#[derive(Serialize)]
+pub struct Valuation {
+    pub dated: NaiveDate,
+    pub value: i64, // pennies
+}
+
+// Value a collection as of every price snapshot in the history, using
+// what the collection held on that day.  Holdings are reconstructed by
+// starting from what is held today, and walking backwards through the
+// transactions, undoing each one as we pass its date.
+pub fn timeline(
+    history: &card::History,
+    current: &Holdings,
+    mut changes: Vec<Change>,
+    currency: card::Currency,
+) -> Vec<Valuation> {
+    changes.sort_by_key(|c| Reverse(c.dated));
+    let mut changes = changes.into_iter().peekable();
+    let mut holdings = current.clone();
+
+    let mut timeline = vec![];
+    for (i, dated) in history.dates.iter().enumerate().rev() {
+        while let Some(change) = changes.peek() {
+            if change.dated <= *dated {
+                break;
+            }
+            for (k, n) in &change.delta {
+                *holdings.entry(k.clone()).or_insert(0) -= n;
+            }
+            changes.next();
+        }
+        timeline.push(Valuation {
+            dated: *dated,
+            value: value_at(history, i, &holdings, currency),
+        });
+    }
+    timeline.reverse();
+    timeline
+}
+
+#[derive(Serialize)]
+pub struct Mover {
+    pub id: String, // print id
+    pub finish: card::Finish,
+    pub quantity: i64,
+    pub then: i64,   // pennies, per card
+    pub now: i64,    // pennies, per card
+    pub change: i64, // pennies, across all copies held
+}
+
+// How far back movers will look.  Price history doesn't go back
+// anywhere near this far, but the cap keeps date arithmetic well clear
+// of overflowing.
+pub const MAX_DAYS: i64 = 3650;
+
+// Find the cards in a collection whose value has moved the most (in
+// either direction) over the last few days of price history.
+pub fn movers(
+    history: &card::History,
+    holdings: &Holdings,
+    currency: card::Currency,
+    days: i64,
+    limit: usize,
+) -> Vec<Mover> {
+    let (now, then) = match history.dates.last() {
+        Some(last) => (
+            history.dates.len() - 1,
+            history
+                .index_at(*last - Duration::days(days.min(MAX_DAYS)))
+                .unwrap_or(0),
+        ),
+        None => return vec![],
+    };
+
+    let mut movers = vec![];
+    for (key, n) in holdings {
+        if *n <= 0 {
+            continue;
+        }
+        if let (Some(a), Some(b)) = (
+            price_at(history, then, key, currency),
+            price_at(history, now, key, currency),
+        ) {
+            if a != b {
+                movers.push(Mover {
+                    id: key.0.to_string(),
+                    finish: key.1,
+                    quantity: *n,
+                    then: a,
+                    now: b,
+                    change: (b - a) * n,
+                });
+            }
+        }
+    }
+
+    movers.sort_by(|a, b| {
+        b.change
+            .abs()
+            .cmp(&a.change.abs())
+            .then_with(|| (&a.id, a.finish).cmp(&(&b.id, b.finish)))
+    });
+    movers.truncate(limit);
+    movers
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 8, d).unwrap()
+    }
+
+    fn quote(usd: f64, foil: Option<f64>) -> card::Quote {
+        card::Quote {
+            usd: Some(usd),
+            usd_foil: foil,
+            ..card::Quote::default()
+        }
+    }
+
+    fn owned(pid: &str, vars: &[&str]) -> card::OwnedCard {
+        card::OwnedCard {
+            pid: pid.to_string(),
+            var: vars.iter().map(|v| v.to_string()).collect(),
+            grade: None,
+        }
+    }
+
+    fn history() -> card::History {
+        let mut history = card::History::default();
+        let mut quotes = HashMap::new();
+
+        quotes.insert("opt".to_string(), quote(0.10, Some(1.00)));
+        history.record(day(1), &quotes);
+
+        quotes.insert("opt".to_string(), quote(0.20, Some(2.00)));
+        quotes.insert("idea".to_string(), quote(0.50, None));
+        history.record(day(2), &quotes);
+
+        quotes.insert("opt".to_string(), quote(0.25, Some(1.50)));
+        history.record(day(3), &quotes);
+        history
+    }
+
+    #[test]
+    fn should_record_price_history_by_column() {
+        let mut history = history();
+        assert_eq!(vec![day(1), day(2), day(3)], history.dates);
+        assert_eq!(
+            vec![None, Some(0.50), Some(0.50)],
+            history.prints["idea"].usd
+        );
+        assert_eq!(Some(1), history.index_at(day(2)));
+        assert_eq!(None, history.index_at(day(1) - Duration::days(1)));
+
+        // re-recording a day replaces it
+        history.record(day(3), &HashMap::new());
+        assert_eq!(3, history.dates.len());
+        assert_eq!(None, history.quote("opt", 2).unwrap().usd);
+        assert_eq!(3, history.prints["opt"].tix.len());
+    }
+
+    #[test]
+    fn should_value_collections_over_time() {
+        let history = history();
+        let mut lookup = card::Map::new();
+        lookup.insert("XLN * Opt".to_string(), "opt".to_string());
+        lookup.insert("GRN * Radical Idea".to_string(), "idea".to_string());
+
+        // we hold 2 Opts (one foil) and 4 Radical Ideas today, but the
+        // Radical Ideas only showed up on the 3rd.
+        let mut current = Holdings::new();
+        current.insert(("opt".to_string(), card::Finish::Nonfoil), 1);
+        current.insert(("opt".to_string(), card::Finish::Foil), 1);
+        current.insert(("idea".to_string(), card::Finish::Nonfoil), 4);
+
+        let gain = cdif::File::from_string("4x GRN Radical Idea\n").unwrap();
+        let loss = cdif::File::from_string("").unwrap();
+        let changes = vec![Change::resolve(&lookup, day(3), &gain, &loss)];
+
+        let values: Vec<i64> = timeline(&history, &current, changes, card::Currency::Usd)
+            .iter()
+            .map(|v| v.value)
+            .collect();
+        assert_eq!(vec![110, 220, 375], values);
+    }
+
+    #[test]
+    fn should_find_the_biggest_movers() {
+        let history = history();
+        let mut holdings = Holdings::new();
+        holdings.insert(("opt".to_string(), card::Finish::Foil), 1);
+        holdings.insert(("idea".to_string(), card::Finish::Nonfoil), 4);
+        holdings.insert(("opt".to_string(), card::Finish::Nonfoil), 10);
+
+        let movers = movers(&history, &holdings, card::Currency::Usd, 2, 10);
+        assert_eq!(2, movers.len());
+        assert_eq!(150, movers[0].change); // ten opts, up 15 cents each
+        assert_eq!(card::Finish::Nonfoil, movers[0].finish);
+        assert_eq!(50, movers[1].change); // foil opt, up 50 cents
+
+        // a tie, between the non-foil opts and the foil
+        let movers = super::movers(&history, &holdings, card::Currency::Usd, 1, 2);
+        assert_eq!(2, movers.len());
+        assert_eq!(50, movers[0].change);
+        assert_eq!(card::Finish::Nonfoil, movers[0].finish);
+        assert_eq!(-50, movers[1].change);
+
+        // looking further back than there is history starts at the start
+        let all = super::movers(&history, &holdings, card::Currency::Usd, 2, 10);
+        let far = super::movers(&history, &holdings, card::Currency::Usd, i64::MAX, 10);
+        assert_eq!(all.len(), far.len());
+        assert_eq!(all[0].change, far[0].change);
+    }
+
+    #[test]
+    fn should_value_each_finish_in_the_preferred_currency() {
+        let mut history = card::History::default();
+        let mut prices = card::Prices::new();
+        prices.insert(
+            "sol-ring".to_string(),
+            card::Quote {
+                usd: Some(2.00),
+                usd_foil: Some(9.00),
+                usd_etched: Some(5.00),
+                eur: Some(1.50),
+                eur_foil: Some(7.00),
+                tix: Some(0.10),
+            },
+        );
+        history.record(day(1), &prices);
+
+        let collection = card::Collection {
+            cards: vec![
+                (2, owned("sol-ring", &[])),
+                (1, owned("sol-ring", &["NM", "foil"])),
+                (1, owned("sol-ring", &["etched"])),
+            ],
+        };
+        let holdings = holdings(&collection);
+        assert_eq!(3, holdings.len());
+
+        let value = |currency| timeline(&history, &holdings, vec![], currency)[0].value;
+        assert_eq!(400 + 900 + 500, value(card::Currency::Usd));
+        assert_eq!(300 + 700 + 700, value(card::Currency::Eur));
+        assert_eq!(40, value(card::Currency::Tix));
+    }
+
+    #[test]
+    fn should_leave_graded_copies_out_of_raw_valuations() {
+        let history = history();
+        let mut lookup = card::Map::new();
+        lookup.insert("XLN * Opt".to_string(), "opt".to_string());
+
+        let mut slab = owned("opt", &[]);
+        slab.grade = Some(card::Grade {
+            grader: "PSA".to_string(),
+            grade: "10".to_string(),
+            subgrades: vec![],
+            cert: "12345678".to_string(),
+        });
+        let collection = card::Collection {
+            cards: vec![(2, owned("opt", &[])), (1, slab)],
+        };
+        assert_eq!(3, holdings(&collection)[&("opt".to_string(), card::Finish::Nonfoil)]);
+        let current = raw_holdings(&collection);
+        assert_eq!(2, current[&("opt".to_string(), card::Finish::Nonfoil)]);
+
+        // a raw Opt and the slab came in on the 2nd; only the raw copy
+        // should show up in the history
+        let gain = cdif::File::from_string(
+            "1x XLN Opt | (grader: PSA) (grade: 10) (cert: 12345678)\n1x XLN Opt\n",
+        )
+        .unwrap();
+        let loss = cdif::File::from_string("").unwrap();
+        let changes = vec![Change::resolve(&lookup, day(2), &gain, &loss)];
+        assert_eq!(1, changes[0].delta[&("opt".to_string(), card::Finish::Nonfoil)]);
+
+        let values: Vec<i64> = timeline(&history, &current, changes, card::Currency::Usd)
+            .iter()
+            .map(|v| v.value)
+            .collect();
+        assert_eq!(vec![10, 40, 50], values);
+    }
+}
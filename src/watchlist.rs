@@ -0,0 +1,249 @@
+use serde::Serialize;
+
+use crate::card;
+use crate::valuation;
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// Collectors can watch a single print, or every print of a card (by
+// its oracle id), in which case the cheapest print is checked against
+// the lower threshold, and the most expensive against the upper.
+//
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Scope {
+    Print,
+    Oracle,
+}
+
+impl Scope {
+    pub fn parse(s: &str) -> Option<Scope> {
+        match s {
+            "" | "print" => Some(Scope::Print),
+            "oracle" => Some(Scope::Oracle),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Above,
+    Below,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Above => "above",
+            Direction::Below => "below",
+        }
+    }
+}
+
+pub fn validate(
+    scope: &str,
+    finish: &str,
+    currency: &str,
+    below: Option<i32>,
+    above: Option<i32>,
+) -> Result<()> {
+    if Scope::parse(scope).is_none() {
+        return Err("invalid-watchlist-scope".into());
+    }
+    if card::Finish::parse(finish).is_none() {
+        return Err("invalid-finish".into());
+    }
+    if card::Currency::parse(currency).is_none() {
+        return Err("invalid-currency".into());
+    }
+    match (below, above) {
+        (None, None) => Err("missing-watchlist-threshold".into()),
+        (Some(b), _) if b < 0 => Err("invalid-watchlist-threshold".into()),
+        (_, Some(a)) if a < 0 => Err("invalid-watchlist-threshold".into()),
+        _ => Ok(()),
+    }
+}
+
+pub struct Watch<'a> {
+    pub prints: &'a [String],
+    pub finish: card::Finish,
+    pub currency: card::Currency,
+    pub below: Option<i32>,
+    pub above: Option<i32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Trigger {
+    pub card: String, // print id
+    pub direction: Direction,
+    pub threshold: i32, // pennies
+    pub price: i32,     // pennies
+}
+
+// The cheapest and most expensive of the watched prints, as (pennies,
+// print id) pairs.
+type Range<'a> = ((i64, &'a str), (i64, &'a str));
+
+impl<'a> Watch<'a> {
+    // Price every watched print as of the i'th snapshot, returning the
+    // cheapest and the most expensive.
+    fn range(&self, history: &card::History, i: usize) -> Option<Range<'_>> {
+        let mut range: Option<Range> = None;
+        for pid in self.prints {
+            let key = (pid.to_string(), self.finish);
+            if let Some(p) = valuation::price_at(history, i, &key, self.currency) {
+                range = Some(match range {
+                    None => ((p, pid), (p, pid)),
+                    Some((lo, hi)) => (
+                        if p < lo.0 { (p, pid) } else { lo },
+                        if p > hi.0 { (p, pid) } else { hi },
+                    ),
+                });
+            }
+        }
+        range
+    }
+
+    // Check the watch against the most recent price snapshot.  Alerts
+    // only trigger when a price crosses a threshold, so that a card
+    // sitting under its threshold for a month doesn't alert every day.
+    pub fn evaluate(&self, history: &card::History) -> Vec<Trigger> {
+        let now = match history.dates.len() {
+            0 => return vec![],
+            n => n - 1,
+        };
+        let (lo, hi) = match self.range(history, now) {
+            Some(range) => range,
+            None => return vec![],
+        };
+        let before = match now {
+            0 => None,
+            i => self.range(history, i - 1),
+        };
+
+        let mut triggers = vec![];
+        if let Some(below) = self.below {
+            let below = below as i64;
+            if lo.0 < below && before.is_none_or(|(was, _)| was.0 >= below) {
+                triggers.push(Trigger {
+                    card: lo.1.to_string(),
+                    direction: Direction::Below,
+                    threshold: below as i32,
+                    price: lo.0 as i32,
+                });
+            }
+        }
+        if let Some(above) = self.above {
+            let above = above as i64;
+            if hi.0 > above && before.is_none_or(|(_, was)| was.0 <= above) {
+                triggers.push(Trigger {
+                    card: hi.1.to_string(),
+                    direction: Direction::Above,
+                    threshold: above as i32,
+                    price: hi.0 as i32,
+                });
+            }
+        }
+        triggers
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn history(days: &[&[(&str, f64)]]) -> card::History {
+        let mut history = card::History::default();
+        for (i, prices) in days.iter().enumerate() {
+            let mut snapshot = card::Prices::new();
+            for (pid, usd) in prices.iter() {
+                snapshot.insert(
+                    pid.to_string(),
+                    card::Quote {
+                        usd: Some(*usd),
+                        ..card::Quote::default()
+                    },
+                );
+            }
+            history.record(
+                NaiveDate::from_ymd_opt(2020, 8, 1 + i as u32).unwrap(),
+                &snapshot,
+            );
+        }
+        history
+    }
+
+    #[test]
+    fn should_validate_watchlists() {
+        assert!(validate("print", "foil", "usd", Some(100), None).is_ok());
+        assert!(validate("", "nonfoil", "EUR", None, Some(100)).is_ok());
+        assert!(validate("set", "foil", "usd", Some(100), None).is_err());
+        assert!(validate("print", "shiny", "usd", Some(100), None).is_err());
+        assert!(validate("print", "foil", "gbp", Some(100), None).is_err());
+        assert!(validate("print", "foil", "usd", None, None).is_err());
+        assert!(validate("print", "foil", "usd", Some(-1), None).is_err());
+    }
+
+    #[test]
+    fn should_only_trigger_when_a_price_crosses_a_threshold() {
+        let prints = vec!["bolt".to_string()];
+        let watch = Watch {
+            prints: &prints,
+            finish: card::Finish::Nonfoil,
+            currency: card::Currency::Usd,
+            below: Some(100),
+            above: Some(500),
+        };
+
+        assert!(watch.evaluate(&history(&[])).is_empty());
+        assert!(watch.evaluate(&history(&[&[("bolt", 2.00)]])).is_empty());
+
+        let triggers = watch.evaluate(&history(&[&[("bolt", 2.00)], &[("bolt", 0.99)]]));
+        assert_eq!(
+            vec![Trigger {
+                card: "bolt".to_string(),
+                direction: Direction::Below,
+                threshold: 100,
+                price: 99,
+            }],
+            triggers
+        );
+
+        // still under; no new alert
+        let still = history(&[&[("bolt", 0.99)], &[("bolt", 0.75)]]);
+        assert!(watch.evaluate(&still).is_empty());
+
+        // a brand new price history alerts right away
+        let spike = watch.evaluate(&history(&[&[("bolt", 6.00)]]));
+        assert_eq!(1, spike.len());
+        assert_eq!(Direction::Above, spike[0].direction);
+    }
+
+    #[test]
+    fn should_watch_every_print_of_an_oracle_card() {
+        let prints = vec!["m10".to_string(), "m11".to_string(), "sld".to_string()];
+        let watch = Watch {
+            prints: &prints,
+            finish: card::Finish::Nonfoil,
+            currency: card::Currency::Usd,
+            below: Some(50),
+            above: Some(1000),
+        };
+
+        let triggers = watch.evaluate(&history(&[
+            &[("m10", 1.00), ("m11", 0.75), ("sld", 8.00)],
+            &[("m10", 1.00), ("m11", 0.40), ("sld", 12.00)],
+        ]));
+        assert_eq!(2, triggers.len());
+        assert_eq!("m11", triggers[0].card);
+        assert_eq!(40, triggers[0].price);
+        assert_eq!("sld", triggers[1].card);
+        assert_eq!(1200, triggers[1].price);
+    }
+}
@@ -0,0 +1,390 @@
+use serde::Serialize;
+
+use crate::prelude::*;
+use crate::{card, cdif};
+
+mod errors {
+    error_chain! {}
+}
+pub use errors::Error;
+use errors::*;
+
+// Wishlists are CDIF files, with the constraints on what will satisfy
+// each wish carried in its local variants:
+//
+//   1x M11 Lightning Bolt | (printing: any) (finish: nonfoil) (max: 1.50)
+//
+// printing    exact (the default) wants the print named by the line;
+//             set will take any print of the card from the named set,
+//             and any will take any print of the card, from any set.
+//
+// finish      any (the default), nonfoil, foil or etched.
+//
+// condition   the worst condition we will settle for, one of M, NM,
+//             LP, MP, HP or DMG (EX, VG, G and P are also understood).
+//
+// max         the most we are willing to pay for a single copy, in the
+//             collector's preferred currency.
+//
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Printing {
+    Exact,
+    Set,
+    Any,
+}
+
+impl Printing {
+    pub fn parse(s: &str) -> Option<Printing> {
+        match s.to_lowercase().as_str() {
+            "" | "exact" => Some(Printing::Exact),
+            "set" => Some(Printing::Set),
+            "any" => Some(Printing::Any),
+            _ => None,
+        }
+    }
+}
+
+// Rank a card condition, from best (0, mint) to worst (5, damaged).
+pub fn condition(s: &str) -> Option<u8> {
+    match s.to_uppercase().as_str() {
+        "M" => Some(0),
+        "NM" => Some(1),
+        "LP" | "EX" => Some(2),
+        "MP" | "VG" => Some(3),
+        "HP" | "G" => Some(4),
+        "DMG" | "P" => Some(5),
+        _ => None,
+    }
+}
+
+pub struct Wish {
+    pub card: String, // the CDIF line identifier, i.e. "MIR Afterlife | (max: 1.00)"
+    pub quantity: u32,
+    pub id: String,  // print id the line resolves to
+    pub oid: String, // oracle id of that print
+    pub set: String,
+    pub printing: Printing,
+    pub finish: Option<card::Finish>,
+    pub condition: Option<u8>,
+    pub max: Option<i64>, // pennies
+}
+
+impl Wish {
+    fn from_line(pool: &card::Pool, lookup: &card::Map, line: &cdif::Line) -> Result<Wish> {
+        let prints = pool.prints();
+        let (set, print) = lookup
+            .get(&format!("{} *{} {}", line.set, line.number, line.oracle))
+            .and_then(|pid| prints.get(pid.as_str()))
+            .ok_or_else(|| Error::from(format!("unknown-card: {} {}", line.set, line.oracle)))?;
+
+        let mut wish = Wish {
+            card: line.id(),
+            quantity: line.quantity.max(0) as u32,
+            id: print.id.to_string(),
+            oid: print.oid.to_string(),
+            set: set.code.to_string(),
+            printing: Printing::Exact,
+            finish: None,
+            condition: None,
+            max: None,
+        };
+
+        for (k, v) in &line.lvars {
+            let v = v.trim();
+            match k.to_lowercase().as_str() {
+                "printing" => {
+                    wish.printing = Printing::parse(v)
+                        .ok_or_else(|| Error::from(format!("invalid-wish-printing: {}", v)))?;
+                }
+                "finish" => {
+                    if v != "any" {
+                        wish.finish = Some(
+                            card::Finish::parse(v)
+                                .ok_or_else(|| Error::from(format!("invalid-finish: {}", v)))?,
+                        );
+                    }
+                }
+                "condition" => {
+                    wish.condition = Some(
+                        condition(v)
+                            .ok_or_else(|| Error::from(format!("invalid-condition: {}", v)))?,
+                    );
+                }
+                "max" => match v.parse::<f64>() {
+                    Ok(max) if max >= 0.0 => wish.max = Some((max * 100.0).round() as i64),
+                    _ => return Err(format!("invalid-wish-max: {}", v).into()),
+                },
+                _ => return Err(format!("unknown-wish-constraint: {}", k).into()),
+            }
+        }
+        Ok(wish)
+    }
+
+    // Would a given print card (from a given set) satisfy this wish?
+    pub fn accepts(&self, set: &card::Set, print: &card::PrintCard) -> bool {
+        match self.printing {
+            Printing::Exact => print.id == self.id,
+            Printing::Set => print.oid == self.oid && set.code == self.set,
+            Printing::Any => print.oid == self.oid,
+        }
+    }
+
+    // Would a physical copy, with the given variants, satisfy this wish?
+    // Copies without a recorded condition are assumed to be near mint.
+    pub fn accepts_copy(&self, vars: &[String]) -> bool {
+        if let Some(finish) = self.finish {
+            if card::Finish::of(vars) != finish {
+                return false;
+            }
+        }
+        if let Some(worst) = self.condition {
+            let have = vars.iter().filter_map(|v| condition(v)).min().unwrap_or(1);
+            if have > worst {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// Parse the wishes out of a wishlist, resolving each to the print card
+// it names.  Wishes are returned in the order of their identifiers.
+pub fn parse(pool: &card::Pool, lookup: &card::Map, cards: &str) -> Result<Vec<Wish>> {
+    let file = cdif::File::from_string(cards).chain_err(|| "invalid-wishlist")?;
+    let mut lines: Vec<&cdif::Line> = file.lines.values().collect();
+    lines.sort_by_key(|l| l.id());
+
+    let mut wishes = vec![];
+    for line in lines {
+        wishes.push(Wish::from_line(pool, lookup, line)?);
+    }
+    Ok(wishes)
+}
+
+#[derive(Serialize)]
+pub struct Deal {
+    pub card: String, // the wish
+    pub quantity: u32,
+    pub id: String, // print id of the cheapest acceptable print
+    pub set: String,
+    pub finish: card::Finish,
+    pub price: i64, // pennies
+    pub max: i64,   // pennies
+}
+
+// Find the wishes that can currently be had for no more than their
+// maximum price, along with the cheapest print (and finish) that will
+// do.  Wishes without a maximum price are never deals.
+pub fn deals(
+    pool: &card::Pool,
+    prices: &card::Prices,
+    wishes: &[Wish],
+    currency: card::Currency,
+) -> Vec<Deal> {
+    let finishes = [card::Finish::Nonfoil, card::Finish::Foil, card::Finish::Etched];
+    let mut deals = vec![];
+
+    for wish in wishes {
+        let max = match wish.max {
+            Some(max) => max,
+            None => continue,
+        };
+
+        let mut best: Option<Deal> = None;
+        for set in pool.sets.values() {
+            for print in set.cards.iter().filter(|p| wish.accepts(set, p)) {
+                let quote = match prices.get(&print.id) {
+                    Some(quote) => quote,
+                    None => continue,
+                };
+                for finish in finishes.iter().filter(|f| wish.finish.is_none_or(|w| w == **f)) {
+                    let price = match quote.price(*finish, currency) {
+                        Some(price) => (price * 100.0).round() as i64,
+                        None => continue,
+                    };
+                    if price > max {
+                        continue;
+                    }
+                    let better = match &best {
+                        None => true,
+                        Some(b) => (price, &print.id, *finish) < (b.price, &b.id, b.finish),
+                    };
+                    if better {
+                        best = Some(Deal {
+                            card: wish.card.to_string(),
+                            quantity: wish.quantity,
+                            id: print.id.to_string(),
+                            set: set.code.to_string(),
+                            finish: *finish,
+                            price,
+                            max,
+                        });
+                    }
+                }
+            }
+        }
+        deals.extend(best);
+    }
+    deals
+}
+
+#[derive(Serialize)]
+pub struct Satisfaction {
+    pub card: String, // the wish
+    pub want: u32,
+    pub have: u32,
+    pub satisfied: bool,
+}
+
+// Check each wish against what the collection already holds.  Each
+// wish is checked on its own, so a single copy may satisfy more than
+// one (overlapping) wish.
+pub fn satisfaction(
+    pool: &card::Pool,
+    collection: &card::Collection,
+    wishes: &[Wish],
+) -> Vec<Satisfaction> {
+    let prints = pool.prints();
+    wishes
+        .iter()
+        .map(|wish| {
+            let have = collection
+                .cards
+                .iter()
+                .filter(|(_, c)| match prints.get(c.pid.as_str()) {
+                    Some((set, print)) => wish.accepts(set, print) && wish.accepts_copy(&c.var),
+                    None => false,
+                })
+                .map(|(n, _)| *n)
+                .sum();
+            Satisfaction {
+                card: wish.card.to_string(),
+                want: wish.quantity,
+                have,
+                satisfied: have >= wish.quantity,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pid(pool: &card::Pool, set: &str, number: &str) -> String {
+        pool.sets[set]
+            .cards
+            .iter()
+            .find(|c| c.number == number)
+            .expect("finding print in test pool")
+            .id
+            .to_string()
+    }
+
+    fn usd(prices: &mut card::Prices, pid: String, usd: f64, foil: Option<f64>) {
+        prices.insert(
+            pid,
+            card::Quote {
+                usd: Some(usd),
+                usd_foil: foil,
+                ..card::Quote::default()
+            },
+        );
+    }
+
+    #[test]
+    fn should_parse_wish_constraints() {
+        let (pool, lookup) = card::test_pool();
+        let wishes = parse(
+            &pool,
+            &lookup,
+            "2x AVR Archangel | (printing: any) (finish: foil) (condition: LP) (max: 3.25)\n\
+             1x MIR *1 Afterlife\n",
+        )
+        .unwrap();
+        assert_eq!(2, wishes.len());
+
+        let angel = &wishes[0];
+        assert_eq!(2, angel.quantity);
+        assert_eq!("AVR", angel.set);
+        assert_eq!(Printing::Any, angel.printing);
+        assert_eq!(Some(card::Finish::Foil), angel.finish);
+        assert_eq!(Some(2), angel.condition);
+        assert_eq!(Some(325), angel.max);
+
+        let afterlife = &wishes[1];
+        assert_eq!(Printing::Exact, afterlife.printing);
+        assert_eq!(None, afterlife.max);
+
+        assert!(parse(&pool, &lookup, "1x MIR Not A Real Card\n").is_err());
+        assert!(parse(&pool, &lookup, "1x MIR Afterlife | (printing: alt)\n").is_err());
+        assert!(parse(&pool, &lookup, "1x MIR Afterlife | (condition: okay)\n").is_err());
+        assert!(parse(&pool, &lookup, "1x MIR Afterlife | (max: free)\n").is_err());
+        assert!(parse(&pool, &lookup, "1x MIR Afterlife | (color: blue)\n").is_err());
+    }
+
+    #[test]
+    fn should_find_wishes_cheaper_than_their_max_price() {
+        let (pool, lookup) = card::test_pool();
+        let mut prices = card::Prices::new();
+        usd(&mut prices, pid(&pool, "AVR", "5"), 2.00, Some(9.00));
+        usd(&mut prices, pid(&pool, "VIS", "1"), 1.25, None);
+        usd(&mut prices, pid(&pool, "MIR", "1"), 0.75, None);
+
+        let wishes = parse(
+            &pool,
+            &lookup,
+            "1x AVR Archangel | (printing: any) (max: 1.50)\n\
+             1x AVR Archangel | (printing: set) (max: 1.50)\n\
+             1x AVR Archangel | (finish: foil) (max: 10)\n\
+             1x MIR Afterlife | (finish: foil) (max: 1)\n\
+             4x MIR Afterlife\n",
+        )
+        .unwrap();
+
+        let deals = deals(&pool, &prices, &wishes, card::Currency::Usd);
+        assert_eq!(2, deals.len());
+
+        assert_eq!(card::Finish::Foil, deals[0].finish);
+        assert_eq!(900, deals[0].price);
+        assert_eq!(1000, deals[0].max);
+
+        // the VIS printing is the only one under $1.50
+        assert_eq!("AVR Archangel | (printing: any) (max: 1.50)", deals[1].card);
+        assert_eq!("VIS", deals[1].set);
+        assert_eq!(125, deals[1].price);
+    }
+
+    #[test]
+    fn should_check_wishes_against_the_collection() {
+        let (pool, lookup) = card::test_pool();
+        let wishes = parse(
+            &pool,
+            &lookup,
+            "2x AVR Archangel | (printing: any)\n\
+             1x AVR Archangel | (condition: NM)\n\
+             1x MIR Afterlife | (finish: foil)\n",
+        )
+        .unwrap();
+
+        let owned = |set, number, var: &[&str]| card::OwnedCard {
+            pid: pid(&pool, set, number),
+            var: var.iter().map(|v| v.to_string()).collect(),
+            grade: None,
+        };
+        let collection = card::Collection {
+            cards: vec![
+                (1, owned("AVR", "5", &["LP"])),
+                (1, owned("VIS", "1", &[])),
+                (3, owned("MIR", "1", &["NM"])),
+            ],
+        };
+
+        let status = satisfaction(&pool, &collection, &wishes);
+        assert_eq!(3, status.len());
+        assert_eq!((0, false), (status[0].have, status[0].satisfied));
+        assert_eq!((2, true), (status[1].have, status[1].satisfied));
+        assert_eq!((0, false), (status[2].have, status[2].satisfied));
+    }
+}
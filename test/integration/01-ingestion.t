@@ -196,7 +196,7 @@ ok($res->is_success, "should be able to retrieve pricing data, as JSON")
 is($res->header('Content-Type'), 'application/json', "response should be JSON");
 
 my $prices = from_json($res->content);
-is($prices->{'2e5cd12a-2a07-44a8-8eac-de00d26fe9e3'}, '19.63',
+is($prices->{'2e5cd12a-2a07-44a8-8eac-de00d26fe9e3'}{usd}, '19.63',
 	"WTH Lotus Vale should be priced at \$19.63 (well, it shouldn't; but it WAS when the test data was retrieved...)");
 ok(!exists $prices->{'01fc5bb3-ebd7-4ab4-8aef-2ece1e1d9b7c'}, "pricing should not exist for oracle cards.");
 
@@ -209,7 +209,7 @@ ok(!exists $prices->{'01fc5bb3-ebd7-4ab4-8aef-2ece1e1d9b7c'}, "pricing should no
 ###   with key (tested-for) amounts changed.
 ###
 
-$prices->{'2e5cd12a-2a07-44a8-8eac-de00d26fe9e3'} = '100.23';
+$prices->{'2e5cd12a-2a07-44a8-8eac-de00d26fe9e3'}{usd} = 100.23;
 my $res = post("/prices.json", payload =>to_json($prices));
 is($res->code, 401, "cards.json bulk updates must be authorized")
 	or diag $res->as_string;
@@ -226,7 +226,7 @@ ok($res->is_success, "should be able to re-retrieve pricing data, as JSON")
 is($res->header('Content-Type'), 'application/json', "response should be JSON");
 
 my $prices = from_json($res->content);
-is($prices->{'2e5cd12a-2a07-44a8-8eac-de00d26fe9e3'}, '100.23',
+is($prices->{'2e5cd12a-2a07-44a8-8eac-de00d26fe9e3'}{usd}, '100.23',
 	"WTH Lotus Vale should be priced at \$100.23 (after our update)");
 ok(!exists $prices->{'01fc5bb3-ebd7-4ab4-8aef-2ece1e1d9b7c'}, "pricing still should not exist for oracle cards.");
 
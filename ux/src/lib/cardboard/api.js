@@ -148,9 +148,11 @@ let API = {
       method: 'PATCH',
       headers: authorized(auth.session),
       body: JSON.stringify({
-        name:    goal.name    || '',
-        target:  goal.target  || '',
-        goal:    goal.goal    || ''
+        name:     goal.name     || '',
+        target:   goal.target   || '',
+        goal:     goal.goal     || '',
+        deadline: goal.deadline || null,
+        budget:   goal.budget   || null
       })
     }).then(r => {
       if (!r.ok) {
@@ -165,10 +167,12 @@ let API = {
       method: 'POST',
       headers: authorized(auth.session),
       body: JSON.stringify({
-        name:    goal.name    || '',
-        target:  goal.target  || '',
-        goal:    goal.goal    || '',
-        ordinal: goal.ordinal || 0,
+        name:     goal.name     || '',
+        target:   goal.target   || '',
+        goal:     goal.goal     || '',
+        ordinal:  goal.ordinal  || 0,
+        deadline: goal.deadline || null,
+        budget:   goal.budget   || null,
       })
     }).then(r => {
       if (!r.ok) {
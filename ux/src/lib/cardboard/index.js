@@ -77,7 +77,8 @@ class Vault {
           cost      : data.cards[card.oid].mana_cost,
           color     : data.cards[card.oid].color_identity.join(''),
 
-          price     : (prices && (card.id in prices)) ? prices[card.id] : undefined,
+          price     : (prices && (card.id in prices)) ? prices[card.id].usd : undefined,
+          prices    : (prices && (card.id in prices)) ? prices[card.id] : {},
           owned     : 0,         // we will backpatch this later, with collection.json data
 
           flavor    : card.flavor,
@@ -142,7 +143,8 @@ class Vault {
 
   update_prices(prices) {
     for (let id in prices) {
-      this.index[id].price = prices[id]
+      this.index[id].price = prices[id].usd
+      this.index[id].prices = prices[id]
     }
     return this
   }
@@ -196,7 +198,7 @@ class Vault {
     this.clear_collection()
     base.forEach((card) => {
       if (card[1].pid in this.index) {
-        this.index[card[1].pid].owned = card[0]
+        this.index[card[1].pid].owned += card[0]
       }
     })
 